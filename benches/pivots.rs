@@ -0,0 +1,45 @@
+//! Pivot strength implementations on a 50k-candle window:
+//! `cargo bench --bench pivots`.
+
+use binance_streamer::features::pivots::{
+    strengths, strengths_naive, strengths_par, PivotKind, PIVOT_WINDOW,
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const WINDOW: usize = 50_000;
+
+/// Deterministic random walk of highs (xorshift, fixed seed).
+fn highs(count: usize) -> Vec<f64> {
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut price = 30_000.0;
+    (0..count)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            price *= 1.0 + ((state >> 11) as f64 / (1u64 << 53) as f64 - 0.5) * 0.006;
+            price
+        })
+        .collect()
+}
+
+fn bench_pivots(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pivot_strengths");
+    // A random walk and a steady trend (long runs, the naive worst case)
+    let trend: Vec<f64> = (0..WINDOW).map(|i| 30_000.0 + i as f64).collect();
+    for (name, prices) in [("random_walk", highs(WINDOW)), ("trend", trend)] {
+        group.bench_with_input(BenchmarkId::new("naive", name), &prices, |b, p| {
+            b.iter(|| strengths_naive(black_box(p), PivotKind::High, PIVOT_WINDOW))
+        });
+        group.bench_with_input(BenchmarkId::new("stack", name), &prices, |b, p| {
+            b.iter(|| strengths(black_box(p), PivotKind::High, PIVOT_WINDOW))
+        });
+        group.bench_with_input(BenchmarkId::new("parallel", name), &prices, |b, p| {
+            b.iter(|| strengths_par(black_box(p), PivotKind::High, PIVOT_WINDOW))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_pivots);
+criterion_main!(benches);
@@ -0,0 +1,31 @@
+fn main() {
+    // Commit recorded in run manifests; unset outside a git checkout
+    let commit = std::process::Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success());
+    if let Some(output) = commit {
+        println!(
+            "cargo:rustc-env=GIT_COMMIT={}",
+            String::from_utf8_lossy(&output.stdout).trim()
+        );
+    }
+    for path in [".git/HEAD", ".git/refs"] {
+        if std::path::Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+
+    #[cfg(feature = "grpc")]
+    {
+        std::env::set_var(
+            "PROTOC",
+            protoc_bin_vendored::protoc_bin_path().expect("vendored protoc"),
+        );
+        tonic_prost_build::configure()
+            .build_client(false)
+            .compile_protos(&["proto/market.proto"], &["proto"])
+            .expect("failed to compile proto/market.proto");
+    }
+}
@@ -0,0 +1,28 @@
+/* Build: cargo build --release -p feature_engine_ffi
+ *        cc ffi/examples/update.c -Iffi/include -Ltarget/release -lfeature_engine -o update
+ * Run:   LD_LIBRARY_PATH=target/release ./update */
+#include <stdio.h>
+#include "feature_engine.h"
+
+int main(void) {
+    if (fe_abi_version() != FE_ABI_VERSION) {
+        fprintf(stderr, "ABI mismatch\n");
+        return 1;
+    }
+    fe_engine *engine = fe_create(50000);
+    const int64_t m15 = 15 * 60 * 1000;
+    fe_feature_row row;
+    for (int i = 0; i < 300; i++) {
+        double price = 30000.0 + i;
+        fe_candle candle = {1600000200000 + i * m15, price, price + 5, price - 5, price + 1, 10.0,
+                            1600000200000 + (i + 1) * m15 - 1};
+        if (fe_update(engine, &candle, &row) != 0) {
+            fprintf(stderr, "fe_update: %s\n", fe_last_error(engine));
+            return 1;
+        }
+    }
+    printf("%zu candles, close %.1f, ema50_m15 %.3f, ema200_m15 %.3f\n", fe_len(engine), row.close,
+           row.ema50_m15, row.ema200_m15);
+    fe_destroy(engine);
+    return 0;
+}
@@ -0,0 +1,82 @@
+/*
+ * C ABI of the stateful feature engine (crate `feature_engine_ffi`).
+ *
+ * The engine keeps a rolling window of candles and computes features with
+ * the same code as the live collector, so embedders get identical values.
+ * Link against libfeature_engine.{so,a}. An engine handle must not be used
+ * from two threads at once; distinct handles are independent.
+ */
+#ifndef FEATURE_ENGINE_H
+#define FEATURE_ENGINE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Bumped whenever fe_candle or fe_feature_row change layout. */
+#define FE_ABI_VERSION 1
+
+typedef struct fe_engine fe_engine;
+
+/* Times are epoch milliseconds. */
+typedef struct {
+    int64_t open_time;
+    double open;
+    double high;
+    double low;
+    double close;
+    double volume;
+    int64_t close_time;
+} fe_candle;
+
+/* Features of one candle. EMAs are NaN until enough history exists. */
+typedef struct {
+    int64_t open_time;
+    double close;
+    double ema50_m15;
+    double ema200_m15;
+    double ema50_h1;
+    double ema200_h1;
+    double ema50_h4;
+    double ema200_h4;
+    uint32_t pivot_high_left;
+    uint32_t pivot_high_right;
+    uint32_t pivot_high_strength;
+    uint32_t pivot_low_left;
+    uint32_t pivot_low_right;
+    uint32_t pivot_low_strength;
+} fe_feature_row;
+
+/* ABI version of the loaded library; compare with FE_ABI_VERSION. */
+uint32_t fe_abi_version(void);
+
+/* New engine keeping at most `capacity` candles. Returns NULL on failure. */
+fe_engine *fe_create(size_t capacity);
+
+/* New engine pre-filled with the newest `capacity` of `count` candles
+ * (oldest first). Much faster than calling fe_update for each of them. */
+fe_engine *fe_create_with_history(size_t capacity, const fe_candle *history, size_t count);
+
+/* Push a closed candle and write its features to `out`. A candle with the
+ * newest open_time replaces it; older candles are rejected.
+ * Returns 0 on success, -1 on error (see fe_last_error). */
+int fe_update(fe_engine *engine, const fe_candle *candle, fe_feature_row *out);
+
+/* Number of candles currently in the window. */
+size_t fe_len(const fe_engine *engine);
+
+/* Message of the last failed call on `engine`, or NULL. Valid until the
+ * next call on the same engine. */
+const char *fe_last_error(const fe_engine *engine);
+
+/* Free an engine (NULL is ignored). */
+void fe_destroy(fe_engine *engine);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FEATURE_ENGINE_H */
@@ -0,0 +1,200 @@
+//! C ABI around [`FeatureEngine`]; see `include/feature_engine.h`.
+//!
+//! Every entry point catches panics so they never unwind into C, and errors
+//! are reported through a return code plus `fe_last_error`.
+
+use binance_streamer::features::FeatureEngine;
+use binance_streamer::kline::Kline;
+use polars::prelude::*;
+use std::ffi::{c_char, c_int, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+pub const FE_ABI_VERSION: u32 = 1;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FeCandle {
+    pub open_time: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub close_time: i64,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FeFeatureRow {
+    pub open_time: i64,
+    pub close: f64,
+    pub ema50_m15: f64,
+    pub ema200_m15: f64,
+    pub ema50_h1: f64,
+    pub ema200_h1: f64,
+    pub ema50_h4: f64,
+    pub ema200_h4: f64,
+    pub pivot_high_left: u32,
+    pub pivot_high_right: u32,
+    pub pivot_high_strength: u32,
+    pub pivot_low_left: u32,
+    pub pivot_low_right: u32,
+    pub pivot_low_strength: u32,
+}
+
+pub struct FeEngine {
+    engine: FeatureEngine,
+    last_error: Option<CString>,
+}
+
+impl From<&FeCandle> for Kline {
+    fn from(c: &FeCandle) -> Self {
+        Kline {
+            open_time: c.open_time,
+            open: c.open,
+            high: c.high,
+            low: c.low,
+            close: c.close,
+            volume: c.volume,
+            close_time: c.close_time,
+        }
+    }
+}
+
+fn float(row: &DataFrame, name: &str) -> anyhow::Result<f64> {
+    Ok(row
+        .column(name)?
+        .get(0)?
+        .extract::<f64>()
+        .unwrap_or(f64::NAN))
+}
+
+fn count(row: &DataFrame, name: &str) -> anyhow::Result<u32> {
+    Ok(row.column(name)?.get(0)?.extract::<u32>().unwrap_or(0))
+}
+
+/// Map the one-row feature frame onto the fixed C struct.
+fn to_row(row: &DataFrame) -> anyhow::Result<FeFeatureRow> {
+    Ok(FeFeatureRow {
+        open_time: row
+            .column("open_time")?
+            .get(0)?
+            .extract::<i64>()
+            .unwrap_or(0),
+        close: float(row, "close")?,
+        ema50_m15: float(row, "ema50_m15")?,
+        ema200_m15: float(row, "ema200_m15")?,
+        ema50_h1: float(row, "ema50_h1")?,
+        ema200_h1: float(row, "ema200_h1")?,
+        ema50_h4: float(row, "ema50_h4")?,
+        ema200_h4: float(row, "ema200_h4")?,
+        pivot_high_left: count(row, "pivot_high_left")?,
+        pivot_high_right: count(row, "pivot_high_right")?,
+        pivot_high_strength: count(row, "pivot_high_strength")?,
+        pivot_low_left: count(row, "pivot_low_left")?,
+        pivot_low_right: count(row, "pivot_low_right")?,
+        pivot_low_strength: count(row, "pivot_low_strength")?,
+    })
+}
+
+fn into_handle(engine: FeatureEngine) -> *mut FeEngine {
+    Box::into_raw(Box::new(FeEngine {
+        engine,
+        last_error: None,
+    }))
+}
+
+#[no_mangle]
+pub extern "C" fn fe_abi_version() -> u32 {
+    FE_ABI_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn fe_create(capacity: usize) -> *mut FeEngine {
+    catch_unwind(|| into_handle(FeatureEngine::new(capacity))).unwrap_or(ptr::null_mut())
+}
+
+/// # Safety
+/// `history` must point to `count` readable candles (or be NULL with `count == 0`).
+#[no_mangle]
+pub unsafe extern "C" fn fe_create_with_history(
+    capacity: usize,
+    history: *const FeCandle,
+    count: usize,
+) -> *mut FeEngine {
+    if history.is_null() && count > 0 {
+        return ptr::null_mut();
+    }
+    catch_unwind(|| {
+        let candles = if count == 0 {
+            &[][..]
+        } else {
+            std::slice::from_raw_parts(history, count)
+        };
+        let klines: Vec<Kline> = candles.iter().map(Kline::from).collect();
+        into_handle(FeatureEngine::with_history(&klines, capacity))
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// # Safety
+/// `engine` must come from `fe_create*`; `candle` and `out` must be valid pointers.
+#[no_mangle]
+pub unsafe extern "C" fn fe_update(
+    engine: *mut FeEngine,
+    candle: *const FeCandle,
+    out: *mut FeFeatureRow,
+) -> c_int {
+    let Some(handle) = engine.as_mut() else {
+        return -1;
+    };
+    if candle.is_null() || out.is_null() {
+        handle.last_error = CString::new("candle and out must not be NULL").ok();
+        return -1;
+    }
+    let kline = Kline::from(&*candle);
+    let engine = &mut handle.engine;
+    let result = catch_unwind(AssertUnwindSafe(|| to_row(&engine.update(kline)?)));
+    match result {
+        Ok(Ok(row)) => {
+            *out = row;
+            handle.last_error = None;
+            0
+        }
+        Ok(Err(e)) => {
+            handle.last_error = CString::new(format!("{:#}", e).replace('\0', " ")).ok();
+            -1
+        }
+        Err(_) => {
+            handle.last_error = CString::new("panic in feature engine").ok();
+            -1
+        }
+    }
+}
+
+/// # Safety
+/// `engine` must come from `fe_create*` (or be NULL).
+#[no_mangle]
+pub unsafe extern "C" fn fe_len(engine: *const FeEngine) -> usize {
+    engine.as_ref().map_or(0, |h| h.engine.len())
+}
+
+/// # Safety
+/// `engine` must come from `fe_create*` (or be NULL).
+#[no_mangle]
+pub unsafe extern "C" fn fe_last_error(engine: *const FeEngine) -> *const c_char {
+    engine
+        .as_ref()
+        .and_then(|h| h.last_error.as_ref())
+        .map_or(ptr::null(), |e| e.as_ptr())
+}
+
+/// # Safety
+/// `engine` must come from `fe_create*` and not be used afterwards (NULL is ignored).
+#[no_mangle]
+pub unsafe extern "C" fn fe_destroy(engine: *mut FeEngine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
@@ -0,0 +1,64 @@
+"""Production feature pipeline and Binance fetchers of the collector.
+
+Every function returns ``polars.DataFrame``s computed by the same Rust code
+the live collector runs, so notebook results match production exactly.
+"""
+
+import io
+
+import polars as pl
+
+from . import _native
+
+__all__ = [
+    "FeatureEngine",
+    "compute_features",
+    "fetch_klines_range",
+    "fetch_latest_klines",
+]
+
+
+def _to_ipc(df: pl.DataFrame) -> bytes:
+    buf = io.BytesIO()
+    df.write_ipc(buf)
+    return buf.getvalue()
+
+
+def _from_ipc(data: bytes) -> pl.DataFrame:
+    return pl.read_ipc(io.BytesIO(data))
+
+
+def compute_features(klines: pl.DataFrame) -> pl.DataFrame:
+    """EMA and pivot features for a frame with the kline columns
+    (open_time, open, high, low, close, volume, close_time)."""
+    return _from_ipc(_native.compute_features_ipc(_to_ipc(klines)))
+
+
+def fetch_latest_klines(symbol: str, interval: str, count: int) -> pl.DataFrame:
+    """The most recent ``count`` candles, e.g. ``fetch_latest_klines("BTCUSDT", "15m", 5000)``."""
+    return _from_ipc(_native.fetch_latest_klines_ipc(symbol, interval, count))
+
+
+def fetch_klines_range(symbol: str, interval: str, start_ms: int, end_ms: int) -> pl.DataFrame:
+    """Candles between two epoch-millisecond timestamps."""
+    return _from_ipc(_native.fetch_klines_range_ipc(symbol, interval, start_ms, end_ms))
+
+
+class FeatureEngine:
+    """Rolling window of at most ``capacity`` candles; ``update`` returns the
+    feature row of each pushed candle as a dict."""
+
+    def __init__(self, capacity: int = 50_000, history: "pl.DataFrame | None" = None):
+        self._engine = _native.FeatureEngine(
+            capacity, None if history is None else _to_ipc(history)
+        )
+
+    def __len__(self) -> int:
+        return len(self._engine)
+
+    def update(self, open_time, open, high, low, close, volume, close_time) -> dict:
+        row = self._engine.update_ipc(open_time, open, high, low, close, volume, close_time)
+        return _from_ipc(row).row(0, named=True)
+
+    def features(self) -> pl.DataFrame:
+        return _from_ipc(self._engine.features_ipc())
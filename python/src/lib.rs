@@ -0,0 +1,174 @@
+//! `binance_streamer._native`: PyO3 bindings to the collector's feature code.
+//!
+//! Frames cross the boundary as Arrow IPC bytes, so the bindings do not pin a
+//! particular Python polars version; `binance_streamer/__init__.py` turns the
+//! bytes back into `polars.DataFrame`s.
+
+use binance_streamer::features::{self, FeatureEngine};
+use binance_streamer::kline::Kline;
+use binance_streamer::{binance_client, data_storage, error};
+use polars::prelude::*;
+use pyo3::exceptions::{PyConnectionError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::io::Cursor;
+
+fn py_err(e: impl std::fmt::Display) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
+
+/// Network failures raise `ConnectionError` and bad arguments `ValueError`,
+/// so Python callers can retry the former.
+fn fetch_err(e: error::Error) -> PyErr {
+    match e {
+        error::Error::Network(_) => PyConnectionError::new_err(e.to_string()),
+        error::Error::Config(_) => PyValueError::new_err(e.to_string()),
+        e => py_err(e),
+    }
+}
+
+fn to_ipc(mut df: DataFrame) -> anyhow::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    IpcWriter::new(&mut buf).finish(&mut df)?;
+    Ok(buf)
+}
+
+/// Read klines from IPC bytes holding at least the seven kline columns.
+fn klines_from_ipc(bytes: &[u8]) -> anyhow::Result<Vec<Kline>> {
+    let df = IpcReader::new(Cursor::new(bytes)).finish()?;
+    let df = df
+        .lazy()
+        .select([
+            col("open_time").cast(DataType::Int64),
+            col("open").cast(DataType::Float64),
+            col("high").cast(DataType::Float64),
+            col("low").cast(DataType::Float64),
+            col("close").cast(DataType::Float64),
+            col("volume").cast(DataType::Float64),
+            col("close_time").cast(DataType::Int64),
+        ])
+        .collect()?;
+    Ok(data_storage::dataframe_to_klines(&df)?)
+}
+
+fn klines_to_ipc(klines: &[Kline]) -> anyhow::Result<Vec<u8>> {
+    to_ipc(data_storage::klines_to_dataframe(klines)?)
+}
+
+fn fetch(
+    py: Python<'_>,
+    f: impl std::future::Future<Output = error::Result<Vec<Kline>>> + Send,
+) -> PyResult<Vec<u8>> {
+    py.detach(|| {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(py_err)?;
+        let klines = runtime.block_on(f).map_err(fetch_err)?;
+        klines_to_ipc(&klines).map_err(py_err)
+    })
+}
+
+/// Features for a kline frame, exactly as computed by the live collector.
+#[pyfunction]
+fn compute_features_ipc<'py>(py: Python<'py>, klines: &[u8]) -> PyResult<Bound<'py, PyBytes>> {
+    let out = py
+        .detach(|| to_ipc(features::compute_features(&klines_from_ipc(klines)?)?))
+        .map_err(py_err)?;
+    Ok(PyBytes::new(py, &out))
+}
+
+/// The most recent `count` candles of `symbol` at `interval` ("15m", "1h", ...).
+#[pyfunction]
+fn fetch_latest_klines_ipc<'py>(
+    py: Python<'py>,
+    symbol: &str,
+    interval: &str,
+    count: usize,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let out = fetch(
+        py,
+        binance_client::fetch_latest_klines(symbol, interval, count),
+    )?;
+    Ok(PyBytes::new(py, &out))
+}
+
+/// Candles of `symbol` between two epoch-millisecond timestamps.
+#[pyfunction]
+fn fetch_klines_range_ipc<'py>(
+    py: Python<'py>,
+    symbol: &str,
+    interval: &str,
+    start_ms: i64,
+    end_ms: i64,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let out = fetch(
+        py,
+        binance_client::fetch_klines_range(symbol, interval, start_ms, end_ms),
+    )?;
+    Ok(PyBytes::new(py, &out))
+}
+
+/// Stateful engine: push candles one at a time, get each one's feature row.
+#[pyclass(name = "FeatureEngine", module = "binance_streamer._native")]
+struct PyFeatureEngine {
+    engine: FeatureEngine,
+}
+
+#[pymethods]
+impl PyFeatureEngine {
+    #[new]
+    #[pyo3(signature = (capacity, history=None))]
+    fn new(capacity: usize, history: Option<&[u8]>) -> PyResult<Self> {
+        let history = match history {
+            Some(bytes) => klines_from_ipc(bytes).map_err(py_err)?,
+            None => Vec::new(),
+        };
+        Ok(Self {
+            engine: FeatureEngine::with_history(&history, capacity),
+        })
+    }
+
+    fn __len__(&self) -> usize {
+        self.engine.len()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn update_ipc<'py>(
+        &mut self,
+        py: Python<'py>,
+        open_time: i64,
+        open: f64,
+        high: f64,
+        low: f64,
+        close: f64,
+        volume: f64,
+        close_time: i64,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let kline = Kline {
+            open_time,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            close_time,
+        };
+        let row = self.engine.update(kline).map_err(py_err)?;
+        Ok(PyBytes::new(py, &to_ipc(row).map_err(py_err)?))
+    }
+
+    fn features_ipc<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let df = self.engine.features().map_err(py_err)?;
+        Ok(PyBytes::new(py, &to_ipc(df).map_err(py_err)?))
+    }
+}
+
+#[pymodule]
+fn _native(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(compute_features_ipc, m)?)?;
+    m.add_function(wrap_pyfunction!(fetch_latest_klines_ipc, m)?)?;
+    m.add_function(wrap_pyfunction!(fetch_klines_range_ipc, m)?)?;
+    m.add_class::<PyFeatureEngine>()?;
+    Ok(())
+}
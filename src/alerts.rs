@@ -0,0 +1,58 @@
+//! Operator alerts.
+//!
+//! Alerts are printed to stderr with a greppable prefix and counted in the
+//! metrics registry (`alerts_total{level,source}`), the event log and the
+//! summary reports. While an exchange is in maintenance, the muted sources
+//! are only logged (see [`crate::maintenance`]).
+
+use crate::events::{self, Event};
+use crate::maintenance;
+use crate::metrics;
+use crate::report;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Warning,
+    Critical,
+}
+
+impl Level {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Warning => "warning",
+            Level::Critical => "critical",
+        }
+    }
+}
+
+/// Raise an alert from `source` (e.g. "disk"). Sources muted during
+/// exchange maintenance are only logged.
+pub fn raise(level: Level, source: &str, message: &str) {
+    if maintenance::mutes(source) {
+        eprintln!(
+            "[maintenance] held back {} alert from {}: {}",
+            level.as_str(),
+            source,
+            message
+        );
+        metrics::counter_inc("alerts_suppressed_total", &[("source", source)], 1);
+        return;
+    }
+    eprintln!(
+        "[ALERT:{}] {}: {}",
+        level.as_str().to_uppercase(),
+        source,
+        message
+    );
+    metrics::counter_inc(
+        "alerts_total",
+        &[("level", level.as_str()), ("source", source)],
+        1,
+    );
+    events::emit(Event::Alert {
+        level: level.as_str().to_string(),
+        source: source.to_string(),
+        message: message.to_string(),
+    });
+    report::record_alert(level, source, message);
+}
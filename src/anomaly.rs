@@ -0,0 +1,190 @@
+//! Screening of incoming candles before they enter the window.
+//!
+//! A candle is suspicious when its prices are inconsistent (high below low,
+//! open or close outside the range, non-positive or non-finite values), when
+//! its close jumps more than `jump_sigma` standard deviations of the recent
+//! log returns away from the previous close (unless the previous candle was
+//! quarantined for jumping to the same level), or when it has zero volume
+//! while a `[[sessions]]` session is open. Suspicious candles are appended
+//! to the quarantine file with the reason instead of being processed, and
+//! counted in `candles_quarantined_total{symbol,reason}`.
+
+use crate::alerts::{self, Level};
+use crate::config::AnomalyConfig;
+use crate::events::{self, Event};
+use crate::metrics;
+use crate::timezone;
+use anyhow::Result;
+use binance_streamer::kline::Kline;
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+/// Fewest recent returns the jump check needs.
+const MIN_RETURNS: usize = 20;
+
+/// Why a candle was quarantined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anomaly {
+    /// Prices that cannot belong to one candle.
+    InvalidRange,
+    /// Close too far from the previous close.
+    PriceJump,
+    /// No volume while a session is open.
+    ZeroVolume,
+}
+
+impl Anomaly {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Anomaly::InvalidRange => "invalid_range",
+            Anomaly::PriceJump => "price_jump",
+            Anomaly::ZeroVolume => "zero_volume",
+        }
+    }
+}
+
+pub struct AnomalyDetector {
+    symbol: String,
+    interval: String,
+    config: AnomalyConfig,
+    quarantine_file: String,
+    /// Close of the newest accepted candle.
+    last_close: Option<f64>,
+    /// Log returns of the newest accepted candles.
+    returns: VecDeque<f64>,
+    /// Close of the candle quarantined for a jump just before.
+    jumped_to: Option<f64>,
+}
+
+impl AnomalyDetector {
+    /// Detector seeded with the closes of `history` (oldest first).
+    pub fn new(
+        symbol: &str,
+        interval: &str,
+        config: &AnomalyConfig,
+        quarantine_file: &str,
+        history: impl IntoIterator<Item = f64>,
+    ) -> Self {
+        let mut detector = Self {
+            symbol: symbol.to_string(),
+            interval: interval.to_string(),
+            config: config.clone(),
+            quarantine_file: quarantine_file.to_string(),
+            last_close: None,
+            returns: VecDeque::with_capacity(config.lookback + 1),
+            jumped_to: None,
+        };
+        for close in history {
+            detector.accept(close);
+        }
+        detector
+    }
+
+    /// Check `kline`; a suspicious one is quarantined and `false` returned.
+    pub fn screen(&mut self, kline: &Kline) -> Result<bool> {
+        let Some(anomaly) = self.check(kline) else {
+            self.accept(kline.close);
+            return Ok(true);
+        };
+        self.jumped_to = (anomaly == Anomaly::PriceJump).then_some(kline.close);
+        self.quarantine(kline, anomaly)?;
+        Ok(false)
+    }
+
+    fn check(&self, k: &Kline) -> Option<Anomaly> {
+        let prices = [k.open, k.high, k.low, k.close];
+        if prices.iter().any(|p| !p.is_finite() || *p <= 0.0)
+            || !k.volume.is_finite()
+            || k.volume < 0.0
+            || k.high < k.low
+            || prices.iter().any(|p| *p > k.high || *p < k.low)
+        {
+            return Some(Anomaly::InvalidRange);
+        }
+        if self.config.jump_sigma > 0.0 && self.returns.len() >= MIN_RETURNS {
+            if let Some(last_close) = self.last_close {
+                let n = self.returns.len() as f64;
+                let mean = self.returns.iter().sum::<f64>() / n;
+                let variance = self.returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n;
+                let limit = self.config.jump_sigma * variance.sqrt();
+                let jumps_from = |close: f64| ((k.close / close).ln() - mean).abs() > limit;
+                // Staying where the previous (quarantined) candle jumped to is
+                // a level shift, not a bad print
+                if limit > 0.0 && jumps_from(last_close) && self.jumped_to.is_none_or(jumps_from) {
+                    return Some(Anomaly::PriceJump);
+                }
+            }
+        }
+        if self.config.zero_volume && k.volume == 0.0 {
+            let open = timezone::active_sessions(k.open_time);
+            if !open.is_empty() {
+                return Some(Anomaly::ZeroVolume);
+            }
+        }
+        None
+    }
+
+    fn accept(&mut self, close: f64) {
+        self.jumped_to = None;
+        if let Some(last) = self.last_close.replace(close) {
+            self.returns.push_back((close / last).ln());
+            while self.returns.len() > self.config.lookback {
+                self.returns.pop_front();
+            }
+        }
+    }
+
+    fn quarantine(&self, k: &Kline, anomaly: Anomaly) -> Result<()> {
+        let reason = anomaly.as_str();
+        metrics::counter_inc(
+            "candles_quarantined_total",
+            &[("symbol", &self.symbol), ("reason", reason)],
+            1,
+        );
+        events::emit(Event::CandleQuarantined {
+            symbol: self.symbol.clone(),
+            interval: self.interval.clone(),
+            open_time: k.open_time,
+            reason: reason.to_string(),
+        });
+        if self.config.alert {
+            alerts::raise(
+                Level::Warning,
+                "anomaly",
+                &format!(
+                    "{}: {} candle {} quarantined ({}): O {} H {} L {} C {} V {}",
+                    self.symbol,
+                    self.interval,
+                    timezone::format_ms(k.open_time),
+                    reason,
+                    k.open,
+                    k.high,
+                    k.low,
+                    k.close,
+                    k.volume
+                ),
+            );
+        }
+        if self.quarantine_file.is_empty() {
+            return Ok(());
+        }
+        let exists = std::path::Path::new(&self.quarantine_file).exists();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.quarantine_file)?;
+        if !exists {
+            writeln!(
+                file,
+                "open_time,open,high,low,close,volume,close_time,reason"
+            )?;
+        }
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{},{}",
+            k.open_time, k.open, k.high, k.low, k.close, k.volume, k.close_time, reason
+        )?;
+        Ok(())
+    }
+}
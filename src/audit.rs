@@ -0,0 +1,64 @@
+//! Signal audit trail.
+//!
+//! Every strategy signal (a non-flat decision, or a flat one closing a
+//! position) is appended to `signal_audit.jsonl` with
+//! everything needed to reproduce and explain it later: the complete feature
+//! row it was made on, the strategy's parameters and model version, and the
+//! decision itself. One JSON object per line.
+
+use crate::clock;
+use crate::strategy::{Signal, Strategy};
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs::OpenOptions;
+use std::io::Write;
+
+#[derive(Debug, Serialize)]
+struct AuditRecord<'a> {
+    /// When the decision was made (epoch ms, see [`clock`]).
+    time: i64,
+    symbol: &'a str,
+    interval: &'a str,
+    strategy: &'a str,
+    model_version: String,
+    params: Value,
+    side: &'static str,
+    open_time: i64,
+    price: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<f64>,
+    /// The feature row of the candle the decision was made on.
+    features: &'a Map<String, Value>,
+}
+
+/// Append the decision `signal` of `strategy` on the feature row `features`
+/// to the audit file at `path`.
+pub fn record(
+    path: &str,
+    strategy: &dyn Strategy,
+    signal: &Signal,
+    features: &Map<String, Value>,
+) -> Result<()> {
+    let record = AuditRecord {
+        time: clock::now_ms(),
+        symbol: &signal.symbol,
+        interval: &signal.interval,
+        strategy: &signal.strategy,
+        model_version: strategy.version(),
+        params: strategy.params(),
+        side: signal.side.as_str(),
+        open_time: signal.open_time,
+        price: signal.price,
+        size: signal.size,
+        stop: signal.stop,
+        features,
+    };
+    let mut line = serde_json::to_vec(&record)?;
+    line.push(b'\n');
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(&line)?;
+    Ok(())
+}
@@ -0,0 +1,217 @@
+//! `backfill-features` subcommand: the point-in-time feature history of a
+//! whole candle archive.
+//!
+//! The live pipeline only ever holds its window of newest candles, so its
+//! feature history starts when the collector did. This command streams the
+//! stored raw candles – by default the ever-growing raw CSV log – through a
+//! [`FeatureBackfill`] in chunks and appends every candle's row, as the live
+//! stream would have written it at the candle's close, to the feature-history
+//! dataset.
+//!
+//! ```text
+//! cargo run --release backfill-features m15 [--input data/m15_latest_50000_raw.csv]
+//!                                           [--output data/m15_feature_history]
+//!                                           [--chunk 100000]
+//! ```
+
+use crate::config::{CliArgs, Config};
+use crate::{live_stream, timezone};
+use anyhow::{bail, Context, Result};
+use binance_streamer::data_storage;
+use binance_streamer::exchange;
+use binance_streamer::features::FeatureBackfill;
+use binance_streamer::kline::Kline;
+use binance_streamer::query::{self, TimeRange};
+use chrono::NaiveDateTime;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Lines};
+use std::time::Instant;
+
+const DEFAULT_CHUNK: usize = 100_000;
+
+/// Stored candles, read a chunk at a time.
+pub enum CandleReader {
+    /// A raw CSV log or journal, line by line.
+    Csv {
+        path: String,
+        lines: Lines<BufReader<File>>,
+    },
+    /// A candle Parquet file, one slice per chunk.
+    Parquet { path: String, offset: i64 },
+}
+
+impl CandleReader {
+    pub fn open(path: &str) -> Result<Self> {
+        if path.ends_with(".parquet") {
+            return Ok(CandleReader::Parquet {
+                path: path.to_string(),
+                offset: 0,
+            });
+        }
+        let file = File::open(path).with_context(|| format!("Failed to open {}", path))?;
+        let mut lines = BufReader::new(file).lines();
+        lines.next().transpose()?; // header
+        Ok(CandleReader::Csv {
+            path: path.to_string(),
+            lines,
+        })
+    }
+
+    /// Up to `size` further candles; empty at the end of the archive.
+    pub fn next_chunk(&mut self, size: usize) -> Result<Vec<Kline>> {
+        match self {
+            CandleReader::Parquet { path, offset } => {
+                let df = query::scan_range(path, TimeRange::all())?
+                    .slice(*offset, size as u32)
+                    .collect()?;
+                *offset += df.height() as i64;
+                Ok(data_storage::dataframe_to_klines(&df)?)
+            }
+            CandleReader::Csv { path, lines } => {
+                let mut klines = Vec::with_capacity(size);
+                while klines.len() < size {
+                    let Some(line) = lines.next().transpose()? else {
+                        break;
+                    };
+                    if line.is_empty() {
+                        continue;
+                    }
+                    match parse_csv_kline(&line) {
+                        Ok(kline) => klines.push(kline),
+                        Err(e) => eprintln!(
+                            "Warning: skipping candle line '{}' in {}: {}",
+                            line, path, e
+                        ),
+                    }
+                }
+                Ok(klines)
+            }
+        }
+    }
+}
+
+/// A line of the raw CSV log (UTC timestamps) or of a journal (epoch ms).
+fn parse_csv_kline(line: &str) -> Result<Kline> {
+    let fields: Vec<&str> = line.split(',').collect();
+    let [open_time, open, high, low, close, volume, close_time] = fields[..] else {
+        bail!("expected 7 fields");
+    };
+    Ok(Kline {
+        open_time: parse_time(open_time)?,
+        open: open.parse()?,
+        high: high.parse()?,
+        low: low.parse()?,
+        close: close.parse()?,
+        volume: volume.parse()?,
+        // The raw log rounds close times down to the second
+        close_time: match close_time.parse() {
+            Ok(ms) => ms,
+            Err(_) => parse_time(close_time)? + 999,
+        },
+    })
+}
+
+/// Epoch ms, or a raw-log timestamp like "2025-03-21 14:30:00 UTC".
+fn parse_time(value: &str) -> Result<i64> {
+    if let Ok(ms) = value.parse() {
+        return Ok(ms);
+    }
+    let time = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S UTC")
+        .with_context(|| format!("invalid timestamp '{}'", value))?;
+    Ok(time.and_utc().timestamp_millis())
+}
+
+pub fn run(cli: &CliArgs, config: &Config) -> Result<()> {
+    let stream_type = cli.positional.get(1).map_or("m15", String::as_str);
+    let interval = live_stream::stream_interval(stream_type);
+    if interval.is_empty() {
+        bail!(
+            "backfill-features expects a candle stream type (m5, m15, ...), got '{}'",
+            stream_type
+        );
+    }
+    config.check_window(interval)?;
+    let interval_ms = exchange::interval_ms(interval)?;
+    let paths = config.output_paths(stream_type, interval)?;
+    let input = cli.value("input").unwrap_or(&paths.raw_csv);
+    let output = cli.value("output").unwrap_or(&paths.feature_history);
+    let chunk: usize = match cli.value("chunk") {
+        Some(v) => v.parse().context("--chunk expects a candle count")?,
+        None => DEFAULT_CHUNK,
+    };
+    if chunk == 0 {
+        bail!("--chunk must be at least 1");
+    }
+
+    println!(
+        "Backfilling the feature history of {} {} from {} into {} ({} candles per chunk)",
+        config.symbol, interval, input, output, chunk
+    );
+    let start = Instant::now();
+    let mut reader = CandleReader::open(input)?;
+    let mut backfill = FeatureBackfill::new(config.window_size(interval));
+    let mut last_open_time = i64::MIN;
+    let (mut skipped, mut gaps) = (0, 0);
+    loop {
+        let mut klines = reader.next_chunk(chunk)?;
+        if klines.is_empty() {
+            break;
+        }
+        // Restarts may log a candle twice; rows must stay in time order
+        let before = klines.len();
+        klines.retain(|k| {
+            let newer = k.open_time > last_open_time;
+            if newer {
+                if last_open_time != i64::MIN && k.open_time - last_open_time > interval_ms {
+                    gaps += 1;
+                }
+                last_open_time = k.open_time;
+            }
+            newer
+        });
+        skipped += before - klines.len();
+        if klines.is_empty() {
+            continue;
+        }
+
+        let mut rows = backfill.push_chunk(&klines)?;
+        if config.float32_features {
+            rows = data_storage::downcast_features(&rows)?;
+        }
+        data_storage::append_feature_history_rows(&rows, output).with_context(|| {
+            format!(
+                "Failed to write {} (a dataset with other feature columns needs a fresh --output)",
+                output
+            )
+        })?;
+        println!(
+            "Backfilled {} candles up to {} ({:.0} candles/s)",
+            backfill.seen(),
+            timezone::format_ms(last_open_time),
+            backfill.seen() as f64 / start.elapsed().as_secs_f64()
+        );
+    }
+
+    if backfill.seen() == 0 {
+        bail!("{} holds no candles", input);
+    }
+    if skipped > 0 {
+        println!(
+            "Skipped {} candles not newer than the one before them",
+            skipped
+        );
+    }
+    if gaps > 0 {
+        println!(
+            "Warning: the archive has {} gaps; rows after a gap are computed as if none was missing",
+            gaps
+        );
+    }
+    println!(
+        "Wrote {} feature rows to {} in {:.1} s",
+        backfill.seen(),
+        output,
+        start.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
@@ -0,0 +1,391 @@
+//! `bench` subcommand: measure feature and persistence throughput.
+//!
+//! Runs the feature pipeline stage by stage, the pivot implementations
+//! (original scan, monotonic stack, rayon chunks) and the persistence targets
+//! over several window sizes and prints comparison tables, so regressions in
+//! the feature pipeline are measurable rather than anecdotal. Before that it
+//! checks the batch and incremental EMAs of every `[ema]` semantics against
+//! their closed forms ([`ema::reference_ema`]). Finer-grained
+//! pivot numbers come from `cargo bench --bench pivots` (criterion).
+//!
+//! ```text
+//! cargo run --release bench [--input data/m15_latest_50000.parquet]
+//!                           [--windows 1000,10000,50000] [--iterations 5]
+//! ```
+//! Without `--input` a deterministic synthetic dataset is generated (from
+//! `--seed` if given).
+
+use crate::config::CliArgs;
+use crate::data_storage;
+use crate::determinism::SeededRng;
+use crate::features::pivots::{self, PivotKind, PIVOT_WINDOW};
+use crate::features::{self, ema, EmaConfig, EmaTracker};
+use crate::kline::Kline;
+use anyhow::{bail, Context, Result};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::time::Instant;
+
+const DEFAULT_WINDOWS: &[usize] = &[1_000, 5_000, 10_000, 50_000];
+const DEFAULT_ITERATIONS: usize = 5;
+const M15_MS: i64 = 15 * 60 * 1000;
+/// Oldest candles the EMA semantics are checked on (the reference is O(n²)).
+const EMA_CHECK_CANDLES: usize = 2_000;
+/// Largest relative error of an EMA against its reference.
+const EMA_CHECK_TOLERANCE: f64 = 1e-9;
+
+/// Mean wall time of `iterations` runs of `f`, in milliseconds.
+fn time_ms<T>(iterations: usize, mut f: impl FnMut() -> Result<T>) -> Result<f64> {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f()?;
+    }
+    Ok(start.elapsed().as_secs_f64() * 1000.0 / iterations as f64)
+}
+
+/// Deterministic geometric random walk of M15 candles from `seed`.
+pub fn synthetic_klines(count: usize, seed: u64) -> Vec<Kline> {
+    let mut rng = SeededRng::new(seed);
+    let mut next_unit = move || rng.next_unit();
+
+    let start_time = 1_600_000_000_000 - (1_600_000_000_000 % M15_MS);
+    let mut price = 30_000.0;
+    (0..count)
+        .map(|i| {
+            let open = price;
+            let ret = (next_unit() - 0.5) * 0.006;
+            let close = open * (1.0 + ret);
+            let high = open.max(close) * (1.0 + next_unit() * 0.002);
+            let low = open.min(close) * (1.0 - next_unit() * 0.002);
+            price = close;
+            let open_time = start_time + i as i64 * M15_MS;
+            Kline {
+                open_time,
+                open,
+                high,
+                low,
+                close,
+                volume: 50.0 + next_unit() * 200.0,
+                close_time: open_time + M15_MS - 1,
+            }
+        })
+        .collect()
+}
+
+/// The `[ema]` semantics checked, with their labels.
+fn ema_variants() -> [(&'static str, EmaConfig); 4] {
+    let recursive = EmaConfig {
+        adjust: false,
+        ..EmaConfig::default()
+    };
+    [
+        ("adjusted", EmaConfig::default()),
+        (
+            "adjusted, min_periods 1",
+            EmaConfig {
+                min_periods: Some(1),
+                ..EmaConfig::default()
+            },
+        ),
+        ("recursive, first seed", recursive),
+        (
+            "recursive, sma seed",
+            EmaConfig {
+                seed: ema::EmaSeed::Sma,
+                ..recursive
+            },
+        ),
+    ]
+}
+
+/// Largest relative error of `values` against `reference`; `None` if one is
+/// null where the other is not.
+fn max_error(values: &[Option<f64>], reference: &[Option<f64>]) -> Option<f64> {
+    let mut worst = 0.0f64;
+    for (value, expected) in values.iter().zip(reference) {
+        match (value, expected) {
+            (Some(a), Some(b)) => worst = worst.max((a - b).abs() / b.abs().max(f64::EPSILON)),
+            (None, None) => {}
+            _ => return None,
+        }
+    }
+    Some(worst)
+}
+
+/// Check the stream-interval EMAs of the batch frame and of an
+/// [`EmaTracker`] replay over `klines` against [`ema::reference_ema`] under
+/// every variant, and the newest higher-timeframe values of both against each
+/// other. Leaves the semantics at the last variant.
+fn check_ema_semantics(klines: &[Kline]) -> Result<Vec<String>> {
+    let closes: Vec<f64> = klines.iter().map(|k| k.close).collect();
+    let mut rows = Vec::new();
+    for (label, semantics) in ema_variants() {
+        ema::set_semantics(semantics);
+        let batch = ema::add_ema_features(features::base_frame(klines)?)?;
+        let tracker = EmaTracker::replay(klines, klines.len());
+        let incremental = tracker.columns(klines.len());
+        let mut errors = Vec::new();
+        for (c, span) in [(0, 50), (1, 200)] {
+            let reference = ema::reference_ema(&closes, span, &semantics);
+            for column in [batch.column(ema::EMA_COLUMNS[c])?, &incremental[c]] {
+                let values: Vec<Option<f64>> = column.f64()?.into_iter().collect();
+                match max_error(&values, &reference) {
+                    Some(error) if error <= EMA_CHECK_TOLERANCE => errors.push(error),
+                    Some(error) => bail!(
+                        "{} EMA{} deviates from its reference by {:.3e} ({})",
+                        ema::EMA_COLUMNS[c],
+                        span,
+                        error,
+                        label
+                    ),
+                    None => bail!(
+                        "{} starts on a different candle than its reference ({})",
+                        ema::EMA_COLUMNS[c],
+                        label
+                    ),
+                }
+            }
+        }
+        let newest = tracker.last().unwrap_or_default();
+        let Some(diff) = ema::max_relative_diff(&newest, &batch)? else {
+            bail!("Batch and incremental EMAs disagree on nulls ({})", label);
+        };
+        if diff > EMA_CHECK_TOLERANCE {
+            bail!(
+                "Batch and incremental EMAs differ by {:.3e} ({})",
+                diff,
+                label
+            );
+        }
+        rows.push(format!(
+            "{:>24} | {:>14.3e} | {:>14.3e}",
+            label,
+            errors.iter().cloned().fold(0.0, f64::max),
+            diff
+        ));
+    }
+    Ok(rows)
+}
+
+fn file_mb(path: &Path) -> f64 {
+    std::fs::metadata(path)
+        .map(|m| m.len() as f64 / (1024.0 * 1024.0))
+        .unwrap_or(0.0)
+}
+
+pub fn run(cli: &CliArgs, seed: u64) -> Result<()> {
+    match bench(cli, seed, &mut std::io::stdout().lock()) {
+        // The reader went away (`bench | head`): it has all it wanted
+        Err(e)
+            if e.downcast_ref::<std::io::Error>()
+                .is_some_and(|e| e.kind() == ErrorKind::BrokenPipe) =>
+        {
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+fn bench(cli: &CliArgs, seed: u64, out: &mut impl Write) -> Result<()> {
+    let iterations = match cli.value("iterations") {
+        Some(v) => v
+            .parse()
+            .context("--iterations must be a positive integer")?,
+        None => DEFAULT_ITERATIONS,
+    };
+    if iterations == 0 {
+        bail!("--iterations must be a positive integer");
+    }
+    let mut windows: Vec<usize> = match cli.value("windows") {
+        Some(v) => v
+            .split(',')
+            .map(|w| w.trim().parse())
+            .collect::<Result<_, _>>()
+            .context("--windows must be a comma-separated list of sizes")?,
+        None => DEFAULT_WINDOWS.to_vec(),
+    };
+    windows.sort_unstable();
+
+    let dataset = match cli.value("input") {
+        Some(path) => {
+            writeln!(out, "Loading dataset from {}", path)?;
+            data_storage::load_klines_from_parquet(path)?
+        }
+        None => {
+            let count = windows.last().copied().unwrap_or(0);
+            writeln!(out, "Generating synthetic dataset of {} M15 candles", count)?;
+            synthetic_klines(count, seed)
+        }
+    };
+    windows.retain(|&w| w > 0 && w <= dataset.len());
+    if windows.is_empty() {
+        bail!(
+            "No window size fits the dataset ({} candles)",
+            dataset.len()
+        );
+    }
+
+    // --- EMA semantics against their closed forms ---
+    let configured = ema::semantics();
+    let ema_rows = check_ema_semantics(&dataset[..dataset.len().min(EMA_CHECK_CANDLES)]);
+    ema::set_semantics(configured);
+    let ema_rows = ema_rows?;
+
+    let out_dir =
+        std::env::temp_dir().join(format!("binance_streamer_bench_{}", std::process::id()));
+    writeln!(
+        out,
+        "{} iteration(s) per measurement, scratch files in {}\n",
+        iterations,
+        out_dir.display()
+    )?;
+    std::fs::create_dir_all(&out_dir)?;
+
+    let mut feature_rows = Vec::new();
+    let mut pivot_rows = Vec::new();
+    let mut persistence_rows = Vec::new();
+
+    for &window in &windows {
+        let klines = &dataset[dataset.len() - window..];
+
+        // --- Feature stages ---
+        let base = features::base_frame(klines)?;
+        let with_ema = ema::add_ema_features(base.clone())?;
+        let base_ms = time_ms(iterations, || features::base_frame(klines))?;
+        let ema_ms = time_ms(iterations, || ema::add_ema_features(base.clone()))?;
+        let pivots_ms = time_ms(iterations, || pivots::add_pivot_features(with_ema.clone()))?;
+        let total_ms = time_ms(iterations, || features::compute_features(klines))?;
+        feature_rows.push(format!(
+            "{:>8} | {:>9.2} | {:>9.2} | {:>10.2} | {:>9.2} | {:>12.0}",
+            window,
+            base_ms,
+            ema_ms,
+            pivots_ms,
+            total_ms,
+            window as f64 / (total_ms / 1000.0)
+        ));
+
+        // --- Pivot implementations (on highs) ---
+        let highs: Vec<f64> = klines.iter().map(|k| k.high).collect();
+        let naive = pivots::strengths_naive(&highs, PivotKind::High, PIVOT_WINDOW);
+        if pivots::strengths(&highs, PivotKind::High, PIVOT_WINDOW) != naive
+            || pivots::strengths_par(&highs, PivotKind::High, PIVOT_WINDOW) != naive
+        {
+            bail!(
+                "Pivot implementations disagree on the {}-candle window",
+                window
+            );
+        }
+        let naive_ms = time_ms(iterations, || {
+            Ok(pivots::strengths_naive(
+                &highs,
+                PivotKind::High,
+                PIVOT_WINDOW,
+            ))
+        })?;
+        let stack_ms = time_ms(iterations, || {
+            Ok(pivots::strengths(&highs, PivotKind::High, PIVOT_WINDOW))
+        })?;
+        let par_ms = time_ms(iterations, || {
+            Ok(pivots::strengths_par(&highs, PivotKind::High, PIVOT_WINDOW))
+        })?;
+        pivot_rows.push(format!(
+            "{:>8} | {:>9.3} | {:>9.3} | {:>9.3} | {:>8.1}x",
+            window,
+            naive_ms,
+            stack_ms,
+            par_ms,
+            naive_ms / stack_ms.min(par_ms)
+        ));
+
+        // --- Persistence targets ---
+        let mut df = features::compute_features(klines)?;
+        let feature_parquet = out_dir.join("features.parquet");
+        let raw_parquet = out_dir.join("raw.parquet");
+        let feature_csv = out_dir.join("features.csv");
+        let append_csv = out_dir.join("append.csv");
+        let fp = feature_parquet.to_string_lossy().to_string();
+        let rp = raw_parquet.to_string_lossy().to_string();
+        let fc = feature_csv.to_string_lossy().to_string();
+        let ac = append_csv.to_string_lossy().to_string();
+
+        let fp_ms = time_ms(iterations, || {
+            Ok(data_storage::save_dataframe_parquet(&mut df, &fp)?)
+        })?;
+        let rp_ms = time_ms(iterations, || {
+            Ok(data_storage::save_klines_to_parquet(klines, &rp)?)
+        })?;
+        let fc_ms = time_ms(iterations, || {
+            Ok(data_storage::save_dataframe_csv_to_path(&df, &fc)?)
+        })?;
+        let ac_ms = time_ms(iterations, || {
+            Ok(data_storage::append_features_row_to_csv(&df, &ac)?)
+        })?;
+        let mbps = |mb: f64, ms: f64| if ms > 0.0 { mb / (ms / 1000.0) } else { 0.0 };
+        persistence_rows.push(format!(
+            "{:>8} | {:>9.2} ({:>6.1} MB/s) | {:>9.2} ({:>6.1} MB/s) | {:>9.2} ({:>6.1} MB/s) | {:>12.3}",
+            window,
+            fp_ms,
+            mbps(file_mb(&feature_parquet), fp_ms),
+            rp_ms,
+            mbps(file_mb(&raw_parquet), rp_ms),
+            fc_ms,
+            mbps(file_mb(&feature_csv), fc_ms),
+            ac_ms
+        ));
+    }
+
+    if let Err(e) = std::fs::remove_dir_all(&out_dir) {
+        eprintln!("Warning: failed to remove {}: {}", out_dir.display(), e);
+    }
+
+    writeln!(
+        out,
+        "=== EMA semantics on {} candles (max relative error) ===",
+        dataset.len().min(EMA_CHECK_CANDLES)
+    )?;
+    writeln!(
+        out,
+        "{:>24} | {:>14} | {:>14}",
+        "semantics", "vs reference", "batch vs live"
+    )?;
+    for row in &ema_rows {
+        writeln!(out, "{}", row)?;
+    }
+
+    writeln!(out, "\n=== Feature computation (mean ms per call) ===")?;
+    writeln!(
+        out,
+        "{:>8} | {:>9} | {:>9} | {:>10} | {:>9} | {:>12}",
+        "window", "base", "ema", "pivots", "total", "rows/s"
+    )?;
+    for row in &feature_rows {
+        writeln!(out, "{}", row)?;
+    }
+
+    writeln!(
+        out,
+        "\n=== Pivot strengths on highs (mean ms per call, {} rayon threads) ===",
+        rayon::current_num_threads()
+    )?;
+    writeln!(
+        out,
+        "{:>8} | {:>9} | {:>9} | {:>9} | {:>9}",
+        "window", "naive", "stack", "parallel", "speedup"
+    )?;
+    for row in &pivot_rows {
+        writeln!(out, "{}", row)?;
+    }
+
+    writeln!(out, "\n=== Persistence (mean ms per call) ===")?;
+    writeln!(
+        out,
+        "{:>8} | {:>23} | {:>23} | {:>23} | {:>12}",
+        "window", "feature parquet", "raw parquet", "feature csv (full)", "csv row append"
+    )?;
+    for row in &persistence_rows {
+        writeln!(out, "{}", row)?;
+    }
+
+    Ok(())
+}
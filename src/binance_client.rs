@@ -1,19 +1,15 @@
+use crate::error::{self, Error, Result};
 use crate::kline::Kline;
-use anyhow::Result;
-use chrono::{DateTime, Utc};
+use crate::progress::FetchProgress;
+use crate::timezone;
+use chrono::Utc;
 use reqwest::Client;
+use serde::Deserialize;
 use std::time::Duration;
 
-/// Convert milliseconds to human-readable UTC time.
-fn format_time(ms: i64) -> String {
-    DateTime::<Utc>::from_timestamp_millis(ms)
-        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S%.3f UTC").to_string())
-        .unwrap_or_else(|| format!("Invalid timestamp {}", ms))
-}
-
 /// Fetch historical klines between start_time and end_time (milliseconds).
 /// Automatically paginates with 1000 candles per request.
-/// Logs progress to stdout.
+/// Logs throttled progress to stdout (see [`crate::progress`]).
 pub async fn fetch_klines_range(
     symbol: &str,
     interval: &str,
@@ -24,28 +20,26 @@ pub async fn fetch_klines_range(
         "Fetching {} {} klines from {} to {}",
         symbol,
         interval,
-        format_time(start_time),
-        format_time(end_time)
+        timezone::format_ms(start_time),
+        timezone::format_ms(end_time)
     );
 
     let client = Client::new();
     let mut all = Vec::new();
     let limit = 1000;
     let mut current_start = start_time;
-    let mut batch_num = 0;
+    let mut progress = FetchProgress::new("binance", symbol, interval, start_time, end_time);
 
     loop {
-        batch_num += 1;
         let url = format!(
             "https://api.binance.com/api/v3/klines?symbol={}&interval={}&startTime={}&endTime={}&limit={}",
             symbol, interval, current_start, end_time, limit
         );
 
-        let response = client.get(&url).send().await?;
+        let response = error::check_response(client.get(&url).send().await?).await?;
         let klines: Vec<Kline> = response.json().await?;
 
         if klines.is_empty() {
-            println!("No more klines returned, stopping.");
             break;
         }
 
@@ -54,26 +48,16 @@ pub async fn fetch_klines_range(
         let last = klines.last().unwrap().clone();
 
         all.extend(klines);
-        let total = all.len();
-
-        println!(
-            "Batch {}: fetched {} klines (total so far: {})",
-            batch_num, batch_len, total
-        );
+        progress.advance(batch_len, last.close_time);
 
         // If we got fewer than the limit, this is the last batch
         if batch_len < limit {
-            println!(
-                "Received less than limit ({} < {}), assuming end of data.",
-                batch_len, limit
-            );
             break;
         }
 
         // Next batch starts after the last candle's close time
         current_start = last.close_time + 1;
         if current_start >= end_time {
-            println!("Reached end time, stopping.");
             break;
         }
 
@@ -81,10 +65,28 @@ pub async fn fetch_klines_range(
         tokio::time::sleep(Duration::from_millis(200)).await;
     }
 
-    println!("Fetched total {} klines.", all.len());
+    progress.finish();
     Ok(all)
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Ticker24h {
+    symbol: String,
+    quote_volume: String,
+}
+
+/// 24h quote volume of every spot pair, e.g. `("BTCUSDT", 1.2e9)` in USDT.
+pub async fn fetch_quote_volumes() -> Result<Vec<(String, f64)>> {
+    let url = "https://api.binance.com/api/v3/ticker/24hr";
+    let response = error::check_response(Client::new().get(url).send().await?).await?;
+    let tickers: Vec<Ticker24h> = response.json().await?;
+    tickers
+        .into_iter()
+        .map(|t| Ok((t.symbol, t.quote_volume.parse()?)))
+        .collect()
+}
+
 /// Fetch the latest `count` candles for the given interval.
 /// Uses an approximate start time based on interval duration.
 pub async fn fetch_latest_klines(symbol: &str, interval: &str, count: usize) -> Result<Vec<Kline>> {
@@ -92,7 +94,7 @@ pub async fn fetch_latest_klines(symbol: &str, interval: &str, count: usize) ->
         "15m" => 15 * 60 * 1000,
         "5m" => 5 * 60 * 1000,
         "1h" => 60 * 60 * 1000,
-        _ => anyhow::bail!("Unsupported interval: {}", interval),
+        _ => return Err(Error::Config(format!("Unsupported interval: {}", interval))),
     };
 
     let now = Utc::now().timestamp_millis();
@@ -102,8 +104,8 @@ pub async fn fetch_latest_klines(symbol: &str, interval: &str, count: usize) ->
         "Fetching latest {} {} klines (approx. from {} to {})",
         count,
         interval,
-        format_time(start_time),
-        format_time(now)
+        timezone::format_ms(start_time),
+        timezone::format_ms(now)
     );
 
     let mut klines = fetch_klines_range(symbol, interval, start_time, now).await?;
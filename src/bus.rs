@@ -0,0 +1,60 @@
+//! In-process fan-out of pipeline output.
+//!
+//! The candle pipeline publishes every closed candle, its feature row and any
+//! strategy signal here; network sinks (WebSocket feed, gRPC streams) each
+//! subscribe and translate updates into their own wire format. A subscriber
+//! that falls more than `CAPACITY` updates behind skips the ones it missed,
+//! so a slow client never blocks the pipeline.
+
+use crate::kline::Kline;
+use crate::strategy::Signal;
+use serde_json::{Map, Value};
+use std::sync::OnceLock;
+use tokio::sync::broadcast;
+
+const CAPACITY: usize = 1024;
+
+// Without the `grpc` feature some fields are never read
+#[cfg_attr(not(feature = "grpc"), allow(dead_code))]
+#[derive(Debug, Clone)]
+pub enum Update {
+    Candle {
+        symbol: String,
+        interval: String,
+        kline: Kline,
+    },
+    Features {
+        symbol: String,
+        interval: String,
+        open_time: i64,
+        /// Column name → value, as produced by `data_storage::row_to_json`.
+        values: Map<String, Value>,
+    },
+    Signal(Signal),
+}
+
+#[cfg_attr(not(feature = "grpc"), allow(dead_code))]
+impl Update {
+    pub fn symbol(&self) -> &str {
+        match self {
+            Update::Candle { symbol, .. } | Update::Features { symbol, .. } => symbol,
+            Update::Signal(signal) => &signal.symbol,
+        }
+    }
+}
+
+fn channel() -> &'static broadcast::Sender<Update> {
+    static CHANNEL: OnceLock<broadcast::Sender<Update>> = OnceLock::new();
+    CHANNEL.get_or_init(|| broadcast::channel(CAPACITY).0)
+}
+
+/// Send an update to every subscriber (no-op without subscribers).
+pub fn publish(update: Update) {
+    // send only fails when nobody is subscribed
+    let _ = channel().send(update);
+}
+
+/// Receive every update published from now on.
+pub fn subscribe() -> broadcast::Receiver<Update> {
+    channel().subscribe()
+}
@@ -0,0 +1,204 @@
+//! REST fetchers for historical candles of Bybit linear (USDT/USDC
+//! margined) perpetuals.
+//!
+//! Same interface as [`crate::binance_client`]: Binance-style symbols and
+//! intervals in, [`Kline`]s (oldest first, `close_time = open_time +
+//! interval - 1`) out. Uses the public v5 market endpoint, which needs no API
+//! key and returns at most 1000 candles per request, newest first.
+
+use crate::error::{self, Error, Result};
+use crate::kline::Kline;
+use crate::progress::FetchProgress;
+use crate::timezone;
+use chrono::Utc;
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::Duration;
+
+const API: &str = "https://api.bybit.com/v5/market/kline";
+
+/// Most candles returned by one request.
+const PAGE: i64 = 1000;
+
+/// Bybit symbol of a pair: "BTC-USDT" or "btc/usdt" → "BTCUSDT". Bybit
+/// names perpetuals like Binance names spot pairs.
+pub fn symbol_id(symbol: &str) -> String {
+    symbol
+        .chars()
+        .filter(|c| !matches!(c, '-' | '/' | '_'))
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// Bybit interval code and length (ms) of a Binance interval.
+pub fn interval_code(interval: &str) -> Result<(&'static str, i64)> {
+    Ok(match interval {
+        "1m" => ("1", 60_000),
+        "3m" => ("3", 3 * 60_000),
+        "5m" => ("5", 5 * 60_000),
+        "15m" => ("15", 15 * 60_000),
+        "30m" => ("30", 30 * 60_000),
+        "1h" => ("60", 60 * 60_000),
+        "2h" => ("120", 2 * 60 * 60_000),
+        "4h" => ("240", 4 * 60 * 60_000),
+        "6h" => ("360", 6 * 60 * 60_000),
+        "12h" => ("720", 12 * 60 * 60_000),
+        "1d" => ("D", 24 * 60 * 60_000),
+        "1w" => ("W", 7 * 24 * 60 * 60_000),
+        _ => {
+            return Err(Error::Config(format!(
+                "Unsupported Bybit interval: {}",
+                interval
+            )))
+        }
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KlineResponse {
+    ret_code: i64,
+    ret_msg: String,
+    result: Option<KlineResult>,
+}
+
+#[derive(Deserialize)]
+struct KlineResult {
+    /// `[startTime, open, high, low, close, volume, turnover]`, all strings.
+    list: Vec<Vec<String>>,
+}
+
+fn to_kline(row: &[String], interval_ms: i64) -> Result<Kline> {
+    let [start, open, high, low, close, volume, ..] = row else {
+        return Err(Error::Parse(format!("Malformed Bybit candle: {:?}", row)));
+    };
+    let open_time = start.parse::<i64>()?;
+    Ok(Kline {
+        open_time,
+        open: open.parse()?,
+        high: high.parse()?,
+        low: low.parse()?,
+        close: close.parse()?,
+        volume: volume.parse()?,
+        close_time: open_time + interval_ms - 1,
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TickerResponse {
+    ret_code: i64,
+    ret_msg: String,
+    result: Option<TickerResult>,
+}
+
+#[derive(Deserialize)]
+struct TickerResult {
+    list: Vec<Ticker>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Ticker {
+    symbol: String,
+    /// Quote-currency volume of the last 24 hours.
+    turnover24h: String,
+}
+
+/// 24h quote volume (turnover) of every linear perpetual, e.g.
+/// `("BTCUSDT", 5.1e9)` in USDT.
+pub async fn fetch_quote_volumes() -> Result<Vec<(String, f64)>> {
+    let url = "https://api.bybit.com/v5/market/tickers?category=linear";
+    let response: TickerResponse = error::check_response(Client::new().get(url).send().await?)
+        .await?
+        .json()
+        .await?;
+    if response.ret_code != 0 {
+        return Err(Error::Exchange {
+            code: response.ret_code,
+            msg: response.ret_msg,
+        });
+    }
+    response
+        .result
+        .map(|r| r.list)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|t| Ok((t.symbol, t.turnover24h.parse()?)))
+        .collect()
+}
+
+/// Fetch historical candles between start_time and end_time (milliseconds).
+/// Automatically paginates with 1000 candles per request.
+pub async fn fetch_klines_range(
+    symbol: &str,
+    interval: &str,
+    start_time: i64,
+    end_time: i64,
+) -> Result<Vec<Kline>> {
+    let symbol = symbol_id(symbol);
+    let (code, interval_ms) = interval_code(interval)?;
+    println!(
+        "Fetching {} {} candles from Bybit, {} to {}",
+        symbol,
+        interval,
+        timezone::format_ms(start_time),
+        timezone::format_ms(end_time)
+    );
+
+    let client = Client::new();
+    let mut all = Vec::new();
+    let mut current_start = start_time - start_time.rem_euclid(interval_ms);
+    let mut progress = FetchProgress::new("bybit", &symbol, interval, start_time, end_time);
+
+    while current_start < end_time {
+        // Both bounds are inclusive
+        let batch_end = (current_start + PAGE * interval_ms - 1).min(end_time);
+        let url = format!(
+            "{}?category=linear&symbol={}&interval={}&start={}&end={}&limit={}",
+            API, symbol, code, current_start, batch_end, PAGE
+        );
+        let response: KlineResponse = error::check_response(client.get(&url).send().await?)
+            .await?
+            .json()
+            .await?;
+        if response.ret_code != 0 {
+            return Err(Error::Exchange {
+                code: response.ret_code,
+                msg: response.ret_msg,
+            });
+        }
+        let rows = response.result.map(|r| r.list).unwrap_or_default();
+
+        let mut klines = rows
+            .iter()
+            .map(|row| to_kline(row, interval_ms))
+            .collect::<Result<Vec<_>>>()?;
+        klines.retain(|k| k.open_time >= start_time && k.open_time <= end_time);
+        klines.sort_by_key(|k| k.open_time);
+        progress.advance(klines.len(), batch_end);
+        all.extend(klines);
+
+        current_start = batch_end + 1;
+        // Polite delay to avoid rate limits
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    all.dedup_by_key(|k| k.open_time);
+    progress.finish();
+    Ok(all)
+}
+
+/// Fetch the latest `count` candles for the given interval.
+pub async fn fetch_latest_klines(symbol: &str, interval: &str, count: usize) -> Result<Vec<Kline>> {
+    let (_, interval_ms) = interval_code(interval)?;
+    let now = Utc::now().timestamp_millis();
+    let start_time = now - (count as i64 * interval_ms);
+
+    let mut klines = fetch_klines_range(symbol, interval, start_time, now).await?;
+    if klines.len() > count {
+        klines = klines.split_off(klines.len() - count);
+    }
+    println!("Successfully fetched {} candles.", klines.len());
+    Ok(klines)
+}
@@ -0,0 +1,113 @@
+//! The scheduled-event calendar every pipeline reads its calendar columns and
+//! signal suppression from.
+//!
+//! `[calendar]` names a local file or a URL (JSON or CSV, see
+//! [`binance_streamer::features::calendar`]). It is loaded once at startup –
+//! a calendar that cannot be loaded stops the collector, since the feature
+//! columns depend on it – and reloaded every `refresh_mins`; a failed reload
+//! raises an alert and keeps the events loaded before.
+
+use crate::alerts::{self, Level};
+use crate::clock;
+use crate::config::CalendarConfig;
+use crate::metrics;
+use anyhow::{Context, Result};
+use binance_streamer::features::calendar::{self, ScheduledEvent};
+use binance_streamer::features::EventCalendar;
+use polars::prelude::{DataFrame, PolarsResult};
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+fn shared() -> &'static RwLock<Option<EventCalendar>> {
+    static CALENDAR: OnceLock<RwLock<Option<EventCalendar>>> = OnceLock::new();
+    CALENDAR.get_or_init(|| RwLock::new(None))
+}
+
+/// Add the calendar columns to a feature frame, if a calendar is loaded.
+pub fn add_columns(df: DataFrame) -> PolarsResult<DataFrame> {
+    match shared().read().unwrap().as_ref() {
+        Some(calendar) => calendar.add_columns(df),
+        None => Ok(df),
+    }
+}
+
+/// The event whose window contains `time_ms`, if a calendar is loaded.
+pub fn active_event(time_ms: i64) -> Option<ScheduledEvent> {
+    shared()
+        .read()
+        .unwrap()
+        .as_ref()
+        .and_then(|calendar| calendar.active_event(time_ms).cloned())
+}
+
+async fn fetch(config: &CalendarConfig) -> Result<Vec<ScheduledEvent>> {
+    let text = match (&config.file, &config.url) {
+        (Some(path), _) => tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read calendar {}", path))?,
+        (None, Some(url)) => reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()?
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+            .with_context(|| format!("Failed to fetch calendar {}", url))?,
+        (None, None) => return Ok(Vec::new()),
+    };
+    calendar::parse_events(&text)
+}
+
+/// Load the calendar of `config`, if one is configured, and keep it fresh.
+pub async fn spawn(config: &CalendarConfig) -> Result<()> {
+    let Some(source) = config.file.clone().or_else(|| config.url.clone()) else {
+        return Ok(());
+    };
+    let events = fetch(config)
+        .await
+        .with_context(|| format!("Failed to load the event calendar {}", source))?;
+    let upcoming = events.iter().filter(|e| e.time >= clock::now_ms()).count();
+    println!(
+        "Loaded {} scheduled events ({} upcoming) from {}",
+        events.len(),
+        upcoming,
+        source
+    );
+    metrics::gauge_set("calendar_events", &[], events.len() as f64);
+    *shared().write().unwrap() = Some(EventCalendar::new(
+        events,
+        config.before_mins,
+        config.after_mins,
+    ));
+
+    if config.refresh_mins == 0 {
+        return Ok(());
+    }
+    let config = config.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(config.refresh_mins * 60));
+        ticker.tick().await; // first tick fires immediately
+        loop {
+            ticker.tick().await;
+            match fetch(&config).await {
+                Ok(events) => {
+                    metrics::gauge_set("calendar_events", &[], events.len() as f64);
+                    if let Some(calendar) = shared().write().unwrap().as_mut() {
+                        calendar.set_events(events);
+                    }
+                }
+                Err(e) => alerts::raise(
+                    Level::Warning,
+                    "calendar",
+                    &format!(
+                        "failed to reload the event calendar {}, keeping the loaded events: {:#}",
+                        source, e
+                    ),
+                ),
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,34 @@
+//! Wall clock used for the timestamps the process writes.
+//!
+//! Normally this is `Utc::now()`. In deterministic mode the clock is pinned
+//! and only moves when the pipeline advances it to the close time of the
+//! candle it processes, so event timestamps, heartbeats and snapshot timing
+//! depend on the data alone.
+
+use chrono::Utc;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+
+static PINNED: AtomicBool = AtomicBool::new(false);
+static NOW_MS: AtomicI64 = AtomicI64::new(0);
+
+/// Stop following the system clock; [`now_ms`] returns `ms` until advanced.
+pub fn pin(ms: i64) {
+    NOW_MS.store(ms, Ordering::SeqCst);
+    PINNED.store(true, Ordering::SeqCst);
+}
+
+/// Move a pinned clock forward to `ms` (never backwards); no-op otherwise.
+pub fn advance(ms: i64) {
+    if PINNED.load(Ordering::SeqCst) {
+        NOW_MS.fetch_max(ms, Ordering::SeqCst);
+    }
+}
+
+/// Current time in epoch milliseconds.
+pub fn now_ms() -> i64 {
+    if PINNED.load(Ordering::SeqCst) {
+        NOW_MS.load(Ordering::SeqCst)
+    } else {
+        Utc::now().timestamp_millis()
+    }
+}
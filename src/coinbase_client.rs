@@ -0,0 +1,164 @@
+//! REST fetchers for historical candles from Coinbase Advanced Trade.
+//!
+//! Same interface as [`crate::binance_client`]: Binance-style symbols and
+//! intervals in, [`Kline`]s (oldest first, `close_time = open_time +
+//! interval - 1`) out. Uses the public market endpoint, which needs no API
+//! key and returns at most 350 candles per request, newest first.
+
+use crate::error::{self, Error, Result};
+use crate::kline::Kline;
+use crate::progress::FetchProgress;
+use crate::timezone;
+use chrono::Utc;
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::Duration;
+
+const API: &str = "https://api.coinbase.com/api/v3/brokerage/market/products";
+
+/// Most candles returned by one request.
+const PAGE: i64 = 350;
+
+/// Quote currencies recognised when splitting a Binance-style symbol.
+const QUOTES: [&str; 8] = ["USDT", "USDC", "USD", "EUR", "GBP", "BTC", "ETH", "DAI"];
+
+/// Coinbase product id of a symbol: "BTCUSD" → "BTC-USD". Ids that already
+/// contain a dash are kept as they are.
+pub fn product_id(symbol: &str) -> Result<String> {
+    let symbol = symbol.to_uppercase();
+    if symbol.contains('-') {
+        return Ok(symbol);
+    }
+    QUOTES
+        .iter()
+        .find_map(|quote| {
+            symbol
+                .strip_suffix(quote)
+                .filter(|base| !base.is_empty())
+                .map(|base| format!("{}-{}", base, quote))
+        })
+        .ok_or_else(|| Error::Config(format!("Cannot map {} to a Coinbase product id", symbol)))
+}
+
+/// Coinbase granularity name and length (ms) of a Binance interval.
+pub fn granularity(interval: &str) -> Result<(&'static str, i64)> {
+    Ok(match interval {
+        "1m" => ("ONE_MINUTE", 60_000),
+        "5m" => ("FIVE_MINUTE", 5 * 60_000),
+        "15m" => ("FIFTEEN_MINUTE", 15 * 60_000),
+        "30m" => ("THIRTY_MINUTE", 30 * 60_000),
+        "1h" => ("ONE_HOUR", 60 * 60_000),
+        "2h" => ("TWO_HOUR", 2 * 60 * 60_000),
+        "6h" => ("SIX_HOUR", 6 * 60 * 60_000),
+        "1d" => ("ONE_DAY", 24 * 60 * 60_000),
+        _ => {
+            return Err(Error::Config(format!(
+                "Unsupported Coinbase interval: {}",
+                interval
+            )))
+        }
+    })
+}
+
+#[derive(Deserialize)]
+struct CandlesResponse {
+    candles: Vec<Candle>,
+}
+
+/// One candle as returned by the API (every field a string, `start` in seconds).
+#[derive(Deserialize)]
+struct Candle {
+    start: String,
+    low: String,
+    high: String,
+    open: String,
+    close: String,
+    volume: String,
+}
+
+impl Candle {
+    fn to_kline(&self, interval_ms: i64) -> Result<Kline> {
+        let open_time = self.start.parse::<i64>()? * 1000;
+        Ok(Kline {
+            open_time,
+            open: self.open.parse()?,
+            high: self.high.parse()?,
+            low: self.low.parse()?,
+            close: self.close.parse()?,
+            volume: self.volume.parse()?,
+            close_time: open_time + interval_ms - 1,
+        })
+    }
+}
+
+/// Fetch historical candles between start_time and end_time (milliseconds).
+/// Automatically paginates with 350 candles per request.
+/// Intervals without trades are not returned by Coinbase and stay missing.
+pub async fn fetch_klines_range(
+    symbol: &str,
+    interval: &str,
+    start_time: i64,
+    end_time: i64,
+) -> Result<Vec<Kline>> {
+    let product = product_id(symbol)?;
+    let (granularity, interval_ms) = granularity(interval)?;
+    println!(
+        "Fetching {} {} candles from Coinbase, {} to {}",
+        product,
+        interval,
+        timezone::format_ms(start_time),
+        timezone::format_ms(end_time)
+    );
+
+    let client = Client::new();
+    let mut all = Vec::new();
+    let mut current_start = start_time - start_time.rem_euclid(interval_ms);
+    let mut progress = FetchProgress::new("coinbase", symbol, interval, start_time, end_time);
+
+    while current_start < end_time {
+        // `end` is exclusive in whole seconds
+        let batch_end = (current_start + PAGE * interval_ms).min(end_time);
+        let url = format!(
+            "{}/{}/candles?start={}&end={}&granularity={}",
+            API,
+            product,
+            current_start / 1000,
+            batch_end / 1000,
+            granularity
+        );
+        let response = error::check_response(client.get(&url).send().await?).await?;
+        let batch: CandlesResponse = response.json().await?;
+
+        let mut klines = batch
+            .candles
+            .iter()
+            .map(|c| c.to_kline(interval_ms))
+            .collect::<Result<Vec<_>>>()?;
+        klines.retain(|k| k.open_time >= start_time && k.open_time <= end_time);
+        klines.sort_by_key(|k| k.open_time);
+        progress.advance(klines.len(), batch_end);
+        all.extend(klines);
+
+        current_start = batch_end;
+        // Polite delay to avoid rate limits
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    all.dedup_by_key(|k| k.open_time);
+    progress.finish();
+    Ok(all)
+}
+
+/// Fetch the latest `count` candles for the given interval.
+pub async fn fetch_latest_klines(symbol: &str, interval: &str, count: usize) -> Result<Vec<Kline>> {
+    let (_, interval_ms) = granularity(interval)?;
+    let now = Utc::now().timestamp_millis();
+    let start_time = now - (count as i64 * interval_ms);
+
+    let mut klines = fetch_klines_range(symbol, interval, start_time, now).await?;
+    if klines.len() > count {
+        klines = klines.split_off(klines.len() - count);
+    }
+    println!("Successfully fetched {} candles.", klines.len());
+    Ok(klines)
+}
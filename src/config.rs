@@ -0,0 +1,2162 @@
+//! Runtime configuration: optional TOML file plus command-line overrides.
+//!
+//! Precedence is defaults < `--config <file>` (or the configuration recorded
+//! in a `--manifest`) < `--params <file>` for the strategies < individual
+//! CLI flags.
+
+use crate::determinism::DEFAULT_SEED;
+use crate::exchange::{self, Exchange};
+use crate::manifest::RunManifest;
+use anyhow::{bail, Context, Result};
+use binance_streamer::data_storage::DecimalFormat;
+use binance_streamer::features::{EmaConfig, FeatureGroup, QualityConfig, RegimeConfig};
+use binance_streamer::query::{DataLayout, DEFAULT_PATH_TEMPLATE};
+use binance_streamer::strategy::{self, Strategy};
+use binance_streamer::tiers::{ColdStore, TieredStorage};
+use binance_streamer::timezone::{self, Session, SessionConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+/// File name of a `--record` session recording.
+pub const RECORDING_FILE: &str = "session.jsonl.zst";
+
+/// Flags that take a value (`--flag value` or `--flag=value`).
+/// Any other `--flag` is treated as a boolean switch.
+const VALUE_FLAGS: &[&str] = &[
+    "config",
+    "data-dir",
+    "symbol",
+    "symbols",
+    "path-template",
+    "grpc-addr",
+    "http-addr",
+    "metrics-addr",
+    "ws-addr",
+    "zmq-addr",
+    "redis-url",
+    "nats-url",
+    "emit",
+    "emit-to",
+    "input",
+    "windows",
+    "iterations",
+    "output",
+    "overlays",
+    "events",
+    "pivot-strength",
+    "size",
+    "from",
+    "to",
+    "role",
+    "ipc-addr",
+    "memory-budget-mb",
+    "seed",
+    "exchange",
+    "warmup",
+    "cross-exchange",
+    "session",
+    "derive-from",
+    "timezone",
+    "usd-pair",
+    "correlation-ref",
+    "report",
+    "chunk",
+    "deadline-ms",
+    "calendar",
+    "tolerance",
+    "streaming-log",
+    "window",
+    "params",
+    "manifest",
+    "profile",
+    "universe",
+    "decimals",
+    "rows",
+    "block",
+    "start",
+    "price",
+    "volume",
+    "log-level",
+];
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// Base directory for every file the bot writes.
+    pub data_dir: String,
+    /// Where candles are fetched and streamed from.
+    pub exchange: Exchange,
+    /// Trading pair, e.g. "BTCUSDT" (on Coinbase also "BTC-USD").
+    pub symbol: String,
+    /// Several trading pairs, each run as its own supervised pipeline
+    /// (overrides `symbol`; more than one needs `{symbol}` in `path_template`).
+    pub symbols: Vec<String>,
+    /// Template for output paths. Placeholders: `{data_dir}`, `{symbol}`,
+    /// `{interval}`, `{stream}` and `{file}` (required, the file name).
+    pub path_template: String,
+    /// Address for the HTTP server (`/metrics`, `/latest`, `/candles`), e.g.
+    /// "127.0.0.1:9898"; disabled if unset. `metrics_addr` is accepted as an alias.
+    #[serde(alias = "metrics_addr")]
+    pub http_addr: Option<String>,
+    /// Address of the WebSocket feed pushing every new feature row (e.g.
+    /// "127.0.0.1:9899"); disabled if unset.
+    pub ws_addr: Option<String>,
+    /// Address of the gRPC service (`proto/market.proto`), e.g. "127.0.0.1:50051";
+    /// disabled if unset.
+    pub grpc_addr: Option<String>,
+    /// ZeroMQ endpoint of the PUB socket for candles, features and signals,
+    /// e.g. "tcp://127.0.0.1:5556"; disabled if unset.
+    pub zmq_addr: Option<String>,
+    /// Seconds between p50/p95/p99 latency summaries in the log (0 disables).
+    pub metrics_summary_secs: u64,
+    /// Print one summary table per pipeline every this many candles instead
+    /// of a block of lines per candle (0 keeps the per-candle lines).
+    pub pipeline_summary_every: u64,
+    /// Routine output per candle: `debug`, `info` or `warn` (changed at
+    /// runtime with `POST /control/log-level`).
+    pub log_level: LogLevel,
+    /// Record the timed start-up operations (history load, initial features
+    /// and saves) as `timed_operation_ms{label,result}`.
+    pub timing_metrics: bool,
+    /// Seconds between progress lines of a long REST candle fetch (0 logs
+    /// every batch).
+    pub fetch_progress_secs: u64,
+    /// Alert when the next candle is this many seconds past its expected close.
+    pub watchdog_grace_secs: u64,
+    /// Check the incremental EMAs against a full recomputation every this
+    /// many candles (0 disables).
+    pub ema_validate_every: u64,
+    /// Seconds between full rewrites of the raw Parquet snapshot; candles in
+    /// between only go to the journal (0 rewrites it after every candle).
+    pub raw_snapshot_secs: u64,
+    /// Store feature columns (not raw prices) as Float32 in the feature
+    /// Parquet, history and CSV outputs.
+    pub float32_features: bool,
+    /// Add the `warmup_flags` column: which features of a row are still
+    /// null for lack of earlier candles.
+    pub warmup_flags: bool,
+    /// Record every raw WebSocket frame to a compressed session file for
+    /// `replay --session`.
+    pub record: bool,
+    /// Append every strategy signal with its feature row, parameters and
+    /// model version to `signal_audit.jsonl`.
+    pub signal_audit: bool,
+    /// What the pipelines persist: everything (`collector`) or only signals
+    /// and a minimal event log (`signal_only`).
+    pub profile: Profile,
+    /// Settings of the `signal_only` profile.
+    pub signal_only: SignalOnlyConfig,
+    /// Stream type ("m1", "m5", ...) every candle pipeline of a symbol is
+    /// aggregated from over one shared connection, instead of each pipeline
+    /// opening its own stream; unset disables.
+    pub derive_from: Option<String>,
+    /// IANA timezone logged timestamps are displayed in (e.g. "Europe/Paris");
+    /// stored timestamps stay UTC epoch ms.
+    pub timezone: String,
+    /// Trading sessions (`[[sessions]]` tables), named in `/latest` and the log.
+    pub sessions: Vec<SessionConfig>,
+    /// Additional feature windows (`[[feature_windows]]` tables), each over
+    /// its own number of candles with its own feature groups and file.
+    pub feature_windows: Vec<FeatureWindowConfig>,
+    /// Strategies (`[[strategies]]` tables, or a `--params` file) run on
+    /// every closed candle; the built-in EMA cross if empty.
+    pub strategies: Vec<StrategyConfig>,
+    /// Rolling window length, overall and per interval.
+    pub window: WindowConfig,
+    /// Format of the streaming feature log.
+    pub streaming_log: StreamingLogConfig,
+    /// Free-space guardrails for the data directory.
+    pub disk: DiskConfig,
+    /// Local and cold object-store tiers of the feature-history datasets.
+    pub storage: StorageConfig,
+    /// RSS budget and how far the feature window may shrink to meet it.
+    pub memory: MemoryConfig,
+    /// Spread, basis and lead/lag features against a second venue.
+    pub cross_exchange: CrossExchangeConfig,
+    /// USD prices and volume through a conversion pair.
+    pub usd: UsdConfig,
+    /// Rolling correlation and beta against a reference symbol.
+    pub correlation: CorrelationConfig,
+    /// Cross-sectional features of the `symbols` basket.
+    pub portfolio: PortfolioConfig,
+    /// Symbols picked and rotated by 24h quote volume instead of `symbols`.
+    pub universe: UniverseConfig,
+    /// Taker imbalance, trade size and block-trade features from the trade
+    /// stream.
+    pub order_flow: OrderFlowConfig,
+    /// Spread and touch-size features from the best bid/ask stream.
+    pub spread: SpreadConfig,
+    /// Market-regime label from ADX, EMA alignment and realized volatility.
+    pub regime: RegimeConfig,
+    /// Per-candle data quality flags and score.
+    pub quality: QualityConfig,
+    /// Adjust flag, min_periods and seeding of every EMA column.
+    pub ema: EmaConfig,
+    /// Decimal places of the floats in the CSV and JSON-lines outputs.
+    pub decimals: DecimalFormat,
+    /// Scheduled-event calendar features and signal suppression around events.
+    pub calendar: CalendarConfig,
+    /// Periodic comparison of stored candles with the exchange's REST API.
+    pub consistency: ConsistencyConfig,
+    /// Screening of incoming candles; suspicious ones are quarantined.
+    pub anomaly: AnomalyConfig,
+    /// Optional Redis sink for feature rows and signals.
+    pub redis: RedisConfig,
+    /// Optional NATS sink for candles and feature rows.
+    pub nats: NatsConfig,
+    /// Outgoing webhooks (`[[webhooks]]` tables).
+    pub webhooks: Vec<WebhookConfig>,
+    /// Daily and weekly summary reports.
+    pub report: ReportConfig,
+    /// Periodic maintenance jobs (compaction, retention, reconciliation, ...).
+    pub scheduler: SchedulerConfig,
+    /// Line-per-candle output to stdout or a Unix socket.
+    pub emit: EmitConfig,
+    /// TradingView alert webhooks accepted on the HTTP server.
+    pub tradingview: TradingViewConfig,
+    /// Collector/worker process split.
+    pub ipc: IpcConfig,
+    /// Restart policy of the per-symbol pipelines.
+    pub supervisor: SupervisorConfig,
+    /// Exchange maintenance detection, muted alerts and slower reconnects.
+    pub maintenance: MaintenanceConfig,
+    /// Per-strategy disabling rules on the paper trades of its signals.
+    pub kill: KillConfig,
+    /// Authenticated runtime operations on the HTTP server.
+    pub control: ControlConfig,
+    /// What a pipeline does when candles queue up faster than it processes them.
+    pub backpressure: BackpressureConfig,
+    /// Per-candle processing deadline and what an overrun degrades.
+    pub deadline: DeadlineConfig,
+    /// Reproducible-run mode.
+    pub deterministic: DeterministicConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DiskConfig {
+    /// Warn when free space drops below this many MB.
+    pub warn_free_mb: u64,
+    /// Switch to reduced persistence below this many MB.
+    pub critical_free_mb: u64,
+    /// Warn when the append files would fill the disk within this many hours.
+    pub warn_hours_to_full: f64,
+}
+
+impl Default for DiskConfig {
+    fn default() -> Self {
+        Self {
+            warn_free_mb: 2048,
+            critical_free_mb: 512,
+            warn_hours_to_full: 24.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Object store the `tiering` job moves feature-history partitions to:
+    /// `file:///mnt/archive` (a local or mounted directory), `gs://bucket/prefix`,
+    /// `az://container/prefix` or an `https://` WebDAV server; no cold tier
+    /// if unset.
+    pub cold_url: Option<String>,
+    /// Settings of the store, e.g. `google_service_account` or
+    /// `azure_storage_account_key`.
+    pub cold_options: BTreeMap<String, String>,
+    /// Days of feature-history partitions kept on local disk, today included.
+    pub warm_days: u64,
+    /// MB of feature-history partitions kept on local disk across datasets;
+    /// the oldest move to the cold store beyond it (0: no limit).
+    pub max_local_mb: u64,
+    /// Where cold partitions read back are cached; `<data_dir>/cold_cache`
+    /// if unset.
+    pub cache_dir: Option<String>,
+    /// MB of the cache; the partitions read least recently go beyond it.
+    pub cache_mb: u64,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            cold_url: None,
+            cold_options: BTreeMap::new(),
+            warm_days: 7,
+            max_local_mb: 0,
+            cache_dir: None,
+            cache_mb: 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+    /// Resident memory budget in MB; the guard is disabled if unset.
+    pub budget_mb: Option<u64>,
+    /// Smallest feature window the guard shrinks to.
+    pub min_feature_window: usize,
+    /// Grow the window back once RSS is below this fraction of the budget.
+    pub recover_ratio: f64,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            budget_mb: None,
+            min_feature_window: 5000,
+            recover_ratio: 0.8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CrossExchangeConfig {
+    /// Venue streamed alongside `exchange`; the features are off if unset.
+    pub reference: Option<Exchange>,
+    /// Pair on the reference venue when it is named differently there
+    /// (single-symbol runs only); defaults to the pipeline's symbol.
+    pub symbol: Option<String>,
+    /// Candles the lead/lag correlations are computed over.
+    pub correlation_window: usize,
+    /// Reference candles fetched at startup, so the newest rows have
+    /// spreads and correlations right away.
+    pub history: usize,
+    /// How long a closed candle waits for the reference candle of the same
+    /// `open_time` before its features are computed without it.
+    pub join_wait_ms: u64,
+}
+
+impl Default for CrossExchangeConfig {
+    fn default() -> Self {
+        Self {
+            reference: None,
+            symbol: None,
+            correlation_window: 96,
+            history: 1000,
+            join_wait_ms: 2000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CalendarConfig {
+    /// JSON or CSV file of scheduled events (CPI, FOMC, exchange
+    /// maintenance, ...); the calendar is off if neither this nor `url` is set.
+    pub file: Option<String>,
+    /// URL the same file is fetched from instead.
+    pub url: Option<String>,
+    /// Minutes between reloads of the calendar (0 loads it once).
+    pub refresh_mins: u64,
+    /// Minutes before an event its window opens.
+    pub before_mins: i64,
+    /// Minutes after an event its window closes.
+    pub after_mins: i64,
+    /// Drop strategy signals of candles closing inside an event window.
+    pub suppress_signals: bool,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            url: None,
+            refresh_mins: 60,
+            before_mins: 30,
+            after_mins: 30,
+            suppress_signals: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CorrelationConfig {
+    /// Symbol every other symbol is correlated with on the same exchange
+    /// (e.g. "BTCUSDT"), streamed alongside; the columns are off if unset.
+    pub reference: Option<String>,
+    /// Candles the correlation and beta are computed over.
+    pub window: usize,
+    /// Reference candles fetched at startup, so the newest rows are joined
+    /// right away.
+    pub history: usize,
+    /// How long a closed candle waits for the reference candle of the same
+    /// `open_time` before its features are computed without it.
+    pub join_wait_ms: u64,
+}
+
+impl Default for CorrelationConfig {
+    fn default() -> Self {
+        Self {
+            reference: None,
+            window: 96,
+            history: 1000,
+            join_wait_ms: 2000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PortfolioConfig {
+    /// Compute the basket features when several `symbols` are streamed.
+    pub enabled: bool,
+    /// Candles of the member RSIs averaged in `avg_rsi`.
+    pub rsi_period: usize,
+    /// Newest basket rows kept in `portfolio_features.parquet`.
+    pub rows: usize,
+}
+
+impl Default for PortfolioConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rsi_period: 14,
+            rows: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UniverseConfig {
+    /// Run the `top` symbols by 24h quote volume instead of `symbols`.
+    pub enabled: bool,
+    /// Symbols run at a time.
+    pub top: usize,
+    /// Quote currency the symbols must end with.
+    pub quote: String,
+    /// Minutes between re-rankings.
+    pub refresh_mins: u64,
+    /// Ranks past `top` a member may fall to before it is retired, so
+    /// symbols around the cut-off do not start and stop every refresh.
+    pub buffer: usize,
+    /// Smallest 24h quote volume of a member.
+    pub min_quote_volume: f64,
+    /// Symbols never picked (stablecoin pairs, ...).
+    pub exclude: Vec<String>,
+    /// Seconds a retired pipeline gets to flush before it is aborted.
+    pub stop_timeout_secs: u64,
+}
+
+impl Default for UniverseConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            top: 20,
+            quote: "USDT".to_string(),
+            refresh_mins: 60,
+            buffer: 5,
+            min_quote_volume: 0.0,
+            exclude: ["USDCUSDT", "FDUSDUSDT", "TUSDUSDT", "USDPUSDT", "DAIUSDT"]
+                .map(String::from)
+                .to_vec(),
+            stop_timeout_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UsdConfig {
+    /// Pair converting the quote currency to USD on the same exchange (e.g.
+    /// "BTCUSDT" for ETHBTC), streamed alongside; the columns are off if unset.
+    pub pair: Option<String>,
+    /// The pair is quoted the other way round (USD in its base, e.g. "USDTTRY").
+    pub invert: bool,
+    /// Conversion candles fetched at startup, so the newest rows are
+    /// converted right away.
+    pub history: usize,
+    /// How long a closed candle waits for the conversion candle of the same
+    /// `open_time` before its features are computed without it.
+    pub join_wait_ms: u64,
+}
+
+impl Default for UsdConfig {
+    fn default() -> Self {
+        Self {
+            pair: None,
+            invert: false,
+            history: 1000,
+            join_wait_ms: 2000,
+        }
+    }
+}
+
+/// One `[[strategies]]` table: a built-in strategy and its parameters.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StrategyConfig {
+    /// Strategy kind, e.g. "ema_cross".
+    pub kind: String,
+    /// Every other key of the table, e.g. `fast = "ema50_m15"`.
+    #[serde(flatten)]
+    pub params: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A strategy parameter file (`--params`): `[[strategies]]` tables only.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ParamsFile {
+    strategies: Vec<StrategyConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FeatureWindowConfig {
+    /// Also names the output file, `features_{name}.parquet`.
+    pub name: String,
+    /// Newest candles the features are computed over (at most the raw
+    /// window of the stream's interval).
+    pub size: usize,
+    /// Feature groups ("ema", "pivots"); every group if empty.
+    pub groups: Vec<FeatureGroup>,
+    /// Candles between two computations (1 recomputes on every candle).
+    pub every: u64,
+}
+
+impl Default for FeatureWindowConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            size: 0,
+            groups: Vec::new(),
+            every: 1,
+        }
+    }
+}
+
+impl FeatureWindowConfig {
+    /// The configured groups, or every group.
+    pub fn groups(&self) -> &[FeatureGroup] {
+        if self.groups.is_empty() {
+            &FeatureGroup::ALL
+        } else {
+            &self.groups
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OrderFlowConfig {
+    /// Stream the symbol's trades alongside the candles for the order-flow
+    /// columns.
+    pub enabled: bool,
+    /// Smallest trade, in the quote currency, counted as a block trade.
+    pub block_notional: f64,
+    /// How long a closed candle waits for a trade of the next candle (which
+    /// completes its trades) before its features are computed without it.
+    pub join_wait_ms: u64,
+}
+
+impl Default for OrderFlowConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            block_notional: 100_000.0,
+            join_wait_ms: 2000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SpreadConfig {
+    /// Stream the symbol's best bid and ask alongside the candles for the
+    /// spread columns and `book_ticker.csv`.
+    pub enabled: bool,
+    /// Smallest gap between two quotes kept in `book_ticker.csv` (0 keeps
+    /// every update).
+    pub sample_ms: u64,
+    /// How long a closed candle waits for a quote of the next candle before
+    /// its features are computed without it.
+    pub join_wait_ms: u64,
+}
+
+impl Default for SpreadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sample_ms: 1000,
+            join_wait_ms: 2000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConsistencyConfig {
+    /// Seconds between checks (0 disables).
+    pub every_secs: u64,
+    /// Newest closed candles compared per check.
+    pub candles: usize,
+    /// Largest relative OHLC difference tolerated.
+    pub tolerance: f64,
+    /// Largest relative volume difference tolerated.
+    pub volume_tolerance: f64,
+    /// Which source is authoritative when the stream and REST disagree.
+    pub prefer: CandleSourcePolicy,
+    /// Minutes after its close from which a REST candle replaces the stored
+    /// one (`prefer = "rest"`); younger candles may still be settling.
+    pub rest_after_mins: u64,
+}
+
+impl Default for ConsistencyConfig {
+    fn default() -> Self {
+        Self {
+            every_secs: 3600,
+            candles: 4,
+            tolerance: 1e-9,
+            volume_tolerance: 1e-6,
+            prefer: CandleSourcePolicy::Stream,
+            rest_after_mins: 5,
+        }
+    }
+}
+
+/// What happens to a stored candle that differs from REST.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CandleSourcePolicy {
+    /// Keep the streamed candle; the difference is only reported.
+    Stream,
+    /// Replace the stored candle with the REST one and recompute the
+    /// features that depend on it.
+    Rest,
+    /// Keep the streamed candle but hold the symbol's signals while a
+    /// checked candle differs.
+    Hold,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AnomalyConfig {
+    /// Screen candles before they enter the window.
+    pub enabled: bool,
+    /// Quarantine a close this many standard deviations of the recent log
+    /// returns away from the previous close (0 disables).
+    pub jump_sigma: f64,
+    /// Recent returns the standard deviation is computed over.
+    pub lookback: usize,
+    /// Quarantine zero-volume candles while a `[[sessions]]` session is open.
+    pub zero_volume: bool,
+    /// Raise an `anomaly` alert for every quarantined candle.
+    pub alert: bool,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            jump_sigma: 10.0,
+            lookback: 96,
+            zero_volume: true,
+            alert: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RedisConfig {
+    /// e.g. "redis://127.0.0.1:6379/0"; the sink is disabled if unset.
+    pub url: Option<String>,
+    /// Prefix of every channel and key.
+    pub prefix: String,
+}
+
+impl Default for RedisConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            prefix: "binance".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NatsConfig {
+    /// e.g. "nats://127.0.0.1:4222"; the sink is disabled if unset.
+    pub url: Option<String>,
+    /// First token of every subject.
+    pub prefix: String,
+    /// Messages kept while the server is unreachable (oldest dropped first).
+    pub buffer: usize,
+}
+
+impl Default for NatsConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            prefix: "binance".to_string(),
+            buffer: 10_000,
+        }
+    }
+}
+
+/// Field names (dotted paths) of incoming TradingView alert bodies.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TradingViewConfig {
+    /// Serve `POST /webhooks/tradingview` (needs `http_addr`).
+    pub enabled: bool,
+    /// Required value of `secret_field` (needed with `enabled`).
+    pub secret: Option<String>,
+    pub secret_field: String,
+    pub symbol_field: String,
+    pub side_field: String,
+    pub price_field: String,
+    /// Epoch ms or RFC 3339; the receive time if absent.
+    pub time_field: String,
+    pub interval_field: String,
+    /// Appended to the strategy name (`tradingview:<name>`) when present.
+    pub strategy_field: Option<String>,
+    pub long_values: Vec<String>,
+    pub short_values: Vec<String>,
+    pub flat_values: Vec<String>,
+}
+
+impl Default for TradingViewConfig {
+    fn default() -> Self {
+        let strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect();
+        Self {
+            enabled: false,
+            secret: None,
+            secret_field: "secret".to_string(),
+            symbol_field: "ticker".to_string(),
+            side_field: "action".to_string(),
+            price_field: "price".to_string(),
+            time_field: "time".to_string(),
+            interval_field: "interval".to_string(),
+            strategy_field: None,
+            long_values: strings(&["buy", "long"]),
+            short_values: strings(&["sell", "short"]),
+            flat_values: strings(&["flat", "exit", "close"]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Stream, compute and persist in one process.
+    Standalone,
+    /// Own the WebSocket and serve raw candles to workers.
+    Collector,
+    /// Compute and persist candles received from a collector.
+    Worker,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Standalone => "standalone",
+            Role::Collector => "collector",
+            Role::Worker => "worker",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct IpcConfig {
+    pub role: Role,
+    /// "unix:/path/to.sock" or "tcp:host:port"; required by both split roles.
+    pub addr: Option<String>,
+}
+
+impl Default for IpcConfig {
+    fn default() -> Self {
+        Self {
+            role: Role::Standalone,
+            addr: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SupervisorConfig {
+    /// Restart a pipeline that returned an error or panicked.
+    pub restart: bool,
+    /// Delay before the first restart; doubles after every further failure.
+    pub min_backoff_secs: u64,
+    pub max_backoff_secs: u64,
+    /// A pipeline that ran this long before failing restarts after `min_backoff_secs` again.
+    pub stable_secs: u64,
+    /// Give up after this many restarts (0 = never).
+    pub max_restarts: u32,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            restart: true,
+            min_backoff_secs: 5,
+            max_backoff_secs: 300,
+            stable_secs: 600,
+            max_restarts: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    /// Enter maintenance mode on the exchange's maintenance answers.
+    pub enabled: bool,
+    /// Seconds between reconnect attempts while in maintenance.
+    pub retry_secs: u64,
+    /// Alert sources held back while in maintenance.
+    pub mute: Vec<String>,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retry_secs: 120,
+            mute: [
+                "watchdog",
+                "consistency",
+                "supervisor",
+                "universe",
+                "derive",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct KillConfig {
+    /// Disable strategies whose paper trades break a limit.
+    pub enabled: bool,
+    /// Losing trades in a row that disable a strategy on a stream (0: no
+    /// limit).
+    pub consecutive_losses: u32,
+    /// Drop of the paper equity below its peak, in percent of the allocated
+    /// capital, that disables a strategy on a stream (0: no limit).
+    pub max_drawdown_pct: f64,
+    /// Limits of single strategies, by strategy name.
+    pub strategies: BTreeMap<String, KillLimits>,
+}
+
+impl Default for KillConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            consecutive_losses: 5,
+            max_drawdown_pct: 20.0,
+            strategies: BTreeMap::new(),
+        }
+    }
+}
+
+/// A `[kill.strategies."<name>"]` table: limits replacing the `[kill]` ones.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KillLimits {
+    pub consecutive_losses: Option<u32>,
+    pub max_drawdown_pct: Option<f64>,
+}
+
+impl KillConfig {
+    /// The (consecutive losses, max drawdown %) limits of `strategy`.
+    pub fn limits(&self, strategy: &str) -> (u32, f64) {
+        let limits = self.strategies.get(strategy);
+        (
+            limits
+                .and_then(|l| l.consecutive_losses)
+                .unwrap_or(self.consecutive_losses),
+            limits
+                .and_then(|l| l.max_drawdown_pct)
+                .unwrap_or(self.max_drawdown_pct),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ControlConfig {
+    /// Serve the `/control` routes (needs `http_addr` and `token`).
+    pub enabled: bool,
+    /// Bearer token every control request must carry
+    /// (`Authorization: Bearer <token>`).
+    pub token: Option<String>,
+    /// Accept control requests from other hosts than the loopback address.
+    pub allow_remote: bool,
+    /// Seconds a command waits for each pipeline to carry it out.
+    pub timeout_secs: u64,
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            token: None,
+            allow_remote: false,
+            timeout_secs: 30,
+        }
+    }
+}
+
+/// How much routine output a pipeline prints per candle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    /// The per-candle lines even with `pipeline_summary_every`.
+    Debug,
+    /// The per-candle lines, or the summary tables.
+    #[default]
+    Info,
+    /// Neither; warnings, errors, alerts and signals only.
+    Warn,
+}
+
+impl LogLevel {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "debug" => Ok(LogLevel::Debug),
+            "info" => Ok(LogLevel::Info),
+            "warn" => Ok(LogLevel::Warn),
+            other => bail!("log level must be debug, info or warn, got '{}'", other),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+        }
+    }
+}
+
+/// Candles of the rolling window unless `[window]` says otherwise.
+pub const DEFAULT_WINDOW: usize = 50_000;
+
+/// Length of a rolling window: a candle count, or a duration the stream's
+/// candles cover ("180d", "12w", "720h"; units as in intervals).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum WindowSize {
+    Candles(usize),
+    Duration(String),
+}
+
+impl WindowSize {
+    /// `--window` value: digits are a count, anything else a duration.
+    fn parse(value: &str) -> Self {
+        match value.parse() {
+            Ok(candles) => WindowSize::Candles(candles),
+            Err(_) => WindowSize::Duration(value.to_string()),
+        }
+    }
+
+    fn validate(&self, context: &str) -> Result<()> {
+        match self {
+            WindowSize::Candles(0) => bail!("{}: a window needs at least one candle", context),
+            WindowSize::Candles(_) => Ok(()),
+            WindowSize::Duration(duration) => exchange::interval_ms(duration)
+                .map(|_| ())
+                .with_context(|| format!("{}: invalid window duration '{}'", context, duration)),
+        }
+    }
+
+    /// Candles of `interval` in the window (0 if a duration is shorter than
+    /// one candle).
+    pub fn candles(&self, interval: &str) -> usize {
+        match self {
+            WindowSize::Candles(candles) => *candles,
+            WindowSize::Duration(duration) => {
+                match (
+                    exchange::interval_ms(duration),
+                    exchange::interval_ms(interval),
+                ) {
+                    (Ok(window), Ok(candle)) => (window / candle) as usize,
+                    // Validated at load; a trade stream has no candles
+                    _ => DEFAULT_WINDOW,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    /// Window of every interval not listed in `intervals`.
+    pub size: WindowSize,
+    /// Window per interval ("5m", "15m", ...).
+    pub intervals: HashMap<String, WindowSize>,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            size: WindowSize::Candles(DEFAULT_WINDOW),
+            intervals: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Profile {
+    /// Persist candles, features and signals.
+    #[default]
+    Collector,
+    /// A low-latency signal generator: candles and features stay in memory,
+    /// only signals and a minimal event log are written.
+    SignalOnly,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SignalOnlyConfig {
+    /// Newest candles of the raw window the feature frame strategies see is
+    /// computed over (the EMAs still cover the whole window).
+    pub feature_window: usize,
+}
+
+impl Default for SignalOnlyConfig {
+    fn default() -> Self {
+        Self {
+            feature_window: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamingLogFormat {
+    /// One CSV line per candle, like the feature CSV.
+    #[default]
+    Csv,
+    /// JSON lines compressed into appended zstd frames.
+    JsonlZst,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StreamingLogConfig {
+    pub format: StreamingLogFormat,
+    /// Rows per zstd frame (`jsonl_zst`); rows wait in memory until their
+    /// frame is full.
+    pub frame_rows: usize,
+    /// zstd compression level (`jsonl_zst`).
+    pub level: i32,
+}
+
+impl Default for StreamingLogConfig {
+    fn default() -> Self {
+        Self {
+            format: StreamingLogFormat::Csv,
+            frame_rows: 1,
+            level: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackpressurePolicy {
+    /// Compute features only for the newest queued candle; older ones still
+    /// enter the window and the raw logs.
+    Coalesce,
+    /// Compute and save features for every candle, however far behind.
+    Queue,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BackpressureConfig {
+    pub policy: BackpressurePolicy,
+    /// Alert when one cycle coalesces at least this many candles (0 disables).
+    pub alert_threshold: usize,
+}
+
+impl Default for BackpressureConfig {
+    fn default() -> Self {
+        Self {
+            policy: BackpressurePolicy::Coalesce,
+            alert_threshold: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadlinePolicy {
+    /// Only report overruns.
+    Log,
+    /// Skip the optional outputs until candles are back within the deadline.
+    #[default]
+    SkipOptional,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DeadlineConfig {
+    /// Budget for one candle's features, strategies and saves in ms (0 disables).
+    pub ms: u64,
+    pub policy: DeadlinePolicy,
+    /// Consecutive candles within the deadline before skipped outputs return.
+    pub recover_after: u32,
+    /// Alert once this many consecutive candles overran (0 disables).
+    pub alert_after: u32,
+}
+
+impl Default for DeadlineConfig {
+    fn default() -> Self {
+        Self {
+            ms: 0,
+            policy: DeadlinePolicy::SkipOptional,
+            recover_after: 10,
+            alert_after: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DeterministicConfig {
+    /// Pin the clock, thread counts, seeds and write order.
+    pub enabled: bool,
+    /// Seed of every random generator.
+    pub seed: u64,
+    /// Threads for polars and rayon.
+    pub threads: usize,
+}
+
+impl Default for DeterministicConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            seed: DEFAULT_SEED,
+            threads: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReportConfig {
+    /// Summary of every day, written at midnight in the display timezone.
+    pub daily: bool,
+    /// Summary of every week, written on Monday 00:00 in the display timezone.
+    pub weekly: bool,
+    pub format: ReportFormat,
+    /// Directory of the report files; `{data_dir}/reports` if unset.
+    pub dir: Option<String>,
+    /// Alert sources listed, most frequent first.
+    pub top_alerts: usize,
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self {
+            daily: false,
+            weekly: false,
+            format: ReportFormat::Markdown,
+            dir: None,
+            top_alerts: 5,
+        }
+    }
+}
+
+/// When a `[scheduler]` job runs: every `every_secs`, or once a day at `at`.
+/// A job with neither is off.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct JobSchedule {
+    /// Seconds between runs (0: not periodic).
+    pub every_secs: u64,
+    /// Daily run time, "HH:MM" in the display timezone.
+    pub at: Option<String>,
+}
+
+impl JobSchedule {
+    pub fn enabled(&self) -> bool {
+        self.every_secs > 0 || self.at.is_some()
+    }
+
+    /// The parsed `at` time.
+    pub fn at_time(&self) -> Result<Option<chrono::NaiveTime>> {
+        self.at
+            .as_deref()
+            .map(|at| {
+                chrono::NaiveTime::parse_from_str(at, "%H:%M")
+                    .with_context(|| format!("at expects HH:MM, got '{}'", at))
+            })
+            .transpose()
+    }
+
+    fn validate(&self, job: &str) -> Result<()> {
+        if self.every_secs > 0 && self.at.is_some() {
+            bail!("[scheduler.{}] takes every_secs or at, not both", job);
+        }
+        self.at_time()
+            .with_context(|| format!("[scheduler.{}]", job))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RetentionJob {
+    #[serde(flatten)]
+    pub schedule: JobSchedule,
+    /// Days of feature-history partitions kept, today included.
+    pub keep_days: u64,
+}
+
+impl Default for RetentionJob {
+    fn default() -> Self {
+        Self {
+            schedule: JobSchedule::default(),
+            keep_days: 90,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DriftJob {
+    #[serde(flatten)]
+    pub schedule: JobSchedule,
+    /// Days of feature history checked for drift, ending now.
+    pub recent_days: u64,
+    /// Days before them the features are compared with.
+    pub baseline_days: u64,
+    /// Baseline quantile bins of the population stability index.
+    pub bins: usize,
+    /// Population stability index from which a feature counts as drifted.
+    pub threshold: f64,
+    /// Feature columns checked; every float column if empty.
+    pub features: Vec<String>,
+}
+
+impl Default for DriftJob {
+    fn default() -> Self {
+        Self {
+            schedule: JobSchedule::default(),
+            recent_days: 1,
+            baseline_days: 7,
+            bins: 10,
+            threshold: 0.25,
+            features: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    /// Log the process RSS and virtual memory.
+    pub memory: JobSchedule,
+    /// Compact every partition of the feature-history datasets, today's too.
+    pub compaction: JobSchedule,
+    /// Delete feature-history partitions older than `keep_days`.
+    pub retention: RetentionJob,
+    /// Run the `[consistency]` REST check of every pipeline.
+    pub reconciliation: JobSchedule,
+    /// Write the running `[report]` summaries so far.
+    pub report: JobSchedule,
+    /// Compare the recent feature distributions with the preceding days.
+    pub drift: DriftJob,
+    /// Move feature-history partitions past `[storage]` warm_days or
+    /// max_local_mb to the cold store.
+    pub tiering: JobSchedule,
+}
+
+impl SchedulerConfig {
+    pub fn validate(&self) -> Result<()> {
+        self.memory.validate("memory")?;
+        self.compaction.validate("compaction")?;
+        self.retention.schedule.validate("retention")?;
+        self.reconciliation.validate("reconciliation")?;
+        self.report.validate("report")?;
+        self.drift.schedule.validate("drift")?;
+        self.tiering.validate("tiering")?;
+        if self.retention.keep_days == 0 {
+            bail!("[scheduler.retention] keep_days must be at least 1");
+        }
+        let drift = &self.drift;
+        if drift.recent_days == 0 || drift.baseline_days == 0 {
+            bail!("[scheduler.drift] recent_days and baseline_days must be at least 1");
+        }
+        if drift.bins < 2 {
+            bail!("[scheduler.drift] bins must be at least 2");
+        }
+        if drift.threshold.is_nan() || drift.threshold <= 0.0 {
+            bail!("[scheduler.drift] threshold must be positive");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmitFormat {
+    Json,
+    Csv,
+}
+
+impl EmitFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EmitFormat::Json => "json",
+            EmitFormat::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EmitConfig {
+    /// Line format; the output is disabled if unset.
+    pub format: Option<EmitFormat>,
+    /// "stdout" or "unix:/path/to.sock".
+    pub to: String,
+}
+
+impl Default for EmitConfig {
+    fn default() -> Self {
+        Self {
+            format: None,
+            to: "stdout".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookEvent {
+    /// Every closed candle.
+    Candle,
+    /// Strategy signals only.
+    Signal,
+    /// Daily and weekly summary reports (`[report]`).
+    Report,
+}
+
+impl WebhookEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebhookEvent::Candle => "candle",
+            WebhookEvent::Signal => "signal",
+            WebhookEvent::Report => "report",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Which events fire the webhook.
+    pub on: WebhookEvent,
+    /// Body with `{field}` placeholders; the event as JSON if unset.
+    pub template: Option<String>,
+    pub content_type: String,
+    /// Extra attempts after a failed delivery.
+    pub retries: u32,
+    /// Delay before the first retry, doubled on each further one.
+    pub backoff_ms: u64,
+    pub timeout_secs: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            on: WebhookEvent::Signal,
+            template: None,
+            content_type: "application/json".to_string(),
+            retries: 3,
+            backoff_ms: 1000,
+            timeout_secs: 10,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            data_dir: "data".to_string(),
+            exchange: Exchange::default(),
+            symbol: "BTCUSDT".to_string(),
+            symbols: Vec::new(),
+            path_template: DEFAULT_PATH_TEMPLATE.to_string(),
+            http_addr: None,
+            ws_addr: None,
+            zmq_addr: None,
+            grpc_addr: None,
+            metrics_summary_secs: 300,
+            pipeline_summary_every: 0,
+            log_level: LogLevel::Info,
+            timing_metrics: true,
+            fetch_progress_secs: 10,
+            watchdog_grace_secs: 60,
+            ema_validate_every: 96,
+            raw_snapshot_secs: 3600,
+            float32_features: false,
+            warmup_flags: false,
+            record: false,
+            signal_audit: true,
+            profile: Profile::default(),
+            signal_only: SignalOnlyConfig::default(),
+            derive_from: None,
+            timezone: "UTC".to_string(),
+            sessions: Vec::new(),
+            feature_windows: Vec::new(),
+            strategies: Vec::new(),
+            regime: RegimeConfig::default(),
+            quality: QualityConfig::default(),
+            ema: EmaConfig::default(),
+            decimals: DecimalFormat::default(),
+            calendar: CalendarConfig::default(),
+            correlation: CorrelationConfig::default(),
+            portfolio: PortfolioConfig::default(),
+            universe: UniverseConfig::default(),
+            window: WindowConfig::default(),
+            streaming_log: StreamingLogConfig::default(),
+            disk: DiskConfig::default(),
+            storage: StorageConfig::default(),
+            memory: MemoryConfig::default(),
+            cross_exchange: CrossExchangeConfig::default(),
+            usd: UsdConfig::default(),
+            order_flow: OrderFlowConfig::default(),
+            spread: SpreadConfig::default(),
+            consistency: ConsistencyConfig::default(),
+            anomaly: AnomalyConfig::default(),
+            redis: RedisConfig::default(),
+            nats: NatsConfig::default(),
+            webhooks: Vec::new(),
+            report: ReportConfig::default(),
+            scheduler: SchedulerConfig::default(),
+            emit: EmitConfig::default(),
+            tradingview: TradingViewConfig::default(),
+            ipc: IpcConfig::default(),
+            supervisor: SupervisorConfig::default(),
+            maintenance: MaintenanceConfig::default(),
+            kill: KillConfig::default(),
+            control: ControlConfig::default(),
+            backpressure: BackpressureConfig::default(),
+            deadline: DeadlineConfig::default(),
+            deterministic: DeterministicConfig::default(),
+        }
+    }
+}
+
+/// Parsed command line: positional arguments in order, plus `--flags`.
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    pub positional: Vec<String>,
+    flags: HashMap<String, Option<String>>,
+}
+
+impl CliArgs {
+    /// Parse raw arguments (without the program name).
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut cli = CliArgs::default();
+        let mut iter = args.into_iter();
+        while let Some(arg) = iter.next() {
+            let Some(flag) = arg.strip_prefix("--") else {
+                cli.positional.push(arg);
+                continue;
+            };
+            if let Some((name, value)) = flag.split_once('=') {
+                cli.flags.insert(name.to_string(), Some(value.to_string()));
+            } else if VALUE_FLAGS.contains(&flag) {
+                let value = iter
+                    .next()
+                    .with_context(|| format!("Missing value for --{}", flag))?;
+                cli.flags.insert(flag.to_string(), Some(value));
+            } else {
+                cli.flags.insert(flag.to_string(), None);
+            }
+        }
+        Ok(cli)
+    }
+
+    /// True if the boolean switch `--name` was given.
+    pub fn has(&self, name: &str) -> bool {
+        self.flags.contains_key(name)
+    }
+
+    /// Value of `--name`, if given.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.flags.get(name).and_then(|v| v.as_deref())
+    }
+}
+
+impl Config {
+    /// Build the configuration from an optional TOML file and CLI overrides.
+    pub fn load(cli: &CliArgs) -> Result<Self> {
+        let mut config = match (cli.value("config"), cli.value("manifest")) {
+            (Some(_), Some(_)) => bail!("--manifest replaces --config; pass only one"),
+            (Some(path), None) => {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read config file {}", path))?;
+                toml::from_str(&text)
+                    .with_context(|| format!("Failed to parse config file {}", path))?
+            }
+            // The configuration the manifest's run had
+            (None, Some(path)) => serde_json::from_value(RunManifest::load(path)?.config)
+                .with_context(|| format!("Failed to read the configuration of {}", path))?,
+            (None, None) => Config::default(),
+        };
+        if let Some(path) = cli.value("params") {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read parameter file {}", path))?;
+            let params: ParamsFile = toml::from_str(&text)
+                .with_context(|| format!("Failed to parse parameter file {}", path))?;
+            config.strategies = params.strategies;
+        }
+
+        if let Some(v) = cli.value("data-dir") {
+            config.data_dir = v.to_string();
+        }
+        if let Some(v) = cli.value("exchange") {
+            config.exchange = Exchange::parse(v)?;
+        }
+        if let Some(v) = cli.value("cross-exchange") {
+            config.cross_exchange.reference = Some(Exchange::parse(v)?);
+        }
+        if let Some(v) = cli.value("correlation-ref") {
+            config.correlation.reference = Some(v.to_uppercase());
+        }
+        if let Some(v) = cli.value("calendar") {
+            config.calendar.file = Some(v.to_string());
+            config.calendar.url = None;
+        }
+        if let Some(v) = cli.value("usd-pair") {
+            config.usd.pair = Some(v.to_uppercase());
+        }
+        if let Some(v) = cli.value("symbol") {
+            config.symbol = v.to_uppercase();
+        }
+        if let Some(v) = cli.value("universe") {
+            config.universe.enabled = true;
+            config.universe.top = v
+                .parse()
+                .context("--universe expects the number of symbols to run")?;
+        }
+        if let Some(v) = cli.value("symbols") {
+            config.symbols = v.split(',').map(|s| s.trim().to_string()).collect();
+        }
+        config.symbols = config
+            .symbols
+            .iter()
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_uppercase())
+            .collect();
+        if let Some(v) = cli.value("path-template") {
+            config.path_template = v.to_string();
+        }
+        if let Some(v) = cli.value("http-addr").or(cli.value("metrics-addr")) {
+            config.http_addr = Some(v.to_string());
+        }
+        if let Some(v) = cli.value("ws-addr") {
+            config.ws_addr = Some(v.to_string());
+        }
+        if let Some(v) = cli.value("grpc-addr") {
+            config.grpc_addr = Some(v.to_string());
+        }
+        if let Some(v) = cli.value("zmq-addr") {
+            config.zmq_addr = Some(v.to_string());
+        }
+        if let Some(v) = cli.value("redis-url") {
+            config.redis.url = Some(v.to_string());
+        }
+        if let Some(v) = cli.value("nats-url") {
+            config.nats.url = Some(v.to_string());
+        }
+        if let Some(v) = cli.value("emit") {
+            config.emit.format = Some(match v {
+                "json" => EmitFormat::Json,
+                "csv" => EmitFormat::Csv,
+                other => bail!("--emit expects json or csv, got '{}'", other),
+            });
+        }
+        if let Some(v) = cli.value("emit-to") {
+            config.emit.to = v.to_string();
+        }
+        if let Some(v) = cli.value("role") {
+            config.ipc.role = match v {
+                "standalone" => Role::Standalone,
+                "collector" => Role::Collector,
+                "worker" => Role::Worker,
+                other => bail!(
+                    "--role expects standalone, collector or worker, got '{}'",
+                    other
+                ),
+            };
+        }
+        if let Some(v) = cli.value("ipc-addr") {
+            config.ipc.addr = Some(v.to_string());
+        }
+        if let Some(v) = cli.value("timezone") {
+            config.timezone = v.to_string();
+        }
+        if let Some(v) = cli.value("report") {
+            for period in v.split(',').map(str::trim) {
+                match period {
+                    "daily" => config.report.daily = true,
+                    "weekly" => config.report.weekly = true,
+                    other => bail!("--report expects daily and/or weekly, got '{}'", other),
+                }
+            }
+        }
+        if let Some(v) = cli.value("derive-from") {
+            config.derive_from = Some(v.to_string());
+        }
+        if let Some(v) = cli.value("window") {
+            config.window.size = WindowSize::parse(v);
+        }
+        if let Some(v) = cli.value("streaming-log") {
+            config.streaming_log.format = match v {
+                "csv" => StreamingLogFormat::Csv,
+                "jsonl_zst" => StreamingLogFormat::JsonlZst,
+                other => bail!("--streaming-log expects csv or jsonl_zst, got '{}'", other),
+            };
+        }
+        if let Some(v) = cli.value("profile") {
+            config.profile = match v {
+                "collector" => Profile::Collector,
+                "signal_only" => Profile::SignalOnly,
+                other => bail!(
+                    "--profile expects collector or signal_only, got '{}'",
+                    other
+                ),
+            };
+        }
+        if cli.has("float32-features") {
+            config.float32_features = true;
+        }
+        if cli.has("record") {
+            config.record = true;
+        }
+        if cli.has("order-flow") {
+            config.order_flow.enabled = true;
+        }
+        if cli.has("spread") {
+            config.spread.enabled = true;
+        }
+        if cli.has("regime") {
+            config.regime.enabled = true;
+        }
+        if cli.has("quality") {
+            config.quality.enabled = true;
+        }
+        if cli.has("deterministic") {
+            config.deterministic.enabled = true;
+        }
+        if let Some(v) = cli.value("seed") {
+            config.deterministic.seed = v
+                .parse()
+                .with_context(|| format!("--seed expects an integer, got '{}'", v))?;
+        }
+        if let Some(v) = cli.value("deadline-ms") {
+            config.deadline.ms = v
+                .parse()
+                .with_context(|| format!("--deadline-ms expects ms, got '{}'", v))?;
+        }
+        if let Some(v) = cli.value("decimals") {
+            config.decimals.precision =
+                Some(v.parse().with_context(|| {
+                    format!("--decimals expects a number of places, got '{}'", v)
+                })?);
+        }
+        if let Some(v) = cli.value("log-level") {
+            config.log_level = LogLevel::parse(v)?;
+        }
+        if let Some(v) = cli.value("memory-budget-mb") {
+            config.memory.budget_mb = Some(
+                v.parse()
+                    .with_context(|| format!("--memory-budget-mb expects MB, got '{}'", v))?,
+            );
+        }
+
+        if !config.path_template.contains("{file}") {
+            bail!(
+                "path_template '{}' must contain the {{file}} placeholder",
+                config.path_template
+            );
+        }
+        if config.tradingview.enabled && config.http_addr.is_none() {
+            bail!("[tradingview] enabled = true needs http_addr for the webhook endpoint");
+        }
+        if config.tradingview.enabled
+            && config
+                .tradingview
+                .secret
+                .as_deref()
+                .is_none_or(str::is_empty)
+        {
+            bail!("[tradingview] enabled = true needs a secret");
+        }
+        if config.ipc.role != Role::Standalone && config.ipc.addr.is_none() {
+            bail!(
+                "--role {} needs an IPC address (--ipc-addr or [ipc] addr)",
+                config.ipc.role.as_str()
+            );
+        }
+        if config.universe.enabled {
+            let universe = &config.universe;
+            if universe.top == 0 || universe.refresh_mins == 0 {
+                bail!("[universe] top and refresh_mins must be at least 1");
+            }
+            if universe.quote.is_empty() {
+                bail!("[universe] quote must name a quote currency, e.g. USDT");
+            }
+            if !config.path_template.contains("{symbol}") {
+                bail!(
+                    "[universe] needs the {{symbol}} placeholder in path_template '{}'",
+                    config.path_template
+                );
+            }
+            if config.ipc.role != Role::Standalone {
+                bail!(
+                    "--role {} handles a single symbol, not a [universe]",
+                    config.ipc.role.as_str()
+                );
+            }
+            if config.cross_exchange.symbol.is_some() {
+                bail!("[cross_exchange] symbol only works with a single symbol, not a [universe]");
+            }
+            config.universe.quote = config.universe.quote.to_uppercase();
+            for symbol in &mut config.universe.exclude {
+                *symbol = symbol.to_uppercase();
+            }
+        }
+        if config.symbols.len() > 1 {
+            if !config.path_template.contains("{symbol}") {
+                bail!(
+                    "symbols = {:?} needs the {{symbol}} placeholder in path_template '{}'",
+                    config.symbols,
+                    config.path_template
+                );
+            }
+            if config.ipc.role != Role::Standalone {
+                bail!(
+                    "--role {} handles a single symbol, got {:?}",
+                    config.ipc.role.as_str(),
+                    config.symbols
+                );
+            }
+        }
+        if !(config.memory.recover_ratio > 0.0 && config.memory.recover_ratio < 1.0) {
+            bail!(
+                "[memory] recover_ratio must be between 0 and 1, got {}",
+                config.memory.recover_ratio
+            );
+        }
+        if config.deterministic.threads == 0 {
+            bail!("[deterministic] threads must be at least 1");
+        }
+        config.window.size.validate("[window] size")?;
+        for (interval, size) in &config.window.intervals {
+            exchange::interval_ms(interval)
+                .with_context(|| format!("[window.intervals] '{}' is not an interval", interval))?;
+            size.validate(&format!("[window.intervals] {}", interval))?;
+            if size.candles(interval) == 0 {
+                bail!(
+                    "[window.intervals] {}: the window is shorter than one candle",
+                    interval
+                );
+            }
+        }
+        if config.portfolio.rsi_period == 0 || config.portfolio.rows == 0 {
+            bail!("[portfolio] rsi_period and rows must be at least 1");
+        }
+        if config.signal_only.feature_window == 0 {
+            bail!("[signal_only] feature_window must be at least 1");
+        }
+        if config.profile == Profile::SignalOnly {
+            // Nothing but signals reaches the disk
+            config.feature_windows.clear();
+            config.record = false;
+        }
+        if config.streaming_log.frame_rows == 0 {
+            bail!("[streaming_log] frame_rows must be at least 1");
+        }
+        if !zstd::compression_level_range().contains(&config.streaming_log.level) {
+            bail!(
+                "[streaming_log] level must be between {} and {}, got {}",
+                zstd::compression_level_range().start(),
+                zstd::compression_level_range().end(),
+                config.streaming_log.level
+            );
+        }
+        if config.deterministic.enabled {
+            // These adapt to machine load, which would leak into the outputs
+            config.backpressure.policy = BackpressurePolicy::Queue;
+            config.memory.budget_mb = None;
+            config.deadline.policy = DeadlinePolicy::Log;
+            // A second live stream is not reproducible
+            config.cross_exchange.reference = None;
+            config.usd.pair = None;
+            config.correlation.reference = None;
+            config.order_flow.enabled = false;
+            config.spread.enabled = false;
+            // The calendar is read once, so every run sees the same events
+            config.calendar.refresh_mins = 0;
+            // Maintenance jobs would rewrite the outputs on the wall clock
+            config.scheduler = SchedulerConfig::default();
+        }
+        if config.calendar.file.is_some() && config.calendar.url.is_some() {
+            bail!("[calendar] takes a file or a url, not both");
+        }
+        if config.calendar.before_mins < 0 || config.calendar.after_mins < 0 {
+            bail!("[calendar] before_mins and after_mins must not be negative");
+        }
+        if config.spread.enabled && config.exchange != Exchange::Binance {
+            bail!("[spread] needs the Binance bookTicker stream");
+        }
+        if config.order_flow.enabled && config.exchange == Exchange::Coinbase {
+            bail!("[order_flow] needs the taker side of trades, which Coinbase does not report");
+        }
+        if config.cross_exchange.reference == Some(config.exchange)
+            && config.cross_exchange.symbol.is_none()
+        {
+            bail!(
+                "[cross_exchange] reference must differ from exchange '{}' (or name another symbol)",
+                config.exchange.as_str()
+            );
+        }
+        if config.cross_exchange.symbol.is_some() && config.symbols.len() > 1 {
+            bail!("[cross_exchange] symbol only works with a single symbol");
+        }
+        if let Some(base) = &config.derive_from {
+            if crate::live_stream::stream_interval(base).is_empty() {
+                bail!(
+                    "derive_from must be a candle stream type (e.g. m1), got '{}'",
+                    base
+                );
+            }
+        }
+        config.trading_sessions()?;
+        config.regime.validate()?;
+        config.quality.validate()?;
+        config.ema.validate()?;
+        config.scheduler.validate()?;
+        if config.scheduler.report.enabled() && !(config.report.daily || config.report.weekly) {
+            bail!("[scheduler.report] needs [report] daily or weekly");
+        }
+        config.build_strategies()?;
+        let mut names = HashSet::new();
+        for window in &config.feature_windows {
+            if window.name.is_empty()
+                || !window
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                bail!(
+                    "[[feature_windows]] name '{}' must be letters, digits, '_' or '-'",
+                    window.name
+                );
+            }
+            if !names.insert(window.name.as_str()) {
+                bail!("[[feature_windows]] name '{}' is used twice", window.name);
+            }
+            if window.size == 0 {
+                bail!(
+                    "[[feature_windows]] '{}': size must be at least 1",
+                    window.name
+                );
+            }
+            if window.every == 0 {
+                bail!(
+                    "[[feature_windows]] '{}': every must be at least 1",
+                    window.name
+                );
+            }
+        }
+        if config.maintenance.retry_secs == 0 {
+            bail!("[maintenance] retry_secs must be at least 1");
+        }
+        let drawdowns = std::iter::once(("[kill]".to_string(), Some(config.kill.max_drawdown_pct)))
+            .chain(config.kill.strategies.iter().map(|(name, limits)| {
+                (
+                    format!("[kill.strategies.\"{}\"]", name),
+                    limits.max_drawdown_pct,
+                )
+            }));
+        for (table, drawdown) in drawdowns {
+            if drawdown.is_some_and(|pct| !pct.is_finite() || pct < 0.0) {
+                bail!("{} max_drawdown_pct must be a non-negative number", table);
+            }
+        }
+        if config.control.enabled {
+            if config.http_addr.is_none() {
+                bail!("[control] enabled = true needs http_addr for the control endpoint");
+            }
+            if config.control.token.as_deref().is_none_or(str::is_empty) {
+                bail!("[control] enabled = true needs a token");
+            }
+            if config.control.timeout_secs == 0 {
+                bail!("[control] timeout_secs must be at least 1");
+            }
+        }
+        if config.storage.warm_days == 0 {
+            bail!("[storage] warm_days must be at least 1");
+        }
+        if config.scheduler.tiering.enabled() && config.storage.cold_url.is_none() {
+            bail!("[scheduler.tiering] needs [storage] cold_url");
+        }
+        config.tiered_storage().context("[storage] cold_url")?;
+        if config.supervisor.min_backoff_secs > config.supervisor.max_backoff_secs {
+            bail!("[supervisor] min_backoff_secs exceeds max_backoff_secs");
+        }
+        if let Some(hook) = config.webhooks.iter().find(|h| h.url.is_empty()) {
+            bail!("Every [[webhooks]] entry needs a url (got {:?})", hook);
+        }
+        Ok(config)
+    }
+
+    /// The configured strategies, or the built-in ones.
+    pub fn build_strategies(&self) -> Result<Vec<Box<dyn Strategy>>> {
+        if self.strategies.is_empty() {
+            return Ok(strategy::default_strategies());
+        }
+        self.strategies
+            .iter()
+            .map(|s| {
+                strategy::from_params(&s.kind, &s.params)
+                    .with_context(|| format!("[[strategies]] '{}'", s.kind))
+            })
+            .collect()
+    }
+
+    /// The `[[sessions]]`, with `timezone` for those without their own.
+    pub fn trading_sessions(&self) -> Result<Vec<Session>> {
+        let display = timezone::parse_tz(&self.timezone)?;
+        self.sessions
+            .iter()
+            .map(|s| Session::from_config(s, display))
+            .collect()
+    }
+
+    /// Symbols to run pipelines for: `symbols`, or else `symbol`.
+    pub fn symbols(&self) -> Vec<String> {
+        if self.symbols.is_empty() {
+            vec![self.symbol.clone()]
+        } else {
+            self.symbols.clone()
+        }
+    }
+
+    /// This configuration narrowed to the pipeline of `symbol`.
+    pub fn for_symbol(&self, symbol: &str) -> Config {
+        Config {
+            symbol: symbol.to_string(),
+            symbols: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Where this configuration writes its files, for `query` scans.
+    pub fn layout(&self) -> DataLayout {
+        DataLayout {
+            data_dir: self.data_dir.clone(),
+            path_template: self.path_template.clone(),
+        }
+    }
+
+    /// The feature-history datasets across local disk and the `[storage]`
+    /// cold store.
+    pub fn tiered_storage(&self) -> Result<TieredStorage> {
+        let storage = &self.storage;
+        let cold = storage
+            .cold_url
+            .as_deref()
+            .map(|url| ColdStore::open(url, &storage.cold_options))
+            .transpose()?;
+        let cache_dir = storage
+            .cache_dir
+            .clone()
+            .unwrap_or_else(|| format!("{}/cold_cache", self.data_dir));
+        Ok(TieredStorage::new(
+            self.layout(),
+            cold,
+            cache_dir,
+            storage.cache_mb,
+        ))
+    }
+
+    /// Resolve a file name against the path template.
+    pub fn resolve_path(&self, stream: &str, interval: &str, file: &str) -> String {
+        self.layout().resolve(&self.symbol, stream, interval, file)
+    }
+
+    /// Candles in the rolling window of `interval`.
+    pub fn window_size(&self, interval: &str) -> usize {
+        self.window
+            .intervals
+            .get(interval)
+            .unwrap_or(&self.window.size)
+            .candles(interval)
+    }
+
+    /// Reject a window of `interval` that holds no candle or fewer than a
+    /// `[[feature_windows]]` entry needs.
+    pub fn check_window(&self, interval: &str) -> Result<()> {
+        let window = self.window_size(interval);
+        if window == 0 {
+            bail!("The [window] of {} is shorter than one candle", interval);
+        }
+        for feature_window in &self.feature_windows {
+            if feature_window.size > window {
+                bail!(
+                    "[[feature_windows]] '{}': size {} exceeds the {}-candle window of {}",
+                    feature_window.name,
+                    feature_window.size,
+                    window,
+                    interval
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve every output path for a kline stream, creating parent directories
+    /// and rejecting templates that map two outputs onto the same file. The
+    /// raw cache names carry the interval's window size.
+    pub fn output_paths(&self, stream: &str, interval: &str) -> Result<OutputPaths> {
+        let historical_count = self.window_size(interval);
+        let paths = OutputPaths {
+            raw_cache: self.resolve_path(
+                stream,
+                interval,
+                &format!("latest_{}.parquet", historical_count),
+            ),
+            raw_journal: self.resolve_path(
+                stream,
+                interval,
+                &format!("latest_{}_journal.csv", historical_count),
+            ),
+            raw_csv: self.resolve_path(
+                stream,
+                interval,
+                &format!("latest_{}_raw.csv", historical_count),
+            ),
+            feature_parquet: self.resolve_path(stream, interval, "features.parquet"),
+            feature_csv: self.resolve_path(stream, interval, "features.csv"),
+            feature_history: self.resolve_path(stream, interval, "feature_history"),
+            streaming_log: self.resolve_path(
+                stream,
+                interval,
+                match self.streaming_log.format {
+                    StreamingLogFormat::Csv => "streaming_features.csv",
+                    StreamingLogFormat::JsonlZst => "streaming_features.jsonl.zst",
+                },
+            ),
+            events: self.resolve_path(stream, interval, "events.jsonl"),
+            heartbeat: self.resolve_path(stream, interval, "heartbeat"),
+            recording: self.resolve_path(stream, interval, RECORDING_FILE),
+            quarantine: self.resolve_path(stream, interval, "quarantine.csv"),
+            audit: self.resolve_path(stream, interval, "signal_audit.jsonl"),
+            book_ticker: self.resolve_path(stream, interval, "book_ticker.csv"),
+            manifest: self.resolve_path(stream, interval, "run_manifest.json"),
+            feature_windows: self
+                .feature_windows
+                .iter()
+                .map(|w| {
+                    self.resolve_path(stream, interval, &format!("features_{}.parquet", w.name))
+                })
+                .collect(),
+        };
+
+        let mut seen = HashSet::new();
+        for path in paths.all() {
+            if !seen.insert(path) {
+                bail!("Output path collision: '{}' is used more than once", path);
+            }
+            if let Some(parent) = Path::new(path).parent() {
+                if !parent.as_os_str().is_empty() {
+                    std::fs::create_dir_all(parent)
+                        .with_context(|| format!("Failed to create {}", parent.display()))?;
+                }
+            }
+        }
+        Ok(paths)
+    }
+}
+
+/// Every file written by a kline stream.
+#[derive(Debug, Clone, Default)]
+pub struct OutputPaths {
+    /// Parquet snapshot of the raw rolling window (rewritten periodically).
+    pub raw_cache: String,
+    /// Raw candles closed since the last snapshot (emptied on every snapshot).
+    pub raw_journal: String,
+    /// Append-only raw candle log.
+    pub raw_csv: String,
+    /// Parquet snapshot of the feature window (overwritten).
+    pub feature_parquet: String,
+    /// Append-only feature log.
+    pub feature_csv: String,
+    /// Directory of the append-only feature-history Parquet dataset.
+    pub feature_history: String,
+    /// Second append-only feature log for separate pipelines (CSV, or
+    /// zstd-compressed JSON lines).
+    pub streaming_log: String,
+    /// Append-only structured JSON event log.
+    pub events: String,
+    /// Rewritten after every processed candle, for external supervisors.
+    pub heartbeat: String,
+    /// Raw frame recording (`--record`).
+    pub recording: String,
+    /// Candles the anomaly screen kept out of the window, with the reason.
+    pub quarantine: String,
+    /// Audit trail of strategy signals (JSON lines).
+    pub audit: String,
+    /// Sampled best bid/ask quotes (`[spread]`).
+    pub book_ticker: String,
+    /// Manifest of the run that wrote these outputs.
+    pub manifest: String,
+    /// Parquet snapshots of the `[[feature_windows]]`, in configuration order.
+    pub feature_windows: Vec<String>,
+}
+
+impl OutputPaths {
+    fn all(&self) -> Vec<&String> {
+        let mut all = vec![
+            &self.raw_cache,
+            &self.raw_journal,
+            &self.raw_csv,
+            &self.feature_parquet,
+            &self.feature_csv,
+            &self.feature_history,
+            &self.streaming_log,
+            &self.events,
+            &self.heartbeat,
+            &self.recording,
+            &self.quarantine,
+            &self.audit,
+            &self.book_ticker,
+            &self.manifest,
+        ];
+        all.extend(&self.feature_windows);
+        all
+    }
+}
@@ -0,0 +1,157 @@
+//! WebSocket connection statistics.
+//!
+//! Tracks uptime, reconnects, message counts per stream, the last disconnect
+//! reason and the longest gap between two messages. Values are mirrored into
+//! the metrics registry and summarized periodically in one log line.
+
+use crate::metrics;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub struct ConnectionStats {
+    started: Instant,
+    connected_since: Option<Instant>,
+    connects: u64,
+    total_messages: u64,
+    messages_per_stream: BTreeMap<String, u64>,
+    last_disconnect_reason: Option<String>,
+    last_message_at: Option<Instant>,
+    longest_silence: Duration,
+}
+
+pub type SharedConnectionStats = Arc<Mutex<ConnectionStats>>;
+
+/// Reconnects since start, by symbol, over all of its streams.
+fn symbol_reconnects() -> &'static Mutex<BTreeMap<String, u64>> {
+    static RECONNECTS: OnceLock<Mutex<BTreeMap<String, u64>>> = OnceLock::new();
+    RECONNECTS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// Count a reconnect of one of `symbol`'s streams.
+pub fn reconnected(symbol: &str) {
+    *symbol_reconnects()
+        .lock()
+        .unwrap()
+        .entry(symbol.to_string())
+        .or_insert(0) += 1;
+}
+
+/// Reconnects of `symbol`'s streams since start.
+pub fn reconnects_of(symbol: &str) -> u64 {
+    symbol_reconnects()
+        .lock()
+        .unwrap()
+        .get(symbol)
+        .copied()
+        .unwrap_or(0)
+}
+
+impl ConnectionStats {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            connected_since: None,
+            connects: 0,
+            total_messages: 0,
+            messages_per_stream: BTreeMap::new(),
+            last_disconnect_reason: None,
+            last_message_at: None,
+            longest_silence: Duration::ZERO,
+        }
+    }
+
+    /// Create shared stats and spawn a task that logs a summary every `period`.
+    pub fn spawn_shared(period: Duration) -> SharedConnectionStats {
+        let stats = Arc::new(Mutex::new(ConnectionStats::new()));
+        if !period.is_zero() {
+            let stats = stats.clone();
+            tokio::spawn(async move {
+                let mut ticker = tokio::time::interval(period);
+                ticker.tick().await; // first tick fires immediately
+                loop {
+                    ticker.tick().await;
+                    println!("{}", stats.lock().unwrap().summary_line());
+                }
+            });
+        }
+        stats
+    }
+
+    /// Number of reconnects (connections after the first one).
+    pub fn reconnects(&self) -> u64 {
+        self.connects.saturating_sub(1)
+    }
+
+    pub fn on_connect(&mut self) {
+        let now = Instant::now();
+        self.connects += 1;
+        self.connected_since = Some(now);
+        // Silence is measured per connection, not across the reconnect gap
+        self.last_message_at = Some(now);
+        metrics::counter_inc("ws_connects_total", &[], 1);
+        metrics::gauge_set("ws_reconnects", &[], self.reconnects() as f64);
+        metrics::gauge_set("ws_connected", &[], 1.0);
+    }
+
+    pub fn on_message(&mut self, stream: &str) {
+        let now = Instant::now();
+        if let Some(last) = self.last_message_at {
+            self.longest_silence = self.longest_silence.max(now - last);
+        }
+        self.last_message_at = Some(now);
+        self.total_messages += 1;
+        *self
+            .messages_per_stream
+            .entry(stream.to_string())
+            .or_insert(0) += 1;
+        metrics::counter_inc("ws_messages_total", &[("stream", stream)], 1);
+        metrics::gauge_set(
+            "ws_longest_silence_seconds",
+            &[],
+            self.longest_silence.as_secs_f64(),
+        );
+    }
+
+    pub fn on_disconnect(&mut self, reason: &str) {
+        self.connected_since = None;
+        self.last_message_at = None;
+        self.last_disconnect_reason = Some(reason.to_string());
+        metrics::gauge_set("ws_connected", &[], 0.0);
+    }
+
+    /// One-line summary for the periodic log.
+    pub fn summary_line(&self) -> String {
+        metrics::gauge_set(
+            "process_uptime_seconds",
+            &[],
+            self.started.elapsed().as_secs_f64(),
+        );
+        let connected = self
+            .connected_since
+            .map(|t| format!("{:.0}s", t.elapsed().as_secs_f64()))
+            .unwrap_or_else(|| "disconnected".to_string());
+        // Include the silence still in progress, not just completed gaps
+        let current_silence = self
+            .last_message_at
+            .map(|t| t.elapsed())
+            .unwrap_or_default();
+        let per_stream = self
+            .messages_per_stream
+            .iter()
+            .map(|(s, n)| format!("{}={}", s, n))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            "[Connection] uptime: {:.0}s | connected: {} | reconnects: {} | messages: {} ({}) | longest silence: {:.1}s | last disconnect: {}",
+            self.started.elapsed().as_secs_f64(),
+            connected,
+            self.reconnects(),
+            self.total_messages,
+            per_stream,
+            self.longest_silence.max(current_silence).as_secs_f64(),
+            self.last_disconnect_reason.as_deref().unwrap_or("none")
+        )
+    }
+}
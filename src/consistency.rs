@@ -0,0 +1,317 @@
+//! Candle consistency cross-check against REST.
+//!
+//! A corrupted or misparsed stream message produces a perfectly plausible
+//! candle that nothing downstream would question. Every `[consistency]
+//! every_secs` a background task fetches the newest closed candles over REST
+//! and compares them, field by field, with the candles the pipeline
+//! processed and stored. Differences beyond the tolerances (and candles the
+//! stream never delivered) raise a `consistency` alert, one
+//! `candle_mismatch` event per field and count in
+//! `candle_mismatches_total{symbol,field}`. A failed REST fetch is only
+//! logged, unless it is not retryable (a rejected symbol, a bad interval),
+//! which raises a `consistency` alert too.
+//!
+//! `prefer` decides which source is authoritative:
+//!
+//! - `stream` – the stored candle stays; the difference is only reported;
+//! - `rest` – REST candles closed at least `rest_after_mins` ago are queued
+//!   as corrections, which the pipeline applies before its next candle
+//!   (window, EMAs, raw log and cache, feature-history rows);
+//! - `hold` – the stored candle stays, but the symbol's signals are held
+//!   until a check finds the stored candles matching REST again.
+//!
+//! The `[scheduler.reconciliation]` job runs the check of every pipeline on
+//! its own schedule, on top of `every_secs` (which may then be 0).
+
+use crate::alerts::{self, Level};
+use crate::config::{CandleSourcePolicy, ConsistencyConfig};
+use crate::events::{self, Event};
+use crate::maintenance;
+use crate::metrics;
+use crate::timezone;
+use binance_streamer::exchange::{Exchange, MarketDataSource};
+use binance_streamer::kline::Kline;
+use chrono::Utc;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Woken by [`check_now`].
+fn reconcile() -> &'static Notify {
+    static RECONCILE: std::sync::OnceLock<Notify> = std::sync::OnceLock::new();
+    RECONCILE.get_or_init(Notify::new)
+}
+
+/// Run the check of every pipeline now. A check already running is not
+/// repeated.
+pub fn check_now() {
+    reconcile().notify_waiters();
+}
+
+/// The next tick of `ticker`, never without one.
+async fn next_tick(ticker: &mut Option<tokio::time::Interval>) {
+    match ticker {
+        Some(ticker) => {
+            ticker.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Handle used by the pipeline to report processed candles.
+#[derive(Clone)]
+pub struct ConsistencyCheck {
+    recent: Arc<Mutex<VecDeque<Kline>>>,
+    /// Candles kept for comparison.
+    keep: usize,
+    /// REST candles waiting to replace the stored ones (`prefer = "rest"`).
+    corrections: Arc<Mutex<Vec<Kline>>>,
+    /// Open times of stored candles found differing and kept, for the
+    /// quality flags.
+    mismatched: Arc<Mutex<Vec<i64>>>,
+    /// Signals are held while set (`prefer = "hold"`).
+    held: Arc<AtomicBool>,
+}
+
+impl ConsistencyCheck {
+    /// Start the check task of `symbol`'s `interval` candles against
+    /// `source`, the REST API of `exchange`.
+    pub fn spawn(
+        symbol: &str,
+        interval: &str,
+        exchange: Exchange,
+        source: Arc<dyn MarketDataSource>,
+        config: &ConsistencyConfig,
+    ) -> Self {
+        // Twice the fetched count, so a check never outruns the stored candles
+        let keep = 2 * config.candles.max(1);
+        let handle = ConsistencyCheck {
+            recent: Arc::new(Mutex::new(VecDeque::with_capacity(keep))),
+            keep,
+            corrections: Arc::new(Mutex::new(Vec::new())),
+            mismatched: Arc::new(Mutex::new(Vec::new())),
+            held: Arc::new(AtomicBool::new(false)),
+        };
+        let recent = handle.recent.clone();
+        let corrections = handle.corrections.clone();
+        let kept = handle.mismatched.clone();
+        let held = handle.held.clone();
+        let symbol = symbol.to_string();
+        let interval = interval.to_string();
+        let config = config.clone();
+
+        tokio::spawn(async move {
+            let mut ticker = (config.every_secs > 0)
+                .then(|| tokio::time::interval(Duration::from_secs(config.every_secs)));
+            if let Some(ticker) = &mut ticker {
+                ticker.tick().await; // first tick fires immediately
+            }
+            loop {
+                tokio::select! {
+                    _ = next_tick(&mut ticker) => {}
+                    _ = reconcile().notified() => {}
+                }
+                // Every handle dropped: the pipeline is gone (e.g. restarted)
+                if Arc::strong_count(&recent) == 1 {
+                    break;
+                }
+                // One more than asked for: the newest may still be forming
+                let fetched = match source
+                    .fetch_latest_klines(&symbol, &interval, config.candles + 1)
+                    .await
+                {
+                    Ok(fetched) => {
+                        maintenance::recovered(exchange);
+                        fetched
+                    }
+                    Err(e) => {
+                        eprintln!("[consistency] {}: REST fetch failed: {}", symbol, e);
+                        if e.is_maintenance() {
+                            maintenance::detected(exchange, &format!("REST answered {}", e));
+                        }
+                        // A network hiccup clears up by itself; a rejected request does not
+                        if !e.is_retryable() {
+                            alerts::raise(
+                                Level::Warning,
+                                "consistency",
+                                &format!("{} REST candle check failed: {}", symbol, e),
+                            );
+                        }
+                        continue;
+                    }
+                };
+                let now = Utc::now().timestamp_millis();
+                let rest: Vec<Kline> = fetched.into_iter().filter(|k| k.close_time < now).collect();
+                let stored: Vec<Kline> = recent.lock().unwrap().iter().cloned().collect();
+                let mismatched = check(&symbol, &interval, &rest, &stored, &config);
+                // Candles the stream never delivered have no row to flag
+                kept.lock().unwrap().extend(
+                    mismatched
+                        .iter()
+                        .filter(|t| stored.iter().any(|k| k.open_time == **t)),
+                );
+                match config.prefer {
+                    CandleSourcePolicy::Stream => {}
+                    CandleSourcePolicy::Rest => {
+                        let settled = now - config.rest_after_mins as i64 * 60_000;
+                        let mut queued = corrections.lock().unwrap();
+                        let mut recent = recent.lock().unwrap();
+                        for kline in rest.iter().filter(|k| {
+                            mismatched.contains(&k.open_time) && k.close_time <= settled
+                        }) {
+                            // Only stored candles can be replaced; gaps are backfilled elsewhere
+                            let Some(stored) =
+                                recent.iter_mut().find(|k| k.open_time == kline.open_time)
+                            else {
+                                continue;
+                            };
+                            *stored = kline.clone();
+                            queued.retain(|k| k.open_time != kline.open_time);
+                            queued.push(kline.clone());
+                        }
+                    }
+                    CandleSourcePolicy::Hold => {
+                        let hold = !mismatched.is_empty();
+                        if held.swap(hold, Ordering::Relaxed) != hold {
+                            println!(
+                                "[consistency] {}: {} signals",
+                                symbol,
+                                if hold { "holding" } else { "releasing" }
+                            );
+                        }
+                        metrics::gauge_set(
+                            "candle_source_hold",
+                            &[("symbol", &symbol)],
+                            hold as u8 as f64,
+                        );
+                    }
+                }
+            }
+        });
+
+        handle
+    }
+
+    /// Record a candle as the pipeline processed it.
+    pub fn record(&self, kline: &Kline) {
+        let mut recent = self.recent.lock().unwrap();
+        recent.push_back(kline.clone());
+        while recent.len() > self.keep {
+            recent.pop_front();
+        }
+    }
+
+    /// Take the REST candles queued to replace stored ones.
+    pub fn take_corrections(&self) -> Vec<Kline> {
+        std::mem::take(&mut *self.corrections.lock().unwrap())
+    }
+
+    /// Take the open times of the stored candles found differing from REST
+    /// since the last call. Candles later replaced by corrections are among
+    /// them too.
+    pub fn take_mismatches(&self) -> Vec<i64> {
+        std::mem::take(&mut *self.mismatched.lock().unwrap())
+    }
+
+    /// Whether the symbol's signals are held until its stored candles match
+    /// REST again.
+    pub fn held(&self) -> bool {
+        self.held.load(Ordering::Relaxed)
+    }
+}
+
+/// Compare the REST candles with the stored ones and report differences;
+/// returns the open times of the candles that differ.
+fn check(
+    symbol: &str,
+    interval: &str,
+    rest: &[Kline],
+    stored: &[Kline],
+    config: &ConsistencyConfig,
+) -> Vec<i64> {
+    let Some(oldest) = stored.first().map(|k| k.open_time) else {
+        return Vec::new();
+    };
+    let newest = stored.last().map_or(oldest, |k| k.open_time);
+    metrics::counter_inc("consistency_checks_total", &[("symbol", symbol)], 1);
+
+    let mut mismatches = Vec::new();
+    // Only candles the stream should already have delivered
+    for expected in rest
+        .iter()
+        .filter(|k| k.open_time >= oldest && k.open_time <= newest)
+    {
+        let Some(got) = stored.iter().find(|k| k.open_time == expected.open_time) else {
+            mismatches.push((expected.open_time, "missing", f64::NAN, expected.close));
+            continue;
+        };
+        for (field, stream_value, rest_value, tolerance) in [
+            ("open", got.open, expected.open, config.tolerance),
+            ("high", got.high, expected.high, config.tolerance),
+            ("low", got.low, expected.low, config.tolerance),
+            ("close", got.close, expected.close, config.tolerance),
+            (
+                "volume",
+                got.volume,
+                expected.volume,
+                config.volume_tolerance,
+            ),
+        ] {
+            let scale = rest_value.abs().max(f64::EPSILON);
+            if (stream_value - rest_value).abs() / scale > tolerance {
+                mismatches.push((expected.open_time, field, stream_value, rest_value));
+            }
+        }
+    }
+
+    if mismatches.is_empty() {
+        println!(
+            "[consistency] {}: stored {} candles match REST",
+            symbol, interval
+        );
+        return Vec::new();
+    }
+    for &(open_time, field, stream_value, rest_value) in &mismatches {
+        metrics::counter_inc(
+            "candle_mismatches_total",
+            &[("symbol", symbol), ("field", field)],
+            1,
+        );
+        events::emit(Event::CandleMismatch {
+            symbol: symbol.to_string(),
+            interval: interval.to_string(),
+            open_time,
+            field: field.to_string(),
+            stream: stream_value,
+            rest: rest_value,
+        });
+    }
+    let details: Vec<String> = mismatches
+        .iter()
+        .map(|(open_time, field, stream_value, rest_value)| {
+            format!(
+                "{} {} stream {} vs REST {}",
+                timezone::format_ms_secs(*open_time),
+                field,
+                stream_value,
+                rest_value
+            )
+        })
+        .collect();
+    alerts::raise(
+        Level::Warning,
+        "consistency",
+        &format!(
+            "{}: {} {} value(s) differ from REST: {}",
+            symbol,
+            mismatches.len(),
+            interval,
+            details.join("; ")
+        ),
+    );
+    let mut open_times: Vec<i64> = mismatches.iter().map(|m| m.0).collect();
+    open_times.dedup();
+    open_times
+}
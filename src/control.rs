@@ -0,0 +1,365 @@
+//! Control endpoint (`[control]`): operate a running instance without a
+//! restart, so the in-memory windows survive.
+//!
+//! Every route is a `POST` under `/control` on the HTTP server, needs
+//! `Authorization: Bearer <token>` and, unless `allow_remote`, a loopback
+//! peer. `symbol` and `interval` query parameters narrow a command to some
+//! pipelines; each pipeline carries it out between two candles and answers
+//! within `timeout_secs`:
+//!
+//! - `pause` / `resume` – a paused pipeline keeps taking candles into its
+//!   window, EMAs and raw logs, but computes no feature rows and evaluates
+//!   no strategy until resumed;
+//! - `flush` – write the raw cache snapshot (superseding the journal) and
+//!   the pending frame of the streaming log now;
+//! - `rotate` – move the feature CSV and streaming log aside under a
+//!   timestamped name and start new ones; without a `symbol` also the events
+//!   file. The raw CSV log stays, reports read it whole;
+//! - `reload` – read the `--config` / `--params` files again and rebuild
+//!   every pipeline's strategies from their `[[strategies]]` (the strategies
+//!   start over on the next candle);
+//! - `flatten` – close the open paper positions (also narrowed by
+//!   `strategy`) with `flat` signals at their last price;
+//! - `log-level?level=debug|info|warn` – the routine per-candle output;
+//! - `strategies/{strategy}/enable` – re-enable a strategy `[kill]` disabled.
+//!
+//! Every command carried out writes a `control` event.
+
+use crate::config::{CliArgs, Config, ControlConfig, LogLevel};
+use crate::events::{self, Event};
+use crate::{kill, signals};
+use axum::extract::{ConnectInfo, Path, Query, Request as HttpRequest, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+/// Commands queued per pipeline.
+const QUEUE: usize = 8;
+
+/// A command for one pipeline.
+pub enum Command {
+    Pause,
+    Resume,
+    Flush,
+    Rotate,
+    /// Rebuild the strategies from this configuration.
+    Reload(Arc<Config>),
+}
+
+impl Command {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Command::Pause => "pause",
+            Command::Resume => "resume",
+            Command::Flush => "flush",
+            Command::Rotate => "rotate",
+            Command::Reload(_) => "reload",
+        }
+    }
+}
+
+/// A command and where the pipeline answers what it did.
+pub struct Request {
+    pub command: Command,
+    pub reply: oneshot::Sender<Result<String, String>>,
+}
+
+type Key = (String, String);
+
+fn pipelines() -> &'static Mutex<BTreeMap<Key, mpsc::Sender<Request>>> {
+    static PIPELINES: OnceLock<Mutex<BTreeMap<Key, mpsc::Sender<Request>>>> = OnceLock::new();
+    PIPELINES.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// The commands for the pipeline of `symbol` and `interval`; a restarted
+/// pipeline takes over from its predecessor.
+pub fn register(symbol: &str, interval: &str) -> mpsc::Receiver<Request> {
+    let (sender, receiver) = mpsc::channel(QUEUE);
+    pipelines()
+        .lock()
+        .unwrap()
+        .insert((symbol.to_string(), interval.to_string()), sender);
+    receiver
+}
+
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+/// The routine output pipelines print from now on.
+pub fn set_log_level(level: LogLevel) {
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn log_level() -> LogLevel {
+    match LOG_LEVEL.load(Ordering::Relaxed) {
+        l if l == LogLevel::Debug as u8 => LogLevel::Debug,
+        l if l == LogLevel::Warn as u8 => LogLevel::Warn,
+        _ => LogLevel::Info,
+    }
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, Json(json!({ "error": message.into() }))).into_response()
+}
+
+fn record(command: &str, detail: String) {
+    println!("[control] {}: {}", command, detail);
+    events::emit(Event::Control {
+        command: command.to_string(),
+        detail,
+    });
+}
+
+/// Whether the credential `given` is the non-empty `token`, compared in
+/// constant time.
+pub fn authorized(given: &str, token: &str) -> bool {
+    !token.is_empty()
+        && given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn guard(
+    State(config): State<Arc<ControlConfig>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: HttpRequest,
+    next: Next,
+) -> Response {
+    if !config.allow_remote && !peer.ip().is_loopback() {
+        return error(
+            StatusCode::FORBIDDEN,
+            "control requests are only accepted from the loopback address",
+        );
+    }
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !authorized(bearer, config.token.as_deref().unwrap_or_default()) {
+        return error(StatusCode::UNAUTHORIZED, "missing or invalid bearer token");
+    }
+    next.run(request).await
+}
+
+/// The `/control` routes (served with `ConnectInfo<SocketAddr>`).
+pub fn router(config: &ControlConfig) -> Router {
+    let state = Arc::new(config.clone());
+    Router::new()
+        .route("/control/pause", post(pause))
+        .route("/control/resume", post(resume))
+        .route("/control/flush", post(flush))
+        .route("/control/rotate", post(rotate))
+        .route("/control/reload", post(reload))
+        .route("/control/flatten", post(flatten))
+        .route("/control/log-level", post(log_level_route))
+        .route(
+            "/control/strategies/{strategy}/enable",
+            post(enable_strategy),
+        )
+        .layer(middleware::from_fn_with_state(Arc::clone(&state), guard))
+        .with_state(state)
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Filter {
+    symbol: Option<String>,
+    interval: Option<String>,
+    strategy: Option<String>,
+    level: Option<String>,
+}
+
+impl Filter {
+    fn matches(&self, (symbol, interval): &Key) -> bool {
+        self.symbol.as_deref().is_none_or(|s| s == symbol)
+            && self.interval.as_deref().is_none_or(|i| i == interval)
+    }
+}
+
+/// Send a command (built per pipeline by `command`) to every matching
+/// pipeline and collect their answers.
+async fn dispatch(
+    filter: &Filter,
+    timeout: Duration,
+    command: impl Fn() -> Command,
+) -> Result<Vec<Value>, Response> {
+    let targets: Vec<(Key, mpsc::Sender<Request>)> = {
+        let mut pipelines = pipelines().lock().unwrap();
+        pipelines.retain(|_, sender| !sender.is_closed());
+        pipelines
+            .iter()
+            .filter(|(key, _)| filter.matches(key))
+            .map(|(key, sender)| (key.clone(), sender.clone()))
+            .collect()
+    };
+    if targets.is_empty() {
+        return Err(error(StatusCode::NOT_FOUND, "no running pipeline matches"));
+    }
+    let mut results = Vec::with_capacity(targets.len());
+    for ((symbol, interval), sender) in targets {
+        let command = command();
+        let name = command.as_str();
+        let (reply, answer) = oneshot::channel();
+        let outcome = match sender.send(Request { command, reply }).await {
+            Err(_) => Err("the pipeline stopped".to_string()),
+            Ok(()) => match tokio::time::timeout(timeout, answer).await {
+                Ok(Ok(outcome)) => outcome,
+                Ok(Err(_)) => Err("the pipeline stopped".to_string()),
+                Err(_) => Err(format!(
+                    "no answer within {}s; still queued",
+                    timeout.as_secs()
+                )),
+            },
+        };
+        let stream = format!("{} {}", symbol, interval);
+        results.push(match outcome {
+            Ok(detail) => {
+                record(name, format!("{}: {}", stream, detail));
+                json!({ "symbol": symbol, "interval": interval, "ok": true, "detail": detail })
+            }
+            Err(e) => {
+                eprintln!("[control] {} failed on {}: {}", name, stream, e);
+                json!({ "symbol": symbol, "interval": interval, "ok": false, "error": e })
+            }
+        });
+    }
+    Ok(results)
+}
+
+async fn run(config: &ControlConfig, filter: &Filter, command: impl Fn() -> Command) -> Response {
+    match dispatch(filter, Duration::from_secs(config.timeout_secs), command).await {
+        Ok(results) => Json(json!({ "pipelines": results })).into_response(),
+        Err(response) => response,
+    }
+}
+
+async fn pause(State(config): State<Arc<ControlConfig>>, Query(filter): Query<Filter>) -> Response {
+    run(&config, &filter, || Command::Pause).await
+}
+
+async fn resume(
+    State(config): State<Arc<ControlConfig>>,
+    Query(filter): Query<Filter>,
+) -> Response {
+    run(&config, &filter, || Command::Resume).await
+}
+
+async fn flush(State(config): State<Arc<ControlConfig>>, Query(filter): Query<Filter>) -> Response {
+    run(&config, &filter, || Command::Flush).await
+}
+
+async fn rotate(
+    State(config): State<Arc<ControlConfig>>,
+    Query(filter): Query<Filter>,
+) -> Response {
+    let response = run(&config, &filter, || Command::Rotate).await;
+    if filter.symbol.is_some() || filter.interval.is_some() {
+        return response;
+    }
+    match events::rotate() {
+        Ok(Some(rotated)) => record("rotate", format!("events → {}", rotated)),
+        Ok(None) => {}
+        Err(e) => eprintln!("[control] rotate failed on the events file: {:#}", e),
+    }
+    response
+}
+
+async fn reload(
+    State(config): State<Arc<ControlConfig>>,
+    Query(filter): Query<Filter>,
+) -> Response {
+    // The files named on the command line, read again
+    let loaded = tokio::task::spawn_blocking(|| {
+        let cli = CliArgs::parse(std::env::args().skip(1))?;
+        let reloaded = Config::load(&cli)?;
+        reloaded.build_strategies()?;
+        anyhow::Ok(reloaded)
+    })
+    .await;
+    let reloaded = match loaded {
+        Ok(Ok(reloaded)) => Arc::new(reloaded),
+        Ok(Err(e)) => return error(StatusCode::BAD_REQUEST, format!("{:#}", e)),
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    run(&config, &filter, || Command::Reload(Arc::clone(&reloaded))).await
+}
+
+async fn flatten(Query(filter): Query<Filter>) -> Response {
+    let flats = kill::flatten(
+        filter.symbol.as_deref(),
+        filter.interval.as_deref(),
+        filter.strategy.as_deref(),
+    );
+    let closed: Vec<Value> = flats
+        .iter()
+        .map(|flat| {
+            json!({
+                "symbol": flat.symbol,
+                "interval": flat.interval,
+                "strategy": flat.strategy,
+                "price": flat.price,
+            })
+        })
+        .collect();
+    for flat in flats {
+        record(
+            "flatten",
+            format!(
+                "{} on {} {} @ {}",
+                flat.strategy, flat.symbol, flat.interval, flat.price
+            ),
+        );
+        signals::deliver(flat);
+    }
+    Json(json!({ "flattened": closed })).into_response()
+}
+
+async fn log_level_route(Query(filter): Query<Filter>) -> Response {
+    let Some(level) = &filter.level else {
+        return error(StatusCode::BAD_REQUEST, "missing ?level=debug|info|warn");
+    };
+    match LogLevel::parse(level) {
+        Ok(level) => {
+            set_log_level(level);
+            record("log-level", level.as_str().to_string());
+            Json(json!({ "log_level": level.as_str() })).into_response()
+        }
+        Err(e) => error(StatusCode::BAD_REQUEST, e.to_string()),
+    }
+}
+
+async fn enable_strategy(Path(strategy): Path<String>, Query(filter): Query<Filter>) -> Response {
+    let enabled = kill::enable(
+        &strategy,
+        filter.symbol.as_deref(),
+        filter.interval.as_deref(),
+    );
+    if enabled.is_empty() {
+        return error(
+            StatusCode::NOT_FOUND,
+            format!("{} is not disabled on any matching stream", strategy),
+        );
+    }
+    let streams: Vec<_> = enabled
+        .into_iter()
+        .map(|(symbol, interval)| json!({ "symbol": symbol, "interval": interval }))
+        .collect();
+    record(
+        "enable",
+        format!("{} on {} stream(s)", strategy, streams.len()),
+    );
+    Json(json!({ "strategy": strategy, "enabled": streams })).into_response()
+}
@@ -0,0 +1,95 @@
+//! Crash reports for post-mortems of unattended runs.
+//!
+//! On a panic (via the panic hook) or a fatal error returned from `main`, a
+//! report with the reason, backtrace, recent events, a config summary and the
+//! process's open file handles is written to `{data_dir}/crash/`.
+
+use crate::config::Config;
+use crate::events;
+use chrono::Utc;
+use std::backtrace::Backtrace;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Directory and config summary captured at startup.
+struct CrashContext {
+    dir: PathBuf,
+    config_summary: String,
+}
+
+static CONTEXT: OnceLock<CrashContext> = OnceLock::new();
+
+/// Remember where reports go and install the panic hook.
+/// The previous hook still runs afterwards, so the usual panic message is kept.
+pub fn install(config: &Config) {
+    let _ = CONTEXT.set(CrashContext {
+        dir: Path::new(&config.data_dir).join("crash"),
+        config_summary: format!("{:#?}", config),
+    });
+
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let backtrace = Backtrace::force_capture();
+        write_report(&format!("panic: {}", info), &backtrace.to_string());
+        previous(info);
+    }));
+}
+
+/// Write a crash report for a fatal error returned from `main`.
+pub fn report_error(error: &anyhow::Error) {
+    write_report(
+        &format!("fatal error: {:#}", error),
+        &error.backtrace().to_string(),
+    );
+}
+
+fn write_report(reason: &str, backtrace: &str) {
+    let Some(ctx) = CONTEXT.get() else {
+        return;
+    };
+
+    let now = Utc::now();
+    let mut report = String::new();
+    let _ = writeln!(report, "=== Crash report ===");
+    let _ = writeln!(report, "time: {}", now.format("%Y-%m-%d %H:%M:%S%.3f UTC"));
+    let _ = writeln!(report, "pid: {}", std::process::id());
+    let _ = writeln!(report, "reason: {}\n", reason);
+    let _ = writeln!(report, "=== Backtrace ===\n{}\n", backtrace);
+
+    let recent = events::recent();
+    let _ = writeln!(report, "=== Last {} events ===", recent.len());
+    for line in &recent {
+        let _ = writeln!(report, "{}", line);
+    }
+
+    let _ = writeln!(report, "\n=== Config ===\n{}\n", ctx.config_summary);
+    let _ = writeln!(report, "=== Open file handles ===\n{}", open_file_handles());
+
+    let path = ctx
+        .dir
+        .join(format!("crash-{}.txt", now.format("%Y%m%d-%H%M%S%.3f")));
+    let result = std::fs::create_dir_all(&ctx.dir).and_then(|_| std::fs::write(&path, report));
+    match result {
+        Ok(()) => eprintln!("Crash report written to {}", path.display()),
+        Err(e) => eprintln!("Failed to write crash report {}: {}", path.display(), e),
+    }
+}
+
+/// List open file descriptors and their targets (Linux /proc only).
+fn open_file_handles() -> String {
+    let Ok(entries) = std::fs::read_dir("/proc/self/fd") else {
+        return "(unavailable on this platform)".to_string();
+    };
+    let mut lines: Vec<String> = entries
+        .flatten()
+        .map(|entry| {
+            let target = std::fs::read_link(entry.path())
+                .map(|t| t.display().to_string())
+                .unwrap_or_else(|_| "?".to_string());
+            format!("{} -> {}", entry.file_name().to_string_lossy(), target)
+        })
+        .collect();
+    lines.sort();
+    lines.join("\n")
+}
@@ -1,10 +1,16 @@
+use crate::error::{Error, Result};
+use crate::exchange::BookQuote;
 use crate::kline::Kline;
-use anyhow::Result;
+use crate::query::{self, TimeRange};
 use chrono::{DateTime, Utc};
 use polars::prelude::*;
 
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Write};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
 use tokio::task;
 
 /// Convert milliseconds to a human‑readable UTC string (e.g., "2025-03-21 14:32:17.456 UTC").
@@ -50,7 +56,22 @@ pub fn load_dataframe(path: &str) -> Result<DataFrame> {
 /// Load klines from a Parquet file (returns Vec<Kline> for convenience).
 pub fn load_klines_from_parquet(path: &str) -> Result<Vec<Kline>> {
     let df = load_dataframe(path)?;
-    // Convert back to Vec<Kline>
+    dataframe_to_klines(&df)
+}
+
+/// Load the rows of a Parquet file whose `open_time` lies in `[from, to]` (ms, inclusive).
+/// The filter is pushed down into the scan, so only matching rows are materialized.
+pub fn load_dataframe_range(path: &str, from: Option<i64>, to: Option<i64>) -> Result<DataFrame> {
+    Ok(query::scan_range(path, TimeRange { from, to })?.collect()?)
+}
+
+/// Load the klines of a Parquet file whose `open_time` lies in `[from, to]` (ms, inclusive).
+pub fn load_klines_range(path: &str, from: Option<i64>, to: Option<i64>) -> Result<Vec<Kline>> {
+    dataframe_to_klines(&load_dataframe_range(path, from, to)?)
+}
+
+/// Convert a DataFrame with the kline columns back into `Vec<Kline>`.
+pub fn dataframe_to_klines(df: &DataFrame) -> Result<Vec<Kline>> {
     let open_time = df.column("open_time")?.i64()?;
     let open = df.column("open")?.f64()?;
     let high = df.column("high")?.f64()?;
@@ -82,6 +103,135 @@ pub fn save_klines_to_parquet(klines: &[Kline], path: &str) -> Result<()> {
     Ok(())
 }
 
+/// How float values are written to the text outputs: the feature CSVs, the
+/// JSON-lines streaming log and the `--emit` lines. Parquet files always keep
+/// full precision.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DecimalFormat {
+    /// Decimal places of every float column; full precision if unset.
+    pub precision: Option<usize>,
+    /// Decimal places by column name, overriding `precision`.
+    pub columns: BTreeMap<String, usize>,
+    /// Drop the trailing zeros (and a trailing point) of CSV values.
+    pub trim_zeros: bool,
+}
+
+impl DecimalFormat {
+    /// Decimal places of `column`, `None` for full precision.
+    pub fn places(&self, column: &str) -> Option<usize> {
+        self.columns.get(column).copied().or(self.precision)
+    }
+
+    /// Whether floats of `column` are written other than at full precision.
+    pub fn applies(&self, column: &str) -> bool {
+        self.trim_zeros || self.places(column).is_some()
+    }
+
+    /// `value` of `column` as CSV text.
+    pub fn format(&self, column: &str, value: f64) -> String {
+        match self.places(column) {
+            Some(places) if value.is_finite() => self.trim(format!("{:.*}", places, value)),
+            _ => self.trim(value.to_string()),
+        }
+    }
+
+    /// `text` without trailing zeros, with `trim_zeros`.
+    fn trim(&self, text: String) -> String {
+        if self.trim_zeros && text.contains('.') && !text.contains(['e', 'E']) {
+            text.trim_end_matches('0').trim_end_matches('.').to_string()
+        } else {
+            text
+        }
+    }
+
+    /// `value` of `column` rounded to its decimal places, for JSON.
+    pub fn round(&self, column: &str, value: f64) -> f64 {
+        match self.places(column) {
+            Some(places) if value.is_finite() => {
+                format!("{:.*}", places, value).parse().unwrap_or(value)
+            }
+            _ => value,
+        }
+    }
+
+    /// Round the float values of a JSON row (see [`row_to_json`]).
+    pub fn round_row(&self, row: &mut serde_json::Map<String, serde_json::Value>) {
+        if self.precision.is_none() && self.columns.is_empty() {
+            return;
+        }
+        for (column, value) in row.iter_mut() {
+            if let Some(v) = value.as_f64().filter(|_| value.is_f64()) {
+                if let Some(number) = serde_json::Number::from_f64(self.round(column, v)) {
+                    *value = serde_json::Value::Number(number);
+                }
+            }
+        }
+    }
+
+    /// CSV text of one cell of `column`; floats as polars prints them unless
+    /// a format is set.
+    fn cell(&self, column: &str, value: &AnyValue) -> String {
+        let formatted = self.applies(column);
+        match value {
+            AnyValue::Float64(v) if formatted => self.format(column, *v),
+            AnyValue::Float32(v) if formatted => match self.places(column) {
+                Some(_) => self.format(column, *v as f64),
+                // Widening would print the float32 rounding error
+                None => self.trim(v.to_string()),
+            },
+            other => format!("{}", other),
+        }
+    }
+}
+
+fn shared_format() -> &'static RwLock<DecimalFormat> {
+    static FORMAT: OnceLock<RwLock<DecimalFormat>> = OnceLock::new();
+    FORMAT.get_or_init(|| RwLock::new(DecimalFormat::default()))
+}
+
+/// Format of the floats written to the text outputs from now on.
+pub fn set_decimal_format(format: DecimalFormat) {
+    *shared_format().write().unwrap() = format;
+}
+
+/// The current format of the floats in the text outputs.
+pub fn decimal_format() -> DecimalFormat {
+    shared_format().read().unwrap().clone()
+}
+
+/// Convert one DataFrame row into a JSON object (column name → value).
+/// Datetimes become epoch milliseconds; NaN and null become `null`.
+pub fn row_to_json(
+    df: &DataFrame,
+    row: usize,
+) -> Result<serde_json::Map<String, serde_json::Value>> {
+    use serde_json::Value;
+
+    let mut map = serde_json::Map::new();
+    for column in df.columns() {
+        let value = match column.get(row)? {
+            AnyValue::Null => Value::Null,
+            AnyValue::Boolean(b) => Value::Bool(b),
+            AnyValue::String(s) => Value::String(s.to_string()),
+            AnyValue::StringOwned(s) => Value::String(s.to_string()),
+            AnyValue::Datetime(v, unit, _) => Value::from(match unit {
+                TimeUnit::Nanoseconds => v / 1_000_000,
+                TimeUnit::Microseconds => v / 1_000,
+                TimeUnit::Milliseconds => v,
+            }),
+            v if v.is_integer() => v.extract::<i64>().map_or(Value::Null, Value::from),
+            v if v.is_float() => v
+                .extract::<f64>()
+                .and_then(serde_json::Number::from_f64)
+                .map_or(Value::Null, Value::Number),
+            other => Value::String(other.to_string()),
+        };
+        map.insert(column.name().to_string(), value);
+    }
+    Ok(map)
+}
+
 /// Append a single Kline to a CSV file. If the file does not exist, headers are written first.
 pub fn append_kline_to_csv(kline: &Kline, path: &str) -> Result<()> {
     let file_exists = std::path::Path::new(path).exists();
@@ -94,8 +244,14 @@ pub fn append_kline_to_csv(kline: &Kline, path: &str) -> Result<()> {
     }
 
     // Write data line
-    writeln!(
-        file,
+    writeln!(file, "{}", raw_csv_line(kline))?;
+
+    Ok(())
+}
+
+/// A candle as a line of the raw CSV log.
+fn raw_csv_line(kline: &Kline) -> String {
+    format!(
         "{},{},{},{},{},{},{}",
         timestamp_to_string(kline.open_time),
         kline.open,
@@ -104,11 +260,113 @@ pub fn append_kline_to_csv(kline: &Kline, path: &str) -> Result<()> {
         kline.close,
         kline.volume,
         timestamp_to_string(kline.close_time)
+    )
+}
+
+/// Replace the lines of `klines` (matched by open time) in a raw CSV log
+/// written by [`append_kline_to_csv`]; returns how many were replaced. The
+/// log is rewritten to a temporary file and renamed over the original.
+pub fn rewrite_csv_klines(path: &str, klines: &[Kline]) -> Result<usize> {
+    if !std::path::Path::new(path).exists() {
+        return Ok(0);
+    }
+    let by_open_time: HashMap<String, &Kline> = klines
+        .iter()
+        .map(|k| (timestamp_to_string(k.open_time), k))
+        .collect();
+    let tmp = format!("{}.tmp", path);
+    let mut out = BufWriter::new(File::create(&tmp)?);
+    let mut replaced = 0;
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let open_time = line.split(',').next().unwrap_or_default();
+        match by_open_time.get(open_time) {
+            Some(kline) => {
+                writeln!(out, "{}", raw_csv_line(kline))?;
+                replaced += 1;
+            }
+            None => writeln!(out, "{}", line)?,
+        }
+    }
+    out.flush()?;
+    drop(out);
+    std::fs::rename(&tmp, path)?;
+    Ok(replaced)
+}
+
+/// Append a single Kline to a journal CSV. Unlike the raw log, values are
+/// written exactly (epoch-ms timestamps) so the journal can be read back.
+pub fn append_kline_to_journal(kline: &Kline, path: &str) -> Result<()> {
+    let file_exists = std::path::Path::new(path).exists();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if !file_exists {
+        writeln!(file, "open_time,open,high,low,close,volume,close_time")?;
+    }
+    writeln!(
+        file,
+        "{},{},{},{},{},{},{}",
+        kline.open_time,
+        kline.open,
+        kline.high,
+        kline.low,
+        kline.close,
+        kline.volume,
+        kline.close_time
     )?;
+    Ok(())
+}
 
+/// Append a best bid/ask quote to a CSV (epoch-ms time).
+pub fn append_quote_to_csv(quote: &BookQuote, path: &str) -> Result<()> {
+    let file_exists = std::path::Path::new(path).exists();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if !file_exists {
+        writeln!(file, "time,bid,bid_qty,ask,ask_qty")?;
+    }
+    writeln!(
+        file,
+        "{},{},{},{},{}",
+        quote.time, quote.bid, quote.bid_qty, quote.ask, quote.ask_qty
+    )?;
     Ok(())
 }
 
+/// Read a journal written by `append_kline_to_journal` (empty if the file does
+/// not exist). A line cut short by a crash is skipped with a warning.
+pub fn load_journal(path: &str) -> Result<Vec<Kline>> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut klines = Vec::new();
+    for line in text.lines().skip(1).filter(|l| !l.is_empty()) {
+        let fields: Vec<&str> = line.split(',').collect();
+        let parsed = (|| -> Result<Kline> {
+            let [open_time, open, high, low, close, volume, close_time] = fields[..] else {
+                return Err(Error::Parse("expected 7 fields".to_string()));
+            };
+            Ok(Kline {
+                open_time: open_time.parse()?,
+                open: open.parse()?,
+                high: high.parse()?,
+                low: low.parse()?,
+                close: close.parse()?,
+                volume: volume.parse()?,
+                close_time: close_time.parse()?,
+            })
+        })();
+        match parsed {
+            Ok(kline) => klines.push(kline),
+            Err(e) => eprintln!(
+                "Warning: skipping journal line '{}' in {}: {}",
+                line, path, e
+            ),
+        }
+    }
+    Ok(klines)
+}
+
 pub fn append_features_row_to_csv(df: &DataFrame, path: &str) -> Result<()> {
     let file_exists = std::path::Path::new(path).exists();
     let mut file = OpenOptions::new().create(true).append(true).open(path)?;
@@ -130,6 +388,7 @@ pub fn append_features_row_to_csv(df: &DataFrame, path: &str) -> Result<()> {
     }
 
     // Collect values from the single row, converting timestamps to readable strings
+    let format = decimal_format();
     let mut values = Vec::new();
     for col_name in last_row_df.get_column_names() {
         let series = last_row_df.column(col_name)?;
@@ -141,7 +400,7 @@ pub fn append_features_row_to_csv(df: &DataFrame, path: &str) -> Result<()> {
                 _ => format!("{}", val), // fallback (should not happen)
             }
         } else {
-            format!("{}", val)
+            format.cell(col_name, &val)
         };
         values.push(s);
     }
@@ -150,6 +409,271 @@ pub fn append_features_row_to_csv(df: &DataFrame, path: &str) -> Result<()> {
     Ok(())
 }
 
+/// Append-only JSON-lines log written as a sequence of zstd frames.
+///
+/// Rows are buffered until `frame_rows` of them are pending, then compressed
+/// into one complete frame and appended, so the file is always a valid zstd
+/// stream up to its last frame: `zstdcat` and `tail -f | zstd -dc` read it
+/// while it grows. Pending rows are written when the log is dropped; a killed
+/// process loses at most `frame_rows - 1` of them.
+#[derive(Debug)]
+pub struct ZstdJsonlLog {
+    path: String,
+    level: i32,
+    frame_rows: usize,
+    pending: Vec<u8>,
+    rows: usize,
+}
+
+impl ZstdJsonlLog {
+    pub fn new(path: &str, level: i32, frame_rows: usize) -> Self {
+        Self {
+            path: path.to_string(),
+            level,
+            frame_rows: frame_rows.max(1),
+            pending: Vec::new(),
+            rows: 0,
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Append the last row of `df` (see [`row_to_json`]).
+    pub fn append_row(&mut self, df: &DataFrame) -> Result<()> {
+        let Some(last) = df.height().checked_sub(1) else {
+            return Ok(()); // nothing to append
+        };
+        let mut row = row_to_json(df, last)?;
+        decimal_format().round_row(&mut row);
+        serde_json::to_writer(&mut self.pending, &row)?;
+        self.pending.push(b'\n');
+        self.rows += 1;
+        if self.rows >= self.frame_rows {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Compress the pending rows into one frame and append it.
+    pub fn flush(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let frame = zstd::bulk::compress(&self.pending, self.level)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&frame)?;
+        self.pending.clear();
+        self.rows = 0;
+        Ok(())
+    }
+}
+
+impl Drop for ZstdJsonlLog {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            eprintln!("Failed to flush {}: {}", self.path, e);
+        }
+    }
+}
+
+/// Append the last row of `df` to the feature-history dataset under `dir`.
+///
+/// Rows are grouped in daily partitions (`{dir}/date=YYYY-MM-DD/`, by the UTC
+/// day of `open_time`). Each row is first written as its own small file
+/// (`{open_time}.parquet`, so re-appending a candle overwrites it); when the
+/// first row of a new day arrives, earlier partitions are compacted into a
+/// single `data.parquet`. Read the dataset with
+/// [`query::load_feature_history_lazy`].
+pub fn append_feature_history_row(df: &DataFrame, dir: &str) -> Result<()> {
+    let mut row = df.tail(Some(1));
+    let Some(open_time) = row.column("open_time")?.i64()?.get(0) else {
+        return Ok(()); // nothing to append
+    };
+    let day = DateTime::<Utc>::from_timestamp_millis(open_time)
+        .map(|dt| dt.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| "invalid".to_string());
+    let partition = Path::new(dir).join(format!("date={}", day));
+    let new_day = !partition.exists();
+    std::fs::create_dir_all(&partition)?;
+
+    let file = partition.join(format!("{}.parquet", open_time));
+    let tmp = file.with_extension("parquet.tmp");
+    ParquetWriter::new(File::create(&tmp)?).finish(&mut row)?;
+    std::fs::rename(&tmp, &file)?;
+
+    if new_day {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() && path != partition {
+                compact_partition(&path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Append every row of `df` (sorted by `open_time`) to the feature-history
+/// dataset under `dir`, one file per daily partition, and compact the
+/// partitions touched. Rows already stored for the same `open_time` are
+/// replaced.
+pub fn append_feature_history_rows(df: &DataFrame, dir: &str) -> Result<()> {
+    let open_times = df.column("open_time")?.i64()?;
+    let mut start = 0;
+    while start < df.height() {
+        let Some(first) = open_times.get(start) else {
+            start += 1;
+            continue;
+        };
+        let day = DateTime::<Utc>::from_timestamp_millis(first)
+            .map(|dt| dt.date_naive())
+            .unwrap_or_default();
+        let mut end = start + 1;
+        while end < df.height()
+            && open_times
+                .get(end)
+                .and_then(DateTime::<Utc>::from_timestamp_millis)
+                .is_some_and(|dt| dt.date_naive() == day)
+        {
+            end += 1;
+        }
+        let partition = Path::new(dir).join(format!("date={}", day.format("%Y-%m-%d")));
+        std::fs::create_dir_all(&partition)?;
+        let file = partition.join(format!("{}.parquet", first));
+        let tmp = file.with_extension("parquet.tmp");
+        let mut rows = df.slice(start as i64, end - start);
+        ParquetWriter::new(File::create(&tmp)?).finish(&mut rows)?;
+        std::fs::rename(&tmp, &file)?;
+        compact_partition(&partition)?;
+        start = end;
+    }
+    Ok(())
+}
+
+/// Compact every partition of the feature-history dataset under `dir`, the
+/// current day's too, and return the partitions looked at.
+pub fn compact_feature_history(dir: &str) -> Result<usize> {
+    let mut partitions = 0;
+    for partition in feature_history_partitions(dir)? {
+        compact_partition(&partition.1)?;
+        partitions += 1;
+    }
+    Ok(partitions)
+}
+
+/// Read the rows of the feature-history datasets under `dirs` (a dataset and
+/// the cache of its cold partitions, say) within `range`, with no partition
+/// compacted or deleted meanwhile.
+pub fn read_feature_history(dirs: &[&str], range: TimeRange) -> Result<DataFrame> {
+    let _guard = PARTITION_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(query::scan_feature_history_dirs(dirs, range)?.collect()?)
+}
+
+/// Delete the partitions of the feature-history dataset under `dir` dated
+/// before `keep_from` (UTC), and return how many were deleted.
+pub fn prune_feature_history(dir: &str, keep_from: chrono::NaiveDate) -> Result<usize> {
+    let mut pruned = 0;
+    for (day, partition) in feature_history_partitions(dir)? {
+        if day < keep_from {
+            let _guard = PARTITION_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            std::fs::remove_dir_all(&partition)?;
+            pruned += 1;
+        }
+    }
+    Ok(pruned)
+}
+
+/// Delete a feature-history partition moved to another tier, unless it
+/// changed since: it must hold only a compacted `data.parquet` of `bytes`.
+/// Whether it was deleted.
+pub fn remove_feature_history_partition(partition: &Path, bytes: u64) -> Result<bool> {
+    let _guard = PARTITION_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(partition)? {
+        let entry = entry?;
+        files.push((entry.file_name(), entry.metadata()?.len()));
+    }
+    if files != [(std::ffi::OsString::from("data.parquet"), bytes)] {
+        return Ok(false);
+    }
+    std::fs::remove_dir_all(partition)?;
+    Ok(true)
+}
+
+/// The `date=YYYY-MM-DD` partitions under `dir`, with their days, oldest first.
+pub fn feature_history_partitions(
+    dir: &str,
+) -> Result<Vec<(chrono::NaiveDate, std::path::PathBuf)>> {
+    if !Path::new(dir).is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut partitions = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let day = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("date="))
+            .and_then(|day| chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d").ok());
+        if let (Some(day), true) = (day, path.is_dir()) {
+            partitions.push((day, path));
+        }
+    }
+    partitions.sort();
+    Ok(partitions)
+}
+
+/// Held while a feature-history partition is rewritten or deleted: the
+/// pipeline and the scheduled maintenance jobs work on the same partitions.
+static PARTITION_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Merge the per-row files of one feature-history partition into `data.parquet`.
+fn compact_partition(partition: &Path) -> Result<()> {
+    let _guard = PARTITION_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if !partition.is_dir() {
+        return Ok(()); // pruned meanwhile
+    }
+    let mut rows = Vec::new();
+    for entry in std::fs::read_dir(partition)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "parquet") {
+            rows.push(path);
+        }
+    }
+    let merged_path = partition.join("data.parquet");
+    if rows.is_empty() || rows == [merged_path.clone()] {
+        return Ok(());
+    }
+    // Compacted rows first, so newer files win for the same `open_time`
+    rows.sort_by_key(|path| (*path != merged_path, path.clone()));
+
+    let mut frames = Vec::with_capacity(rows.len());
+    for path in &rows {
+        frames.push(ParquetReader::new(File::open(path)?).finish()?.lazy());
+    }
+    let mut merged = concat(frames, UnionArgs::default())?
+        .unique_stable(Some(cols(["open_time"])), UniqueKeepStrategy::Last)
+        .sort(["open_time"], Default::default())
+        .collect()?;
+
+    let tmp = partition.join("data.parquet.tmp");
+    ParquetWriter::new(File::create(&tmp)?).finish(&mut merged)?;
+    std::fs::rename(&tmp, &merged_path)?;
+    for path in rows.iter().filter(|p| **p != merged_path) {
+        std::fs::remove_file(path)?;
+    }
+    println!(
+        "Compacted {} feature-history rows into {}",
+        merged.height(),
+        merged_path.display()
+    );
+    Ok(())
+}
+
 /// Optional: Save all klines to CSV (overwrite) – useful for initial baseline.
 pub fn save_klines_to_csv(klines: &[Kline], path: &str) -> Result<()> {
     let mut file = File::create(path)?;
@@ -171,6 +695,87 @@ pub fn save_klines_to_csv(klines: &[Kline], path: &str) -> Result<()> {
     Ok(())
 }
 
+/// Which auxiliary row [`asof_lookup`] aligns with a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AsofDirection {
+    /// The last row at or before the key: the value known at that time.
+    #[default]
+    Backward,
+    /// The first row at or after the key.
+    Forward,
+    /// The closer of the two, the later one on a tie (as polars).
+    Nearest,
+}
+
+/// Direction and tolerance of an asof alignment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Asof {
+    pub direction: AsofDirection,
+    /// Largest distance (ms) between a key and its row; any if `None`.
+    pub tolerance_ms: Option<i64>,
+}
+
+impl Asof {
+    /// Only the row with exactly the key.
+    pub const EXACT: Self = Self {
+        direction: AsofDirection::Backward,
+        tolerance_ms: Some(0),
+    };
+
+    /// The entry of `series` aligned with `key`.
+    pub fn find<'a, T>(&self, key: i64, series: &'a BTreeMap<i64, T>) -> Option<&'a T> {
+        let before = || series.range(..=key).next_back();
+        let after = || series.range(key..).next();
+        let (&time, value) = match self.direction {
+            AsofDirection::Backward => before()?,
+            AsofDirection::Forward => after()?,
+            AsofDirection::Nearest => match (before(), after()) {
+                (Some(b), Some(a)) if a.0 - key <= key - b.0 => a,
+                (b, a) => b.or(a)?,
+            },
+        };
+        self.tolerance_ms
+            .is_none_or(|tolerance| time.abs_diff(key) <= tolerance.unsigned_abs())
+            .then_some(value)
+    }
+}
+
+/// The entries of an in-memory auxiliary series keyed by epoch ms (a live
+/// state's rows by `open_time`) aligned with every key of a candle frame,
+/// `None` where `asof` finds none.
+pub fn asof_lookup<'a, T>(
+    keys: &Int64Chunked,
+    series: &'a BTreeMap<i64, T>,
+    asof: Asof,
+) -> Vec<Option<&'a T>> {
+    keys.into_iter()
+        .map(|key| key.and_then(|key| asof.find(key, series)))
+        .collect()
+}
+
+/// Candle columns kept at full precision by [`downcast_features`].
+const RAW_PRICE_COLUMNS: [&str; 5] = ["open", "high", "low", "close", "volume"];
+
+/// Cast every Float64 feature column of `df` to Float32, leaving the raw
+/// candle columns untouched. Halves the size of the EMA columns on disk and
+/// in whatever loads them, at ~7 significant digits.
+pub fn downcast_features(df: &DataFrame) -> Result<DataFrame> {
+    let columns = df
+        .columns()
+        .iter()
+        .map(|column| {
+            if column.dtype() == &DataType::Float64
+                && !RAW_PRICE_COLUMNS.contains(&column.name().as_str())
+            {
+                column.cast(&DataType::Float32)
+            } else {
+                Ok(column.clone())
+            }
+        })
+        .collect::<PolarsResult<Vec<_>>>()?;
+    Ok(DataFrame::new(df.height(), columns)?)
+}
+
 /// Save a DataFrame to a Parquet file (overwrites).
 pub fn save_dataframe_parquet(df: &mut DataFrame, path: &str) -> Result<()> {
     let file = File::create(path)?;
@@ -193,6 +798,7 @@ pub fn save_dataframe_csv_to_path(df: &DataFrame, path: &str) -> Result<()> {
     let close_time_idx = df.get_column_index("close_time");
 
     let height = df.height();
+    let format = decimal_format();
 
     for row_idx in 0..height {
         let mut values = Vec::with_capacity(headers.len());
@@ -204,7 +810,7 @@ pub fn save_dataframe_csv_to_path(df: &DataFrame, path: &str) -> Result<()> {
             if Some(col_idx) == open_time_idx || Some(col_idx) == close_time_idx {
                 if let Ok(ca) = series.i64() {
                     let opt = ca.get(row_idx);
-                    let s = opt.map_or(String::new(), |ms| timestamp_to_string(ms));
+                    let s = opt.map_or(String::new(), timestamp_to_string);
                     values.push(s);
                 } else {
                     // Fallback: use debug representation
@@ -212,7 +818,7 @@ pub fn save_dataframe_csv_to_path(df: &DataFrame, path: &str) -> Result<()> {
                 }
             } else {
                 // For other columns, get the value and convert to string
-                values.push(format!("{}", series.get(row_idx)?));
+                values.push(format.cell(col_name, &series.get(row_idx)?));
             }
         }
 
@@ -233,17 +839,126 @@ pub async fn append_kline_to_csv_async(kline: Kline, path: String) -> Result<()>
     task::spawn_blocking(move || append_kline_to_csv(&kline, &path)).await?
 }
 
+/// Async version: appends a single kline to a journal CSV.
+pub async fn append_kline_to_journal_async(kline: Kline, path: String) -> Result<()> {
+    task::spawn_blocking(move || append_kline_to_journal(&kline, &path)).await?
+}
+
+/// Async version: appends a best bid/ask quote to CSV.
+pub async fn append_quote_to_csv_async(quote: BookQuote, path: String) -> Result<()> {
+    task::spawn_blocking(move || append_quote_to_csv(&quote, &path)).await?
+}
+
 /// Async version: appends the last feature row to CSV.
 pub async fn append_features_row_to_csv_async(df: DataFrame, path: String) -> Result<()> {
     task::spawn_blocking(move || append_features_row_to_csv(&df, &path)).await?
 }
 
+/// Async version: appends the last feature row to the feature-history dataset.
+pub async fn append_feature_history_row_async(df: DataFrame, dir: String) -> Result<()> {
+    task::spawn_blocking(move || append_feature_history_row(&df, &dir)).await?
+}
+
 /// Async version: saves a full feature DataFrame to Parquet.
 pub async fn save_dataframe_parquet_async(mut df: DataFrame, path: String) -> Result<()> {
     task::spawn_blocking(move || save_dataframe_parquet(&mut df, &path)).await?
 }
 
-/// Async version: saves a full feature DataFrame to CSV.
-pub async fn save_dataframe_csv_to_path_async(df: DataFrame, path: String) -> Result<()> {
-    task::spawn_blocking(move || save_dataframe_csv_to_path(&df, &path)).await?
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Entries at 100, 200 and 400 ms.
+    fn series() -> BTreeMap<i64, &'static str> {
+        BTreeMap::from([(100, "a"), (200, "b"), (400, "c")])
+    }
+
+    fn asof(direction: AsofDirection, tolerance_ms: Option<i64>) -> Asof {
+        Asof {
+            direction,
+            tolerance_ms,
+        }
+    }
+
+    #[test]
+    fn find_follows_the_direction() {
+        let series = series();
+        let found = |asof: Asof, key| asof.find(key, &series).copied();
+        let backward = asof(AsofDirection::Backward, None);
+        let forward = asof(AsofDirection::Forward, None);
+        let nearest = asof(AsofDirection::Nearest, None);
+
+        assert_eq!(found(backward, 99), None);
+        assert_eq!(found(backward, 200), Some("b"));
+        assert_eq!(found(backward, 399), Some("b"));
+        assert_eq!(found(backward, 1_000), Some("c"));
+
+        assert_eq!(found(forward, 0), Some("a"));
+        assert_eq!(found(forward, 200), Some("b"));
+        assert_eq!(found(forward, 201), Some("c"));
+        assert_eq!(found(forward, 401), None);
+
+        assert_eq!(found(nearest, 0), Some("a"));
+        assert_eq!(found(nearest, 260), Some("b"));
+        assert_eq!(found(nearest, 340), Some("c"));
+        // A tie goes to the later entry
+        assert_eq!(found(nearest, 150), Some("b"));
+        assert_eq!(found(nearest, 300), Some("c"));
+        assert_eq!(found(nearest, 5_000), Some("c"));
+    }
+
+    #[test]
+    fn find_keeps_to_the_tolerance() {
+        let series = series();
+        let found = |asof: Asof, key| asof.find(key, &series).copied();
+        for direction in [
+            AsofDirection::Backward,
+            AsofDirection::Forward,
+            AsofDirection::Nearest,
+        ] {
+            assert_eq!(found(asof(direction, Some(0)), 200), Some("b"));
+        }
+        // The tolerance is inclusive
+        assert_eq!(
+            found(asof(AsofDirection::Backward, Some(50)), 250),
+            Some("b")
+        );
+        assert_eq!(found(asof(AsofDirection::Backward, Some(50)), 251), None);
+        assert_eq!(
+            found(asof(AsofDirection::Forward, Some(50)), 350),
+            Some("c")
+        );
+        assert_eq!(found(asof(AsofDirection::Forward, Some(50)), 349), None);
+        // Nearest picks the closer entry first, then checks its distance
+        assert_eq!(found(asof(AsofDirection::Nearest, Some(89)), 310), None);
+        assert_eq!(
+            found(asof(AsofDirection::Nearest, Some(90)), 310),
+            Some("c")
+        );
+        assert_eq!(
+            found(asof(AsofDirection::Nearest, Some(90)), 290),
+            Some("b")
+        );
+        assert_eq!(found(Asof::EXACT, 201), None);
+    }
+
+    #[test]
+    fn lookup_aligns_every_key() {
+        let series = series();
+        let keys = Int64Chunked::from_iter([Some(100), None, Some(150), Some(400), Some(450)]);
+        let found = |asof| -> Vec<Option<&str>> {
+            asof_lookup(&keys, &series, asof)
+                .into_iter()
+                .map(|value| value.copied())
+                .collect()
+        };
+        assert_eq!(
+            found(Asof::EXACT),
+            vec![Some("a"), None, None, Some("c"), None]
+        );
+        assert_eq!(
+            found(asof(AsofDirection::Backward, Some(60))),
+            vec![Some("a"), None, Some("a"), Some("c"), Some("c")]
+        );
+    }
 }
@@ -0,0 +1,146 @@
+//! Per-candle processing deadline.
+//!
+//! The work a closed candle triggers – feature computation, strategies and
+//! saves – is timed against `[deadline] ms`. An overrun writes a
+//! `deadline_overrun` event with the time of every stage, counts in
+//! `candle_deadline_overruns_total{symbol}` and, with the `skip_optional`
+//! policy, makes the following candles skip the optional outputs (the two
+//! feature CSV logs and the `[[feature_windows]]`) until `recover_after`
+//! candles in a row finish in time. Every skipped output counts in
+//! `optional_outputs_skipped_total{symbol,output}`, so chronic overruns show
+//! up instead of silently delaying the candles behind them.
+
+use crate::alerts::{self, Level};
+use crate::config::{DeadlineConfig, DeadlinePolicy};
+use crate::events::{self, Event};
+use crate::metrics;
+use crate::timezone;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+pub struct DeadlineGuard {
+    symbol: String,
+    interval: String,
+    config: DeadlineConfig,
+    /// Optional outputs are skipped.
+    degraded: bool,
+    /// Consecutive candles over the deadline.
+    overruns: u32,
+    /// Consecutive candles within the deadline while degraded.
+    within: u32,
+}
+
+impl DeadlineGuard {
+    pub fn new(symbol: &str, interval: &str, config: &DeadlineConfig) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            interval: interval.to_string(),
+            config: config.clone(),
+            degraded: false,
+            overruns: 0,
+            within: 0,
+        }
+    }
+
+    /// Whether the next candle skips the optional outputs.
+    pub fn degraded(&self) -> bool {
+        self.degraded
+    }
+
+    /// Count an optional output the current candle skips.
+    pub fn skip(&self, output: &str) {
+        metrics::counter_inc(
+            "optional_outputs_skipped_total",
+            &[("symbol", &self.symbol), ("output", output)],
+            1,
+        );
+    }
+
+    /// Check the processing time of the candle opening at `open_time`,
+    /// `stages` being its breakdown.
+    pub fn record(&mut self, open_time: i64, total: Duration, stages: &[(&str, Duration)]) {
+        if self.config.ms == 0 {
+            return;
+        }
+        let deadline = Duration::from_millis(self.config.ms);
+        if total <= deadline {
+            self.overruns = 0;
+            if self.degraded {
+                self.within += 1;
+                if self.within >= self.config.recover_after {
+                    self.set_degraded(false);
+                    println!(
+                        "[deadline] {}: {} candles in a row within {} ms, optional outputs restored",
+                        self.symbol, self.within, self.config.ms
+                    );
+                }
+            }
+            return;
+        }
+
+        self.overruns += 1;
+        self.within = 0;
+        let total_ms = total.as_secs_f64() * 1000.0;
+        metrics::counter_inc(
+            "candle_deadline_overruns_total",
+            &[("symbol", &self.symbol)],
+            1,
+        );
+        let breakdown: BTreeMap<String, f64> = stages
+            .iter()
+            .map(|(stage, d)| (stage.to_string(), d.as_secs_f64() * 1000.0))
+            .collect();
+        println!(
+            "[deadline] {}: candle {} took {:.1} ms (deadline {} ms): {}",
+            self.symbol,
+            timezone::format_ms(open_time),
+            total_ms,
+            self.config.ms,
+            breakdown
+                .iter()
+                .map(|(stage, ms)| format!("{} {:.1} ms", stage, ms))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        if self.config.policy == DeadlinePolicy::SkipOptional && !self.degraded {
+            self.set_degraded(true);
+        }
+        events::emit(Event::DeadlineOverrun {
+            symbol: self.symbol.clone(),
+            interval: self.interval.clone(),
+            open_time,
+            deadline_ms: self.config.ms,
+            total_ms,
+            stages: breakdown,
+            degraded: self.degraded,
+        });
+        if self.config.alert_after > 0 && self.overruns == self.config.alert_after {
+            alerts::raise(
+                Level::Warning,
+                "deadline",
+                &format!(
+                    "{}: {} candles in a row took longer than {} ms (last {:.0} ms){}",
+                    self.symbol,
+                    self.overruns,
+                    self.config.ms,
+                    total_ms,
+                    if self.degraded {
+                        ", optional outputs skipped"
+                    } else {
+                        ""
+                    }
+                ),
+            );
+        }
+    }
+
+    fn set_degraded(&mut self, degraded: bool) {
+        self.degraded = degraded;
+        self.within = 0;
+        metrics::gauge_set(
+            "deadline_degraded",
+            &[("symbol", &self.symbol)],
+            degraded as u8 as f64,
+        );
+    }
+}
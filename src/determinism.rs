@@ -0,0 +1,110 @@
+//! Reproducible-run mode (`--deterministic` / `[deterministic]`).
+//!
+//! Pins every source of nondeterminism the pipeline has: the clock (see
+//! [`crate::clock`]), the polars and rayon thread counts, the seed of every
+//! random generator, and the order of the per-candle writes. Timing-driven
+//! adaptations (candle coalescing, the memory guard, reduced persistence)
+//! are switched off by [`crate::config::Config::load`] and the pipeline.
+//! Together with [`hash_outputs`], a replayed dataset can be checked to
+//! produce byte-identical files.
+
+use crate::clock;
+use crate::config::{DeterministicConfig, OutputPaths};
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Seed used when none is configured; the `bench` synthetic dataset has
+/// always been generated from it.
+pub const DEFAULT_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Pin the clock and thread counts. Must run before polars or rayon start
+/// their thread pools, i.e. first thing in `main`.
+pub fn apply(config: &DeterministicConfig) {
+    if !config.enabled {
+        return;
+    }
+    clock::pin(0);
+    std::env::set_var("POLARS_MAX_THREADS", config.threads.to_string());
+    if let Err(e) = rayon::ThreadPoolBuilder::new()
+        .num_threads(config.threads)
+        .build_global()
+    {
+        eprintln!("Warning: unable to pin the rayon thread count: {}", e);
+    }
+    println!(
+        "Deterministic mode: clock pinned to candle time, {} thread(s), seed {:#x}",
+        config.threads, config.seed
+    );
+}
+
+/// Small xorshift generator: reproducible from its seed, for synthetic data
+/// and any sampling or slippage model.
+pub struct SeededRng(u64);
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift never leaves the all-zero state
+        Self(if seed == 0 { DEFAULT_SEED } else { seed })
+    }
+
+    /// Uniform sample in `[0, 1)`.
+    pub fn next_unit(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Files below `path` (itself if it is a file), in a stable order.
+fn files_under(path: &Path) -> Vec<PathBuf> {
+    if path.is_file() {
+        return vec![path.to_path_buf()];
+    }
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return Vec::new();
+    };
+    let mut children: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path())).collect();
+    children.sort();
+    children.iter().flat_map(|c| files_under(c)).collect()
+}
+
+/// SHA-256 of every data output (raw and feature files, feature windows, the
+/// feature-history dataset, quarantined candles and the signal audit trail),
+/// printed and returned per output file name, and one hash over all of them.
+/// The event log, heartbeat and run manifest are left out: they record
+/// latencies and wall-clock times.
+pub fn hash_outputs(paths: &OutputPaths) -> Result<(BTreeMap<String, String>, String)> {
+    let mut outputs = vec![
+        &paths.raw_cache,
+        &paths.raw_journal,
+        &paths.raw_csv,
+        &paths.feature_parquet,
+        &paths.feature_csv,
+        &paths.feature_history,
+        &paths.streaming_log,
+        &paths.quarantine,
+        &paths.audit,
+    ];
+    outputs.extend(&paths.feature_windows);
+    let mut hashes = BTreeMap::new();
+    let mut total = Sha256::new();
+    for output in outputs {
+        let mut hasher = Sha256::new();
+        let root = Path::new(output.as_str());
+        for file in files_under(root) {
+            // Relative names, so the same run in another directory hashes equal
+            let name = file.strip_prefix(root).unwrap_or(&file);
+            hasher.update(name.to_string_lossy().as_bytes());
+            hasher.update(std::fs::read(&file)?);
+        }
+        let digest = hasher.finalize();
+        println!("  {:x}  {}", digest, output);
+        total.update(digest);
+        let name = Path::new(output.as_str()).file_name().unwrap_or_default();
+        hashes.insert(name.to_string_lossy().to_string(), format!("{:x}", digest));
+    }
+    Ok((hashes, format!("{:x}", total.finalize())))
+}
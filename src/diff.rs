@@ -0,0 +1,412 @@
+//! `diff-features` subcommand: column-by-column comparison of two feature
+//! Parquet files, e.g. the feature snapshot before and after an indicator
+//! change.
+//!
+//! Rows are matched on `open_time`. Two values differ when they are more
+//! than the tolerance apart – relative to the larger magnitude above 1,
+//! absolute below – or when only one of them is null; non-numeric columns
+//! (datetimes, labels, flags) must be equal. For every column shared by both
+//! files the report gives the number of differing rows, the maximum and
+//! mean absolute and the maximum relative deviation, and the `open_time`
+//! ranges of consecutive differing rows. Columns and rows present in one
+//! file only are listed too.
+//!
+//! ```text
+//! cargo run --release diff-features before.parquet after.parquet
+//!     [--tolerance 1e-9[,ema200_h4=1e-6,...]] [--from ...] [--to ...]
+//!     [--output diff.md|diff.html]
+//! ```
+//!
+//! The command fails when a column, the schema or the set of rows differs,
+//! so it can gate a refactor in CI.
+
+use crate::config::CliArgs;
+use crate::report::{self, Table};
+use crate::{timezone, utils};
+use anyhow::{bail, Context, Result};
+use binance_streamer::data_storage;
+use polars::prelude::*;
+use std::collections::HashMap;
+
+const DEFAULT_TOLERANCE: f64 = 1e-9;
+
+/// Differing ranges listed per column; the rest are summed up in one row.
+const MAX_RANGES: usize = 10;
+
+/// Default tolerance and per-column overrides, from
+/// `1e-9,ema200_h4=1e-6`.
+struct Tolerances {
+    default: f64,
+    columns: HashMap<String, f64>,
+}
+
+impl Tolerances {
+    fn parse(spec: Option<&str>) -> Result<Self> {
+        let mut tolerances = Tolerances {
+            default: DEFAULT_TOLERANCE,
+            columns: HashMap::new(),
+        };
+        for part in spec.unwrap_or_default().split(',').map(str::trim) {
+            if part.is_empty() {
+                continue;
+            }
+            let (column, value) = match part.split_once('=') {
+                Some((column, value)) => (Some(column.trim()), value.trim()),
+                None => (None, part),
+            };
+            let value: f64 = value
+                .parse()
+                .ok()
+                .filter(|v: &f64| *v >= 0.0)
+                .with_context(|| format!("invalid tolerance '{}'", part))?;
+            match column {
+                Some(column) => {
+                    tolerances.columns.insert(column.to_string(), value);
+                }
+                None => tolerances.default = value,
+            }
+        }
+        Ok(tolerances)
+    }
+
+    fn of(&self, column: &str) -> f64 {
+        self.columns.get(column).copied().unwrap_or(self.default)
+    }
+}
+
+/// Deviations of one column over the matched rows.
+#[derive(Default)]
+struct ColumnDiff {
+    name: String,
+    numeric: bool,
+    /// Matched rows where both values are set.
+    compared: usize,
+    differing: usize,
+    null_mismatches: usize,
+    max_abs: f64,
+    sum_abs: f64,
+    max_rel: f64,
+    /// First and last `open_time` and row count of each run of consecutive
+    /// differing rows.
+    ranges: Vec<(i64, i64, usize)>,
+}
+
+impl ColumnDiff {
+    fn record(&mut self, open_time: i64, differs: bool, previous_differed: bool) {
+        if !differs {
+            return;
+        }
+        self.differing += 1;
+        match self.ranges.last_mut() {
+            Some((_, last, rows)) if previous_differed => {
+                *last = open_time;
+                *rows += 1;
+            }
+            _ => self.ranges.push((open_time, open_time, 1)),
+        }
+    }
+}
+
+fn open_times(df: &DataFrame) -> Result<Vec<i64>> {
+    let column = df
+        .column("open_time")
+        .context("feature files are matched on their `open_time` column")?
+        .cast(&DataType::Int64)?;
+    Ok(column
+        .i64()?
+        .into_iter()
+        .map(|t| t.unwrap_or(i64::MIN))
+        .collect())
+}
+
+fn floats(column: &Column) -> Result<Vec<Option<f64>>> {
+    Ok(column
+        .cast(&DataType::Float64)?
+        .f64()?
+        .into_iter()
+        .map(|v| v.filter(|v| !v.is_nan()))
+        .collect())
+}
+
+fn strings(column: &Column) -> Result<Vec<Option<String>>> {
+    Ok(column
+        .cast(&DataType::String)?
+        .str()?
+        .into_iter()
+        .map(|v| v.map(str::to_string))
+        .collect())
+}
+
+/// Compare one column over the matched row pairs.
+fn diff_column(
+    name: &str,
+    before: &Column,
+    after: &Column,
+    pairs: &[(usize, usize, i64)],
+    tolerance: f64,
+) -> Result<ColumnDiff> {
+    let numeric = before.dtype().is_primitive_numeric() && after.dtype().is_primitive_numeric();
+    let mut diff = ColumnDiff {
+        name: name.to_string(),
+        numeric,
+        ..Default::default()
+    };
+    let mut previous = false;
+    if numeric {
+        let (a, b) = (floats(before)?, floats(after)?);
+        for &(i, j, open_time) in pairs {
+            let differs = match (a[i], b[j]) {
+                (Some(x), Some(y)) => {
+                    let abs = (x - y).abs();
+                    let scale = x.abs().max(y.abs());
+                    diff.compared += 1;
+                    diff.sum_abs += abs;
+                    diff.max_abs = diff.max_abs.max(abs);
+                    if scale > 0.0 {
+                        diff.max_rel = diff.max_rel.max(abs / scale);
+                    }
+                    abs > tolerance * scale.max(1.0)
+                }
+                (None, None) => false,
+                _ => {
+                    diff.null_mismatches += 1;
+                    true
+                }
+            };
+            diff.record(open_time, differs, previous);
+            previous = differs;
+        }
+    } else {
+        let (a, b) = (strings(before)?, strings(after)?);
+        for &(i, j, open_time) in pairs {
+            let differs = match (&a[i], &b[j]) {
+                (Some(x), Some(y)) => {
+                    diff.compared += 1;
+                    x != y
+                }
+                (None, None) => false,
+                _ => {
+                    diff.null_mismatches += 1;
+                    true
+                }
+            };
+            diff.record(open_time, differs, previous);
+            previous = differs;
+        }
+    }
+    Ok(diff)
+}
+
+pub fn run(cli: &CliArgs) -> Result<()> {
+    let (Some(before_path), Some(after_path)) = (cli.positional.get(1), cli.positional.get(2))
+    else {
+        bail!("usage: diff-features <before.parquet> <after.parquet> [--tolerance 1e-9[,column=tolerance,...]]");
+    };
+    let tolerances = Tolerances::parse(cli.value("tolerance"))?;
+    let from = cli.value("from").map(utils::parse_time_bound).transpose()?;
+    let to = cli.value("to").map(utils::parse_time_bound).transpose()?;
+    let load = |path: &str| -> Result<DataFrame> {
+        let df = data_storage::load_dataframe_range(path, from, to)
+            .with_context(|| format!("Failed to load {}", path))?;
+        Ok(df.sort(["open_time"], Default::default())?)
+    };
+    let (before, after) = (load(before_path)?, load(after_path)?);
+
+    // Row pairs in `open_time` order (the last row wins for a duplicated time)
+    let (before_times, after_times) = (open_times(&before)?, open_times(&after)?);
+    let after_rows: HashMap<i64, usize> = after_times
+        .iter()
+        .enumerate()
+        .map(|(j, &t)| (t, j))
+        .collect();
+    let before_rows: HashMap<i64, usize> = before_times
+        .iter()
+        .enumerate()
+        .map(|(i, &t)| (t, i))
+        .collect();
+    let pairs: Vec<(usize, usize, i64)> = before_times
+        .iter()
+        .enumerate()
+        .filter(|&(i, t)| before_rows[t] == i)
+        .filter_map(|(i, &t)| after_rows.get(&t).map(|&j| (i, j, t)))
+        .collect();
+    let only_before = before_rows.len() - pairs.len();
+    let only_after = after_rows.len() - pairs.len();
+
+    let mut schema_rows = Vec::new();
+    let mut diffs = Vec::new();
+    for column in before.columns() {
+        let name = column.name().as_str();
+        match after.column(name) {
+            Ok(other) => {
+                if column.dtype() != other.dtype() {
+                    schema_rows.push(vec![
+                        name.to_string(),
+                        column.dtype().to_string(),
+                        other.dtype().to_string(),
+                    ]);
+                }
+                if name != "open_time" {
+                    diffs.push(diff_column(
+                        name,
+                        column,
+                        other,
+                        &pairs,
+                        tolerances.of(name),
+                    )?);
+                }
+            }
+            Err(_) => schema_rows.push(vec![
+                name.to_string(),
+                column.dtype().to_string(),
+                "-".to_string(),
+            ]),
+        }
+    }
+    for column in after.columns() {
+        if before.column(column.name()).is_err() {
+            schema_rows.push(vec![
+                column.name().to_string(),
+                "-".to_string(),
+                column.dtype().to_string(),
+            ]);
+        }
+    }
+    let differing: Vec<&str> = diffs
+        .iter()
+        .filter(|d| d.differing > 0)
+        .map(|d| d.name.as_str())
+        .collect();
+
+    let time = |t: i64| timezone::format_ms_secs(t);
+    let overview = Table {
+        title: "Overview",
+        headers: vec![
+            "Rows before",
+            "Rows after",
+            "Matched rows",
+            "Only before",
+            "Only after",
+            "Columns compared",
+            "Columns differing",
+            "Tolerance",
+        ],
+        rows: vec![vec![
+            before.height().to_string(),
+            after.height().to_string(),
+            pairs.len().to_string(),
+            only_before.to_string(),
+            only_after.to_string(),
+            diffs.len().to_string(),
+            differing.len().to_string(),
+            format!("{:e}", tolerances.default),
+        ]],
+    };
+    let schema_differs = !schema_rows.is_empty();
+    let schema = Table {
+        title: "Schema differences",
+        headers: vec!["Column", "Before", "After"],
+        rows: schema_rows,
+    };
+    let columns = Table {
+        title: "Columns",
+        headers: vec![
+            "Column",
+            "Compared",
+            "Differing",
+            "Null mismatches",
+            "Max abs",
+            "Mean abs",
+            "Max rel",
+            "Tolerance",
+        ],
+        rows: diffs
+            .iter()
+            .map(|d| {
+                let deviation = |v: f64| {
+                    if d.numeric {
+                        format!("{:.3e}", v)
+                    } else {
+                        "-".to_string()
+                    }
+                };
+                vec![
+                    d.name.clone(),
+                    d.compared.to_string(),
+                    d.differing.to_string(),
+                    d.null_mismatches.to_string(),
+                    deviation(d.max_abs),
+                    deviation(d.sum_abs / d.compared.max(1) as f64),
+                    deviation(d.max_rel),
+                    if d.numeric {
+                        format!("{:e}", tolerances.of(&d.name))
+                    } else {
+                        "exact".to_string()
+                    },
+                ]
+            })
+            .collect(),
+    };
+    let mut range_rows = Vec::new();
+    for d in &diffs {
+        for &(first, last, rows) in d.ranges.iter().take(MAX_RANGES) {
+            range_rows.push(vec![
+                d.name.clone(),
+                time(first),
+                time(last),
+                rows.to_string(),
+            ]);
+        }
+        if d.ranges.len() > MAX_RANGES {
+            let rest = &d.ranges[MAX_RANGES..];
+            range_rows.push(vec![
+                d.name.clone(),
+                format!("… {} more ranges", rest.len()),
+                time(rest[rest.len() - 1].1),
+                rest.iter().map(|r| r.2).sum::<usize>().to_string(),
+            ]);
+        }
+    }
+    let ranges = Table {
+        title: "Differing rows",
+        headers: vec!["Column", "From", "To", "Rows"],
+        rows: range_rows,
+    };
+    let tables = [overview, schema, columns, ranges];
+    let title = format!("Feature diff: {} → {}", before_path, after_path);
+
+    match cli.value("output") {
+        Some(path) => {
+            let text = if path.ends_with(".html") {
+                report::render_html(&title, &tables)
+            } else {
+                report::render_markdown(&title, &tables)
+            };
+            std::fs::write(path, text).with_context(|| format!("Failed to write {}", path))?;
+            println!("Wrote the feature diff to {}", path);
+        }
+        None => print!("{}", report::render_markdown(&title, &tables)),
+    }
+    let mut problems = Vec::new();
+    if !differing.is_empty() {
+        problems.push(format!(
+            "{} of {} columns differ ({})",
+            differing.len(),
+            diffs.len(),
+            differing.join(", ")
+        ));
+    }
+    if schema_differs {
+        problems.push("the schemas differ".to_string());
+    }
+    if only_before + only_after > 0 {
+        problems.push(format!(
+            "{} rows are in one file only",
+            only_before + only_after
+        ));
+    }
+    if !problems.is_empty() {
+        bail!("{}", problems.join("; "));
+    }
+    Ok(())
+}
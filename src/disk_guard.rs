@@ -0,0 +1,144 @@
+//! Disk usage monitoring for the data directory.
+//!
+//! Tracks free space and the growth rate of the append-only files. When free
+//! space gets low the pipeline switches to reduced persistence (CSV feature
+//! mirrors are skipped) instead of failing with ENOSPC mid-write; it returns
+//! to full persistence once space is back above the warning threshold.
+
+use crate::alerts::{self, Level};
+use crate::config::DiskConfig;
+use crate::metrics;
+use std::time::Instant;
+
+const MB: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PersistenceMode {
+    /// Write every output.
+    Full,
+    /// Skip the CSV feature mirrors; keep the raw log and Parquet snapshots.
+    Reduced,
+    /// Write nothing but signals and events (the `signal_only` profile).
+    SignalsOnly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ok,
+    Warning,
+    Critical,
+}
+
+pub struct DiskGuard {
+    data_dir: String,
+    append_files: Vec<String>,
+    config: DiskConfig,
+    state: State,
+    /// Total size of the append files at the previous check.
+    last_sample: Option<(Instant, u64)>,
+}
+
+impl DiskGuard {
+    pub fn new(data_dir: &str, append_files: &[&str], config: &DiskConfig) -> Self {
+        Self {
+            data_dir: data_dir.to_string(),
+            append_files: append_files.iter().map(|s| s.to_string()).collect(),
+            config: config.clone(),
+            state: State::Ok,
+            last_sample: None,
+        }
+    }
+
+    /// Sample free space and file growth, alert on threshold crossings,
+    /// and return the persistence mode to use for the next writes.
+    pub fn check(&mut self) -> PersistenceMode {
+        let available = match fs4::available_space(&self.data_dir) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!(
+                    "Warning: unable to read free space of {}: {}",
+                    self.data_dir, e
+                );
+                return self.mode();
+            }
+        };
+        metrics::gauge_set("disk_available_bytes", &[], available as f64);
+
+        let growth_per_hour = self.sample_growth();
+        let hours_to_full = growth_per_hour
+            .filter(|&g| g > 0.0)
+            .map(|g| available as f64 / g);
+        if let Some(g) = growth_per_hour {
+            metrics::gauge_set("append_files_growth_bytes_per_hour", &[], g);
+        }
+
+        // Once critical, stay reduced until space is back above the warning level
+        let critical = available < self.config.critical_free_mb * MB
+            || (self.state == State::Critical && available < self.config.warn_free_mb * MB);
+        let new_state = if critical {
+            State::Critical
+        } else if available < self.config.warn_free_mb * MB
+            || hours_to_full.is_some_and(|h| h < self.config.warn_hours_to_full)
+        {
+            State::Warning
+        } else {
+            State::Ok
+        };
+
+        if new_state != self.state {
+            self.report_transition(new_state, available, hours_to_full);
+            self.state = new_state;
+        }
+        metrics::gauge_set(
+            "persistence_reduced",
+            &[],
+            (self.mode() == PersistenceMode::Reduced) as u8 as f64,
+        );
+        self.mode()
+    }
+
+    fn mode(&self) -> PersistenceMode {
+        match self.state {
+            State::Critical => PersistenceMode::Reduced,
+            _ => PersistenceMode::Full,
+        }
+    }
+
+    /// Bytes per hour the append files grew since the previous call.
+    fn sample_growth(&mut self) -> Option<f64> {
+        let total: u64 = self
+            .append_files
+            .iter()
+            .filter_map(|p| std::fs::metadata(p).ok())
+            .map(|m| m.len())
+            .sum();
+        let now = Instant::now();
+        let growth = self.last_sample.and_then(|(then, prev)| {
+            let hours = now.duration_since(then).as_secs_f64() / 3600.0;
+            (hours > 0.0).then(|| total.saturating_sub(prev) as f64 / hours)
+        });
+        self.last_sample = Some((now, total));
+        growth
+    }
+
+    fn report_transition(&self, new_state: State, available: u64, hours_to_full: Option<f64>) {
+        let eta = hours_to_full
+            .map(|h| format!(", ~{:.1} h until full at current growth", h))
+            .unwrap_or_default();
+        let free = format!("{} MB free on {}{}", available / MB, self.data_dir, eta);
+        match new_state {
+            State::Critical => alerts::raise(
+                Level::Critical,
+                "disk",
+                &format!(
+                    "{}; switching to reduced persistence (CSV mirrors skipped)",
+                    free
+                ),
+            ),
+            State::Warning => {
+                alerts::raise(Level::Warning, "disk", &format!("low disk space: {}", free))
+            }
+            State::Ok => println!("Disk space recovered ({}); full persistence resumed.", free),
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Pipe-friendly output: one line per processed candle.
+//!
+//! With `--emit json|csv` every feature row is written as one line to stdout
+//! or to the clients of a Unix socket (`--emit-to unix:/path/to.sock`). In
+//! stdout mode the regular log output is discarded so the stream can be piped
+//! straight into another tool (`binance_streamer m15 --emit json | jq .`);
+//! warnings, errors and alerts still go to stderr. CSV output starts with a
+//! header line (`symbol,interval,<feature columns>`), repeated for every
+//! socket client. Floats follow the `[decimals]` format.
+
+use crate::bus::{self, Update};
+use crate::config::{EmitConfig, EmitFormat};
+use crate::{data_storage, latest, metrics};
+use anyhow::{bail, Result};
+use binance_streamer::data_storage::DecimalFormat;
+use serde_json::{Map, Value};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::broadcast;
+
+/// Turns feature rows into output lines, remembering the CSV header.
+struct LineWriter {
+    format: EmitFormat,
+    decimals: DecimalFormat,
+    columns: Option<Vec<String>>,
+}
+
+impl LineWriter {
+    fn new(format: EmitFormat) -> Self {
+        Self {
+            format,
+            decimals: data_storage::decimal_format(),
+            columns: None,
+        }
+    }
+
+    /// Newline-terminated text for one row (preceded by the header on the first CSV row).
+    fn line(&mut self, symbol: &str, interval: &str, values: &Map<String, Value>) -> String {
+        match self.format {
+            EmitFormat::Json => {
+                let mut values = values.clone();
+                self.decimals.round_row(&mut values);
+                let mut text = latest::feature_message(symbol, interval, &values).to_string();
+                text.push('\n');
+                text
+            }
+            EmitFormat::Csv => {
+                let mut text = String::new();
+                let columns = self.columns.get_or_insert_with(|| {
+                    let columns: Vec<String> = values.keys().cloned().collect();
+                    text.push_str("symbol,interval,");
+                    text.push_str(&columns.join(","));
+                    text.push('\n');
+                    columns
+                });
+                let fields = columns.iter().map(|name| match values.get(name) {
+                    None | Some(Value::Null) => String::new(),
+                    Some(Value::String(s)) if s.contains([',', '"', '\n']) => {
+                        format!("\"{}\"", s.replace('"', "\"\""))
+                    }
+                    Some(Value::String(s)) => s.clone(),
+                    Some(Value::Number(n)) if n.is_f64() && self.decimals.applies(name) => {
+                        self.decimals.format(name, n.as_f64().unwrap_or_default())
+                    }
+                    Some(other) => other.to_string(),
+                });
+                let row: Vec<String> = [symbol.to_string(), interval.to_string()]
+                    .into_iter()
+                    .chain(fields)
+                    .collect();
+                text.push_str(&row.join(","));
+                text.push('\n');
+                text
+            }
+        }
+    }
+}
+
+/// Write every feature row from the bus to `out` until it fails or the bus closes.
+async fn pump(mut out: impl AsyncWrite + Unpin, format: EmitFormat) -> std::io::Result<()> {
+    let mut lines = LineWriter::new(format);
+    let mut updates = bus::subscribe();
+    loop {
+        match updates.recv().await {
+            Ok(Update::Features {
+                symbol,
+                interval,
+                values,
+                ..
+            }) => {
+                let text = lines.line(&symbol.to_uppercase(), &interval, &values);
+                out.write_all(text.as_bytes()).await?;
+                out.flush().await?;
+                metrics::counter_inc("emit_lines_total", &[], 1);
+            }
+            Ok(_) => {}
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                metrics::counter_inc("emit_skipped_updates_total", &[], skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
+/// Keep the real stdout for the line stream and point fd 1 at /dev/null,
+/// silencing every `println!` elsewhere in the process.
+#[cfg(unix)]
+fn take_stdout() -> Result<std::fs::File> {
+    use std::io::Write;
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+
+    std::io::stdout().flush()?;
+    let devnull = std::fs::OpenOptions::new().write(true).open("/dev/null")?;
+    // SAFETY: plain descriptor calls; the duplicated fd is owned by the returned File
+    unsafe {
+        let fd = libc::dup(1);
+        if fd < 0 || libc::dup2(devnull.as_raw_fd(), 1) < 0 {
+            bail!(
+                "Failed to redirect stdout: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+        Ok(std::fs::File::from_raw_fd(fd))
+    }
+}
+
+#[cfg(not(unix))]
+fn take_stdout() -> Result<std::fs::File> {
+    bail!("--emit to stdout is only supported on Unix");
+}
+
+#[cfg(unix)]
+async fn serve_socket(path: &str, format: EmitFormat) -> Result<()> {
+    use anyhow::Context;
+
+    // A socket file left over from a previous run would make bind fail
+    let _ = std::fs::remove_file(path);
+    let listener = tokio::net::UnixListener::bind(path)
+        .with_context(|| format!("Failed to bind Unix socket {}", path))?;
+    println!("Emitting {} lines on unix:{}", format.as_str(), path);
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(async move {
+                        // A client hanging up just ends its own stream
+                        let _ = pump(stream, format).await;
+                    });
+                }
+                Err(e) => eprintln!("Unix socket accept error: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn serve_socket(_path: &str, _format: EmitFormat) -> Result<()> {
+    bail!("--emit-to unix: is only supported on Unix");
+}
+
+/// Start the line stream if `--emit` / `[emit] format` is set.
+pub async fn spawn(config: &EmitConfig) -> Result<()> {
+    let Some(format) = config.format else {
+        return Ok(());
+    };
+    if config.to == "stdout" {
+        let out = tokio::fs::File::from_std(take_stdout()?);
+        tokio::spawn(async move {
+            if let Err(e) = pump(out, format).await {
+                // The reading end of the pipe went away; nothing left to write to
+                eprintln!("Emit stream closed: {}", e);
+                std::process::exit(0);
+            }
+        });
+        return Ok(());
+    }
+    let Some(path) = config.to.strip_prefix("unix:") else {
+        bail!(
+            "Unknown emit target '{}' (expected \"stdout\" or \"unix:/path\")",
+            config.to
+        );
+    };
+    serve_socket(path, format).await
+}
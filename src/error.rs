@@ -0,0 +1,159 @@
+//! The error type of the library's I/O boundary: the exchange clients and
+//! adapters, the history store and the on-disk datasets.
+//!
+//! Callers branch on the kind instead of matching message text, e.g. retry a
+//! [`Error::Network`] failure but not a rejected symbol:
+//!
+//! ```no_run
+//! # async fn demo() -> binance_streamer::error::Result<()> {
+//! use binance_streamer::{binance_client, Error};
+//!
+//! match binance_client::fetch_latest_klines("BTCUSDT", "15m", 1_000).await {
+//!     Ok(klines) => println!("{} candles", klines.len()),
+//!     Err(Error::Exchange { code: -1121, .. }) => println!("unknown symbol"),
+//!     Err(e) if e.is_retryable() => println!("try again later: {}", e),
+//!     Err(e) => return Err(e),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The feature pipeline and strategies keep `anyhow` errors: their failures
+//! are bugs in a computation rather than conditions to handle.
+
+use polars::prelude::PolarsError;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The request never got an answer: connection, TLS or timeout.
+    #[error("network error: {0}")]
+    Network(#[source] BoxError),
+    /// The exchange answered with an error: the Binance `code`/`msg` body,
+    /// the Bybit `retCode`/`retMsg`, or else the HTTP status and body. `code`
+    /// is 0 when the venue gives none, e.g. for a rejected subscription.
+    #[error("exchange error {code}: {msg}")]
+    Exchange { code: i64, msg: String },
+    /// Reading or writing a Parquet, CSV or journal file failed.
+    #[error("storage error: {0}")]
+    Storage(#[source] BoxError),
+    /// A response, frame or stored row could not be decoded.
+    #[error("parse error: {0}")]
+    Parse(String),
+    /// A setting the library cannot work with: unsupported interval, unknown
+    /// exchange or timezone, missing credentials.
+    #[error("config error: {0}")]
+    Config(String),
+}
+
+impl Error {
+    /// `network`, `exchange`, `storage`, `parse` or `config`, e.g. for metric
+    /// labels.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Network(_) => "network",
+            Error::Exchange { .. } => "exchange",
+            Error::Storage(_) => "storage",
+            Error::Parse(_) => "parse",
+            Error::Config(_) => "config",
+        }
+    }
+
+    /// Whether the same call may succeed later: network failures, and
+    /// exchange errors for rate limits (HTTP 418/429, Binance -1003) or an
+    /// unavailable server (5xx, Binance -1001/-1007).
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Network(_) => true,
+            Error::Exchange { code, .. } => {
+                matches!(code, 418 | 429 | 500..=599 | -1001 | -1003 | -1007)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the exchange reports being under maintenance: HTTP 503
+    /// without a venue code, Binance -1016 (service shutting down), or a
+    /// message saying so.
+    pub fn is_maintenance(&self) -> bool {
+        match self {
+            Error::Exchange { code, msg } => {
+                matches!(code, 503 | -1016) || msg.to_lowercase().contains("maintenance")
+            }
+            _ => false,
+        }
+    }
+}
+
+/// `response` if its status is a success, otherwise its error as
+/// [`Error::Exchange`].
+pub(crate) async fn check_response(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await?;
+    let parsed: Option<serde_json::Value> = serde_json::from_str(&body).ok();
+    let (code, msg) = match parsed {
+        Some(json) if json["code"].is_i64() => (
+            json["code"].as_i64().unwrap_or_default(),
+            json["msg"].as_str().unwrap_or(&body).to_string(),
+        ),
+        Some(json) if json["message"].is_string() => (
+            i64::from(status.as_u16()),
+            json["message"].as_str().unwrap_or_default().to_string(),
+        ),
+        _ => (i64::from(status.as_u16()), body),
+    };
+    Err(Error::Exchange { code, msg })
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            Error::Parse(e.to_string())
+        } else {
+            Error::Network(Box::new(e))
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Storage(Box::new(e))
+    }
+}
+
+impl From<PolarsError> for Error {
+    fn from(e: PolarsError) -> Self {
+        Error::Storage(Box::new(e))
+    }
+}
+
+/// A blocking storage task that panicked or was cancelled.
+impl From<tokio::task::JoinError> for Error {
+    fn from(e: tokio::task::JoinError) -> Self {
+        Error::Storage(Box::new(e))
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+
+impl From<std::num::ParseFloatError> for Error {
+    fn from(e: std::num::ParseFloatError) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+
+impl From<std::num::ParseIntError> for Error {
+    fn from(e: std::num::ParseIntError) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
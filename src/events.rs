@@ -0,0 +1,252 @@
+//! Structured JSON event log.
+//!
+//! One JSON object per line, appended to a dedicated events file so external
+//! systems can tail the collector's activity. Every line has the envelope
+//! `{"ts": <ms>, "schema_version": 1, "event": "<kind>", ...fields}`; fields
+//! of an existing event kind are only ever added, never renamed or removed.
+//!
+//! With [`set_minimal`] (the `signal_only` profile) the per-candle routine –
+//! processed candles, saves and trades – only goes to the in-memory buffer
+//! of recent events, not to the file.
+
+use crate::{clock, utils};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Number of recent events kept in memory for crash reports.
+const RECENT_CAPACITY: usize = 200;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// A closed candle went through the full pipeline; `close_lag_ms` is how
+    /// long after its nominal close it was done (live candles only).
+    CandleProcessed {
+        symbol: String,
+        interval: String,
+        open_time: i64,
+        close_time: i64,
+        close: f64,
+        volume: f64,
+        latency_ms: f64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        close_lag_ms: Option<f64>,
+    },
+    /// Processing fell behind: `skipped` queued candles went into the window
+    /// without feature rows of their own (backpressure policy `coalesce`).
+    Coalesced {
+        symbol: String,
+        interval: String,
+        skipped: usize,
+    },
+    /// The WebSocket connection was established.
+    Connected { symbol: String, stream: String },
+    /// The WebSocket connection ended.
+    Disconnected {
+        symbol: String,
+        stream: String,
+        reason: String,
+    },
+    /// The exchange reported maintenance; alerts of the muted sources are
+    /// held back until it ends.
+    MaintenanceStarted { exchange: String, reason: String },
+    /// The exchange answered normally again after maintenance from `from`
+    /// to `to` (epoch ms).
+    MaintenanceEnded {
+        exchange: String,
+        from: i64,
+        to: i64,
+    },
+    /// A strategy broke a `[kill]` limit on a stream; its signals are held.
+    StrategyDisabled {
+        symbol: String,
+        interval: String,
+        strategy: String,
+        reason: String,
+    },
+    /// A disabled strategy was re-enabled on a stream.
+    StrategyEnabled {
+        symbol: String,
+        interval: String,
+        strategy: String,
+    },
+    /// An operator command of the control endpoint was carried out;
+    /// `detail` lists what it applied to.
+    Control { command: String, detail: String },
+    /// One persistence target was written (or failed to be).
+    Save {
+        target: String,
+        path: String,
+        ok: bool,
+        duration_ms: f64,
+        error: Option<String>,
+    },
+    /// An operator alert was raised.
+    Alert {
+        level: String,
+        source: String,
+        message: String,
+    },
+    /// A strategy made a non-flat decision on a closed candle, or closed a
+    /// position; `size` and `stop` when the strategy sets them.
+    Signal {
+        symbol: String,
+        interval: String,
+        strategy: String,
+        side: String,
+        open_time: i64,
+        price: f64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        size: Option<f64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        stop: Option<f64>,
+    },
+    /// A stored candle differs from the exchange's REST answer (`field` is
+    /// "open" .. "volume", or "missing" with a null `stream` value).
+    CandleMismatch {
+        symbol: String,
+        interval: String,
+        open_time: i64,
+        field: String,
+        stream: f64,
+        rest: f64,
+    },
+    /// A stored candle was replaced with the exchange's REST answer
+    /// (`[consistency] prefer = "rest"`) and its features recomputed.
+    CandleReconciled {
+        symbol: String,
+        interval: String,
+        open_time: i64,
+        close: f64,
+        volume: f64,
+    },
+    /// A suspicious candle was kept out of the window (`reason` is
+    /// "invalid_range", "price_jump" or "zero_volume").
+    CandleQuarantined {
+        symbol: String,
+        interval: String,
+        open_time: i64,
+        reason: String,
+    },
+    /// Processing a closed candle took longer than `[deadline] ms`; `stages`
+    /// holds the time of each stage in ms, `degraded` whether the optional
+    /// outputs are now skipped.
+    DeadlineOverrun {
+        symbol: String,
+        interval: String,
+        open_time: i64,
+        deadline_ms: u64,
+        total_ms: f64,
+        stages: BTreeMap<String, f64>,
+        degraded: bool,
+    },
+    /// A public trade from the trade stream.
+    Trade {
+        symbol: String,
+        price: f64,
+        qty: f64,
+        trade_time: i64,
+    },
+}
+
+impl Event {
+    /// Emitted for every candle, save or trade rather than for something
+    /// that happened.
+    fn is_routine(&self) -> bool {
+        matches!(
+            self,
+            Event::CandleProcessed { .. } | Event::Save { .. } | Event::Trade { .. }
+        )
+    }
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    ts: i64,
+    schema_version: u32,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+/// The events file and its path.
+fn sink() -> &'static Mutex<Option<(String, File)>> {
+    static SINK: OnceLock<Mutex<Option<(String, File)>>> = OnceLock::new();
+    SINK.get_or_init(|| Mutex::new(None))
+}
+
+static MINIMAL: AtomicBool = AtomicBool::new(false);
+
+/// Keep routine events (processed candles, saves, trades) out of the events
+/// file from now on.
+pub fn set_minimal(minimal: bool) {
+    MINIMAL.store(minimal, Ordering::Relaxed);
+}
+
+fn recent_buffer() -> &'static Mutex<VecDeque<String>> {
+    static RECENT: OnceLock<Mutex<VecDeque<String>>> = OnceLock::new();
+    RECENT.get_or_init(|| Mutex::new(VecDeque::with_capacity(RECENT_CAPACITY)))
+}
+
+/// The most recent events (oldest first), as serialized JSON lines.
+pub fn recent() -> Vec<String> {
+    // try_lock: this is called from the panic hook, possibly while a lock is held
+    match recent_buffer().try_lock() {
+        Ok(buf) => buf.iter().cloned().collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Open (append) the events file. Until this is called, events are only kept in memory.
+pub fn init(path: &str) -> anyhow::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    *sink().lock().unwrap() = Some((path.to_string(), file));
+    println!("Writing structured events to {}", path);
+    Ok(())
+}
+
+/// Move the events file aside (see [`utils::rotate_file`]) and continue in a
+/// new one; the rotated file, if any.
+pub fn rotate() -> anyhow::Result<Option<String>> {
+    let mut sink = sink().lock().unwrap();
+    let Some((path, file)) = sink.as_mut() else {
+        return Ok(None);
+    };
+    let rotated = utils::rotate_file(path)?;
+    *file = OpenOptions::new().create(true).append(true).open(&*path)?;
+    Ok(rotated)
+}
+
+/// Append one event. Write failures are reported on stderr but never propagate.
+pub fn emit(event: Event) {
+    let envelope = Envelope {
+        ts: clock::now_ms(),
+        schema_version: SCHEMA_VERSION,
+        event: &event,
+    };
+    let line = match serde_json::to_string(&envelope) {
+        Ok(line) => line,
+        Err(e) => {
+            eprintln!("Warning: failed to serialize event: {}", e);
+            return;
+        }
+    };
+
+    let logged = !(MINIMAL.load(Ordering::Relaxed) && event.is_routine());
+    if let Some((_, file)) = sink().lock().unwrap().as_mut().filter(|_| logged) {
+        if let Err(e) = writeln!(file, "{}", line) {
+            eprintln!("Warning: failed to write event: {}", e);
+        }
+    }
+
+    let mut recent = recent_buffer().lock().unwrap();
+    if recent.len() == RECENT_CAPACITY {
+        recent.pop_front();
+    }
+    recent.push_back(line);
+}
@@ -0,0 +1,144 @@
+//! Binance spot: market data over the public REST and WebSocket APIs.
+
+use super::{BookQuote, MarketDataSource, StreamEvent, StreamKind, StreamRequest};
+use crate::binance_client;
+use crate::error::Result;
+use crate::kline::Kline;
+use chrono::Utc;
+use futures_util::future::{BoxFuture, FutureExt};
+use serde_json::Value;
+
+const WS_BASE: &str = "wss://stream.binance.com:9443/ws";
+
+/// Binance spot market data.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Binance;
+
+impl MarketDataSource for Binance {
+    fn name(&self) -> &'static str {
+        "binance"
+    }
+
+    fn fetch_klines_range<'a>(
+        &'a self,
+        symbol: &'a str,
+        interval: &'a str,
+        start_time: i64,
+        end_time: i64,
+    ) -> BoxFuture<'a, Result<Vec<Kline>>> {
+        binance_client::fetch_klines_range(symbol, interval, start_time, end_time).boxed()
+    }
+
+    fn fetch_latest_klines<'a>(
+        &'a self,
+        symbol: &'a str,
+        interval: &'a str,
+        count: usize,
+    ) -> BoxFuture<'a, Result<Vec<Kline>>> {
+        binance_client::fetch_latest_klines(symbol, interval, count).boxed()
+    }
+
+    fn fetch_quote_volumes(&self) -> BoxFuture<'_, Result<Vec<(String, f64)>>> {
+        binance_client::fetch_quote_volumes().boxed()
+    }
+
+    fn stream_request(&self, symbol: &str, kind: &StreamKind) -> Result<StreamRequest> {
+        let suffix = match kind {
+            StreamKind::Trades => "trade".to_string(),
+            StreamKind::AggTrades => "aggTrade".to_string(),
+            StreamKind::BookTicker => "bookTicker".to_string(),
+            StreamKind::Klines(interval) => format!("kline_{}", interval),
+        };
+        let name = format!("{}@{}", symbol.to_lowercase(), suffix);
+        Ok(StreamRequest {
+            url: format!("{}/{}", WS_BASE, name),
+            subscribe: Vec::new(),
+            name,
+            trade_candles: false,
+            keepalive: None,
+        })
+    }
+
+    fn parse_message(&self, text: &str) -> Result<Vec<StreamEvent>> {
+        let data: Value = serde_json::from_str(text)?;
+        if matches!(data["e"].as_str(), Some("trade" | "aggTrade")) {
+            let (Some(price), Some(qty), Some(time)) =
+                (data["p"].as_str(), data["q"].as_str(), data["T"].as_i64())
+            else {
+                return Ok(Vec::new());
+            };
+            return Ok(vec![StreamEvent::Trade {
+                price: price.parse().unwrap_or(f64::NAN),
+                qty: qty.parse().unwrap_or(f64::NAN),
+                time,
+                // "m": the buyer was the maker, so the taker sold
+                taker_buy: data["m"].as_bool().map(|buyer_maker| !buyer_maker),
+            }]);
+        }
+        if data["u"].is_u64() && data["b"].is_string() && data["a"].is_string() {
+            return Ok(parse_book_ticker(&data).into_iter().collect());
+        }
+        Ok(parse_closed_kline(&data)?
+            .map(StreamEvent::Candle)
+            .into_iter()
+            .collect())
+    }
+}
+
+/// Decode a bookTicker message. Spot quotes carry no time and are stamped on
+/// receipt.
+fn parse_book_ticker(data: &Value) -> Option<StreamEvent> {
+    let number = |key: &str| data[key].as_str()?.parse::<f64>().ok();
+    Some(StreamEvent::Quote(BookQuote {
+        time: data["E"]
+            .as_i64()
+            .unwrap_or_else(|| Utc::now().timestamp_millis()),
+        bid: number("b")?,
+        bid_qty: number("B")?,
+        ask: number("a")?,
+        ask_qty: number("A")?,
+    }))
+}
+
+/// Extract a closed candle from a kline stream message.
+/// Returns `None` for candles that are still forming or messages without kline data.
+fn parse_closed_kline(data: &Value) -> Result<Option<Kline>> {
+    let Some(kline) = data["k"].as_object() else {
+        return Ok(None);
+    };
+    // Only process closed candles
+    if kline["x"].as_bool() == Some(false) {
+        return Ok(None);
+    }
+
+    let (
+        Some(open_str),
+        Some(high_str),
+        Some(low_str),
+        Some(close_str),
+        Some(volume_str),
+        Some(open_time),
+        Some(close_time),
+    ) = (
+        kline["o"].as_str(),
+        kline["h"].as_str(),
+        kline["l"].as_str(),
+        kline["c"].as_str(),
+        kline["v"].as_str(),
+        kline["t"].as_u64(),
+        kline["T"].as_u64(),
+    )
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(Kline {
+        open_time: open_time as i64,
+        open: open_str.parse::<f64>()?,
+        high: high_str.parse::<f64>()?,
+        low: low_str.parse::<f64>()?,
+        close: close_str.parse::<f64>()?,
+        volume: volume_str.parse::<f64>()?,
+        close_time: close_time as i64,
+    }))
+}
@@ -0,0 +1,144 @@
+//! Bybit linear perpetuals market data. The public v5 stream has closed
+//! candles (`confirm: true`) for every interval and needs an application-level
+//! ping to stay open.
+
+use super::{MarketDataSource, StreamEvent, StreamKind, StreamRequest};
+use crate::bybit_client;
+use crate::error::{Error, Result};
+use crate::kline::Kline;
+use futures_util::future::{BoxFuture, FutureExt};
+use serde_json::{json, Value};
+
+const WS_URL: &str = "wss://stream.bybit.com/v5/public/linear";
+
+/// Bybit USDT/USDC-margined perpetuals.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bybit;
+
+impl MarketDataSource for Bybit {
+    fn name(&self) -> &'static str {
+        "bybit"
+    }
+
+    fn fetch_klines_range<'a>(
+        &'a self,
+        symbol: &'a str,
+        interval: &'a str,
+        start_time: i64,
+        end_time: i64,
+    ) -> BoxFuture<'a, Result<Vec<Kline>>> {
+        bybit_client::fetch_klines_range(symbol, interval, start_time, end_time).boxed()
+    }
+
+    fn fetch_latest_klines<'a>(
+        &'a self,
+        symbol: &'a str,
+        interval: &'a str,
+        count: usize,
+    ) -> BoxFuture<'a, Result<Vec<Kline>>> {
+        bybit_client::fetch_latest_klines(symbol, interval, count).boxed()
+    }
+
+    fn fetch_quote_volumes(&self) -> BoxFuture<'_, Result<Vec<(String, f64)>>> {
+        bybit_client::fetch_quote_volumes().boxed()
+    }
+
+    fn stream_request(&self, symbol: &str, kind: &StreamKind) -> Result<StreamRequest> {
+        let symbol = bybit_client::symbol_id(symbol);
+        let topic = match kind {
+            StreamKind::Trades | StreamKind::AggTrades => format!("publicTrade.{}", symbol),
+            StreamKind::BookTicker => {
+                return Err(Error::Config(
+                    "The Bybit adapter has no bookTicker stream".to_string(),
+                ))
+            }
+            StreamKind::Klines(interval) => {
+                format!(
+                    "kline.{}.{}",
+                    bybit_client::interval_code(interval)?.0,
+                    symbol
+                )
+            }
+        };
+        Ok(StreamRequest {
+            url: WS_URL.to_string(),
+            subscribe: vec![json!({"op": "subscribe", "args": [topic]}).to_string()],
+            name: format!("bybit:{}", topic),
+            trade_candles: false,
+            keepalive: Some(json!({"op": "ping"}).to_string()),
+        })
+    }
+
+    fn parse_message(&self, text: &str) -> Result<Vec<StreamEvent>> {
+        let data: Value = serde_json::from_str(text)?;
+        // Subscription and ping acknowledgements
+        if let Some(op) = data["op"].as_str() {
+            if data["success"].as_bool() == Some(false) {
+                return Err(Error::Exchange {
+                    code: 0,
+                    msg: format!("Bybit rejected '{}': {}", op, data["ret_msg"]),
+                });
+            }
+            return Ok(Vec::new());
+        }
+        let topic = data["topic"].as_str().unwrap_or_default();
+        let Some(items) = data["data"].as_array() else {
+            return Ok(Vec::new());
+        };
+
+        if topic.starts_with("publicTrade.") {
+            return Ok(items
+                .iter()
+                .filter_map(|trade| {
+                    Some(StreamEvent::Trade {
+                        price: trade["p"].as_str()?.parse().ok()?,
+                        qty: trade["v"].as_str()?.parse().ok()?,
+                        time: trade["T"].as_i64()?,
+                        // "S" is the taker side
+                        taker_buy: trade["S"].as_str().map(|side| side == "Buy"),
+                    })
+                })
+                .collect());
+        }
+        if !topic.starts_with("kline.") {
+            return Ok(Vec::new());
+        }
+        let mut events = Vec::new();
+        // Only process closed candles
+        for kline in items
+            .iter()
+            .filter(|k| k["confirm"].as_bool() == Some(true))
+        {
+            let (
+                Some(open),
+                Some(high),
+                Some(low),
+                Some(close),
+                Some(volume),
+                Some(open_time),
+                Some(close_time),
+            ) = (
+                kline["open"].as_str(),
+                kline["high"].as_str(),
+                kline["low"].as_str(),
+                kline["close"].as_str(),
+                kline["volume"].as_str(),
+                kline["start"].as_i64(),
+                kline["end"].as_i64(),
+            )
+            else {
+                continue;
+            };
+            events.push(StreamEvent::Candle(Kline {
+                open_time,
+                open: open.parse()?,
+                high: high.parse()?,
+                low: low.parse()?,
+                close: close.parse()?,
+                volume: volume.parse()?,
+                close_time,
+            }));
+        }
+        Ok(events)
+    }
+}
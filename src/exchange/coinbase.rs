@@ -0,0 +1,169 @@
+//! Coinbase Advanced Trade market data. There is no closed-candle stream for
+//! every interval, so the live stream subscribes to `market_trades` and lets
+//! [`TradeCandles`](super::TradeCandles) build the candles.
+
+use super::{MarketDataSource, StreamEvent, StreamKind, StreamRequest};
+use crate::coinbase_client;
+use crate::error::{Error, Result};
+use crate::kline::Kline;
+use chrono::DateTime;
+use futures_util::future::{BoxFuture, FutureExt};
+use serde_json::{json, Value};
+
+const WS_URL: &str = "wss://advanced-trade-ws.coinbase.com";
+
+/// Coinbase Advanced Trade spot market data.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Coinbase;
+
+impl MarketDataSource for Coinbase {
+    fn name(&self) -> &'static str {
+        "coinbase"
+    }
+
+    fn fetch_klines_range<'a>(
+        &'a self,
+        symbol: &'a str,
+        interval: &'a str,
+        start_time: i64,
+        end_time: i64,
+    ) -> BoxFuture<'a, Result<Vec<Kline>>> {
+        coinbase_client::fetch_klines_range(symbol, interval, start_time, end_time).boxed()
+    }
+
+    fn fetch_latest_klines<'a>(
+        &'a self,
+        symbol: &'a str,
+        interval: &'a str,
+        count: usize,
+    ) -> BoxFuture<'a, Result<Vec<Kline>>> {
+        coinbase_client::fetch_latest_klines(symbol, interval, count).boxed()
+    }
+
+    fn stream_request(&self, symbol: &str, kind: &StreamKind) -> Result<StreamRequest> {
+        match kind {
+            StreamKind::AggTrades => {
+                return Err(Error::Config(
+                    "Coinbase trades do not carry the taker side".to_string(),
+                ))
+            }
+            StreamKind::BookTicker => {
+                return Err(Error::Config(
+                    "The Coinbase adapter has no bookTicker stream".to_string(),
+                ))
+            }
+            _ => {}
+        }
+        let product = coinbase_client::product_id(symbol)?;
+        // Heartbeats keep the connection open through quiet markets
+        let subscribe = ["market_trades", "heartbeats"]
+            .iter()
+            .map(|channel| {
+                json!({
+                    "type": "subscribe",
+                    "product_ids": [product],
+                    "channel": channel,
+                })
+                .to_string()
+            })
+            .collect();
+        Ok(StreamRequest {
+            url: WS_URL.to_string(),
+            subscribe,
+            name: format!("coinbase:{}@market_trades", product),
+            trade_candles: matches!(kind, StreamKind::Klines(_)),
+            keepalive: None,
+        })
+    }
+
+    fn parse_message(&self, text: &str) -> Result<Vec<StreamEvent>> {
+        let data: Value = serde_json::from_str(text)?;
+        if data["type"].as_str() == Some("error") {
+            return Err(Error::Exchange {
+                code: 0,
+                msg: format!("Coinbase stream error: {}", data["message"]),
+            });
+        }
+        if data["channel"].as_str() != Some("market_trades") {
+            return Ok(Vec::new());
+        }
+        let Some(events) = data["events"].as_array() else {
+            return Ok(Vec::new());
+        };
+        Ok(events
+            .iter()
+            // The snapshot replays recent trades from before the subscription
+            .filter(|event| event["type"].as_str() == Some("update"))
+            .filter_map(|event| event["trades"].as_array())
+            .flatten()
+            .filter_map(|trade| {
+                let price = trade["price"].as_str()?.parse().ok()?;
+                let qty = trade["size"].as_str()?.parse().ok()?;
+                let time = DateTime::parse_from_rfc3339(trade["time"].as_str()?).ok()?;
+                Some(StreamEvent::Trade {
+                    price,
+                    qty,
+                    time: time.timestamp_millis(),
+                    taker_buy: None,
+                })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trades(events: Vec<StreamEvent>) -> Vec<(f64, f64, i64)> {
+        events
+            .into_iter()
+            .map(|event| match event {
+                StreamEvent::Trade {
+                    price,
+                    qty,
+                    time,
+                    taker_buy: None,
+                } => (price, qty, time),
+                other => panic!("not a trade: {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn market_trades_updates_become_trades() {
+        let update = r#"{"channel":"market_trades","client_id":"","timestamp":"2024-01-01T00:00:01.2Z","sequence_num":12,"events":[{"type":"update","trades":[{"trade_id":"5872841","product_id":"BTC-USD","price":"42150.01","size":"0.0125","side":"BUY","time":"2024-01-01T00:00:00.5Z"},{"trade_id":"5872842","product_id":"BTC-USD","price":"42149.5","size":"0.3","side":"SELL","time":"2024-01-01T00:00:01.123456Z"}]}]}"#;
+        assert_eq!(
+            trades(Coinbase.parse_message(update).unwrap()),
+            vec![
+                (42150.01, 0.0125, 1_704_067_200_500),
+                (42149.5, 0.3, 1_704_067_201_123),
+            ]
+        );
+
+        // The snapshot holds trades from before the subscription
+        let snapshot = r#"{"channel":"market_trades","client_id":"","timestamp":"2024-01-01T00:00:00Z","sequence_num":0,"events":[{"type":"snapshot","trades":[{"trade_id":"5872840","product_id":"BTC-USD","price":"42100","size":"1","side":"BUY","time":"2023-12-31T23:59:58Z"}]}]}"#;
+        assert!(Coinbase.parse_message(snapshot).unwrap().is_empty());
+    }
+
+    #[test]
+    fn other_channels_carry_no_events() {
+        for text in [
+            r#"{"channel":"candles","client_id":"","timestamp":"2024-01-01T00:05:00Z","sequence_num":3,"events":[{"type":"update","candles":[{"start":"1704067200","high":"42200","low":"42100","open":"42150","close":"42180","volume":"12.5","product_id":"BTC-USD"}]}]}"#,
+            r#"{"channel":"heartbeats","client_id":"","timestamp":"2024-01-01T00:00:01Z","sequence_num":4,"events":[{"current_time":"2024-01-01 00:00:01.000000000 +0000 UTC m=+1.0","heartbeat_counter":"1"}]}"#,
+            r#"{"channel":"subscriptions","client_id":"","timestamp":"2024-01-01T00:00:00Z","sequence_num":1,"events":[{"subscriptions":{"market_trades":["BTC-USD"]}}]}"#,
+        ] {
+            assert!(Coinbase.parse_message(text).unwrap().is_empty(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn error_frames_end_the_session() {
+        let error = r#"{"type":"error","message":"failure to subscribe"}"#;
+        assert!(matches!(
+            Coinbase.parse_message(error),
+            Err(Error::Exchange { .. })
+        ));
+        assert!(Coinbase.parse_message("not json").is_err());
+    }
+}
@@ -0,0 +1,196 @@
+//! Higher-interval candles built from the closed candles of a shorter one,
+//! so that one base stream (e.g. 1m) feeds 15m, 1h and 4h pipelines.
+//!
+//! Buckets are aligned to the epoch like the exchange's own candles (a 4h
+//! candle opens at 00:00, 04:00, ... UTC). A candle is closed by the last base
+//! candle of its bucket or, when that one never arrives, by the first base
+//! candle of a later bucket. A base candle delivered twice counts once.
+
+use crate::error::{Error, Result};
+use crate::kline::Kline;
+
+#[derive(Debug, Clone)]
+pub struct IntervalCandles {
+    base_ms: i64,
+    interval_ms: i64,
+    /// Candle being built and the number of base candles in it.
+    current: Option<(Kline, usize)>,
+    /// Open time of the newest base candle in `current`.
+    last_base: i64,
+    /// Open time of the newest closed candle; older base candles are ignored.
+    closed_until: i64,
+}
+
+impl IntervalCandles {
+    /// Builder of `interval_ms` candles from `base_ms` candles; the interval
+    /// must be a whole multiple of the base.
+    pub fn new(base_ms: i64, interval_ms: i64) -> Result<Self> {
+        if base_ms <= 0 || interval_ms < base_ms || interval_ms % base_ms != 0 {
+            return Err(Error::Config(format!(
+                "A {} ms candle cannot be built from {} ms candles",
+                interval_ms, base_ms
+            )));
+        }
+        Ok(Self {
+            base_ms,
+            interval_ms,
+            current: None,
+            last_base: i64::MIN,
+            closed_until: i64::MIN,
+        })
+    }
+
+    /// Base candles in one candle of the interval.
+    pub fn parts(&self) -> usize {
+        (self.interval_ms / self.base_ms) as usize
+    }
+
+    /// Open time of the candle containing `time_ms`.
+    pub fn bucket(&self, time_ms: i64) -> i64 {
+        time_ms - time_ms.rem_euclid(self.interval_ms)
+    }
+
+    /// Add a closed base candle; returns the candles it closed, each with the
+    /// number of base candles it was built from (fewer than [`Self::parts`]
+    /// after a gap).
+    pub fn push(&mut self, base: &Kline) -> Vec<(Kline, usize)> {
+        let open_time = self.bucket(base.open_time);
+        if open_time <= self.closed_until {
+            return Vec::new();
+        }
+        let mut closed = Vec::new();
+        match &mut self.current {
+            // A base candle already in it (a repeated delivery)
+            Some((k, _)) if k.open_time == open_time && base.open_time <= self.last_base => {
+                return Vec::new()
+            }
+            Some((k, parts)) if k.open_time == open_time => {
+                k.high = k.high.max(base.high);
+                k.low = k.low.min(base.low);
+                k.close = base.close;
+                k.volume += base.volume;
+                *parts += 1;
+            }
+            // A base candle older than the current one
+            Some((k, _)) if k.open_time > open_time => return Vec::new(),
+            current => {
+                if let Some(done) = current.take() {
+                    self.closed_until = done.0.open_time;
+                    closed.push(done);
+                }
+                self.current = Some((
+                    Kline {
+                        open_time,
+                        open: base.open,
+                        high: base.high,
+                        low: base.low,
+                        close: base.close,
+                        volume: base.volume,
+                        close_time: open_time + self.interval_ms - 1,
+                    },
+                    1,
+                ));
+            }
+        }
+        self.last_base = base.open_time;
+        // The last base candle of the bucket completes it
+        if base.open_time + self.base_ms >= open_time + self.interval_ms {
+            if let Some(done) = self.current.take() {
+                self.closed_until = done.0.open_time;
+                closed.push(done);
+            }
+        }
+        closed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: i64 = 60_000;
+
+    /// The 1m candle opening at `minute`, at `price` with volume 1.
+    fn base(minute: i64, price: f64) -> Kline {
+        Kline {
+            open_time: minute * MINUTE,
+            open: price,
+            high: price + 1.0,
+            low: price - 1.0,
+            close: price,
+            volume: 1.0,
+            close_time: (minute + 1) * MINUTE - 1,
+        }
+    }
+
+    /// 5m candles: (open time in minutes, open, high, low, close, volume, parts)
+    fn summary(closed: Vec<(Kline, usize)>) -> Vec<(i64, f64, f64, f64, f64, f64, usize)> {
+        closed
+            .into_iter()
+            .map(|(k, parts)| {
+                assert_eq!(k.close_time, k.open_time + 5 * MINUTE - 1);
+                let minute = k.open_time / MINUTE;
+                (minute, k.open, k.high, k.low, k.close, k.volume, parts)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rejects_intervals_that_are_not_multiples() {
+        assert!(IntervalCandles::new(MINUTE, 90_000).is_err());
+        assert!(IntervalCandles::new(5 * MINUTE, MINUTE).is_err());
+        assert!(IntervalCandles::new(0, MINUTE).is_err());
+    }
+
+    #[test]
+    fn closes_a_complete_candle_once() {
+        let mut builder = IntervalCandles::new(MINUTE, 5 * MINUTE).unwrap();
+        for (minute, price) in [(5, 100.0), (6, 104.0), (7, 98.0), (8, 101.0)] {
+            // The open candle is never emitted, however often it changes
+            assert!(builder.push(&base(minute, price)).is_empty());
+        }
+        let closed = builder.push(&base(9, 102.0));
+        assert_eq!(
+            summary(closed),
+            vec![(5, 100.0, 105.0, 97.0, 102.0, 5.0, 5)]
+        );
+        // Repeated base candles of a closed candle are ignored
+        assert!(builder.push(&base(9, 102.0)).is_empty());
+        assert!(builder.push(&base(7, 98.0)).is_empty());
+        assert!(summary(builder.push(&base(10, 103.0))).is_empty());
+    }
+
+    #[test]
+    fn repeated_base_candles_count_once() {
+        let mut builder = IntervalCandles::new(MINUTE, 5 * MINUTE).unwrap();
+        for minute in [0, 1, 1, 2, 0, 3] {
+            assert!(builder.push(&base(minute, 100.0)).is_empty());
+        }
+        let closed = builder.push(&base(4, 100.0));
+        assert_eq!(
+            summary(closed),
+            vec![(0, 100.0, 101.0, 99.0, 100.0, 5.0, 5)]
+        );
+    }
+
+    #[test]
+    fn a_later_bucket_closes_an_incomplete_candle() {
+        let mut builder = IntervalCandles::new(MINUTE, 5 * MINUTE).unwrap();
+        builder.push(&base(0, 100.0));
+        builder.push(&base(1, 101.0));
+        // Minutes 2-4 and the whole 5m interval at 5 never arrive
+        let closed = builder.push(&base(11, 110.0));
+        assert_eq!(
+            summary(closed),
+            vec![(0, 100.0, 102.0, 99.0, 101.0, 2.0, 2)]
+        );
+        for minute in 12..14 {
+            assert!(builder.push(&base(minute, 110.0)).is_empty());
+        }
+        let closed = builder.push(&base(14, 111.0));
+        assert_eq!(
+            summary(closed),
+            vec![(10, 110.0, 112.0, 109.0, 111.0, 4.0, 4)]
+        );
+    }
+}
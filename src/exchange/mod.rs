@@ -0,0 +1,208 @@
+//! Exchange abstraction: where candles come from.
+//!
+//! The rest of the collector only talks to [`MarketDataSource`]: REST history
+//! ([`HistoryStore`](crate::history::HistoryStore)) and the WebSocket
+//! protocol of the live stream: which URL to open, which subscription frames
+//! to send, and how to decode a text frame into [`StreamEvent`]s. The binary
+//! owns the connection loop (reconnects, statistics, events) once for every
+//! exchange.
+//!
+//! [`Exchange`] is the configured choice and resolves to a source with
+//! [`Exchange::source`]. Adapters: [`Binance`], [`Coinbase`] and [`Bybit`]
+//! linear perpetuals. Sources without a candle stream set
+//! [`StreamRequest::trade_candles`] and have their trades aggregated by
+//! [`TradeCandles`]; [`IntervalCandles`] builds longer candles from a shorter
+//! candle stream. [`recorded`] reads `--record` session recordings back
+//! and replays them against an offline [`recorded::Recorded`] source.
+
+mod binance;
+mod bybit;
+mod coinbase;
+mod interval_candles;
+pub mod recorded;
+mod trade_candles;
+
+pub use binance::Binance;
+pub use bybit::Bybit;
+pub use coinbase::Coinbase;
+pub use interval_candles::IntervalCandles;
+pub use trade_candles::TradeCandles;
+
+use crate::error::{Error, Result};
+use crate::kline::Kline;
+use futures_util::future::{BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often a [`StreamRequest::keepalive`] frame is sent.
+pub const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(20);
+
+/// What a live stream delivers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamKind {
+    /// Individual trades, only logged.
+    Trades,
+    /// Trades with their taker side, for order-flow features (Binance
+    /// aggTrades, Bybit public trades).
+    AggTrades,
+    /// Best bid and ask updates (Binance bookTicker).
+    BookTicker,
+    /// Closed candles of a Binance-style interval ("5m", "15m", ...).
+    Klines(String),
+}
+
+/// How to open a live stream.
+#[derive(Debug, Clone)]
+pub struct StreamRequest {
+    pub url: String,
+    /// Text frames sent right after connecting.
+    pub subscribe: Vec<String>,
+    /// Stream name for logs, events and connection statistics.
+    pub name: String,
+    /// The stream carries trades only; closed candles are built from them
+    /// with [`TradeCandles`].
+    pub trade_candles: bool,
+    /// Text frame the server expects every [`KEEPALIVE_INTERVAL`] to keep
+    /// the connection open.
+    pub keepalive: Option<String>,
+}
+
+/// One decoded item of a live stream.
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    Trade {
+        price: f64,
+        qty: f64,
+        time: i64,
+        /// Whether the taker bought; `None` when the venue does not say.
+        taker_buy: Option<bool>,
+    },
+    /// A closed candle; candles still forming are never reported.
+    Candle(Kline),
+    /// A change of the best bid or ask.
+    Quote(BookQuote),
+}
+
+/// Best bid and ask with the quantities quoted at them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookQuote {
+    /// Epoch ms.
+    pub time: i64,
+    pub bid: f64,
+    pub bid_qty: f64,
+    pub ask: f64,
+    pub ask_qty: f64,
+}
+
+/// Historical and live market data of one exchange.
+pub trait MarketDataSource: Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Historical candles between `start_time` and `end_time` (ms), oldest first.
+    fn fetch_klines_range<'a>(
+        &'a self,
+        symbol: &'a str,
+        interval: &'a str,
+        start_time: i64,
+        end_time: i64,
+    ) -> BoxFuture<'a, Result<Vec<Kline>>>;
+
+    /// The latest `count` candles, oldest first.
+    fn fetch_latest_klines<'a>(
+        &'a self,
+        symbol: &'a str,
+        interval: &'a str,
+        count: usize,
+    ) -> BoxFuture<'a, Result<Vec<Kline>>>;
+
+    /// WebSocket endpoint and subscription of `kind` for `symbol`.
+    fn stream_request(&self, symbol: &str, kind: &StreamKind) -> Result<StreamRequest>;
+
+    /// Decode one text frame. Frames that carry nothing of interest
+    /// (acknowledgements, heartbeats, forming candles) yield no events.
+    fn parse_message(&self, text: &str) -> Result<Vec<StreamEvent>>;
+
+    /// 24h quote-currency volume of every symbol, for ranking a universe.
+    fn fetch_quote_volumes(&self) -> BoxFuture<'_, Result<Vec<(String, f64)>>> {
+        let name = self.name();
+        async move {
+            Err(Error::Config(format!(
+                "The {} adapter does not report 24h volumes",
+                name
+            )))
+        }
+        .boxed()
+    }
+}
+
+/// Length of a Binance-style interval in ms.
+pub fn interval_ms(interval: &str) -> Result<i64> {
+    let unit_at = interval
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(interval.len());
+    let (count, unit) = interval.split_at(unit_at);
+    let count: i64 = count.parse().unwrap_or(0);
+    let unit_ms = match unit {
+        "m" => 60_000,
+        "h" => 60 * 60_000,
+        "d" => 24 * 60 * 60_000,
+        "w" => 7 * 24 * 60 * 60_000,
+        _ => 0,
+    };
+    if count == 0 || unit_ms == 0 {
+        return Err(Error::Config(format!("Unsupported interval: {}", interval)));
+    }
+    Ok(count * unit_ms)
+}
+
+/// Exchanges the collector can take candles from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Exchange {
+    #[default]
+    Binance,
+    /// Coinbase Advanced Trade (spot).
+    Coinbase,
+    /// Bybit linear perpetuals.
+    Bybit,
+}
+
+impl Exchange {
+    pub fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "binance" => Exchange::Binance,
+            "coinbase" => Exchange::Coinbase,
+            "bybit" => Exchange::Bybit,
+            other => {
+                return Err(Error::Config(format!(
+                    "Unknown exchange '{}' (binance, coinbase or bybit)",
+                    other
+                )))
+            }
+        })
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Exchange::Binance => "binance",
+            Exchange::Coinbase => "coinbase",
+            Exchange::Bybit => "bybit",
+        }
+    }
+
+    /// Whether this exchange's candles are of a perpetual future, not spot.
+    pub fn is_perpetual(self) -> bool {
+        matches!(self, Exchange::Bybit)
+    }
+
+    /// The market-data adapter of this exchange.
+    pub fn source(self) -> Arc<dyn MarketDataSource> {
+        match self {
+            Exchange::Binance => Arc::new(Binance),
+            Exchange::Coinbase => Arc::new(Coinbase),
+            Exchange::Bybit => Arc::new(Bybit),
+        }
+    }
+}
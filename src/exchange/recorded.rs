@@ -0,0 +1,190 @@
+//! Recorded sessions: every raw WebSocket frame of a live run with its
+//! receive time, as JSON lines in a zstd-compressed file (`--record`).
+//!
+//! [`session_klines`] turns a recording back into the closed candles the live
+//! stream produced, through the same [`MarketDataSource::parse_message`] and
+//! [`TradeCandles`] as the live loop, and [`Recorded`] stands in for the
+//! exchange while a session is replayed: history is served from memory and
+//! nothing touches the network.
+
+use super::{Exchange, MarketDataSource, StreamEvent, StreamKind, StreamRequest, TradeCandles};
+use crate::error::{Error, Result};
+use crate::kline::Kline;
+use futures_util::future::{self, BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+/// One line of a recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// Wall-clock receive time (epoch ms).
+    pub t: i64,
+    pub exchange: Exchange,
+    /// [`StreamRequest::name`] of the connection the frame arrived on.
+    pub stream: String,
+    /// A (re)connect marker instead of a frame; `text` is empty.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub connect: bool,
+    #[serde(default)]
+    pub text: String,
+}
+
+/// Read every frame of a recording. A run that was killed leaves its last
+/// compressed block unfinished; the frames before it are still returned.
+pub fn load_session(path: &str) -> Result<Vec<RecordedFrame>> {
+    let file = File::open(path)
+        .map_err(|e| Error::Storage(format!("Failed to open recording {}: {}", path, e).into()))?;
+    let decoder = zstd::stream::read::Decoder::new(file)?;
+    let mut frames = Vec::new();
+    for (number, line) in BufReader::new(decoder).lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!(
+                    "Warning: recording {} ends in a truncated block after {} frames: {}",
+                    path,
+                    frames.len(),
+                    e
+                );
+                break;
+            }
+        };
+        if line.is_empty() {
+            continue;
+        }
+        frames.push(serde_json::from_str(&line).map_err(|e| {
+            Error::Parse(format!(
+                "Bad frame on line {} of {}: {}",
+                number + 1,
+                path,
+                e
+            ))
+        })?);
+    }
+    Ok(frames)
+}
+
+/// The closed candles `request` delivered in a recording, in arrival order.
+/// Frames of other streams (e.g. a cross-exchange reference) are skipped.
+pub fn session_klines(
+    frames: &[RecordedFrame],
+    source: &dyn MarketDataSource,
+    request: &StreamRequest,
+    kind: &StreamKind,
+) -> Result<Vec<Kline>> {
+    let interval_ms = match kind {
+        StreamKind::Klines(interval) if request.trade_candles => {
+            Some(super::interval_ms(interval)?)
+        }
+        _ => None,
+    };
+    let mut builder = None;
+    let mut klines = Vec::new();
+    for frame in frames.iter().filter(|f| f.stream == request.name) {
+        // Every connection starts its own builder, as the live loop does
+        if frame.connect {
+            builder = interval_ms.map(|ms| TradeCandles::new(ms, frame.t));
+            continue;
+        }
+        if let Some(builder) = builder.as_mut() {
+            klines.extend(builder.tick(frame.t));
+        }
+        // The live loop drops the connection on a rejected frame
+        let Ok(events) = source.parse_message(&frame.text) else {
+            builder = None;
+            continue;
+        };
+        for event in events {
+            match (event, builder.as_mut()) {
+                (
+                    StreamEvent::Trade {
+                        price, qty, time, ..
+                    },
+                    Some(builder),
+                ) => {
+                    klines.extend(builder.trade(price, qty, time));
+                }
+                (StreamEvent::Trade { .. }, None) | (StreamEvent::Quote(_), _) => {}
+                (StreamEvent::Candle(kline), _) => klines.push(kline),
+            }
+        }
+    }
+    Ok(klines)
+}
+
+/// Offline stand-in for `exchange` during a replay: decodes frames like the
+/// real adapter and answers history requests from `history`.
+#[derive(Debug)]
+pub struct Recorded {
+    exchange: Exchange,
+    live: Arc<dyn MarketDataSource>,
+    history: Vec<Kline>,
+}
+
+impl Recorded {
+    pub fn new(exchange: Exchange, history: Vec<Kline>) -> Self {
+        Self {
+            exchange,
+            live: exchange.source(),
+            history,
+        }
+    }
+
+    fn range(&self, start_time: i64, end_time: i64) -> Vec<Kline> {
+        self.history
+            .iter()
+            .filter(|k| k.open_time >= start_time && k.open_time <= end_time)
+            .cloned()
+            .collect()
+    }
+}
+
+impl MarketDataSource for Recorded {
+    fn name(&self) -> &'static str {
+        self.exchange.as_str()
+    }
+
+    fn fetch_klines_range<'a>(
+        &'a self,
+        _symbol: &'a str,
+        _interval: &'a str,
+        start_time: i64,
+        end_time: i64,
+    ) -> BoxFuture<'a, Result<Vec<Kline>>> {
+        future::ready(Ok(self.range(start_time, end_time))).boxed()
+    }
+
+    fn fetch_latest_klines<'a>(
+        &'a self,
+        _symbol: &'a str,
+        _interval: &'a str,
+        count: usize,
+    ) -> BoxFuture<'a, Result<Vec<Kline>>> {
+        let skip = self.history.len().saturating_sub(count);
+        future::ready(Ok(self.history[skip..].to_vec())).boxed()
+    }
+
+    fn stream_request(&self, symbol: &str, kind: &StreamKind) -> Result<StreamRequest> {
+        self.live.stream_request(symbol, kind)
+    }
+
+    fn parse_message(&self, text: &str) -> Result<Vec<StreamEvent>> {
+        self.live.parse_message(text)
+    }
+}
+
+/// Check that a recording holds frames of `stream`, naming the streams it does hold.
+pub fn ensure_stream(frames: &[RecordedFrame], stream: &str) -> Result<()> {
+    if frames.iter().any(|f| f.stream == stream) {
+        return Ok(());
+    }
+    let mut streams: Vec<&str> = frames.iter().map(|f| f.stream.as_str()).collect();
+    streams.sort_unstable();
+    streams.dedup();
+    Err(Error::Config(format!(
+        "The recording has no frames of '{}' (it holds {:?}); check --exchange and --symbol",
+        stream, streams
+    )))
+}
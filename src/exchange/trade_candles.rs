@@ -0,0 +1,202 @@
+//! Closed candles built from a trade stream, for sources without a candle
+//! stream.
+//!
+//! Trades are bucketed by `open_time`. A candle is closed when a trade of a
+//! later bucket arrives or, in a quiet market, [`CLOSE_GRACE_MS`] after its
+//! interval ended (see [`TradeCandles::tick`]). Buckets without trades become
+//! flat zero-volume candles at the previous close, as Binance reports them.
+//! The bucket the builder starts in is incomplete and skipped.
+
+use crate::kline::Kline;
+
+/// How long after its interval ends a candle waits for late trades.
+pub const CLOSE_GRACE_MS: i64 = 2_000;
+
+#[derive(Debug, Clone)]
+pub struct TradeCandles {
+    interval_ms: i64,
+    /// Trades before this bucket are ignored (the start bucket is partial).
+    start_from: i64,
+    current: Option<Kline>,
+    /// Newest closed candle, the base of flat candles for empty buckets.
+    last: Option<Kline>,
+}
+
+impl TradeCandles {
+    /// Builder for `interval_ms` candles, started at `now_ms`.
+    pub fn new(interval_ms: i64, now_ms: i64) -> Self {
+        Self {
+            interval_ms,
+            start_from: now_ms - now_ms.rem_euclid(interval_ms) + interval_ms,
+            current: None,
+            last: None,
+        }
+    }
+
+    fn bucket(&self, time_ms: i64) -> i64 {
+        time_ms - time_ms.rem_euclid(self.interval_ms)
+    }
+
+    /// Add a trade; returns the candles it closed.
+    pub fn trade(&mut self, price: f64, qty: f64, time_ms: i64) -> Vec<Kline> {
+        if time_ms < self.start_from {
+            return Vec::new();
+        }
+        let open_time = self.bucket(time_ms);
+        let closed = self.close_before(open_time);
+        match &mut self.current {
+            Some(k) if k.open_time == open_time => {
+                k.high = k.high.max(price);
+                k.low = k.low.min(price);
+                k.close = price;
+                k.volume += qty;
+            }
+            // A late trade of an already closed candle
+            Some(k) if k.open_time > open_time => {}
+            _ if self.last.as_ref().is_some_and(|k| k.open_time >= open_time) => {}
+            _ => {
+                self.current = Some(Kline {
+                    open_time,
+                    open: price,
+                    high: price,
+                    low: price,
+                    close: price,
+                    volume: qty,
+                    close_time: open_time + self.interval_ms - 1,
+                })
+            }
+        }
+        closed
+    }
+
+    /// Close every candle whose interval ended more than the grace period
+    /// before `now_ms`.
+    pub fn tick(&mut self, now_ms: i64) -> Vec<Kline> {
+        let open_time = self.bucket(now_ms - CLOSE_GRACE_MS);
+        self.close_before(open_time)
+    }
+
+    /// Close the current candle and fill empty buckets, up to (not
+    /// including) the bucket opening at `open_time`.
+    fn close_before(&mut self, open_time: i64) -> Vec<Kline> {
+        let mut closed = Vec::new();
+        if self
+            .current
+            .as_ref()
+            .is_some_and(|k| k.open_time < open_time)
+        {
+            closed.extend(self.current.take());
+        }
+        let mut prev = closed.last().or(self.last.as_ref()).cloned();
+        while let Some(p) = prev.filter(|p| p.open_time + self.interval_ms < open_time) {
+            let flat = Kline {
+                open_time: p.open_time + self.interval_ms,
+                open: p.close,
+                high: p.close,
+                low: p.close,
+                close: p.close,
+                volume: 0.0,
+                close_time: p.open_time + 2 * self.interval_ms - 1,
+            };
+            closed.push(flat.clone());
+            prev = Some(flat);
+        }
+        if let Some(last) = closed.last() {
+            self.last = Some(last.clone());
+        }
+        closed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: i64 = 60_000;
+
+    /// (open time in minutes, open, high, low, close, volume)
+    fn summary(klines: &[Kline]) -> Vec<(i64, f64, f64, f64, f64, f64)> {
+        klines
+            .iter()
+            .map(|k| {
+                assert_eq!(k.close_time, k.open_time + MINUTE - 1);
+                (
+                    k.open_time / MINUTE,
+                    k.open,
+                    k.high,
+                    k.low,
+                    k.close,
+                    k.volume,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn skips_the_bucket_it_starts_in() {
+        let mut builder = TradeCandles::new(MINUTE, 10 * MINUTE + 30_000);
+        assert!(builder.trade(100.0, 1.0, 10 * MINUTE + 40_000).is_empty());
+        assert!(builder.trade(101.0, 1.0, 11 * MINUTE).is_empty());
+        // Nothing of the partial start bucket is ever emitted
+        let closed = builder.trade(102.0, 1.0, 12 * MINUTE);
+        assert_eq!(
+            summary(&closed),
+            vec![(11, 101.0, 101.0, 101.0, 101.0, 1.0)]
+        );
+    }
+
+    #[test]
+    fn rolls_over_on_a_trade_of_a_later_bucket() {
+        let mut builder = TradeCandles::new(MINUTE, 0);
+        for (price, qty, time) in [
+            (100.0, 1.0, MINUTE),
+            (103.0, 0.5, MINUTE + 1_000),
+            (99.0, 2.0, MINUTE + 30_000),
+            (101.0, 0.25, 2 * MINUTE - 1),
+        ] {
+            assert!(builder.trade(price, qty, time).is_empty());
+        }
+        let closed = builder.trade(102.0, 1.0, 2 * MINUTE);
+        assert_eq!(summary(&closed), vec![(1, 100.0, 103.0, 99.0, 101.0, 3.75)]);
+        // A late trade of the closed candle changes nothing
+        assert!(builder.trade(50.0, 9.0, 2 * MINUTE - 5).is_empty());
+        let closed = builder.trade(102.5, 1.0, 3 * MINUTE);
+        assert_eq!(summary(&closed), vec![(2, 102.0, 102.0, 102.0, 102.0, 1.0)]);
+    }
+
+    #[test]
+    fn fills_empty_buckets_with_flat_candles() {
+        let mut builder = TradeCandles::new(MINUTE, 0);
+        builder.trade(100.0, 1.0, MINUTE);
+        builder.trade(105.0, 1.0, MINUTE + 1);
+        let closed = builder.trade(110.0, 2.0, 4 * MINUTE + 5);
+        assert_eq!(
+            summary(&closed),
+            vec![
+                (1, 100.0, 105.0, 100.0, 105.0, 2.0),
+                (2, 105.0, 105.0, 105.0, 105.0, 0.0),
+                (3, 105.0, 105.0, 105.0, 105.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn tick_closes_after_the_grace_period() {
+        let mut builder = TradeCandles::new(MINUTE, 0);
+        builder.trade(100.0, 1.0, MINUTE + 10);
+        assert!(builder.tick(2 * MINUTE + CLOSE_GRACE_MS - 1).is_empty());
+        let closed = builder.tick(2 * MINUTE + CLOSE_GRACE_MS);
+        assert_eq!(summary(&closed), vec![(1, 100.0, 100.0, 100.0, 100.0, 1.0)]);
+        // A quiet market: one flat candle per interval, each closed once
+        assert!(builder.tick(3 * MINUTE).is_empty());
+        let closed = builder.tick(4 * MINUTE + CLOSE_GRACE_MS);
+        assert_eq!(
+            summary(&closed),
+            vec![
+                (2, 100.0, 100.0, 100.0, 100.0, 0.0),
+                (3, 100.0, 100.0, 100.0, 100.0, 0.0),
+            ]
+        );
+        assert!(builder.tick(4 * MINUTE + CLOSE_GRACE_MS).is_empty());
+    }
+}
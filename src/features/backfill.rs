@@ -0,0 +1,93 @@
+//! Point-in-time feature rows for a whole candle archive, computed chunk by
+//! chunk instead of one rolling window per candle.
+//!
+//! Every row equals the newest row of the live feature window when its candle
+//! closed: the EMAs advance through one [`EmaTracker`] across all chunks, and
+//! the pivot left runs are counted over the chunk plus the last
+//! [`PIVOT_WINDOW`] candles of the previous one (the warm-up overlap), so no
+//! run is cut at a chunk boundary. Right runs and strengths are 0, since no
+//! later candle is known at the close. Memory stays bounded by the chunk size
+//! however long the archive is.
+
+use super::ema::{EmaRow, EMA_COLUMNS};
+use super::pivots::{self, PIVOT_WINDOW};
+use super::{base_frame, EmaTracker};
+use crate::kline::Kline;
+use anyhow::{bail, Result};
+use polars::prelude::*;
+
+pub struct FeatureBackfill {
+    ema: EmaTracker,
+    /// The newest candles of the previous chunk, at most [`PIVOT_WINDOW`].
+    overlap: Vec<Kline>,
+    /// Candles seen so far.
+    seen: usize,
+    /// Size of the live window, which caps the `index` column.
+    window: usize,
+}
+
+impl FeatureBackfill {
+    /// Backfill whose rows are numbered like those of a `window`-candle live
+    /// window.
+    pub fn new(window: usize) -> Self {
+        Self {
+            ema: EmaTracker::replay(&[], 1),
+            overlap: Vec::new(),
+            seen: 0,
+            window: window.max(1),
+        }
+    }
+
+    /// Candles seen so far.
+    pub fn seen(&self) -> usize {
+        self.seen
+    }
+
+    /// Feature rows of `klines` (oldest first, all newer than the previous
+    /// chunk), one per candle.
+    pub fn push_chunk(&mut self, klines: &[Kline]) -> Result<DataFrame> {
+        if let (Some(last), Some(first)) = (self.overlap.last(), klines.first()) {
+            if first.open_time <= last.open_time {
+                bail!(
+                    "Candle at {} is not newer than the previous chunk's newest at {}",
+                    first.open_time,
+                    last.open_time
+                );
+            }
+        }
+        let rows: Vec<EmaRow> = klines.iter().map(|k| self.ema.update(k)).collect();
+
+        let skip = self.overlap.len();
+        let mut candles = std::mem::take(&mut self.overlap);
+        candles.extend_from_slice(klines);
+        let df = pivots::add_pivot_features_as_of_close(base_frame(&candles)?)?;
+        let mut df = df.slice(skip as i64, klines.len());
+
+        // The live window numbers its rows 1..=len; a closed candle is the last
+        let index: Vec<u32> = (1..=klines.len())
+            .map(|i| (self.seen + i).min(self.window) as u32)
+            .collect();
+        df.with_column(Series::new("index".into(), index).into())?;
+        for (c, name) in EMA_COLUMNS.iter().enumerate() {
+            let values: Vec<Option<f64>> = rows.iter().map(|row| row[c]).collect();
+            df.with_column(Series::new((*name).into(), values).into())?;
+        }
+        // Same column order as the live frame: candle columns, EMAs, pivots
+        let order: Vec<String> = df
+            .get_column_names()
+            .into_iter()
+            .filter(|name| !name.starts_with("pivot_"))
+            .chain(
+                df.get_column_names()
+                    .into_iter()
+                    .filter(|n| n.starts_with("pivot_")),
+            )
+            .map(|name| name.to_string())
+            .collect();
+        let df = df.select(order)?;
+
+        self.seen += klines.len();
+        self.overlap = candles.split_off(candles.len().saturating_sub(PIVOT_WINDOW));
+        Ok(df)
+    }
+}
@@ -0,0 +1,184 @@
+//! Scheduled-event features from an economic or exchange calendar (CPI
+//! releases, FOMC decisions, exchange maintenance, ...), so strategies and
+//! models can tell when the market is about to be moved by news.
+//!
+//! Both columns are read at the candle's close, i.e. as known when its
+//! feature row is written:
+//!
+//! - `minutes_to_event` – minutes until the next scheduled event (null when
+//!   the calendar has none left);
+//! - `event_window` – whether the close lies within `before_mins` before to
+//!   `after_mins` after any event.
+//!
+//! A calendar is a JSON array of `{"time", "name", "kind"}` objects or a CSV
+//! file with a `time,name[,kind]` header. Times are RFC 3339
+//! ("2025-03-12T12:30:00Z") or epoch ms; `kind` is free text ("macro",
+//! "maintenance", ...) and optional.
+
+use anyhow::{bail, Context, Result};
+use chrono::DateTime;
+use polars::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Column names added by [`EventCalendar::add_columns`].
+pub const CALENDAR_COLUMNS: [&str; 2] = ["minutes_to_event", "event_window"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledEvent {
+    /// Scheduled time (epoch ms).
+    pub time: i64,
+    pub name: String,
+    pub kind: String,
+}
+
+#[derive(Deserialize)]
+struct RawEvent {
+    time: Value,
+    name: String,
+    #[serde(default)]
+    kind: String,
+}
+
+/// Epoch ms, or an RFC 3339 timestamp.
+fn parse_time(value: &str) -> Result<i64> {
+    let value = value.trim();
+    if let Ok(ms) = value.parse() {
+        return Ok(ms);
+    }
+    let time = DateTime::parse_from_rfc3339(value)
+        .with_context(|| format!("invalid event time '{}'", value))?;
+    Ok(time.timestamp_millis())
+}
+
+/// Events of a calendar file's contents, JSON or CSV, in time order.
+pub fn parse_events(text: &str) -> Result<Vec<ScheduledEvent>> {
+    let mut events = if text.trim_start().starts_with('[') {
+        let raw: Vec<RawEvent> = serde_json::from_str(text).context("invalid calendar JSON")?;
+        raw.into_iter()
+            .map(|e| {
+                let time = match &e.time {
+                    Value::Number(n) => n.as_i64().context("event time must be epoch ms")?,
+                    Value::String(s) => parse_time(s)?,
+                    other => bail!("invalid event time {}", other),
+                };
+                Ok(ScheduledEvent {
+                    time,
+                    name: e.name,
+                    kind: e.kind,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+        let header: Vec<&str> = lines
+            .next()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .collect();
+        let column = |name: &str| header.iter().position(|h| *h == name);
+        let (Some(time), Some(name)) = (column("time"), column("name")) else {
+            bail!("a calendar CSV needs a header with `time` and `name` columns");
+        };
+        let kind = column("kind");
+        lines
+            .map(|line| {
+                let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+                let field = |i: usize| fields.get(i).copied().unwrap_or_default();
+                Ok(ScheduledEvent {
+                    time: parse_time(field(time))?,
+                    name: field(name).to_string(),
+                    kind: kind.map(field).unwrap_or_default().to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
+    events.sort_by_key(|e| e.time);
+    Ok(events)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EventCalendar {
+    /// In time order.
+    events: Vec<ScheduledEvent>,
+    before_ms: i64,
+    after_ms: i64,
+}
+
+impl EventCalendar {
+    /// A calendar of `events` whose windows open `before_mins` before and
+    /// close `after_mins` after each of them.
+    pub fn new(mut events: Vec<ScheduledEvent>, before_mins: i64, after_mins: i64) -> Self {
+        events.sort_by_key(|e| e.time);
+        Self {
+            events,
+            before_ms: before_mins * 60_000,
+            after_ms: after_mins * 60_000,
+        }
+    }
+
+    /// Replace the events, keeping the window sizes.
+    pub fn set_events(&mut self, mut events: Vec<ScheduledEvent>) {
+        events.sort_by_key(|e| e.time);
+        self.events = events;
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// First event at or after `time_ms`.
+    pub fn next_event(&self, time_ms: i64) -> Option<&ScheduledEvent> {
+        let i = self.events.partition_point(|e| e.time < time_ms);
+        self.events.get(i)
+    }
+
+    /// An event whose window contains `time_ms` (the nearest one ahead if
+    /// windows overlap).
+    pub fn active_event(&self, time_ms: i64) -> Option<&ScheduledEvent> {
+        let i = self
+            .events
+            .partition_point(|e| e.time + self.after_ms < time_ms);
+        self.events
+            .get(i)
+            .filter(|e| e.time - self.before_ms <= time_ms)
+    }
+
+    /// One column per [`CALENDAR_COLUMNS`] name, for candles closing at
+    /// `close_times`.
+    pub fn columns(&self, close_times: &Int64Chunked) -> Vec<Column> {
+        let (minutes, window): (Vec<Option<f64>>, Vec<Option<bool>>) = close_times
+            .into_iter()
+            .map(|t| match t {
+                // Close times end on the last millisecond of the candle
+                Some(t) => {
+                    let closed = t + 1;
+                    (
+                        self.next_event(closed)
+                            .map(|e| (e.time - closed) as f64 / 60_000.0),
+                        Some(self.active_event(closed).is_some()),
+                    )
+                }
+                None => (None, None),
+            })
+            .unzip();
+        vec![
+            Series::new(CALENDAR_COLUMNS[0].into(), minutes).into(),
+            Series::new(CALENDAR_COLUMNS[1].into(), window).into(),
+        ]
+    }
+
+    /// Add the calendar columns to a feature frame with a `close_time` column.
+    pub fn add_columns(&self, mut df: DataFrame) -> PolarsResult<DataFrame> {
+        let close_times = df.column("close_time")?.i64()?.clone();
+        for column in self.columns(&close_times) {
+            df.with_column(column)?;
+        }
+        Ok(df)
+    }
+}
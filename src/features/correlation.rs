@@ -0,0 +1,144 @@
+//! Correlation and beta of a symbol against a reference symbol of the same
+//! exchange (e.g. every altcoin against BTCUSDT), joined by candle
+//! `open_time`.
+//!
+//! - `ref_corr` – correlation of the two symbols' log returns over the last
+//!   `window` candles;
+//! - `ref_beta` – the symbol's return sensitivity to the reference over the
+//!   same candles: covariance over the reference's variance.
+//!
+//! Returns are taken back to the first gap on either side. A row is computed
+//! once both closes of its `open_time` are known, and stays null until then
+//! or while fewer than ten aligned returns exist.
+
+use super::cross_exchange::{correlation, MIN_PAIRS};
+use crate::data_storage::{asof_lookup, Asof};
+use crate::kline::Kline;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+
+/// Column names, in row order.
+pub const CORRELATION_COLUMNS: [&str; 2] = ["ref_corr", "ref_beta"];
+
+type CorrelationRow = [Option<f64>; 2];
+
+#[derive(Debug, Clone)]
+pub struct ReferenceCorrelation {
+    interval_ms: i64,
+    window: usize,
+    capacity: usize,
+    primary: BTreeMap<i64, f64>,
+    reference: BTreeMap<i64, f64>,
+    rows: BTreeMap<i64, CorrelationRow>,
+}
+
+impl ReferenceCorrelation {
+    /// State for candles of `interval_ms`, over `window` returns, keeping
+    /// the newest `capacity` candles of each symbol.
+    pub fn new(interval_ms: i64, window: usize, capacity: usize) -> Self {
+        let window = window.max(MIN_PAIRS);
+        Self {
+            interval_ms,
+            window,
+            capacity: capacity.max(window + 2),
+            primary: BTreeMap::new(),
+            reference: BTreeMap::new(),
+            rows: BTreeMap::new(),
+        }
+    }
+
+    /// Add a closed candle of the symbol.
+    pub fn push_primary(&mut self, kline: &Kline) {
+        self.primary.insert(kline.open_time, kline.close);
+        self.refresh(kline.open_time);
+    }
+
+    /// Add a closed candle of the reference symbol.
+    pub fn push_reference(&mut self, kline: &Kline) {
+        self.reference.insert(kline.open_time, kline.close);
+        self.refresh(kline.open_time);
+    }
+
+    /// Whether the reference candle opening at `open_time` has arrived.
+    pub fn has_reference(&self, open_time: i64) -> bool {
+        self.reference.contains_key(&open_time)
+    }
+
+    /// One column per [`CORRELATION_COLUMNS`] name, aligned with `open_times`.
+    pub fn columns(&self, open_times: &Int64Chunked) -> Vec<Column> {
+        let rows = asof_lookup(open_times, &self.rows, Asof::EXACT);
+        (0..CORRELATION_COLUMNS.len())
+            .map(|c| {
+                let values: Vec<Option<f64>> =
+                    rows.iter().map(|row| row.and_then(|row| row[c])).collect();
+                Series::new(CORRELATION_COLUMNS[c].into(), values).into()
+            })
+            .collect()
+    }
+
+    /// Add the correlation columns to a feature frame with an `open_time`
+    /// column.
+    pub fn add_columns(&self, mut df: DataFrame) -> PolarsResult<DataFrame> {
+        let open_times = df.column("open_time")?.i64()?.clone();
+        for column in self.columns(&open_times) {
+            df.with_column(column)?;
+        }
+        Ok(df)
+    }
+
+    fn refresh(&mut self, open_time: i64) {
+        if let Some(row) = self.compute(open_time) {
+            self.rows.insert(open_time, row);
+        }
+        for map in [&mut self.primary, &mut self.reference] {
+            while map.len() > self.capacity {
+                map.pop_first();
+            }
+        }
+        while self.rows.len() > self.capacity {
+            self.rows.pop_first();
+        }
+    }
+
+    fn compute(&self, open_time: i64) -> Option<CorrelationRow> {
+        if !self.primary.contains_key(&open_time) || !self.has_reference(open_time) {
+            return None;
+        }
+        let log_return = |map: &BTreeMap<i64, f64>, t: i64| {
+            Some((map.get(&t)? / map.get(&(t - self.interval_ms))?).ln())
+        };
+        let mut primary_returns = Vec::with_capacity(self.window);
+        let mut reference_returns = Vec::with_capacity(self.window);
+        for k in 0..self.window as i64 {
+            let t = open_time - k * self.interval_ms;
+            let (Some(pr), Some(rr)) =
+                (log_return(&self.primary, t), log_return(&self.reference, t))
+            else {
+                break;
+            };
+            primary_returns.push(pr);
+            reference_returns.push(rr);
+        }
+        Some([
+            correlation(&primary_returns, &reference_returns),
+            beta(&primary_returns, &reference_returns),
+        ])
+    }
+}
+
+/// Covariance of `a` and `b` over the variance of `b`; `None` for fewer
+/// than [`MIN_PAIRS`] pairs or a flat `b`.
+fn beta(a: &[f64], b: &[f64]) -> Option<f64> {
+    let n = a.len().min(b.len());
+    if n < MIN_PAIRS {
+        return None;
+    }
+    let mean_a = a[..n].iter().sum::<f64>() / n as f64;
+    let mean_b = b[..n].iter().sum::<f64>() / n as f64;
+    let (mut cov, mut var_b) = (0.0, 0.0);
+    for (x, y) in a[..n].iter().zip(&b[..n]) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_b += (y - mean_b).powi(2);
+    }
+    (var_b > 0.0).then(|| cov / var_b)
+}
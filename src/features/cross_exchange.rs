@@ -0,0 +1,182 @@
+//! Cross-exchange features: the same pair on two venues, joined by candle
+//! `open_time`.
+//!
+//! - `xex_spread`, `xex_spread_bps` – primary close minus reference close,
+//!   absolute and in basis points of the reference.
+//! - `xex_basis_bps` – perpetual minus spot close in basis points of spot,
+//!   when exactly one of the venues is a perpetual (null otherwise).
+//! - `xex_corr` – correlation of the two venues' log returns over the last
+//!   `window` candles; `xex_corr_ref_leads` correlates the primary return
+//!   with the reference return one candle earlier, `xex_corr_primary_leads`
+//!   the other way round. The larger of the two says which venue moves first.
+//!
+//! Candles of either venue may arrive in any order; a row is computed once
+//! both closes of its `open_time` are known, and stays null until then.
+
+use crate::data_storage::{asof_lookup, Asof};
+use crate::kline::Kline;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+
+/// Column names, in row order.
+pub const CROSS_COLUMNS: [&str; 6] = [
+    "xex_spread",
+    "xex_spread_bps",
+    "xex_basis_bps",
+    "xex_corr",
+    "xex_corr_ref_leads",
+    "xex_corr_primary_leads",
+];
+
+/// Fewest return pairs a correlation is computed from.
+pub(crate) const MIN_PAIRS: usize = 10;
+
+type CrossRow = [Option<f64>; 6];
+
+/// Which venue is the perpetual when one of them is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Basis {
+    None,
+    PrimaryIsPerp,
+    ReferenceIsPerp,
+}
+
+#[derive(Debug, Clone)]
+pub struct CrossExchange {
+    interval_ms: i64,
+    window: usize,
+    capacity: usize,
+    basis: Basis,
+    primary: BTreeMap<i64, f64>,
+    reference: BTreeMap<i64, f64>,
+    rows: BTreeMap<i64, CrossRow>,
+}
+
+impl CrossExchange {
+    /// State for candles of `interval_ms`, correlating over `window` returns
+    /// and keeping the newest `capacity` candles of each venue.
+    pub fn new(interval_ms: i64, window: usize, capacity: usize, basis: Basis) -> Self {
+        Self {
+            interval_ms,
+            window: window.max(MIN_PAIRS),
+            capacity: capacity.max(window + 2),
+            basis,
+            primary: BTreeMap::new(),
+            reference: BTreeMap::new(),
+            rows: BTreeMap::new(),
+        }
+    }
+
+    /// Add a closed candle of the primary venue.
+    pub fn push_primary(&mut self, kline: &Kline) {
+        self.primary.insert(kline.open_time, kline.close);
+        self.refresh(kline.open_time);
+    }
+
+    /// Add a closed candle of the reference venue.
+    pub fn push_reference(&mut self, kline: &Kline) {
+        self.reference.insert(kline.open_time, kline.close);
+        self.refresh(kline.open_time);
+    }
+
+    /// Whether the reference candle opening at `open_time` has arrived.
+    pub fn has_reference(&self, open_time: i64) -> bool {
+        self.reference.contains_key(&open_time)
+    }
+
+    /// One column per [`CROSS_COLUMNS`] name, aligned with `open_times`.
+    pub fn columns(&self, open_times: &Int64Chunked) -> Vec<Column> {
+        let rows = asof_lookup(open_times, &self.rows, Asof::EXACT);
+        (0..CROSS_COLUMNS.len())
+            .map(|c| {
+                let values: Vec<Option<f64>> =
+                    rows.iter().map(|row| row.and_then(|row| row[c])).collect();
+                Series::new(CROSS_COLUMNS[c].into(), values).into()
+            })
+            .collect()
+    }
+
+    /// Add the cross-exchange columns to a feature frame with an `open_time`
+    /// column.
+    pub fn add_columns(&self, mut df: DataFrame) -> PolarsResult<DataFrame> {
+        let open_times = df.column("open_time")?.i64()?.clone();
+        for column in self.columns(&open_times) {
+            df.with_column(column)?;
+        }
+        Ok(df)
+    }
+
+    fn refresh(&mut self, open_time: i64) {
+        if let Some(row) = self.compute(open_time) {
+            self.rows.insert(open_time, row);
+        }
+        for map in [&mut self.primary, &mut self.reference] {
+            while map.len() > self.capacity {
+                map.pop_first();
+            }
+        }
+        while self.rows.len() > self.capacity {
+            self.rows.pop_first();
+        }
+    }
+
+    fn compute(&self, open_time: i64) -> Option<CrossRow> {
+        let p = *self.primary.get(&open_time)?;
+        let r = *self.reference.get(&open_time)?;
+        let basis = match self.basis {
+            Basis::None => None,
+            Basis::PrimaryIsPerp => Some((p - r) / r * 1e4),
+            Basis::ReferenceIsPerp => Some((r - p) / p * 1e4),
+        };
+
+        // Aligned log returns, newest first, back to the first gap on either venue
+        let log_return = |map: &BTreeMap<i64, f64>, t: i64| {
+            Some((map.get(&t)? / map.get(&(t - self.interval_ms))?).ln())
+        };
+        let mut primary_returns = Vec::with_capacity(self.window + 1);
+        let mut reference_returns = Vec::with_capacity(self.window + 1);
+        for k in 0..=self.window as i64 {
+            let t = open_time - k * self.interval_ms;
+            let (Some(pr), Some(rr)) =
+                (log_return(&self.primary, t), log_return(&self.reference, t))
+            else {
+                break;
+            };
+            primary_returns.push(pr);
+            reference_returns.push(rr);
+        }
+        let n = primary_returns.len().min(self.window);
+        // Index 0 is the newest candle, so `[1..]` is one candle earlier
+        let lagged = primary_returns.len().saturating_sub(1).min(self.window);
+        let leading = |newer: &[f64], older: &[f64]| {
+            correlation(&newer[..lagged], older.get(1..=lagged).unwrap_or_default())
+        };
+
+        Some([
+            Some(p - r),
+            Some((p - r) / r * 1e4),
+            basis,
+            correlation(&primary_returns[..n], &reference_returns[..n]),
+            leading(&primary_returns, &reference_returns),
+            leading(&reference_returns, &primary_returns),
+        ])
+    }
+}
+
+/// Pearson correlation; `None` for fewer than [`MIN_PAIRS`] pairs or a flat series.
+pub(crate) fn correlation(a: &[f64], b: &[f64]) -> Option<f64> {
+    let n = a.len().min(b.len());
+    if n < MIN_PAIRS {
+        return None;
+    }
+    let mean_a = a[..n].iter().sum::<f64>() / n as f64;
+    let mean_b = b[..n].iter().sum::<f64>() / n as f64;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a[..n].iter().zip(&b[..n]) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+    }
+    let denominator = (var_a * var_b).sqrt();
+    (denominator > 0.0).then(|| cov / denominator)
+}
@@ -1,32 +1,233 @@
 //! Exponential Moving Average (EMA) calculations for multiple timeframes.
+//!
+//! [`add_ema_features`] computes the columns over a whole frame, resampling
+//! the window to H1 and H4 each time; [`EmaTracker`] keeps the same values up
+//! to date one candle at a time for the live path and [`super::FeatureEngine`].
+//! Instead of resampled frames it caches, per timeframe, the EWMA sums over
+//! the completed buckets plus the close of the open one, so a candle only
+//! updates the last bucket (or starts a new one). Higher-timeframe buckets
+//! are aligned to the clock (`[10:00, 11:00)`), so both agree no matter where
+//! the window starts.
+//!
+//! How an EMA weights its history and when it starts is set by
+//! [`EmaConfig`] (`[ema]`), process-wide through [`set_semantics`]:
+//!
+//! - `adjust = true` (default) – the weighted mean
+//!   `Σ (1−α)^i·x[t−i] / Σ (1−α)^i` over every value so far, like pandas and
+//!   polars `ewm_mean(adjust=True)`;
+//! - `adjust = false` – the recursion `y[t] = α·x[t] + (1−α)·y[t−1]`, seeded
+//!   with the first value (`seed = "first"`, pandas `adjust=False`) or with
+//!   the simple mean of the first `span` values (`seed = "sma"`, TA-Lib and
+//!   most charting packages);
+//! - `min_periods` – values before which the EMA stays null (default the
+//!   span; an SMA seed has no value before `span` values either way).
+//!
+//! With `α = 2/(span+1)`. [`reference_ema`] evaluates each of them from its
+//! closed form, which the `bench` command checks both computations against.
 
-use anyhow::Result;
+use crate::kline::Kline;
+use anyhow::{bail, Result};
 use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{OnceLock, RwLock};
+
+/// EMA columns in the order `add_ema_features` appends them.
+pub const EMA_COLUMNS: [&str; 6] = [
+    "ema50_m15",
+    "ema200_m15",
+    "ema50_h1",
+    "ema200_h1",
+    "ema50_h4",
+    "ema200_h4",
+];
+
+/// Bucket lengths (ms) of the EMA timeframes; 0 is the stream interval itself.
+const TIMEFRAME_MS: [i64; 3] = [0, 60 * 60 * 1000, 4 * 60 * 60 * 1000];
+
+/// Span of the slowest EMA of every timeframe.
+const SLOW_SPAN: usize = 200;
+
+/// How an unadjusted EMA starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmaSeed {
+    /// The first value.
+    #[default]
+    First,
+    /// The simple mean of the first `span` values.
+    Sma,
+}
+
+/// EMA semantics of every EMA column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EmaConfig {
+    /// Weighted mean over the whole history instead of the recursion.
+    pub adjust: bool,
+    /// Values before the EMA is reported; the span if unset.
+    pub min_periods: Option<usize>,
+    /// Start of the recursion (`adjust = false` only).
+    pub seed: EmaSeed,
+}
+
+impl Default for EmaConfig {
+    fn default() -> Self {
+        Self {
+            adjust: true,
+            min_periods: None,
+            seed: EmaSeed::First,
+        }
+    }
+}
+
+impl EmaConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.adjust && self.seed == EmaSeed::Sma {
+            bail!("[ema] seed = \"sma\" needs adjust = false");
+        }
+        if self.min_periods == Some(0) {
+            bail!("[ema] min_periods must be at least 1");
+        }
+        Ok(())
+    }
+
+    /// Values an EMA of `span` needs before it has one.
+    pub fn min_periods(&self, span: usize) -> usize {
+        let min_periods = self.min_periods.unwrap_or(span).max(1);
+        match self.seed {
+            EmaSeed::Sma if !self.adjust => min_periods.max(span),
+            _ => min_periods,
+        }
+    }
+}
+
+fn shared() -> &'static RwLock<EmaConfig> {
+    static SEMANTICS: OnceLock<RwLock<EmaConfig>> = OnceLock::new();
+    SEMANTICS.get_or_init(|| RwLock::new(EmaConfig::default()))
+}
+
+/// Semantics of every EMA computed from now on. Set it before any pipeline
+/// starts: an [`EmaTracker`] keeps the semantics it was built with.
+pub fn set_semantics(config: EmaConfig) {
+    *shared().write().unwrap() = config;
+}
+
+/// The current EMA semantics.
+pub fn semantics() -> EmaConfig {
+    *shared().read().unwrap()
+}
+
+/// Candles of `interval_ms` needed before every EMA column has a value: the
+/// slow span (or a longer `min_periods`) in buckets of each timeframe, plus
+/// the bucket the first candle may fall in the middle of. 200 H4 EMAs need
+/// ~800 hours, i.e. 3,216 M15 candles. A shorter `min_periods` does not
+/// shorten it: the early values would be reported, not converged.
+pub fn warmup_candles(interval_ms: i64) -> usize {
+    let interval_ms = interval_ms.max(1);
+    let buckets = semantics().min_periods(SLOW_SPAN).max(SLOW_SPAN) as i64 + 1;
+    TIMEFRAME_MS
+        .iter()
+        .map(|&bucket_ms| {
+            let span_ms = buckets * bucket_ms.max(interval_ms);
+            ((span_ms + interval_ms - 1) / interval_ms) as usize
+        })
+        .max()
+        .unwrap_or(SLOW_SPAN)
+}
+
+/// Smoothing factor of an EMA of `span`.
+fn alpha(span: usize) -> f64 {
+    2.0 / (span as f64 + 1.0)
+}
 
 /// Helper: create EWMOptions from a span (typical for EMA).
-fn ewma_opts_from_span(span: usize) -> EWMOptions {
-    let alpha = 2.0 / (span as f64 + 1.0);
+fn ewma_opts_from_span(span: usize, semantics: &EmaConfig) -> EWMOptions {
     EWMOptions {
-        alpha,
-        adjust: true,
+        alpha: alpha(span),
+        adjust: semantics.adjust,
         bias: false,
-        min_periods: span,
+        min_periods: semantics.min_periods(span),
         ignore_nulls: false,
     }
 }
 
+/// Add the EMA of `span` over `close` as `name`: polars `ewm_mean` for a
+/// first-value seed, [`Ewma`] for an SMA seed, which polars lacks.
+fn with_ema(mut df: DataFrame, span: usize, name: &str) -> Result<DataFrame> {
+    let semantics = semantics();
+    if semantics.adjust || semantics.seed == EmaSeed::First {
+        let opts = ewma_opts_from_span(span, &semantics);
+        return Ok(df
+            .lazy()
+            .with_column(col("close").ewm_mean(opts).alias(name))
+            .collect()?);
+    }
+    let mut ewma = Ewma::new(span, semantics);
+    let values: Vec<Option<f64>> = df
+        .column("close")?
+        .cast(&DataType::Float64)?
+        .f64()?
+        .into_iter()
+        .map(|close| {
+            close.and_then(|x| {
+                ewma.push(x);
+                ewma.value()
+            })
+        })
+        .collect();
+    df.with_column(Series::new(name.into(), values).into())?;
+    Ok(df)
+}
+
+/// The EMA of `span` over `values` under `semantics`, every row evaluated
+/// from its closed form rather than a running sum – O(n²), for checking the
+/// computations on a few thousand values:
+///
+/// - adjusted: `Σ (1−α)^i·x[t−i] / Σ (1−α)^i`, `i` in `0..=t`;
+/// - first-value seed: `(1−α)^t·x[0] + Σ α(1−α)^i·x[t−i]`, `i` in `0..t`;
+/// - SMA seed, with `s` the mean of `x[0..span]`:
+///   `(1−α)^(t−span+1)·s + Σ α(1−α)^i·x[t−i]`, `i` in `0..=t−span`.
+pub fn reference_ema(values: &[f64], span: usize, semantics: &EmaConfig) -> Vec<Option<f64>> {
+    let alpha = alpha(span);
+    let decay = 1.0 - alpha;
+    let min_periods = semantics.min_periods(span);
+    (0..values.len())
+        .map(|t| {
+            if t + 1 < min_periods {
+                return None;
+            }
+            let recent = |count: usize| -> f64 {
+                (0..count)
+                    .map(|i| alpha * decay.powi(i as i32) * values[t - i])
+                    .sum()
+            };
+            Some(match (semantics.adjust, semantics.seed) {
+                (true, _) => {
+                    let (mut sum, mut weight) = (0.0, 0.0);
+                    for i in 0..=t {
+                        sum += decay.powi(i as i32) * values[t - i];
+                        weight += decay.powi(i as i32);
+                    }
+                    sum / weight
+                }
+                (false, EmaSeed::First) => decay.powi(t as i32) * values[0] + recent(t),
+                (false, EmaSeed::Sma) => {
+                    let seed = values[..span].iter().sum::<f64>() / span as f64;
+                    let steps = t + 1 - span;
+                    decay.powi(steps as i32) * seed + recent(steps)
+                }
+            })
+        })
+        .collect()
+}
+
 /// Add EMA50 and EMA200 columns for M15, H1, and H4 to the input DataFrame.
 /// Expects the DataFrame to have columns "datetime" and "close".
 pub fn add_ema_features(mut df: DataFrame) -> Result<DataFrame> {
     // ---- 1. M15 EMAs (direct on close) ----
-    let ema50_opts = ewma_opts_from_span(50);
-    let ema200_opts = ewma_opts_from_span(200);
-
-    df = df
-        .lazy()
-        .with_column(col("close").ewm_mean(ema50_opts).alias("ema50_m15"))
-        .with_column(col("close").ewm_mean(ema200_opts).alias("ema200_m15"))
-        .collect()?;
+    df = with_ema(df, 50, "ema50_m15")?;
+    df = with_ema(df, 200, "ema200_m15")?;
 
     // ---- 2. H1 EMAs (resample to 1 hour) ----
     let h1_ema50 = compute_resampled_ema(&df, "1h", 50, "ema50_h1")?;
@@ -85,8 +286,8 @@ fn compute_resampled_ema(
         every,
         period,
         offset,
-        closed_window: ClosedWindow::Right,
-        start_by: StartBy::DataPoint,
+        closed_window: ClosedWindow::Left,
+        start_by: StartBy::WindowBound,
         include_boundaries: false,
         ..Default::default()
     };
@@ -98,12 +299,415 @@ fn compute_resampled_ema(
         .agg([col("close").last().alias("close")])
         .collect()?;
 
-    let ema_opts = ewma_opts_from_span(span);
-    let ema = resampled
-        .lazy()
-        .with_column(col("close").ewm_mean(ema_opts).alias(col_name))
-        .select([col("datetime"), col(col_name)])
-        .collect()?;
+    let ema = with_ema(resampled, span, col_name)?.select(["datetime", col_name])?;
 
     Ok(ema)
 }
+
+/// EWMA of one span under an [`EmaConfig`], as a running state: the
+/// adjusted weighted sum and weight, or the recursion's last value (the mean
+/// so far while an SMA seed fills).
+#[derive(Debug, Clone)]
+struct Ewma {
+    span: usize,
+    alpha: f64,
+    semantics: EmaConfig,
+    min_periods: usize,
+    level: f64,
+    weight: f64,
+    count: usize,
+}
+
+impl Ewma {
+    fn new(span: usize, semantics: EmaConfig) -> Self {
+        Self {
+            span,
+            alpha: alpha(span),
+            semantics,
+            min_periods: semantics.min_periods(span),
+            level: 0.0,
+            weight: 0.0,
+            count: 0,
+        }
+    }
+
+    /// Value after `x` without committing it.
+    fn peek(&self, x: f64) -> Option<f64> {
+        let mut next = self.clone();
+        next.push(x);
+        next.value()
+    }
+
+    fn push(&mut self, x: f64) {
+        let decay = 1.0 - self.alpha;
+        self.count += 1;
+        if self.semantics.adjust {
+            self.level = x + decay * self.level;
+            self.weight = 1.0 + decay * self.weight;
+        } else if self.count == 1 {
+            self.level = x;
+        } else if self.semantics.seed == EmaSeed::Sma && self.count <= self.span {
+            self.level += (x - self.level) / self.count as f64;
+        } else {
+            self.level = self.alpha * x + decay * self.level;
+        }
+    }
+
+    fn value(&self) -> Option<f64> {
+        if self.count < self.min_periods {
+            return None;
+        }
+        Some(if self.semantics.adjust {
+            self.level / self.weight
+        } else {
+            self.level
+        })
+    }
+}
+
+/// EMA50/200 on one timeframe. The close of the open bucket is held back and
+/// only committed once the next bucket starts, so later candles of the same
+/// bucket (or a revised candle) just replace it.
+#[derive(Debug, Clone)]
+struct TimeframeEma {
+    /// Bucket length in ms; 0 makes every candle its own bucket (the stream
+    /// interval itself).
+    bucket_ms: i64,
+    bucket: Option<i64>,
+    bucket_close: f64,
+    fast: Ewma,
+    slow: Ewma,
+}
+
+impl TimeframeEma {
+    fn new(bucket_ms: i64, semantics: EmaConfig) -> Self {
+        Self {
+            bucket_ms,
+            bucket: None,
+            bucket_close: f64::NAN,
+            fast: Ewma::new(50, semantics),
+            slow: Ewma::new(SLOW_SPAN, semantics),
+        }
+    }
+
+    fn update(&mut self, open_time: i64, close: f64) -> [Option<f64>; 2] {
+        let bucket = if self.bucket_ms == 0 {
+            open_time
+        } else {
+            open_time.div_euclid(self.bucket_ms)
+        };
+        if self.bucket.is_some_and(|b| b != bucket) {
+            self.fast.push(self.bucket_close);
+            self.slow.push(self.bucket_close);
+        }
+        self.bucket = Some(bucket);
+        self.bucket_close = close;
+        [self.fast.peek(close), self.slow.peek(close)]
+    }
+}
+
+/// One row of [`EMA_COLUMNS`].
+pub type EmaRow = [Option<f64>; 6];
+
+/// Incremental EMA state for the live path: O(1) work per closed candle
+/// instead of recomputing every timeframe over the whole window.
+///
+/// The newest row always equals what [`add_ema_features`] returns for the
+/// same window. Earlier rows keep the value they had when their candle
+/// closed, whereas the batch computation also revises the rows of a
+/// higher-timeframe bucket that was still open.
+#[derive(Debug, Clone)]
+pub struct EmaTracker {
+    timeframes: [TimeframeEma; 3],
+    rows: VecDeque<EmaRow>,
+    capacity: usize,
+    last_open_time: Option<i64>,
+}
+
+impl EmaTracker {
+    /// Build the state from `klines` (oldest first). Row values are taken
+    /// from `features`, the batch frame over the newest of those candles.
+    pub fn new(klines: &[Kline], features: &DataFrame, capacity: usize) -> Result<Self> {
+        let mut tracker = Self::replay(klines, capacity);
+        let columns = EMA_COLUMNS
+            .iter()
+            .map(|name| Ok(features.column(name)?.f64()?.clone()))
+            .collect::<Result<Vec<_>>>()?;
+        tracker.rows = (0..features.height())
+            .skip(features.height().saturating_sub(tracker.capacity))
+            .map(|i| std::array::from_fn(|c| columns[c].get(i)))
+            .collect();
+        Ok(tracker)
+    }
+
+    /// Build the state and rows by feeding `klines` (oldest first) one by one.
+    pub fn replay(klines: &[Kline], capacity: usize) -> Self {
+        let mut tracker = Self {
+            timeframes: TIMEFRAME_MS.map(|bucket_ms| TimeframeEma::new(bucket_ms, semantics())),
+            rows: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            last_open_time: None,
+        };
+        for kline in klines {
+            tracker.update(kline);
+        }
+        tracker
+    }
+
+    /// Advance every timeframe by one closed candle and return its row. A
+    /// candle with the same `open_time` as the previous one replaces it.
+    pub fn update(&mut self, kline: &Kline) -> EmaRow {
+        if self.last_open_time == Some(kline.open_time) {
+            self.rows.pop_back();
+        }
+        self.last_open_time = Some(kline.open_time);
+        let mut row = [None; 6];
+        for (i, timeframe) in self.timeframes.iter_mut().enumerate() {
+            let [fast, slow] = timeframe.update(kline.open_time, kline.close);
+            row[2 * i] = fast;
+            row[2 * i + 1] = slow;
+        }
+        if self.rows.len() == self.capacity {
+            self.rows.pop_front();
+        }
+        self.rows.push_back(row);
+        row
+    }
+
+    /// The newest row, if any candle was seen.
+    pub fn last(&self) -> Option<EmaRow> {
+        self.rows.back().copied()
+    }
+
+    /// [`EMA_COLUMNS`] over the newest `len` rows (null-padded at the front
+    /// if fewer rows are kept).
+    pub fn columns(&self, len: usize) -> Vec<Column> {
+        let missing = len.saturating_sub(self.rows.len());
+        let skip = self.rows.len().saturating_sub(len);
+        EMA_COLUMNS
+            .iter()
+            .enumerate()
+            .map(|(c, name)| {
+                let values: Vec<Option<f64>> = std::iter::repeat_n(None, missing)
+                    .chain(self.rows.iter().skip(skip).map(|row| row[c]))
+                    .collect();
+                Series::new((*name).into(), values).into()
+            })
+            .collect()
+    }
+}
+
+/// Largest relative difference between `row` and the last row of the batch
+/// EMA columns in `features`; `None` if a value is null on one side only.
+pub fn max_relative_diff(row: &EmaRow, features: &DataFrame) -> Result<Option<f64>> {
+    let last = features.height().saturating_sub(1);
+    let mut worst = 0.0f64;
+    for (c, name) in EMA_COLUMNS.iter().enumerate() {
+        match (row[c], features.column(name)?.f64()?.get(last)) {
+            (Some(a), Some(b)) => worst = worst.max((a - b).abs() / b.abs().max(f64::EPSILON)),
+            (None, None) => {}
+            _ => return Ok(None),
+        }
+    }
+    Ok(Some(worst))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::base_frame;
+    use std::sync::Mutex;
+
+    /// The semantics are process-wide: tests changing them run one at a time.
+    static SEMANTICS_LOCK: Mutex<()> = Mutex::new(());
+
+    const M15_MS: i64 = 15 * 60 * 1000;
+    const H1_MS: i64 = 60 * 60 * 1000;
+    const TOLERANCE: f64 = 1e-9;
+    /// A 4h bucket boundary (2023-11-14 20:00 UTC).
+    const BASE: i64 = 1_699_992_000_000;
+
+    /// A random walk of `count` M15 candles from `start`.
+    fn klines(count: usize, start: i64, seed: u64) -> Vec<Kline> {
+        let mut state = seed;
+        let mut close = 100.0;
+        (0..count)
+            .map(|i| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                let step = (state >> 11) as f64 / (1u64 << 53) as f64 - 0.5;
+                let open = close;
+                close = open * (1.0 + step * 0.01);
+                let open_time = start + i as i64 * M15_MS;
+                Kline {
+                    open_time,
+                    open,
+                    high: open.max(close),
+                    low: open.min(close),
+                    close,
+                    volume: 1.0,
+                    close_time: open_time + M15_MS - 1,
+                }
+            })
+            .collect()
+    }
+
+    /// Every valid combination of adjust, min_periods and seed.
+    fn variants() -> Vec<EmaConfig> {
+        let mut variants = Vec::new();
+        for (adjust, seed) in [
+            (true, EmaSeed::First),
+            (false, EmaSeed::First),
+            (false, EmaSeed::Sma),
+        ] {
+            for min_periods in [None, Some(1), Some(10), Some(300)] {
+                variants.push(EmaConfig {
+                    adjust,
+                    min_periods,
+                    seed,
+                });
+            }
+        }
+        variants
+    }
+
+    fn values(column: &Column) -> Vec<Option<f64>> {
+        column.f64().unwrap().into_iter().collect()
+    }
+
+    /// Assert `actual` has the nulls of `expected` and is within the
+    /// tolerance elsewhere.
+    fn assert_close(actual: &[Option<f64>], expected: &[Option<f64>], what: &str) {
+        assert_eq!(actual.len(), expected.len(), "{}: length", what);
+        for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+            match (a, e) {
+                (Some(a), Some(e)) => assert!(
+                    (a - e).abs() <= TOLERANCE * e.abs().max(1.0),
+                    "{}: row {}: {} vs {}",
+                    what,
+                    i,
+                    a,
+                    e
+                ),
+                (None, None) => {}
+                _ => panic!("{}: row {}: {:?} vs {:?}", what, i, a, e),
+            }
+        }
+    }
+
+    #[test]
+    fn stream_interval_emas_match_reference() {
+        let _lock = SEMANTICS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let klines = klines(600, BASE, 7);
+        let closes: Vec<f64> = klines.iter().map(|k| k.close).collect();
+        for semantics in variants() {
+            semantics.validate().unwrap();
+            set_semantics(semantics);
+            let batch = add_ema_features(base_frame(&klines).unwrap()).unwrap();
+            let incremental = EmaTracker::replay(&klines, klines.len()).columns(klines.len());
+            for (c, span) in [(0, 50), (1, 200)] {
+                let reference = reference_ema(&closes, span, &semantics);
+                let what = format!("{} {:?}", EMA_COLUMNS[c], semantics);
+                assert_close(
+                    &values(batch.column(EMA_COLUMNS[c]).unwrap()),
+                    &reference,
+                    &format!("batch {}", what),
+                );
+                assert_close(
+                    &values(&incremental[c]),
+                    &reference,
+                    &format!("tracker {}", what),
+                );
+            }
+        }
+        set_semantics(EmaConfig::default());
+    }
+
+    #[test]
+    fn resampled_emas_match_reference_over_bucket_closes() {
+        let _lock = SEMANTICS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // Starts mid-hour, so the first bucket is partial
+        let klines = klines(1_200, BASE + 2 * M15_MS, 11);
+        let mut bucket_closes: Vec<f64> = Vec::new();
+        let mut bucket_last = Vec::new(); // index of each bucket's last candle
+        for (i, kline) in klines.iter().enumerate() {
+            let bucket = kline.open_time.div_euclid(H1_MS);
+            if i > 0 && klines[i - 1].open_time.div_euclid(H1_MS) == bucket {
+                *bucket_closes.last_mut().unwrap() = kline.close;
+                *bucket_last.last_mut().unwrap() = i;
+            } else {
+                bucket_closes.push(kline.close);
+                bucket_last.push(i);
+            }
+        }
+        for semantics in variants() {
+            set_semantics(semantics);
+            let df = base_frame(&klines).unwrap();
+            let tracker = EmaTracker::replay(&klines, klines.len());
+            let rows = tracker.columns(klines.len());
+            for (c, span) in [(2, 50), (3, 200)] {
+                let reference = reference_ema(&bucket_closes, span, &semantics);
+                let what = format!("{} {:?}", EMA_COLUMNS[c], semantics);
+                let resampled = compute_resampled_ema(&df, "1h", span, "ema").unwrap();
+                assert_close(
+                    &values(resampled.column("ema").unwrap()),
+                    &reference,
+                    &format!("resampled {}", what),
+                );
+                // A bucket's last candle carries its final value
+                let tracked = values(&rows[c]);
+                let at_bucket_ends: Vec<Option<f64>> =
+                    bucket_last.iter().map(|&i| tracked[i]).collect();
+                assert_close(&at_bucket_ends, &reference, &format!("tracker {}", what));
+            }
+        }
+        set_semantics(EmaConfig::default());
+    }
+
+    /// Stream candles one by one and compare the newest row with the batch
+    /// frame over the same candles: before the epoch (negative open times,
+    /// bucketed with `div_euclid`), starting mid-bucket, and with open
+    /// H1/H4 buckets held back on most rows.
+    #[test]
+    fn streamed_rows_match_batch_frame() {
+        let _lock = SEMANTICS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        set_semantics(EmaConfig::default());
+        for start in [
+            BASE + 5 * M15_MS,
+            -(200 * H1_MS) - 3 * M15_MS,
+            -(4 * H1_MS) + M15_MS,
+        ] {
+            let klines = klines(1_100, start, 3);
+            let mut tracker = EmaTracker::replay(&[], klines.len());
+            for (i, kline) in klines.iter().enumerate() {
+                let row = tracker.update(kline);
+                if i % 7 != 0 && i + 1 != klines.len() {
+                    continue;
+                }
+                let batch = add_ema_features(base_frame(&klines[..=i]).unwrap()).unwrap();
+                let diff = max_relative_diff(&row, &batch).unwrap();
+                assert!(
+                    diff.is_some_and(|diff| diff <= TOLERANCE),
+                    "start {} candle {}: {:?} vs batch, diff {:?}",
+                    start,
+                    i,
+                    row,
+                    diff
+                );
+            }
+
+            // A revised last candle replaces the previous one
+            let mut revised = klines.clone();
+            revised.last_mut().unwrap().close *= 1.01;
+            let row = tracker.update(revised.last().unwrap());
+            let batch = add_ema_features(base_frame(&revised).unwrap()).unwrap();
+            let diff = max_relative_diff(&row, &batch).unwrap();
+            assert!(
+                diff.is_some_and(|diff| diff <= TOLERANCE),
+                "revised: {:?}",
+                diff
+            );
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Stateful feature engine: a rolling candle window plus the feature pipeline.
+//!
+//! Computes features the way the live stream does, so embedding code (Python
+//! bindings, the C ABI) gets identical indicator values: EMAs (including the
+//! H1/H4 ones) advance incrementally through an [`EmaTracker`] instead of
+//! resampling the window on every update, pivots are recomputed over the
+//! window. Callers only see "push a candle, get its row".
+
+use super::{base_frame, compute_features_with_ema, EmaTracker};
+use crate::kline::Kline;
+use anyhow::{bail, Result};
+use polars::prelude::*;
+use std::collections::VecDeque;
+
+pub struct FeatureEngine {
+    window: VecDeque<Kline>,
+    ema: EmaTracker,
+    capacity: usize,
+}
+
+impl FeatureEngine {
+    /// Empty engine keeping at most `capacity` candles (at least 1).
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            window: VecDeque::with_capacity(capacity),
+            ema: EmaTracker::replay(&[], capacity),
+            capacity,
+        }
+    }
+
+    /// Engine pre-filled with the newest `capacity` candles of `history`.
+    pub fn with_history(history: &[Kline], capacity: usize) -> Self {
+        let mut engine = Self::new(capacity);
+        let skip = history.len().saturating_sub(engine.capacity);
+        engine.window.extend(history[skip..].iter().cloned());
+        engine.ema = EmaTracker::replay(&history[skip..], engine.capacity);
+        engine
+    }
+
+    pub fn len(&self) -> usize {
+        self.window.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.window.is_empty()
+    }
+
+    /// Add a candle and return its feature row (a one-row DataFrame).
+    /// A candle with the same `open_time` as the newest one replaces it;
+    /// older candles are rejected.
+    pub fn update(&mut self, kline: Kline) -> Result<DataFrame> {
+        match self.window.back() {
+            Some(last) if kline.open_time < last.open_time => bail!(
+                "Candle at {} is older than the newest candle at {}",
+                kline.open_time,
+                last.open_time
+            ),
+            Some(last) if kline.open_time == last.open_time => {
+                self.window.pop_back();
+            }
+            _ => {}
+        }
+        self.ema.update(&kline);
+        self.window.push_back(kline);
+        if self.window.len() > self.capacity {
+            self.window.pop_front();
+        }
+        Ok(self.features()?.tail(Some(1)))
+    }
+
+    /// Features for the whole window. EMA values of earlier rows are the ones
+    /// computed when their candle arrived (see [`EmaTracker`]).
+    pub fn features(&self) -> Result<DataFrame> {
+        let klines: Vec<Kline> = self.window.iter().cloned().collect();
+        compute_features_with_ema(base_frame(&klines)?, &self.ema)
+    }
+}
@@ -2,21 +2,44 @@
 //! and returns a DataFrame enriched with computed indicators.
 
 use crate::kline::Kline;
+use crate::window::KlineWindow;
 use anyhow::Result;
 use polars::prelude::*;
-use std::time::Instant;
+use serde::{Deserialize, Serialize};
 
-mod ema;
-mod pivots;
+pub mod backfill;
+pub mod calendar;
+pub mod correlation;
+pub mod cross_exchange;
+pub mod ema;
+pub mod engine;
+pub mod order_flow;
+pub mod pivots;
+pub mod portfolio;
+pub mod quality;
+pub mod regime;
+pub mod spread;
+pub mod usd;
+pub mod warmup;
 
-/// Compute all features on a slice of klines and return a DataFrame with added columns.
-/// Currently adds EMA50/200 for M15, H1, H4. Pivot points will be added later.
-pub fn compute_features(klines: &[Kline]) -> Result<DataFrame> {
-    let start = Instant::now();
+pub use backfill::FeatureBackfill;
+pub use calendar::EventCalendar;
+pub use correlation::ReferenceCorrelation;
+pub use cross_exchange::CrossExchange;
+pub use ema::{EmaConfig, EmaTracker};
+pub use engine::FeatureEngine;
+pub use order_flow::OrderFlow;
+pub use portfolio::PortfolioFeatures;
+pub use quality::{CandleQuality, QualityConfig};
+pub use regime::RegimeConfig;
+pub use spread::BookSpread;
+pub use usd::UsdConversion;
 
-    // Convert klines to DataFrame and add a proper datetime column
-    let mut df = crate::data_storage::klines_to_dataframe(klines)?;
-    df = df
+/// Convert klines to a DataFrame sorted by a proper `datetime` column.
+/// This is the input every feature group expects.
+pub fn base_frame(klines: &[Kline]) -> Result<DataFrame> {
+    let df = crate::data_storage::klines_to_dataframe(klines)?;
+    let df = df
         .lazy()
         .with_column(
             col("open_time")
@@ -25,18 +48,74 @@ pub fn compute_features(klines: &[Kline]) -> Result<DataFrame> {
         )
         .sort(vec!["datetime"], Default::default())
         .collect()?;
+    Ok(df)
+}
+
+/// [`base_frame`] of the newest `n` candles of `window`, sliced straight from
+/// its columns (the window is already in `open_time` order).
+pub fn window_frame(window: &KlineWindow, n: usize) -> Result<DataFrame> {
+    let df = window
+        .frame(n)?
+        .lazy()
+        .with_column(
+            col("open_time")
+                .cast(DataType::Datetime(TimeUnit::Milliseconds, None))
+                .alias("datetime"),
+        )
+        .collect()?;
+    Ok(df)
+}
+
+/// Compute all features on a slice of klines and return a DataFrame with added columns.
+/// Adds EMA50/200 for M15, H1, H4 and pivot strengths.
+pub fn compute_features(klines: &[Kline]) -> Result<DataFrame> {
+    let mut df = base_frame(klines)?;
 
     // Add EMA features
     df = ema::add_ema_features(df)?;
 
-    // Placeholder for pivot points:
-    df = pivots::add_pivot_features(df)?;
+    // Add pivot strength features (batch path: chunked across threads)
+    df = pivots::add_pivot_features_par(df)?;
+
+    Ok(df)
+}
+
+/// A feature group that can be computed on its own, e.g. for a named
+/// feature window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeatureGroup {
+    /// EMA50/200 for M15, H1 and H4.
+    Ema,
+    /// Pivot strengths.
+    Pivots,
+}
 
-    let elapsed = start.elapsed();
-    println!(
-        "Feature computation took: {:.2} ms",
-        elapsed.as_secs_f64() * 1000.0
-    );
+impl FeatureGroup {
+    pub const ALL: [FeatureGroup; 2] = [FeatureGroup::Ema, FeatureGroup::Pivots];
+}
+
+/// Add `groups` to a [`base_frame`] / [`window_frame`], in order.
+pub fn add_groups(mut df: DataFrame, groups: &[FeatureGroup]) -> Result<DataFrame> {
+    for group in groups {
+        df = match group {
+            FeatureGroup::Ema => ema::add_ema_features(df)?,
+            FeatureGroup::Pivots => pivots::add_pivot_features(df)?,
+        };
+    }
+    Ok(df)
+}
+
+/// [`compute_features`] on a [`base_frame`] / [`window_frame`], with the EMA
+/// columns taken from `ema` instead of recomputed; `ema` must already include
+/// the newest candle of `df`.
+pub fn compute_features_with_ema(mut df: DataFrame, ema: &EmaTracker) -> Result<DataFrame> {
+    for column in ema.columns(df.height()) {
+        df.with_column(column)?;
+    }
+
+    // Add pivot strength features
+    df = pivots::add_pivot_features(df)?;
 
     Ok(df)
 }
@@ -0,0 +1,144 @@
+//! Order-flow features from the trade stream (Binance aggTrades), buffered
+//! per candle.
+//!
+//! Every trade is attributed to the candle its time falls in and to the side
+//! of its taker (the aggressor). Per candle:
+//!
+//! - `volume_imbalance` – (taker buy − taker sell volume) / total volume, in [-1, 1];
+//! - `trade_count_imbalance` – the same over the number of trades;
+//! - `avg_trade_size` – base volume per trade;
+//! - `block_trades` – trades of at least `block_notional` in the quote currency.
+//!
+//! The candle the stream connected in is incomplete and stays null, as do
+//! candles before it; a candle without any trade has null ratios and no
+//! block trades.
+
+use crate::data_storage::{asof_lookup, Asof};
+use polars::prelude::*;
+use std::collections::BTreeMap;
+
+/// Column names, in row order.
+pub const ORDER_FLOW_COLUMNS: [&str; 4] = [
+    "volume_imbalance",
+    "trade_count_imbalance",
+    "avg_trade_size",
+    "block_trades",
+];
+
+/// Trades of one candle, by taker side.
+#[derive(Debug, Clone, Copy, Default)]
+struct Flow {
+    buy_volume: f64,
+    sell_volume: f64,
+    buys: u32,
+    sells: u32,
+    blocks: u32,
+}
+
+impl Flow {
+    fn row(&self) -> [Option<f64>; 4] {
+        let volume = self.buy_volume + self.sell_volume;
+        let trades = (self.buys + self.sells) as f64;
+        let ratio = |value: f64, total: f64| (total > 0.0).then(|| value / total);
+        [
+            ratio(self.buy_volume - self.sell_volume, volume),
+            ratio(self.buys as f64 - self.sells as f64, trades),
+            ratio(volume, trades),
+            Some(self.blocks as f64),
+        ]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderFlow {
+    interval_ms: i64,
+    capacity: usize,
+    block_notional: f64,
+    /// Oldest candle whose trades are known, set by the first trade.
+    start_from: Option<i64>,
+    /// Time of the newest trade.
+    newest_trade: i64,
+    flows: BTreeMap<i64, Flow>,
+}
+
+impl OrderFlow {
+    /// State for `interval_ms` candles keeping the newest `capacity` of them;
+    /// trades worth `block_notional` or more are block trades.
+    pub fn new(interval_ms: i64, capacity: usize, block_notional: f64) -> Self {
+        Self {
+            interval_ms,
+            capacity: capacity.max(1),
+            block_notional,
+            start_from: None,
+            newest_trade: i64::MIN,
+            flows: BTreeMap::new(),
+        }
+    }
+
+    fn bucket(&self, time_ms: i64) -> i64 {
+        time_ms - time_ms.rem_euclid(self.interval_ms)
+    }
+
+    /// Add a trade; `taker_buy` when the taker bought (lifted the ask).
+    pub fn push_trade(&mut self, price: f64, qty: f64, time_ms: i64, taker_buy: bool) {
+        if !price.is_finite() || !qty.is_finite() {
+            return;
+        }
+        let open_time = self.bucket(time_ms);
+        let start_from = *self.start_from.get_or_insert(open_time + self.interval_ms);
+        self.newest_trade = self.newest_trade.max(time_ms);
+        if open_time < start_from {
+            return;
+        }
+        let block = price * qty >= self.block_notional;
+        let flow = self.flows.entry(open_time).or_default();
+        if taker_buy {
+            flow.buy_volume += qty;
+            flow.buys += 1;
+        } else {
+            flow.sell_volume += qty;
+            flow.sells += 1;
+        }
+        flow.blocks += block as u32;
+        while self.flows.len() > self.capacity {
+            self.flows.pop_first();
+            // Dropped candles are unknown again, not empty
+            self.start_from = self.flows.first_key_value().map(|(&t, _)| t);
+        }
+    }
+
+    /// Whether the trades of the candle opening at `open_time` are complete,
+    /// i.e. a trade of a later candle has arrived.
+    pub fn is_complete(&self, open_time: i64) -> bool {
+        self.start_from.is_some() && self.newest_trade >= open_time + self.interval_ms
+    }
+
+    /// One column per [`ORDER_FLOW_COLUMNS`] name, aligned with `open_times`.
+    pub fn columns(&self, open_times: &Int64Chunked) -> Vec<Column> {
+        let start_from = self.start_from.unwrap_or(i64::MAX);
+        let flows = asof_lookup(open_times, &self.flows, Asof::EXACT);
+        let rows: Vec<[Option<f64>; 4]> = open_times
+            .into_iter()
+            .zip(flows)
+            .map(|(t, flow)| match t {
+                Some(t) if t >= start_from => flow.copied().unwrap_or_default().row(),
+                _ => [None; 4],
+            })
+            .collect();
+        (0..ORDER_FLOW_COLUMNS.len())
+            .map(|c| {
+                let values: Vec<Option<f64>> = rows.iter().map(|row| row[c]).collect();
+                Series::new(ORDER_FLOW_COLUMNS[c].into(), values).into()
+            })
+            .collect()
+    }
+
+    /// Add the order-flow columns to a feature frame with an `open_time` column.
+    pub fn add_columns(&self, mut df: DataFrame) -> PolarsResult<DataFrame> {
+        let open_times = df.column("open_time")?.i64()?.clone();
+        for column in self.columns(&open_times) {
+            df.with_column(column)?;
+        }
+        Ok(df)
+    }
+}
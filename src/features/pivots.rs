@@ -1,16 +1,71 @@
 //! Pivot point calculations.
 //! For each candle we compute the number of consecutive candles to the left/right
 //! that satisfy the condition for a pivot high or pivot low.
+//!
+//! Runs are found with a monotonic stack in O(n): a candle popped from the
+//! stack can never end a later run, because anything that beats the candle
+//! that popped it beats it too. [`strengths_par`] splits the series into
+//! chunks (overlapping by the window) across rayon threads for the batch and
+//! backfill path; [`strengths_naive`] is the original O(n·window) scan, kept
+//! as the reference for the `bench` command and the criterion benchmarks.
 
 use anyhow::Result;
 use polars::prelude::*;
+use rayon::prelude::*;
 
 /// Maximum number of candles to look on each side when computing pivot strength.
-const PIVOT_WINDOW: usize = 5000;
+pub const PIVOT_WINDOW: usize = 5000;
+
+/// Smallest chunk handed to one rayon task; below this the overlap dominates.
+const MIN_PAR_CHUNK: usize = 16_384;
+
+/// Which extreme a pivot is measured on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PivotKind {
+    /// Neighbouring highs strictly lower than the candle's high.
+    High,
+    /// Neighbouring lows strictly higher than the candle's low.
+    Low,
+}
+
+impl PivotKind {
+    /// True if `other` continues the run of `curr` (false for NaN).
+    fn extends(self, other: f64, curr: f64) -> bool {
+        match self {
+            PivotKind::High => other < curr,
+            PivotKind::Low => other > curr,
+        }
+    }
+}
 
 /// Add pivot strength columns to the DataFrame.
 /// Expects columns "high" and "low" to exist.
-pub fn add_pivot_features(mut df: DataFrame) -> Result<DataFrame> {
+pub fn add_pivot_features(df: DataFrame) -> Result<DataFrame> {
+    add_columns(df, |prices, kind| strengths(prices, kind, PIVOT_WINDOW))
+}
+
+/// [`add_pivot_features`] with highs and lows computed concurrently, each
+/// chunked across rayon threads. Meant for long batch/backfill frames; the
+/// live window is small enough for the sequential version.
+pub fn add_pivot_features_par(df: DataFrame) -> Result<DataFrame> {
+    add_columns(df, |prices, kind| strengths_par(prices, kind, PIVOT_WINDOW))
+}
+
+/// [`add_pivot_features`] as each candle's row looked when it closed: the
+/// left runs only, since no later candle was known yet (right runs and
+/// strengths are 0, as in the newest row of the live window).
+pub fn add_pivot_features_as_of_close(df: DataFrame) -> Result<DataFrame> {
+    add_columns(df, |prices, kind| {
+        let left = run_lengths(prices, kind, PIVOT_WINDOW, false);
+        let right = vec![0; left.len()];
+        (left, right)
+    })
+}
+
+fn add_columns(
+    mut df: DataFrame,
+    compute: impl Fn(&[f64], PivotKind) -> (Vec<u32>, Vec<u32>) + Sync,
+) -> Result<DataFrame> {
     // Extract high and low as vectors of f64 (NaNs become f64::NAN)
     let high_prices: Vec<f64> = df
         .column("high")?
@@ -25,20 +80,10 @@ pub fn add_pivot_features(mut df: DataFrame) -> Result<DataFrame> {
         .map(|opt| opt.unwrap_or(f64::NAN))
         .collect();
 
-    // Compute strengths for highs: left = previous high < current high, right = next high < current high
-    let (left_high, right_high) = consecutive_strength_vec(
-        &high_prices,
-        |prev, curr| prev < curr, // left condition: previous high is lower
-        |next, curr| next < curr, // right condition: next high is lower
-        PIVOT_WINDOW,
-    );
-
-    // Compute strengths for lows: left = previous low > current low, right = next low > current low
-    let (left_low, right_low) = consecutive_strength_vec(
-        &low_prices,
-        |prev, curr| prev > curr, // left condition: previous low is higher
-        |next, curr| next > curr, // right condition: next low is higher
-        PIVOT_WINDOW,
+    // Highs: neighbours must be lower; lows: neighbours must be higher
+    let ((left_high, right_high), (left_low, right_low)) = rayon::join(
+        || compute(&high_prices, PivotKind::High),
+        || compute(&low_prices, PivotKind::Low),
     );
 
     let high_strength: Vec<u32> = left_high
@@ -63,14 +108,70 @@ pub fn add_pivot_features(mut df: DataFrame) -> Result<DataFrame> {
     Ok(df)
 }
 
-/// Helper: compute left and right consecutive counts for a price series.
-/// Returns two vectors of u32 (left strengths, right strengths).
-fn consecutive_strength_vec(
-    prices: &[f64],
-    left_cond: impl Fn(f64, f64) -> bool,
-    right_cond: impl Fn(f64, f64) -> bool,
-    window: usize,
-) -> (Vec<u32>, Vec<u32>) {
+/// Left and right run lengths of every candle, capped at `window`.
+pub fn strengths(prices: &[f64], kind: PivotKind, window: usize) -> (Vec<u32>, Vec<u32>) {
+    (
+        run_lengths(prices, kind, window, false),
+        run_lengths(prices, kind, window, true),
+    )
+}
+
+/// [`strengths`] over chunks processed on rayon threads. Each chunk is
+/// extended by `window` candles on the side it looks towards, so runs that
+/// cross a chunk boundary are still counted exactly up to the cap.
+pub fn strengths_par(prices: &[f64], kind: PivotKind, window: usize) -> (Vec<u32>, Vec<u32>) {
+    let n = prices.len();
+    let chunk = n.div_ceil(rayon::current_num_threads()).max(MIN_PAR_CHUNK);
+    let parts: Vec<(Vec<u32>, Vec<u32>)> = (0..n)
+        .step_by(chunk)
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|start| {
+            let end = (start + chunk).min(n);
+            let lo = start.saturating_sub(window);
+            let hi = (end + window).min(n);
+            let left = run_lengths(&prices[lo..end], kind, window, false);
+            let right = run_lengths(&prices[start..hi], kind, window, true);
+            (left[start - lo..].to_vec(), right[..end - start].to_vec())
+        })
+        .collect();
+
+    let mut left = Vec::with_capacity(n);
+    let mut right = Vec::with_capacity(n);
+    for (l, r) in parts {
+        left.extend(l);
+        right.extend(r);
+    }
+    (left, right)
+}
+
+/// Run length of every candle towards the start of `prices` (or the end,
+/// with `reverse`), using a stack of the candles that can still end a run.
+fn run_lengths(prices: &[f64], kind: PivotKind, window: usize, reverse: bool) -> Vec<u32> {
+    let n = prices.len();
+    let mut counts = vec![0u32; n];
+    let mut stack: Vec<usize> = Vec::new();
+    for step in 0..n {
+        let i = if reverse { n - 1 - step } else { step };
+        let curr = prices[i];
+        // A NaN candle has no run and ends every run reaching it
+        if !curr.is_nan() {
+            while stack
+                .last()
+                .is_some_and(|&top| kind.extends(prices[top], curr))
+            {
+                stack.pop();
+            }
+            let run = stack.last().map_or(step, |&end| end.abs_diff(i) - 1);
+            counts[i] = run.min(window) as u32;
+        }
+        stack.push(i);
+    }
+    counts
+}
+
+/// Original O(n·window) scan, kept as the reference implementation.
+pub fn strengths_naive(prices: &[f64], kind: PivotKind, window: usize) -> (Vec<u32>, Vec<u32>) {
     let n = prices.len();
     let mut left_strength = Vec::with_capacity(n);
     let mut right_strength = Vec::with_capacity(n);
@@ -83,22 +184,21 @@ fn consecutive_strength_vec(
             continue;
         }
 
-        // Count consecutive left candles that satisfy left_cond
+        // Count consecutive left candles that satisfy the condition
         let mut left_count = 0;
         for j in (0..i).rev().take(window) {
             let prev = prices[j];
-            if prev.is_nan() || !left_cond(prev, curr) {
+            if prev.is_nan() || !kind.extends(prev, curr) {
                 break;
             }
             left_count += 1;
         }
         left_strength.push(left_count);
 
-        // Count consecutive right candles that satisfy right_cond
+        // Count consecutive right candles that satisfy the condition
         let mut right_count = 0;
-        for j in i + 1..n.min(i + 1 + window) {
-            let next = prices[j];
-            if next.is_nan() || !right_cond(next, curr) {
+        for &next in &prices[i + 1..n.min(i + 1 + window)] {
+            if next.is_nan() || !kind.extends(next, curr) {
                 break;
             }
             right_count += 1;
@@ -108,3 +208,67 @@ fn consecutive_strength_vec(
 
     (left_strength, right_strength)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `count` random prices from `seed`, rounded to `levels` values so that
+    /// neighbours tie, with a NaN every 997 candles and a rising then falling
+    /// ramp longer than [`PIVOT_WINDOW`] in the middle.
+    fn prices(count: usize, levels: f64, seed: u64) -> Vec<f64> {
+        let mut state = seed;
+        let mut prices: Vec<f64> = (0..count)
+            .map(|i| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                let unit = (state >> 11) as f64 / (1u64 << 53) as f64;
+                if i % 997 == 996 {
+                    f64::NAN
+                } else {
+                    (unit * levels).floor()
+                }
+            })
+            .collect();
+        let ramp = PIVOT_WINDOW + 300;
+        if count > 2 * ramp {
+            let mid = count / 2 - ramp;
+            for j in 0..ramp {
+                prices[mid + j] = levels + j as f64;
+                prices[mid + ramp + j] = levels + (ramp - j) as f64;
+            }
+        }
+        prices
+    }
+
+    #[test]
+    fn fast_strengths_match_naive() {
+        // More than one chunk per thread even on a single-core runner
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        for (count, levels) in [
+            (0, 10.0),
+            (1, 10.0),
+            (7, 2.0),
+            (1_000, 5.0),
+            (1_000, 1e9),
+            (3 * MIN_PAR_CHUNK + 123, 8.0),
+            (3 * MIN_PAR_CHUNK + 123, 1e9),
+        ] {
+            let prices = prices(count, levels, count as u64 ^ levels.to_bits());
+            for kind in [PivotKind::High, PivotKind::Low] {
+                for window in [1, 5, 100, PIVOT_WINDOW] {
+                    let naive = strengths_naive(&prices, kind, window);
+                    let context =
+                        format!("{count} prices, {levels} levels, {kind:?}, window {window}");
+                    assert_eq!(strengths(&prices, kind, window), naive, "{context}");
+                    let par = pool.install(|| strengths_par(&prices, kind, window));
+                    assert_eq!(par, naive, "parallel, {context}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Cross-sectional features of a basket of symbols, one row per candle
+//! `open_time`, for allocation strategies that weigh the basket as a whole:
+//!
+//! - `symbols` – members whose candle of that time arrived;
+//! - `breadth_ema200` – share of them (0–100) closing above their EMA200 on
+//!   the stream interval (members still warming up their EMA are left out);
+//! - `avg_rsi` – mean of their RSI (Wilder's smoothing over `rsi_period`
+//!   candles);
+//! - `return_mean` / `return_dispersion` – mean and cross-sectional standard
+//!   deviation of their log returns since their previous candle.
+//!
+//! A row is finished once every member reported its candle. A member that
+//! skips a candle (a gap, a coalesced row, a restart) does not hold the
+//! basket back: the row is finished with the others as soon as a newer row
+//! completes, or once [`MAX_PENDING`] newer rows are waiting.
+
+use anyhow::Result;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+
+/// Feature columns after `open_time` and `datetime`, in frame order.
+pub const PORTFOLIO_COLUMNS: [&str; 5] = [
+    "symbols",
+    "breadth_ema200",
+    "avg_rsi",
+    "return_mean",
+    "return_dispersion",
+];
+
+/// Unfinished rows kept while a member is missing.
+const MAX_PENDING: usize = 8;
+
+/// One finished basket row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PortfolioRow {
+    pub open_time: i64,
+    pub symbols: usize,
+    pub breadth_ema200: Option<f64>,
+    pub avg_rsi: Option<f64>,
+    pub return_mean: Option<f64>,
+    pub return_dispersion: Option<f64>,
+}
+
+/// What one member contributed to a row.
+#[derive(Debug, Clone, Copy)]
+struct Observation {
+    above_ema: Option<bool>,
+    rsi: Option<f64>,
+    log_return: Option<f64>,
+}
+
+/// Running RSI and previous close of one member.
+#[derive(Debug, Clone, Default)]
+struct Member {
+    last_open_time: Option<i64>,
+    prev_close: Option<f64>,
+    changes: usize,
+    gain: f64,
+    loss: f64,
+}
+
+impl Member {
+    /// Advance by a close; the log return since the previous close and the
+    /// RSI after it.
+    fn push(&mut self, close: f64, period: usize) -> (Option<f64>, Option<f64>) {
+        let Some(prev) = self.prev_close.replace(close) else {
+            return (None, None);
+        };
+        let change = close - prev;
+        let (gain, loss) = (change.max(0.0), (-change).max(0.0));
+        self.changes += 1;
+        // Plain average over the first `period` changes, then Wilder's smoothing
+        if self.changes <= period {
+            self.gain += gain / period as f64;
+            self.loss += loss / period as f64;
+        } else {
+            self.gain = (self.gain * (period - 1) as f64 + gain) / period as f64;
+            self.loss = (self.loss * (period - 1) as f64 + loss) / period as f64;
+        }
+        let log_return = (prev > 0.0 && close > 0.0).then(|| (close / prev).ln());
+        let rsi = (self.changes >= period).then(|| {
+            if self.loss > 0.0 {
+                100.0 - 100.0 / (1.0 + self.gain / self.loss)
+            } else if self.gain > 0.0 {
+                100.0
+            } else {
+                50.0
+            }
+        });
+        (log_return, rsi)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PortfolioFeatures {
+    rsi_period: usize,
+    capacity: usize,
+    members: BTreeMap<String, Member>,
+    pending: BTreeMap<i64, BTreeMap<String, Observation>>,
+    rows: BTreeMap<i64, PortfolioRow>,
+}
+
+impl PortfolioFeatures {
+    /// State for the basket `symbols`, with RSIs over `rsi_period` candles,
+    /// keeping the newest `capacity` rows.
+    pub fn new(symbols: &[String], rsi_period: usize, capacity: usize) -> Self {
+        Self {
+            rsi_period: rsi_period.max(1),
+            capacity: capacity.max(1),
+            members: symbols
+                .iter()
+                .map(|s| (s.clone(), Member::default()))
+                .collect(),
+            pending: BTreeMap::new(),
+            rows: BTreeMap::new(),
+        }
+    }
+
+    /// Add the closed candle of `symbol` opening at `open_time`, with its
+    /// `ema200` (`None` while warming up). Returns the rows it finished,
+    /// oldest first; candles of other symbols and repeated or older candles
+    /// of a member are ignored.
+    pub fn push(
+        &mut self,
+        symbol: &str,
+        open_time: i64,
+        close: f64,
+        ema200: Option<f64>,
+    ) -> Vec<PortfolioRow> {
+        let members = self.members.len();
+        let Some(member) = self.members.get_mut(symbol) else {
+            return Vec::new();
+        };
+        if member.last_open_time.is_some_and(|t| t >= open_time) {
+            return Vec::new();
+        }
+        member.last_open_time = Some(open_time);
+        let (log_return, rsi) = member.push(close, self.rsi_period);
+        // A candle of a row finished without it only advances the member
+        if self
+            .rows
+            .last_key_value()
+            .is_some_and(|(&t, _)| t >= open_time)
+        {
+            return Vec::new();
+        }
+        let observations = self.pending.entry(open_time).or_default();
+        observations.insert(
+            symbol.to_string(),
+            Observation {
+                above_ema: ema200.map(|ema| close > ema),
+                rsi,
+                log_return,
+            },
+        );
+
+        let mut finished = Vec::new();
+        if observations.len() == members {
+            while let Some((&t, _)) = self.pending.first_key_value() {
+                if t > open_time {
+                    break;
+                }
+                finished.push(self.finish(t));
+            }
+        }
+        while self.pending.len() > MAX_PENDING {
+            let t = *self.pending.keys().next().unwrap();
+            finished.push(self.finish(t));
+        }
+        finished
+    }
+
+    /// Compute and keep the row of the pending `open_time`.
+    fn finish(&mut self, open_time: i64) -> PortfolioRow {
+        let observations = self.pending.remove(&open_time).unwrap_or_default();
+        let mean = |values: &[f64]| {
+            (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+        };
+        let above: Vec<f64> = observations
+            .values()
+            .filter_map(|o| o.above_ema)
+            .map(|above| if above { 100.0 } else { 0.0 })
+            .collect();
+        let rsi: Vec<f64> = observations.values().filter_map(|o| o.rsi).collect();
+        let returns: Vec<f64> = observations.values().filter_map(|o| o.log_return).collect();
+        let return_mean = mean(&returns);
+        let return_dispersion = return_mean.filter(|_| returns.len() >= 2).map(|m| {
+            (returns.iter().map(|r| (r - m).powi(2)).sum::<f64>() / returns.len() as f64).sqrt()
+        });
+        let row = PortfolioRow {
+            open_time,
+            symbols: observations.len(),
+            breadth_ema200: mean(&above),
+            avg_rsi: mean(&rsi),
+            return_mean,
+            return_dispersion,
+        };
+        self.rows.insert(open_time, row);
+        while self.rows.len() > self.capacity {
+            self.rows.pop_first();
+        }
+        row
+    }
+
+    /// The kept rows, oldest first.
+    pub fn rows(&self) -> impl Iterator<Item = &PortfolioRow> {
+        self.rows.values()
+    }
+
+    /// The kept rows as a frame: `open_time`, `datetime`, then
+    /// [`PORTFOLIO_COLUMNS`].
+    pub fn frame(&self) -> Result<DataFrame> {
+        let rows: Vec<&PortfolioRow> = self.rows.values().collect();
+        let float = |name: &str, value: fn(&PortfolioRow) -> Option<f64>| -> Column {
+            Series::new(
+                name.into(),
+                rows.iter().map(|r| value(r)).collect::<Vec<_>>(),
+            )
+            .into()
+        };
+        let open_time = Series::new(
+            "open_time".into(),
+            rows.iter().map(|r| r.open_time).collect::<Vec<_>>(),
+        );
+        let datetime = open_time
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?
+            .with_name("datetime".into());
+        let df = DataFrame::new(
+            rows.len(),
+            vec![
+                open_time.into(),
+                datetime.into(),
+                Series::new(
+                    PORTFOLIO_COLUMNS[0].into(),
+                    rows.iter().map(|r| r.symbols as u32).collect::<Vec<_>>(),
+                )
+                .into(),
+                float(PORTFOLIO_COLUMNS[1], |r| r.breadth_ema200),
+                float(PORTFOLIO_COLUMNS[2], |r| r.avg_rsi),
+                float(PORTFOLIO_COLUMNS[3], |r| r.return_mean),
+                float(PORTFOLIO_COLUMNS[4], |r| r.return_dispersion),
+            ],
+        )?;
+        Ok(df)
+    }
+}
@@ -0,0 +1,213 @@
+//! Candle-level data quality, so model training can weight or exclude rows
+//! built from doubtful candles.
+//!
+//! Every row gets a `quality_flags` bit set and a `quality_score`:
+//!
+//! - [`REST`] – the candle did not come from the live stream: it was loaded
+//!   at start-up (REST or the cache) or replaced by its REST values;
+//! - [`INVALID`] – its OHLCV values fail validation (a non-finite or
+//!   non-positive price, a high below the open or close, a low above them, a
+//!   negative volume);
+//! - [`NO_SPREAD`] – the spread columns are on but hold no quote sample for
+//!   the candle;
+//! - [`AFTER_GAP`] – the candle before it is missing, so the stream
+//!   resumed after a gap (an outage, a quarantined candle);
+//! - [`MISMATCH`] – the consistency check found the stored candle differing
+//!   from REST and kept it;
+//! - [`MAINTENANCE`] – the candle's interval overlaps an exchange maintenance
+//!   window, so it may have been built from part of its trades.
+//!
+//! The score starts at 1 and loses the configured penalty of every flag set,
+//! down to 0; an invalid candle scores 0.
+
+use anyhow::{bail, Result};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Column names added by [`CandleQuality::add_columns`].
+pub const QUALITY_COLUMNS: [&str; 2] = ["quality_flags", "quality_score"];
+
+pub const REST: u32 = 1;
+pub const INVALID: u32 = 1 << 1;
+pub const NO_SPREAD: u32 = 1 << 2;
+pub const AFTER_GAP: u32 = 1 << 3;
+pub const MISMATCH: u32 = 1 << 4;
+pub const MAINTENANCE: u32 = 1 << 5;
+
+/// Spread column whose nulls mark candles without quote samples.
+const SPREAD_COLUMN: &str = "spread_bps_avg";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct QualityConfig {
+    pub enabled: bool,
+    /// Score lost by a candle not from the live stream.
+    pub rest_penalty: f64,
+    /// Score lost by the first candle after a gap.
+    pub gap_penalty: f64,
+    /// Score lost by a candle without spread samples.
+    pub spread_penalty: f64,
+    /// Score lost by a candle the consistency check found differing.
+    pub mismatch_penalty: f64,
+    /// Score lost by a candle overlapping an exchange maintenance window.
+    pub maintenance_penalty: f64,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rest_penalty: 0.1,
+            gap_penalty: 0.3,
+            spread_penalty: 0.2,
+            mismatch_penalty: 0.5,
+            maintenance_penalty: 0.3,
+        }
+    }
+}
+
+impl QualityConfig {
+    pub fn validate(&self) -> Result<()> {
+        for (name, penalty) in [
+            ("rest_penalty", self.rest_penalty),
+            ("gap_penalty", self.gap_penalty),
+            ("spread_penalty", self.spread_penalty),
+            ("mismatch_penalty", self.mismatch_penalty),
+            ("maintenance_penalty", self.maintenance_penalty),
+        ] {
+            if !(0.0..=1.0).contains(&penalty) {
+                bail!("[quality] {} must be between 0 and 1", name);
+            }
+        }
+        Ok(())
+    }
+
+    /// The score of a candle with `flags`.
+    pub fn score(&self, flags: u32) -> f64 {
+        if flags & INVALID != 0 {
+            return 0.0;
+        }
+        let penalty: f64 = [
+            (REST, self.rest_penalty),
+            (AFTER_GAP, self.gap_penalty),
+            (NO_SPREAD, self.spread_penalty),
+            (MISMATCH, self.mismatch_penalty),
+            (MAINTENANCE, self.maintenance_penalty),
+        ]
+        .iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, penalty)| penalty)
+        .sum();
+        (1.0 - penalty).max(0.0)
+    }
+}
+
+/// Provenance marks of the candles of a window, by `open_time`. The flags
+/// read from the frame itself (validation, spread samples, gaps) are
+/// computed when the columns are added.
+#[derive(Debug, Clone)]
+pub struct CandleQuality {
+    config: QualityConfig,
+    interval_ms: i64,
+    capacity: usize,
+    marks: BTreeMap<i64, u32>,
+}
+
+impl CandleQuality {
+    /// Marks of up to `capacity` candles of `interval_ms`.
+    pub fn new(config: &QualityConfig, interval_ms: i64, capacity: usize) -> Self {
+        Self {
+            config: config.clone(),
+            interval_ms,
+            capacity: capacity.max(1),
+            marks: BTreeMap::new(),
+        }
+    }
+
+    /// Set `flags` on the candle opening at `open_time`.
+    pub fn mark(&mut self, open_time: i64, flags: u32) {
+        *self.marks.entry(open_time).or_default() |= flags;
+        while self.marks.len() > self.capacity {
+            self.marks.pop_first();
+        }
+    }
+
+    /// The candle opening at `open_time` was replaced by its REST values,
+    /// which settle any earlier mismatch.
+    pub fn replaced(&mut self, open_time: i64) {
+        self.mark(open_time, REST);
+        if let Some(flags) = self.marks.get_mut(&open_time) {
+            *flags &= !MISMATCH;
+        }
+    }
+
+    /// The flags of every row of a feature frame with the candle columns.
+    pub fn flags(&self, df: &DataFrame) -> PolarsResult<Vec<u32>> {
+        let values = |name: &str| -> PolarsResult<Vec<f64>> {
+            Ok(df
+                .column(name)?
+                .cast(&DataType::Float64)?
+                .f64()?
+                .into_iter()
+                .map(|v| v.unwrap_or(f64::NAN))
+                .collect())
+        };
+        let open_times = df.column("open_time")?.i64()?;
+        let (open, high, low, close, volume) = (
+            values("open")?,
+            values("high")?,
+            values("low")?,
+            values("close")?,
+            values("volume")?,
+        );
+        let spread = match df.column(SPREAD_COLUMN) {
+            Ok(column) => Some(column.is_null()),
+            Err(_) => None,
+        };
+
+        let mut previous: Option<i64> = None;
+        let flags = open_times
+            .into_iter()
+            .enumerate()
+            .map(|(i, open_time)| {
+                let mut flags = open_time
+                    .and_then(|t| self.marks.get(&t))
+                    .copied()
+                    .unwrap_or_default();
+                let prices = [open[i], high[i], low[i], close[i]];
+                let valid = prices.iter().all(|p| p.is_finite() && *p > 0.0)
+                    && high[i] >= open[i].max(close[i])
+                    && low[i] <= open[i].min(close[i])
+                    && volume[i] >= 0.0;
+                if !valid {
+                    flags |= INVALID;
+                }
+                if spread
+                    .as_ref()
+                    .is_some_and(|nulls| nulls.get(i) == Some(true))
+                {
+                    flags |= NO_SPREAD;
+                }
+                if let (Some(t), Some(p)) = (open_time, previous) {
+                    if self.interval_ms > 0 && t - p > self.interval_ms {
+                        flags |= AFTER_GAP;
+                    }
+                }
+                previous = open_time.or(previous);
+                flags
+            })
+            .collect();
+        Ok(flags)
+    }
+
+    /// Add [`QUALITY_COLUMNS`] to a feature frame with the candle columns
+    /// (and the spread columns, if on).
+    pub fn add_columns(&self, mut df: DataFrame) -> PolarsResult<DataFrame> {
+        let flags = self.flags(&df)?;
+        let scores: Vec<f64> = flags.iter().map(|&f| self.config.score(f)).collect();
+        df.with_column(Series::new(QUALITY_COLUMNS[0].into(), flags).into())?;
+        df.with_column(Series::new(QUALITY_COLUMNS[1].into(), scores).into())?;
+        Ok(df)
+    }
+}
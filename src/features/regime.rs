@@ -0,0 +1,223 @@
+//! Market-regime classification: one explicit `regime` label per candle, so
+//! strategies and models need not infer the market state themselves.
+//!
+//! Three inputs are computed first:
+//!
+//! - `adx` – Wilder's average directional index over `adx_period` candles
+//!   (trend strength, 0 … 100);
+//! - `realized_vol` – standard deviation of the log returns of the last
+//!   `vol_window` candles;
+//! - the EMA alignment of `ema_fast` and `ema_slow` (feature columns, so the
+//!   EMA features must already be in the frame).
+//!
+//! The label is the first rule that matches:
+//!
+//! 1. `high_vol` – `realized_vol` is at least `high_vol_ratio` times its mean
+//!    over the last `vol_lookback` candles;
+//! 2. `trending_up` – `adx` is at least `adx_trend` and close > fast EMA >
+//!    slow EMA;
+//! 3. `trending_down` – the same with close < fast EMA < slow EMA;
+//! 4. `ranging` – otherwise.
+//!
+//! Rows whose inputs are still warming up are null.
+
+use super::ema::EMA_COLUMNS;
+use anyhow::{bail, Result};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Column names added by [`add_regime_features`].
+pub const REGIME_COLUMNS: [&str; 3] = ["adx", "realized_vol", "regime"];
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RegimeConfig {
+    pub enabled: bool,
+    /// Candles of the ADX smoothing.
+    pub adx_period: usize,
+    /// ADX from which a market counts as trending.
+    pub adx_trend: f64,
+    /// Fast and slow EMA feature columns the alignment is read from.
+    pub ema_fast: String,
+    pub ema_slow: String,
+    /// Returns the realized volatility is computed over.
+    pub vol_window: usize,
+    /// Candles the realized volatility is averaged over for the high-vol rule.
+    pub vol_lookback: usize,
+    /// Realized volatility over its average from which the regime is `high_vol`.
+    pub high_vol_ratio: f64,
+}
+
+impl Default for RegimeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            adx_period: 14,
+            adx_trend: 25.0,
+            ema_fast: "ema50_h1".to_string(),
+            ema_slow: "ema200_h1".to_string(),
+            vol_window: 96,
+            vol_lookback: 2000,
+            high_vol_ratio: 2.0,
+        }
+    }
+}
+
+impl RegimeConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.adx_period == 0 || self.vol_window < 2 || self.vol_lookback == 0 {
+            bail!("[regime] adx_period and vol_lookback must be at least 1, vol_window at least 2");
+        }
+        if self.high_vol_ratio.is_nan() || self.high_vol_ratio <= 0.0 {
+            bail!("[regime] high_vol_ratio must be positive");
+        }
+        for column in [&self.ema_fast, &self.ema_slow] {
+            if !EMA_COLUMNS.contains(&column.as_str()) {
+                bail!(
+                    "[regime] '{}' is not an EMA column ({})",
+                    column,
+                    EMA_COLUMNS.join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Wilder's ADX of every candle (null for the first `2 * period - 1`).
+pub fn adx(high: &[f64], low: &[f64], close: &[f64], period: usize) -> Vec<Option<f64>> {
+    let n = close.len();
+    let mut out = vec![None; n];
+    if period == 0 || n < 2 * period {
+        return out;
+    }
+    let p = period as f64;
+    let (mut tr_sm, mut plus_sm, mut minus_sm) = (0.0, 0.0, 0.0);
+    let mut adx: Option<f64> = None;
+    let mut dx_sum = 0.0;
+    for i in 1..n {
+        let up = high[i] - high[i - 1];
+        let down = low[i - 1] - low[i];
+        let plus_dm = if up > down && up > 0.0 { up } else { 0.0 };
+        let minus_dm = if down > up && down > 0.0 { down } else { 0.0 };
+        let tr = (high[i] - low[i])
+            .max((high[i] - close[i - 1]).abs())
+            .max((low[i] - close[i - 1]).abs());
+        if i <= period {
+            // The first smoothed values are plain sums
+            tr_sm += tr;
+            plus_sm += plus_dm;
+            minus_sm += minus_dm;
+            if i < period {
+                continue;
+            }
+        } else {
+            tr_sm += tr - tr_sm / p;
+            plus_sm += plus_dm - plus_sm / p;
+            minus_sm += minus_dm - minus_sm / p;
+        }
+        let plus_di = if tr_sm > 0.0 { plus_sm / tr_sm } else { 0.0 };
+        let minus_di = if tr_sm > 0.0 { minus_sm / tr_sm } else { 0.0 };
+        let dx = if plus_di + minus_di > 0.0 {
+            100.0 * (plus_di - minus_di).abs() / (plus_di + minus_di)
+        } else {
+            0.0
+        };
+        adx = match adx {
+            Some(prev) => Some((prev * (p - 1.0) + dx) / p),
+            None => {
+                dx_sum += dx;
+                // The first ADX is the mean of the first `period` DX values
+                (i == 2 * period - 1).then(|| dx_sum / p)
+            }
+        };
+        out[i] = adx;
+    }
+    out
+}
+
+/// Standard deviation of the last `window` log returns at every candle
+/// (null until `window` returns exist).
+pub fn realized_vol(close: &[f64], window: usize) -> Vec<Option<f64>> {
+    let n = close.len();
+    let mut out = vec![None; n];
+    let (mut sum, mut sq_sum) = (0.0, 0.0);
+    let returns: Vec<f64> = (1..n).map(|i| (close[i] / close[i - 1]).ln()).collect();
+    for (j, r) in returns.iter().enumerate() {
+        sum += r;
+        sq_sum += r * r;
+        if j >= window {
+            let old = returns[j - window];
+            sum -= old;
+            sq_sum -= old * old;
+        }
+        if j + 1 >= window {
+            let w = window as f64;
+            let mean = sum / w;
+            let variance = (sq_sum / w - mean * mean).max(0.0);
+            let vol = variance.sqrt();
+            out[j + 1] = vol.is_finite().then_some(vol);
+        }
+    }
+    out
+}
+
+/// Add [`REGIME_COLUMNS`] to a feature frame with candle and EMA columns.
+pub fn add_regime_features(mut df: DataFrame, config: &RegimeConfig) -> Result<DataFrame> {
+    let values = |name: &str| -> Result<Vec<f64>> {
+        Ok(df
+            .column(name)?
+            .cast(&DataType::Float64)?
+            .f64()?
+            .into_iter()
+            .map(|v| v.unwrap_or(f64::NAN))
+            .collect())
+    };
+    let (high, low, close) = (values("high")?, values("low")?, values("close")?);
+    let (fast, slow) = (values(&config.ema_fast)?, values(&config.ema_slow)?);
+
+    let adx = adx(&high, &low, &close, config.adx_period);
+    let vol = realized_vol(&close, config.vol_window);
+
+    // Mean of the defined realized volatilities of the last `vol_lookback` rows
+    let mut vol_mean = vec![None; vol.len()];
+    let (mut sum, mut count) = (0.0, 0usize);
+    for i in 0..vol.len() {
+        if let Some(v) = vol[i] {
+            sum += v;
+            count += 1;
+        }
+        if i >= config.vol_lookback {
+            if let Some(v) = vol[i - config.vol_lookback] {
+                sum -= v;
+                count -= 1;
+            }
+        }
+        vol_mean[i] = (count > 0).then(|| sum / count as f64);
+    }
+
+    let regime: Vec<Option<&str>> = (0..close.len())
+        .map(|i| {
+            let (Some(adx), Some(vol), Some(mean)) = (adx[i], vol[i], vol_mean[i]) else {
+                return None;
+            };
+            if fast[i].is_nan() || slow[i].is_nan() {
+                return None;
+            }
+            Some(if mean > 0.0 && vol >= config.high_vol_ratio * mean {
+                "high_vol"
+            } else if adx >= config.adx_trend && close[i] > fast[i] && fast[i] > slow[i] {
+                "trending_up"
+            } else if adx >= config.adx_trend && close[i] < fast[i] && fast[i] < slow[i] {
+                "trending_down"
+            } else {
+                "ranging"
+            })
+        })
+        .collect();
+
+    df.with_column(Series::new("adx".into(), adx).into())?;
+    df.with_column(Series::new("realized_vol".into(), vol).into())?;
+    df.with_column(Series::new("regime".into(), regime).into())?;
+    Ok(df)
+}
@@ -0,0 +1,147 @@
+//! Spread and liquidity features from the best bid/ask stream (Binance
+//! bookTicker), buffered per candle, as inputs for execution-cost-aware
+//! strategies.
+//!
+//! Every quote update is attributed to the candle its time falls in. Per
+//! candle, with the spread in basis points of the mid price:
+//!
+//! - `spread_bps_avg` / `spread_bps_max` – mean and widest spread;
+//! - `spread_bps_std` – standard deviation of the spread;
+//! - `touch_size_avg` – mean base quantity quoted at the best bid and ask
+//!   together.
+//!
+//! Quotes are also sampled, at most one per `sample_ms`, for the quote
+//! history on disk. The candle the stream connected in is incomplete and
+//! stays null, as do candles before it and candles without any quote.
+
+use crate::data_storage::{asof_lookup, Asof};
+use crate::exchange::BookQuote;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+
+/// Column names, in row order.
+pub const SPREAD_COLUMNS: [&str; 4] = [
+    "spread_bps_avg",
+    "spread_bps_max",
+    "spread_bps_std",
+    "touch_size_avg",
+];
+
+/// Quotes of one candle.
+#[derive(Debug, Clone, Copy, Default)]
+struct Quotes {
+    count: u32,
+    spread_sum: f64,
+    spread_sq_sum: f64,
+    spread_max: f64,
+    touch_sum: f64,
+}
+
+impl Quotes {
+    fn row(&self) -> [Option<f64>; 4] {
+        if self.count == 0 {
+            return [None; 4];
+        }
+        let n = self.count as f64;
+        let mean = self.spread_sum / n;
+        let variance = (self.spread_sq_sum / n - mean * mean).max(0.0);
+        [
+            Some(mean),
+            Some(self.spread_max),
+            Some(variance.sqrt()),
+            Some(self.touch_sum / n),
+        ]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BookSpread {
+    interval_ms: i64,
+    capacity: usize,
+    sample_ms: i64,
+    /// First complete candle, set by the first quote.
+    start_from: Option<i64>,
+    /// Time of the newest quote.
+    newest_quote: i64,
+    /// Time of the newest sampled quote.
+    last_sample: i64,
+    quotes: BTreeMap<i64, Quotes>,
+}
+
+impl BookSpread {
+    /// State for `interval_ms` candles keeping the newest `capacity` of them,
+    /// sampling a quote at most every `sample_ms`.
+    pub fn new(interval_ms: i64, capacity: usize, sample_ms: i64) -> Self {
+        Self {
+            interval_ms,
+            capacity: capacity.max(1),
+            sample_ms,
+            start_from: None,
+            newest_quote: i64::MIN,
+            last_sample: i64::MIN,
+            quotes: BTreeMap::new(),
+        }
+    }
+
+    fn bucket(&self, time_ms: i64) -> i64 {
+        time_ms - time_ms.rem_euclid(self.interval_ms)
+    }
+
+    /// Add a quote update; returns whether it is a sample for the history.
+    pub fn push_quote(&mut self, quote: &BookQuote) -> bool {
+        let mid = (quote.bid + quote.ask) / 2.0;
+        let spread_bps = (quote.ask - quote.bid) / mid * 10_000.0;
+        // One-sided or crossed books say nothing about the cost of a trade
+        if !spread_bps.is_finite() || spread_bps < 0.0 || quote.bid <= 0.0 {
+            return false;
+        }
+        let open_time = self.bucket(quote.time);
+        let start_from = *self.start_from.get_or_insert(open_time + self.interval_ms);
+        self.newest_quote = self.newest_quote.max(quote.time);
+        if open_time >= start_from {
+            let quotes = self.quotes.entry(open_time).or_default();
+            quotes.count += 1;
+            quotes.spread_sum += spread_bps;
+            quotes.spread_sq_sum += spread_bps * spread_bps;
+            quotes.spread_max = quotes.spread_max.max(spread_bps);
+            quotes.touch_sum += quote.bid_qty + quote.ask_qty;
+            while self.quotes.len() > self.capacity {
+                self.quotes.pop_first();
+            }
+        }
+        if quote.time < self.last_sample.saturating_add(self.sample_ms) {
+            return false;
+        }
+        self.last_sample = quote.time;
+        true
+    }
+
+    /// Whether the quotes of the candle opening at `open_time` are complete,
+    /// i.e. a quote of a later candle has arrived.
+    pub fn is_complete(&self, open_time: i64) -> bool {
+        self.start_from.is_some() && self.newest_quote >= open_time + self.interval_ms
+    }
+
+    /// One column per [`SPREAD_COLUMNS`] name, aligned with `open_times`.
+    pub fn columns(&self, open_times: &Int64Chunked) -> Vec<Column> {
+        let rows: Vec<[Option<f64>; 4]> = asof_lookup(open_times, &self.quotes, Asof::EXACT)
+            .into_iter()
+            .map(|quotes| quotes.map_or([None; 4], Quotes::row))
+            .collect();
+        (0..SPREAD_COLUMNS.len())
+            .map(|c| {
+                let values: Vec<Option<f64>> = rows.iter().map(|row| row[c]).collect();
+                Series::new(SPREAD_COLUMNS[c].into(), values).into()
+            })
+            .collect()
+    }
+
+    /// Add the spread columns to a feature frame with an `open_time` column.
+    pub fn add_columns(&self, mut df: DataFrame) -> PolarsResult<DataFrame> {
+        let open_times = df.column("open_time")?.i64()?.clone();
+        for column in self.columns(&open_times) {
+            df.with_column(column)?;
+        }
+        Ok(df)
+    }
+}
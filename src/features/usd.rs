@@ -0,0 +1,114 @@
+//! Prices and volume in USD, for pairs quoted in another currency (ETHBTC,
+//! SOLETH, ...), so that symbols can be compared with each other.
+//!
+//! The rate is the close of a conversion pair (e.g. BTCUSDT for a BTC-quoted
+//! pair) of the same `open_time`, or its inverse for a pair quoted the other
+//! way round. Every price of a candle is converted at that rate:
+//! `open_usd`, `high_usd`, `low_usd`, `close_usd`, and `volume_usd` is the
+//! base volume times `close_usd`. Rows stay null until the conversion candle
+//! of their `open_time` is known.
+
+use crate::data_storage::{asof_lookup, Asof};
+use crate::kline::Kline;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+
+/// Column names, in row order.
+pub const USD_COLUMNS: [&str; 5] = ["open_usd", "high_usd", "low_usd", "close_usd", "volume_usd"];
+
+type UsdRow = [f64; 5];
+
+#[derive(Debug, Clone)]
+pub struct UsdConversion {
+    capacity: usize,
+    invert: bool,
+    primary: BTreeMap<i64, Kline>,
+    /// USD per unit of the quote currency, by `open_time`.
+    rates: BTreeMap<i64, f64>,
+    rows: BTreeMap<i64, UsdRow>,
+}
+
+impl UsdConversion {
+    /// State keeping the newest `capacity` candles of each pair; with
+    /// `invert` the conversion pair is quoted in the pair's quote currency
+    /// (e.g. USDTTRY for a TRY-quoted pair).
+    pub fn new(capacity: usize, invert: bool) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            invert,
+            primary: BTreeMap::new(),
+            rates: BTreeMap::new(),
+            rows: BTreeMap::new(),
+        }
+    }
+
+    /// Add a closed candle of the converted pair.
+    pub fn push_primary(&mut self, kline: &Kline) {
+        self.primary.insert(kline.open_time, kline.clone());
+        self.refresh(kline.open_time);
+    }
+
+    /// Add a closed candle of the conversion pair.
+    pub fn push_reference(&mut self, kline: &Kline) {
+        let rate = if self.invert {
+            1.0 / kline.close
+        } else {
+            kline.close
+        };
+        if rate.is_finite() {
+            self.rates.insert(kline.open_time, rate);
+            self.refresh(kline.open_time);
+        }
+    }
+
+    /// Whether the conversion candle opening at `open_time` has arrived.
+    pub fn has_reference(&self, open_time: i64) -> bool {
+        self.rates.contains_key(&open_time)
+    }
+
+    /// One column per [`USD_COLUMNS`] name, aligned with `open_times`.
+    pub fn columns(&self, open_times: &Int64Chunked) -> Vec<Column> {
+        let rows = asof_lookup(open_times, &self.rows, Asof::EXACT);
+        (0..USD_COLUMNS.len())
+            .map(|c| {
+                let values: Vec<Option<f64>> =
+                    rows.iter().map(|row| row.map(|row| row[c])).collect();
+                Series::new(USD_COLUMNS[c].into(), values).into()
+            })
+            .collect()
+    }
+
+    /// Add the USD columns to a feature frame with an `open_time` column.
+    pub fn add_columns(&self, mut df: DataFrame) -> PolarsResult<DataFrame> {
+        let open_times = df.column("open_time")?.i64()?.clone();
+        for column in self.columns(&open_times) {
+            df.with_column(column)?;
+        }
+        Ok(df)
+    }
+
+    fn refresh(&mut self, open_time: i64) {
+        if let (Some(k), Some(&rate)) = (self.primary.get(&open_time), self.rates.get(&open_time)) {
+            let close = k.close * rate;
+            self.rows.insert(
+                open_time,
+                [
+                    k.open * rate,
+                    k.high * rate,
+                    k.low * rate,
+                    close,
+                    k.volume * close,
+                ],
+            );
+        }
+        while self.primary.len() > self.capacity {
+            self.primary.pop_first();
+        }
+        while self.rates.len() > self.capacity {
+            self.rates.pop_first();
+        }
+        while self.rows.len() > self.capacity {
+            self.rows.pop_first();
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! Warm-up flags of partial windows.
+//!
+//! A freshly listed symbol has far fewer candles than the window: the M15
+//! EMAs fill in after 200 candles, the H4 EMA200 only after ~800 hours (see
+//! [`super::ema::warmup_candles`]). Every feature that can be computed is;
+//! the others stay null, and `warmup_flags` tells which of them are null
+//! because too few candles preceded the row rather than for lack of data:
+//! bit `i` for the `i`-th of [`WARMUP_SOURCES`] present in the frame. A row
+//! with `warmup_flags == 0` is fully warm.
+
+use super::ema::EMA_COLUMNS;
+use polars::prelude::*;
+
+/// Column added by [`add_warmup_flags`].
+pub const WARMUP_COLUMN: &str = "warmup_flags";
+
+/// Columns that are null while warming up, in bit order: the EMAs (1 … 32),
+/// then the `[regime]` inputs `adx` (64) and `realized_vol` (128).
+pub const WARMUP_SOURCES: [&str; 8] = [
+    EMA_COLUMNS[0],
+    EMA_COLUMNS[1],
+    EMA_COLUMNS[2],
+    EMA_COLUMNS[3],
+    EMA_COLUMNS[4],
+    EMA_COLUMNS[5],
+    "adx",
+    "realized_vol",
+];
+
+/// The warm-up flags of every row of `df`.
+pub fn warmup_flags(df: &DataFrame) -> Vec<u32> {
+    let mut flags = vec![0u32; df.height()];
+    for (bit, name) in WARMUP_SOURCES.iter().enumerate() {
+        let Ok(column) = df.column(name) else {
+            continue;
+        };
+        for (row, null) in column.is_null().into_iter().enumerate() {
+            if null == Some(true) {
+                flags[row] |= 1 << bit;
+            }
+        }
+    }
+    flags
+}
+
+/// The names of the [`WARMUP_SOURCES`] set in `flags`.
+pub fn warming_columns(flags: u32) -> Vec<&'static str> {
+    WARMUP_SOURCES
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| flags & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// Add [`WARMUP_COLUMN`] to a feature frame.
+pub fn add_warmup_flags(mut df: DataFrame) -> PolarsResult<DataFrame> {
+    let flags = warmup_flags(&df);
+    df.with_column(Series::new(WARMUP_COLUMN.into(), flags).into())?;
+    Ok(df)
+}
@@ -0,0 +1,272 @@
+//! Arrow Flight endpoint for bulk access to the stored datasets.
+//!
+//! Served on the gRPC port (`grpc_addr`) when built with the `flight`
+//! feature. Tickets and command descriptors are JSON:
+//! `{"symbol": "BTCUSDT", "dataset": "features" | "candles", "from": ms, "to": ms}`
+//! (`from` / `to` optional, inclusive, on `open_time`). Path descriptors
+//! `[symbol, dataset]` select a whole dataset. `list_flights` enumerates every
+//! dataset of every published symbol.
+//!
+//! Frames go Parquet → polars → Arrow IPC → arrow-rs record batches, so
+//! clients (`pyarrow.flight`, R `arrow`) receive columnar data without any
+//! CSV or Parquet round trip on their side.
+
+use crate::{data_storage, history, latest};
+use anyhow::{bail, Context, Result};
+use arrow_array::RecordBatch;
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::flight_descriptor::DescriptorType;
+use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+use arrow_flight::{
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo,
+    HandshakeRequest, HandshakeResponse, PollInfo, PutResult, SchemaAsIpc, SchemaResult, Ticket,
+};
+use arrow_ipc::reader::StreamReader;
+use arrow_ipc::writer::IpcWriteOptions;
+use arrow_schema::{ArrowError, SchemaRef};
+use futures_util::stream::{self, Stream, StreamExt, TryStreamExt};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+use tonic::{Request, Response, Status, Streaming};
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+const DATASETS: &[&str] = &["features", "candles"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DatasetRequest {
+    symbol: String,
+    dataset: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    from: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    to: Option<i64>,
+}
+
+impl DatasetRequest {
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        serde_json::from_slice(bytes)
+            .context("expected JSON {\"symbol\", \"dataset\", \"from\"?, \"to\"?}")
+    }
+
+    fn from_descriptor(descriptor: &FlightDescriptor) -> Result<Self> {
+        if descriptor.r#type == DescriptorType::Path as i32 {
+            let [symbol, dataset] = descriptor.path.as_slice() else {
+                bail!("path descriptors must be [symbol, dataset]");
+            };
+            return Ok(Self {
+                symbol: symbol.clone(),
+                dataset: dataset.clone(),
+                from: None,
+                to: None,
+            });
+        }
+        Self::parse(&descriptor.cmd)
+    }
+
+    /// Published state of the symbol, after checking the dataset name.
+    fn state(&self) -> Result<latest::SymbolState> {
+        if !DATASETS.contains(&self.dataset.as_str()) {
+            bail!(
+                "Unknown dataset '{}' (expected one of {:?})",
+                self.dataset,
+                DATASETS
+            );
+        }
+        latest::get(&self.symbol).with_context(|| format!("No data for symbol {}", self.symbol))
+    }
+
+    /// Parquet file backing the requested dataset (for candles, the snapshot
+    /// without the journaled candles; enough for the schema).
+    fn path(&self) -> Result<String> {
+        let state = self.state()?;
+        Ok(match self.dataset.as_str() {
+            "features" => state.feature_parquet,
+            _ => state.raw_cache,
+        })
+    }
+
+    /// The requested rows.
+    fn load(&self) -> Result<DataFrame> {
+        let state = self.state()?;
+        Ok(match self.dataset.as_str() {
+            "features" => {
+                data_storage::load_dataframe_range(&state.feature_parquet, self.from, self.to)?
+            }
+            _ => data_storage::klines_to_dataframe(&history::load_range(
+                &state.raw_cache,
+                &state.raw_journal,
+                self.from,
+                self.to,
+            )?)?,
+        })
+    }
+}
+
+/// Convert a polars frame into arrow-rs record batches via an IPC stream.
+fn to_record_batches(mut df: DataFrame) -> Result<(SchemaRef, Vec<RecordBatch>)> {
+    let mut buf = Vec::new();
+    IpcStreamWriter::new(&mut buf).finish(&mut df)?;
+    let reader = StreamReader::try_new(std::io::Cursor::new(buf), None)?;
+    let schema = reader.schema();
+    let batches = reader.collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok((schema, batches))
+}
+
+/// Arrow schema of a dataset without reading its rows.
+fn dataset_schema(path: &str) -> Result<SchemaRef> {
+    let empty = LazyFrame::scan_parquet(path.into(), Default::default())?
+        .limit(0)
+        .collect()?;
+    Ok(to_record_batches(empty)?.0)
+}
+
+fn flight_info(request: &DatasetRequest) -> Result<FlightInfo> {
+    let schema = dataset_schema(&request.path()?)?;
+    let ticket = serde_json::to_vec(request)?;
+    Ok(FlightInfo::new()
+        .try_with_schema(&schema)?
+        .with_descriptor(FlightDescriptor::new_cmd(ticket.clone()))
+        .with_endpoint(FlightEndpoint::new().with_ticket(Ticket::new(ticket)))
+        .with_total_records(-1))
+}
+
+/// Run blocking Parquet work off the async workers and map errors to gRPC statuses.
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T, Status> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .map_err(|e| Status::invalid_argument(format!("{:#}", e)))
+}
+
+struct Service;
+
+#[tonic::async_trait]
+impl FlightService for Service {
+    type HandshakeStream = ResponseStream<HandshakeResponse>;
+    type ListFlightsStream = ResponseStream<FlightInfo>;
+    type DoGetStream = ResponseStream<FlightData>;
+    type DoPutStream = ResponseStream<PutResult>;
+    type DoExchangeStream = ResponseStream<FlightData>;
+    type DoActionStream = ResponseStream<arrow_flight::Result>;
+    type ListActionsStream = ResponseStream<ActionType>;
+
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("no authentication required"))
+    }
+
+    async fn list_flights(
+        &self,
+        _request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        let infos = blocking(|| {
+            let mut infos = Vec::new();
+            for symbol in latest::symbols() {
+                for dataset in DATASETS {
+                    let request = DatasetRequest {
+                        symbol: symbol.clone(),
+                        dataset: dataset.to_string(),
+                        from: None,
+                        to: None,
+                    };
+                    // A dataset whose file is not written yet is simply not listed
+                    if let Ok(info) = flight_info(&request) {
+                        infos.push(Ok(info));
+                    }
+                }
+            }
+            Ok(infos)
+        })
+        .await?;
+        Ok(Response::new(Box::pin(stream::iter(infos))))
+    }
+
+    async fn get_flight_info(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let descriptor = request.into_inner();
+        let info =
+            blocking(move || flight_info(&DatasetRequest::from_descriptor(&descriptor)?)).await?;
+        Ok(Response::new(info))
+    }
+
+    async fn poll_flight_info(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<PollInfo>, Status> {
+        Err(Status::unimplemented("use get_flight_info"))
+    }
+
+    async fn get_schema(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        let descriptor = request.into_inner();
+        let schema = blocking(move || {
+            dataset_schema(&DatasetRequest::from_descriptor(&descriptor)?.path()?)
+        })
+        .await?;
+        let result = SchemaAsIpc::new(&schema, &IpcWriteOptions::default())
+            .try_into()
+            .map_err(|e: ArrowError| Status::internal(e.to_string()))?;
+        Ok(Response::new(result))
+    }
+
+    async fn do_get(
+        &self,
+        request: Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        let ticket = request.into_inner();
+        let (schema, batches) = blocking(move || {
+            let request = DatasetRequest::parse(&ticket.ticket)?;
+            to_record_batches(request.load()?)
+        })
+        .await?;
+
+        let stream = FlightDataEncoderBuilder::new()
+            .with_schema(schema)
+            .build(stream::iter(batches.into_iter().map(Ok)))
+            .map_err(Status::from);
+        Ok(Response::new(stream.boxed()))
+    }
+
+    async fn do_put(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("datasets are read-only"))
+    }
+
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("datasets are read-only"))
+    }
+
+    async fn do_action(
+        &self,
+        _request: Request<Action>,
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("no actions"))
+    }
+
+    async fn list_actions(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Ok(Response::new(Box::pin(stream::empty())))
+    }
+}
+
+/// Flight service added to the gRPC server in `grpc::spawn`.
+pub fn server() -> FlightServiceServer<impl FlightService> {
+    FlightServiceServer::new(Service)
+}
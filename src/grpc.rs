@@ -0,0 +1,213 @@
+//! gRPC service (`proto/market.proto`, package `market.v1`).
+//!
+//! Unary lookups read the same state as the REST API (`latest` registry, raw
+//! Parquet cache); the streaming RPCs subscribe to the update bus and filter
+//! by symbol. Clients in any language can be generated from the proto file.
+
+use crate::bus::{self, Update};
+use crate::kline::Kline;
+use crate::strategy::{self, Side};
+use crate::{history, latest, metrics};
+use anyhow::{Context, Result};
+use futures_util::stream::{self, Stream};
+use serde_json::{Map, Value};
+use std::pin::Pin;
+use tokio::sync::broadcast;
+use tonic::{Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("market.v1");
+}
+
+use proto::market_data_server::{MarketData, MarketDataServer};
+use proto::{Candle, CandleRangeRequest, FeatureRow, SymbolRequest};
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+fn candle(symbol: &str, interval: &str, k: &Kline) -> Candle {
+    Candle {
+        symbol: symbol.to_string(),
+        interval: interval.to_string(),
+        open_time: k.open_time,
+        open: k.open,
+        high: k.high,
+        low: k.low,
+        close: k.close,
+        volume: k.volume,
+        close_time: k.close_time,
+    }
+}
+
+fn feature_row(
+    symbol: &str,
+    interval: &str,
+    open_time: i64,
+    values: &Map<String, Value>,
+) -> FeatureRow {
+    FeatureRow {
+        symbol: symbol.to_string(),
+        interval: interval.to_string(),
+        open_time,
+        values: values
+            .iter()
+            .filter_map(|(name, v)| v.as_f64().map(|v| (name.clone(), v)))
+            .collect(),
+    }
+}
+
+fn signal(s: &strategy::Signal) -> proto::Signal {
+    let side = match s.side {
+        Side::Flat => proto::Side::Flat,
+        Side::Long => proto::Side::Long,
+        Side::Short => proto::Side::Short,
+    };
+    proto::Signal {
+        symbol: s.symbol.clone(),
+        interval: s.interval.clone(),
+        strategy: s.strategy.clone(),
+        side: side as i32,
+        open_time: s.open_time,
+        price: s.price,
+    }
+}
+
+/// Stream bus updates for `symbol` (all symbols when empty), mapped by `map`.
+fn bus_stream<T, F>(symbol: String, map: F) -> ResponseStream<T>
+where
+    T: Send + 'static,
+    F: Fn(Update) -> Option<T> + Send + 'static,
+{
+    let state = (bus::subscribe(), symbol, map);
+    Box::pin(stream::unfold(state, |(mut rx, symbol, map)| async move {
+        loop {
+            match rx.recv().await {
+                Ok(update) => {
+                    if !symbol.is_empty() && !update.symbol().eq_ignore_ascii_case(&symbol) {
+                        continue;
+                    }
+                    if let Some(item) = map(update) {
+                        return Some((Ok(item), (rx, symbol, map)));
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    metrics::counter_inc("grpc_skipped_updates_total", &[], skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }))
+}
+
+struct Service;
+
+#[tonic::async_trait]
+impl MarketData for Service {
+    type GetCandlesStream = ResponseStream<Candle>;
+    type StreamCandlesStream = ResponseStream<Candle>;
+    type StreamFeaturesStream = ResponseStream<FeatureRow>;
+    type StreamSignalsStream = ResponseStream<proto::Signal>;
+
+    async fn get_latest(
+        &self,
+        request: Request<SymbolRequest>,
+    ) -> Result<Response<FeatureRow>, Status> {
+        let symbol = request.into_inner().symbol;
+        let state = latest::get(&symbol)
+            .ok_or_else(|| Status::not_found(format!("No data for symbol {}", symbol)))?;
+        let open_time = state
+            .features
+            .get("open_time")
+            .and_then(Value::as_i64)
+            .unwrap_or_default();
+        Ok(Response::new(feature_row(
+            &symbol.to_uppercase(),
+            &state.interval,
+            open_time,
+            &state.features,
+        )))
+    }
+
+    async fn get_candles(
+        &self,
+        request: Request<CandleRangeRequest>,
+    ) -> Result<Response<Self::GetCandlesStream>, Status> {
+        let range = request.into_inner();
+        let state = latest::get(&range.symbol)
+            .ok_or_else(|| Status::not_found(format!("No data for symbol {}", range.symbol)))?;
+        let from = (range.from != 0).then_some(range.from);
+        let to = (range.to != 0).then_some(range.to);
+        let (cache, journal) = (state.raw_cache.clone(), state.raw_journal.clone());
+        let klines =
+            tokio::task::spawn_blocking(move || history::load_range(&cache, &journal, from, to))
+                .await
+                .map_err(|e| Status::internal(e.to_string()))?
+                .map_err(|e| Status::internal(e.to_string()))?;
+
+        let symbol = range.symbol.to_uppercase();
+        let candles: Vec<_> = klines
+            .iter()
+            .map(|k| Ok(candle(&symbol, &state.interval, k)))
+            .collect();
+        Ok(Response::new(Box::pin(stream::iter(candles))))
+    }
+
+    async fn stream_candles(
+        &self,
+        request: Request<SymbolRequest>,
+    ) -> Result<Response<Self::StreamCandlesStream>, Status> {
+        let stream = bus_stream(request.into_inner().symbol, |update| match update {
+            Update::Candle {
+                symbol,
+                interval,
+                kline,
+            } => Some(candle(&symbol, &interval, &kline)),
+            _ => None,
+        });
+        Ok(Response::new(stream))
+    }
+
+    async fn stream_features(
+        &self,
+        request: Request<SymbolRequest>,
+    ) -> Result<Response<Self::StreamFeaturesStream>, Status> {
+        let stream = bus_stream(request.into_inner().symbol, |update| match update {
+            Update::Features {
+                symbol,
+                interval,
+                open_time,
+                values,
+            } => Some(feature_row(&symbol, &interval, open_time, &values)),
+            _ => None,
+        });
+        Ok(Response::new(stream))
+    }
+
+    async fn stream_signals(
+        &self,
+        request: Request<SymbolRequest>,
+    ) -> Result<Response<Self::StreamSignalsStream>, Status> {
+        let stream = bus_stream(request.into_inner().symbol, |update| match update {
+            Update::Signal(s) => Some(signal(&s)),
+            _ => None,
+        });
+        Ok(Response::new(stream))
+    }
+}
+
+/// Bind `addr` and serve the gRPC service in a background task.
+pub async fn spawn(addr: &str) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind gRPC server on {}", addr))?;
+    println!("gRPC server listening on {}", addr);
+    let incoming = tonic::transport::server::TcpIncoming::from(listener);
+    let router = tonic::transport::Server::builder().add_service(MarketDataServer::new(Service));
+    #[cfg(feature = "flight")]
+    let router = router.add_service(crate::flight::server());
+    tokio::spawn(async move {
+        if let Err(e) = router.serve_with_incoming(incoming).await {
+            eprintln!("gRPC server error: {}", e);
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,49 @@
+//! Liveness signals for external supervisors.
+//!
+//! After every fully processed candle the heartbeat file is rewritten (so its
+//! mtime moves; content is `<unix_ms> <candle open_time>`), and when running
+//! under systemd (`NOTIFY_SOCKET` set) a `WATCHDOG=1` notification is sent.
+//! A hung process stops both, even if its TCP connection still looks alive.
+
+use crate::clock;
+
+/// Rewrite the heartbeat file. Failures are logged, never fatal.
+pub fn beat(path: &str, open_time: i64) {
+    let content = format!("{} {}\n", clock::now_ms(), open_time);
+    if let Err(e) = std::fs::write(path, content) {
+        eprintln!("Warning: failed to write heartbeat {}: {}", path, e);
+    }
+    sd_notify("WATCHDOG=1");
+}
+
+/// Tell systemd the service finished starting up.
+pub fn ready() {
+    sd_notify("READY=1");
+}
+
+/// Send a state string to the systemd notification socket, if any.
+#[cfg(unix)]
+fn sd_notify(state: &str) {
+    use std::os::unix::net::UnixDatagram;
+
+    let Some(socket_path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    let result = UnixDatagram::unbound().and_then(|socket| {
+        let path = socket_path.to_string_lossy();
+        // A leading '@' denotes a Linux abstract-namespace socket
+        #[cfg(target_os = "linux")]
+        if let Some(name) = path.strip_prefix('@') {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            return socket.send_to_addr(state.as_bytes(), &addr);
+        }
+        socket.send_to(state.as_bytes(), path.as_ref())
+    });
+    if let Err(e) = result {
+        eprintln!("Warning: sd_notify({}) failed: {}", state, e);
+    }
+}
+
+#[cfg(not(unix))]
+fn sd_notify(_state: &str) {}
@@ -0,0 +1,253 @@
+//! Historical candle window on disk: a Parquet snapshot of the newest candles,
+//! a journal of the candles closed since that snapshot, and an append-only raw
+//! CSV log, refreshed from the exchange when stale.
+//!
+//! The live stream appends one row per candle to the journal and only rewrites
+//! the snapshot periodically (see [`HistoryStore::snapshot`]); readers merge
+//! the two with [`HistoryStore::load_cached`] or [`load_range`].
+//!
+//! A loaded window too short to warm every EMA timeframe (see
+//! [`ema::warmup_candles`]) is extended with older candles from the exchange.
+
+use crate::data_storage;
+use crate::error::{Error, Result};
+use crate::exchange::{self, MarketDataSource};
+use crate::features::ema;
+use crate::kline::Kline;
+use crate::query::{self, TimeRange};
+use chrono::{Duration, Utc};
+use polars::prelude::col;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Where the historical window lives and how it is refreshed.
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    /// Parquet snapshot of the newest `count` candles.
+    pub cache_file: String,
+    /// Candles closed after the snapshot was written (see `data_storage::append_kline_to_journal`).
+    pub journal_file: String,
+    /// Append-only CSV log of raw candles.
+    pub csv_file: String,
+    /// Number of candles kept in the window.
+    pub count: usize,
+    /// A cache older than this is re-fetched by `load_or_fetch`.
+    pub max_age: Duration,
+    /// Where missing candles are fetched from.
+    pub source: Arc<dyn MarketDataSource>,
+}
+
+impl HistoryStore {
+    /// Load cached historical data if it exists and is fresh; otherwise fetch from the exchange.
+    pub async fn load_or_fetch(&self, symbol: &str, interval: &str) -> Result<Vec<Kline>> {
+        let cache_file = self.cache_file.as_str();
+        let csv_file = self.csv_file.as_str();
+
+        let should_fetch = if Path::new(cache_file).exists() {
+            // The journal is newer than the snapshot whenever it exists
+            let newest = [cache_file, self.journal_file.as_str()]
+                .iter()
+                .filter_map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+                .max()
+                .ok_or_else(|| {
+                    Error::Storage(
+                        format!("Cannot read modification time of {}", cache_file).into(),
+                    )
+                })?;
+            let age = Utc::now().signed_duration_since(chrono::DateTime::<Utc>::from(newest));
+            age > self.max_age
+        } else {
+            true
+        };
+
+        let klines = if should_fetch {
+            println!(
+                "Fetching latest {} {} candles from {}...",
+                self.count,
+                interval,
+                self.source.name()
+            );
+            let klines = self
+                .source
+                .fetch_latest_klines(symbol, interval, self.count)
+                .await?;
+            println!("Fetched {} klines. Saving to cache...", klines.len());
+            self.snapshot(&klines)?;
+            klines
+        } else {
+            println!("Loading cached historical data from {}", cache_file);
+            self.load_cached()?
+        };
+
+        // Write initial CSV only if the file does NOT already exist
+        if !Path::new(csv_file).exists() {
+            println!("Writing initial historical data to CSV: {}", csv_file);
+            if let Err(e) = data_storage::save_klines_to_csv(&klines, csv_file) {
+                eprintln!("Warning: failed to write initial CSV: {}", e);
+            }
+        } else {
+            println!(
+                "CSV file {} already exists, skipping initial write.",
+                csv_file
+            );
+        }
+
+        self.extend_for_warmup(symbol, interval, klines).await
+    }
+
+    /// Resume from the raw window snapshot regardless of its age.
+    /// Only the gap between the last stored candle and now is fetched from the exchange;
+    /// the backfilled candles are appended to the raw CSV log and the snapshot is rewritten.
+    pub async fn resume(&self, symbol: &str, interval: &str) -> Result<Vec<Kline>> {
+        let cache_file = self.cache_file.as_str();
+        println!("Resuming from snapshot {}", cache_file);
+        let mut klines = self.load_cached()?;
+
+        let Some(last) = klines.last() else {
+            println!("Snapshot is empty, falling back to a full load.");
+            return self.load_or_fetch(symbol, interval).await;
+        };
+        let last_open_time = last.open_time;
+        let gap_start = last.close_time + 1;
+
+        let now = Utc::now().timestamp_millis();
+        let gap: Vec<Kline> = self
+            .source
+            .fetch_klines_range(symbol, interval, gap_start, now)
+            .await?
+            .into_iter()
+            // Skip anything already stored and the candle that is still forming
+            .filter(|k| k.open_time > last_open_time && k.close_time < now)
+            .collect();
+
+        if gap.is_empty() {
+            println!("Snapshot is up to date, no backfill needed.");
+            return self.extend_for_warmup(symbol, interval, klines).await;
+        }
+
+        println!("Backfilled {} missing {} candles.", gap.len(), interval);
+        for k in &gap {
+            if let Err(e) = data_storage::append_kline_to_csv(k, &self.csv_file) {
+                eprintln!("Warning: failed to append backfilled kline to CSV: {}", e);
+            }
+        }
+        klines.extend(gap);
+
+        if klines.len() > self.count {
+            klines = klines.split_off(klines.len() - self.count);
+        }
+
+        self.snapshot(&klines)?;
+        self.extend_for_warmup(symbol, interval, klines).await
+    }
+
+    /// Prepend older candles to `klines` (oldest first) until the window
+    /// holds the [`ema::warmup_candles`] of `interval`, as far as the
+    /// exchange has them, and rewrite the snapshot. The raw CSV log is left
+    /// alone: it records what was collected, in order.
+    async fn extend_for_warmup(
+        &self,
+        symbol: &str,
+        interval: &str,
+        klines: Vec<Kline>,
+    ) -> Result<Vec<Kline>> {
+        let interval_ms = exchange::interval_ms(interval)?;
+        let required = ema::warmup_candles(interval_ms).min(self.count);
+        let Some(first) = klines.first().map(|k| k.open_time) else {
+            return Ok(klines);
+        };
+        if klines.len() >= required {
+            return Ok(klines);
+        }
+        let missing = required - klines.len();
+        println!(
+            "Only {} {} candles loaded, {} needed to warm every EMA timeframe; fetching {} older ones...",
+            klines.len(),
+            interval,
+            required,
+            missing
+        );
+        let start = first - missing as i64 * interval_ms;
+        let older: Vec<Kline> = match self
+            .source
+            .fetch_klines_range(symbol, interval, start, first - 1)
+            .await
+        {
+            Ok(older) => older.into_iter().filter(|k| k.open_time < first).collect(),
+            Err(e) => {
+                eprintln!("Warning: fetching older {} candles failed: {}", interval, e);
+                return Ok(klines);
+            }
+        };
+        if older.is_empty() {
+            return Ok(klines);
+        }
+        println!("Fetched {} older {} candles.", older.len(), interval);
+        let mut extended = older;
+        extended.extend(klines);
+        if extended.len() > self.count {
+            extended = extended.split_off(extended.len() - self.count);
+        }
+        self.snapshot(&extended)?;
+        Ok(extended)
+    }
+
+    /// The snapshot plus every journaled candle after it, trimmed to the newest `count`.
+    pub fn load_cached(&self) -> Result<Vec<Kline>> {
+        let mut klines = data_storage::load_klines_from_parquet(&self.cache_file)?;
+        let journal = data_storage::load_journal(&self.journal_file)?;
+        if !journal.is_empty() {
+            let last_open_time = klines.last().map_or(i64::MIN, |k| k.open_time);
+            let before = klines.len();
+            klines.extend(journal.into_iter().filter(|k| k.open_time > last_open_time));
+            println!(
+                "Replayed {} journaled candles from {}",
+                klines.len() - before,
+                self.journal_file
+            );
+        }
+        if klines.len() > self.count {
+            klines = klines.split_off(klines.len() - self.count);
+        }
+        Ok(klines)
+    }
+
+    /// Rewrite the snapshot with `klines` and empty the journal. The snapshot
+    /// is written to a temporary file first, so a crash never leaves it torn.
+    pub fn snapshot(&self, klines: &[Kline]) -> Result<()> {
+        let tmp = format!("{}.tmp", self.cache_file);
+        data_storage::save_klines_to_parquet(klines, &tmp)?;
+        std::fs::rename(&tmp, &self.cache_file)?;
+        match std::fs::remove_file(&self.journal_file) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Stored candles with `open_time` in `[from, to]` (ms, inclusive): the
+/// snapshot in `cache_file` merged with the newer candles of `journal_file`.
+pub fn load_range(
+    cache_file: &str,
+    journal_file: &str,
+    from: Option<i64>,
+    to: Option<i64>,
+) -> Result<Vec<Kline>> {
+    let mut klines = data_storage::load_klines_range(cache_file, from, to)?;
+    // Only candles after the snapshot count; the journal may still hold older ones
+    let last_open_time = query::scan_range(cache_file, TimeRange::all())?
+        .select([col("open_time").max()])
+        .collect()?
+        .column("open_time")?
+        .i64()?
+        .get(0)
+        .unwrap_or(i64::MIN);
+    klines.extend(
+        data_storage::load_journal(journal_file)?
+            .into_iter()
+            .filter(|k| k.open_time > last_open_time)
+            .filter(|k| from.is_none_or(|from| k.open_time >= from))
+            .filter(|k| to.is_none_or(|to| k.open_time <= to)),
+    );
+    Ok(klines)
+}
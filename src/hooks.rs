@@ -0,0 +1,190 @@
+//! Callbacks on the live pipeline's bar-close events, so custom integrations
+//! (notifications, order routing, extra sinks) plug into the collector
+//! without forking the candle pipeline.
+//!
+//! Four points are exposed:
+//!
+//! - [`on_candle_closed`] – a closed candle entered the window (also when its
+//!   features were skipped by the backpressure policy);
+//! - [`on_features_ready`] – its feature row was computed and saved;
+//! - [`on_signal`] – a strategy or an external alert produced a [`Signal`];
+//! - [`on_portfolio_ready`] – the basket row of a candle time was finished
+//!   (several symbols with `[portfolio]`), e.g. for allocation strategies.
+//!
+//! Hooks are registered once, before the pipelines start:
+//!
+//! ```no_run
+//! use binance_streamer::hooks;
+//!
+//! hooks::on_signal("notify", |signal| async move {
+//!     println!("{} {} {}", signal.strategy, signal.side.as_str(), signal.symbol);
+//!     Ok(())
+//! });
+//! ```
+//!
+//! The pipeline collects the calls of a point with [`candle_closed`],
+//! [`features_ready`], [`signal`] or [`portfolio_ready`] and runs each as its own task, so a slow
+//! callback delays neither the next candle nor the other hooks; a failing
+//! one is reported and stays registered.
+
+use crate::features::portfolio::PortfolioRow;
+use crate::kline::Kline;
+use crate::strategy::Signal;
+use anyhow::Result;
+use futures_util::future::{BoxFuture, FutureExt};
+use polars::prelude::DataFrame;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// A closed candle of one stream.
+#[derive(Debug, Clone)]
+pub struct CandleClosed {
+    pub symbol: String,
+    pub interval: String,
+    pub kline: Kline,
+}
+
+/// The feature row of a closed candle.
+#[derive(Debug, Clone)]
+pub struct FeaturesReady {
+    pub symbol: String,
+    pub interval: String,
+    pub kline: Kline,
+    /// The candle's row of the feature frame strategies see.
+    pub row: DataFrame,
+}
+
+/// A finished row of the basket features.
+#[derive(Debug, Clone)]
+pub struct PortfolioReady {
+    pub interval: String,
+    pub row: PortfolioRow,
+    /// The kept basket rows, up to the newest finished one (see
+    /// [`crate::features::PortfolioFeatures::frame`]).
+    pub frame: DataFrame,
+    /// The newest feature row of every member, by symbol.
+    pub members: BTreeMap<String, Map<String, Value>>,
+}
+
+pub type HookFuture = BoxFuture<'static, Result<()>>;
+
+type Hook<T> = Arc<dyn Fn(Arc<T>) -> HookFuture + Send + Sync>;
+type Registry<T> = RwLock<Vec<(String, Hook<T>)>>;
+
+/// One pending run of a registered hook.
+pub struct HookCall {
+    /// `candle_closed`, `features_ready`, `signal` or `portfolio_ready`.
+    pub point: &'static str,
+    /// Name the hook was registered with.
+    pub hook: String,
+    pub future: HookFuture,
+}
+
+fn candle_hooks() -> &'static Registry<CandleClosed> {
+    static HOOKS: OnceLock<Registry<CandleClosed>> = OnceLock::new();
+    HOOKS.get_or_init(Default::default)
+}
+
+fn feature_hooks() -> &'static Registry<FeaturesReady> {
+    static HOOKS: OnceLock<Registry<FeaturesReady>> = OnceLock::new();
+    HOOKS.get_or_init(Default::default)
+}
+
+fn signal_hooks() -> &'static Registry<Signal> {
+    static HOOKS: OnceLock<Registry<Signal>> = OnceLock::new();
+    HOOKS.get_or_init(Default::default)
+}
+
+fn portfolio_hooks() -> &'static Registry<PortfolioReady> {
+    static HOOKS: OnceLock<Registry<PortfolioReady>> = OnceLock::new();
+    HOOKS.get_or_init(Default::default)
+}
+
+fn register<T, F, Fut>(registry: &Registry<T>, name: &str, hook: F)
+where
+    F: Fn(Arc<T>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let hook: Hook<T> = Arc::new(move |event| hook(event).boxed());
+    registry.write().unwrap().push((name.to_string(), hook));
+}
+
+fn calls<T>(point: &'static str, registry: &Registry<T>, event: T) -> Vec<HookCall> {
+    let hooks = registry.read().unwrap();
+    if hooks.is_empty() {
+        return Vec::new();
+    }
+    let event = Arc::new(event);
+    hooks
+        .iter()
+        .map(|(name, hook)| HookCall {
+            point,
+            hook: name.clone(),
+            future: hook(event.clone()),
+        })
+        .collect()
+}
+
+/// Run `hook` on every closed candle.
+pub fn on_candle_closed<F, Fut>(name: &str, hook: F)
+where
+    F: Fn(Arc<CandleClosed>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    register(candle_hooks(), name, hook);
+}
+
+/// Run `hook` on every feature row.
+pub fn on_features_ready<F, Fut>(name: &str, hook: F)
+where
+    F: Fn(Arc<FeaturesReady>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    register(feature_hooks(), name, hook);
+}
+
+/// Run `hook` on every signal.
+pub fn on_signal<F, Fut>(name: &str, hook: F)
+where
+    F: Fn(Arc<Signal>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    register(signal_hooks(), name, hook);
+}
+
+/// Run `hook` on every finished basket row.
+pub fn on_portfolio_ready<F, Fut>(name: &str, hook: F)
+where
+    F: Fn(Arc<PortfolioReady>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    register(portfolio_hooks(), name, hook);
+}
+
+/// Whether any feature-row hook is registered, so the pipeline can skip
+/// building the row otherwise.
+pub fn wants_features() -> bool {
+    !feature_hooks().read().unwrap().is_empty()
+}
+
+/// The calls of the candle hooks for `event`.
+pub fn candle_closed(event: CandleClosed) -> Vec<HookCall> {
+    calls("candle_closed", candle_hooks(), event)
+}
+
+/// The calls of the feature-row hooks for `event`.
+pub fn features_ready(event: FeaturesReady) -> Vec<HookCall> {
+    calls("features_ready", feature_hooks(), event)
+}
+
+/// The calls of the signal hooks for `signal`.
+pub fn signal(signal: Signal) -> Vec<HookCall> {
+    calls("signal", signal_hooks(), signal)
+}
+
+/// The calls of the basket hooks for `event`.
+pub fn portfolio_ready(event: PortfolioReady) -> Vec<HookCall> {
+    calls("portfolio_ready", portfolio_hooks(), event)
+}
@@ -0,0 +1,416 @@
+//! Collector/worker process split.
+//!
+//! A collector (`--role collector`) only owns the Binance WebSocket and serves
+//! every closed candle to worker processes over a Unix or TCP socket; workers
+//! (`--role worker`) run the feature pipeline and persistence on what they
+//! receive. A slow or crashed worker therefore never stalls the connection.
+//!
+//! The protocol is newline-delimited JSON. A worker opens with
+//! `{"since": <open_time>}` (its newest stored candle); the collector replays
+//! the candles after it from a backlog of the last `BACKLOG` and then streams
+//! new ones as `{"symbol", "interval", "open_time", "open", ..., "close_time"}`.
+//! A worker that falls behind is disconnected and catches up on reconnect.
+
+use crate::config::{Config, RECORDING_FILE};
+use crate::events::{self, Event};
+use crate::kline::Kline;
+use crate::live_stream::{self, CandleSender, MAX_BACKOFF, MIN_BACKOFF};
+use crate::metrics;
+use crate::recorder::Recorder;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{broadcast, mpsc};
+
+/// Candles kept by the collector for workers that reconnect.
+const BACKLOG: usize = 1000;
+
+/// Where collector and workers meet.
+enum IpcAddr {
+    Unix(String),
+    Tcp(String),
+}
+
+impl IpcAddr {
+    fn parse(addr: &str) -> Result<Self> {
+        if let Some(path) = addr.strip_prefix("unix:") {
+            Ok(IpcAddr::Unix(path.to_string()))
+        } else if let Some(host) = addr.strip_prefix("tcp:") {
+            Ok(IpcAddr::Tcp(host.to_string()))
+        } else {
+            bail!(
+                "Unknown IPC address '{}' (expected \"unix:/path\" or \"tcp:host:port\")",
+                addr
+            )
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Hello {
+    since: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WireCandle {
+    symbol: String,
+    interval: String,
+    open_time: i64,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+    close_time: i64,
+}
+
+impl WireCandle {
+    fn new(symbol: &str, interval: &str, k: &Kline) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            interval: interval.to_string(),
+            open_time: k.open_time,
+            open: k.open,
+            high: k.high,
+            low: k.low,
+            close: k.close,
+            volume: k.volume,
+            close_time: k.close_time,
+        }
+    }
+
+    fn kline(&self) -> Kline {
+        Kline {
+            open_time: self.open_time,
+            open: self.open,
+            high: self.high,
+            low: self.low,
+            close: self.close,
+            volume: self.volume,
+            close_time: self.close_time,
+        }
+    }
+}
+
+/// Recent candles plus the live feed, shared by every worker connection.
+struct Hub {
+    symbol: String,
+    interval: String,
+    backlog: Mutex<VecDeque<Kline>>,
+    live: broadcast::Sender<Kline>,
+}
+
+impl Hub {
+    fn push(&self, kline: Kline) {
+        let mut backlog = self.backlog.lock().unwrap();
+        if backlog.len() == BACKLOG {
+            backlog.pop_front();
+        }
+        backlog.push_back(kline.clone());
+        drop(backlog);
+        // send only fails when no worker is connected
+        let _ = self.live.send(kline);
+    }
+
+    fn line(&self, kline: &Kline) -> Result<String> {
+        let mut text =
+            serde_json::to_string(&WireCandle::new(&self.symbol, &self.interval, kline))?;
+        text.push('\n');
+        Ok(text)
+    }
+}
+
+/// Run as a collector: serve closed candles on `[ipc] addr` until the
+/// WebSocket source fails.
+pub async fn serve(stream_type: &str, config: &Config) -> Result<()> {
+    let interval = live_stream::stream_interval(stream_type);
+    if interval.is_empty() {
+        bail!("--role collector needs a kline stream (m5 or m15)");
+    }
+    let addr = config.ipc.addr.as_deref().unwrap_or_default();
+    let hub = Arc::new(Hub {
+        symbol: config.symbol.clone(),
+        interval: interval.to_string(),
+        backlog: Mutex::new(VecDeque::with_capacity(BACKLOG)),
+        live: broadcast::channel(BACKLOG).0,
+    });
+    listen(&IpcAddr::parse(addr)?, hub.clone()).await?;
+    println!("Collector serving {} candles on {}", interval, addr);
+
+    let recorder = if config.record {
+        let path = config.resolve_path(stream_type, interval, RECORDING_FILE);
+        if let Some(parent) = Path::new(&path)
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        Some(Recorder::spawn(&path)?)
+    } else {
+        None
+    };
+    let (candles, mut received) = mpsc::channel(BACKLOG);
+    let source = tokio::spawn(live_stream::collect(
+        stream_type.to_string(),
+        config.clone(),
+        candles,
+        recorder,
+    ));
+    while let Some((kline, _)) = received.recv().await {
+        hub.push(kline);
+        metrics::counter_inc("ipc_candles_collected_total", &[], 1);
+    }
+    source.await?
+}
+
+/// Bind the collector socket and accept workers in a background task.
+async fn listen(addr: &IpcAddr, hub: Arc<Hub>) -> Result<()> {
+    match addr {
+        IpcAddr::Tcp(host) => {
+            let listener = tokio::net::TcpListener::bind(host)
+                .await
+                .with_context(|| format!("Failed to bind collector socket on {}", host))?;
+            tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, peer)) => {
+                            tokio::spawn(serve_worker(stream, peer.to_string(), hub.clone()));
+                        }
+                        Err(e) => eprintln!("Collector accept error: {}", e),
+                    }
+                }
+            });
+        }
+        #[cfg(unix)]
+        IpcAddr::Unix(path) => {
+            // A socket file left over from a previous run would make bind fail
+            let _ = std::fs::remove_file(path);
+            let listener = tokio::net::UnixListener::bind(path)
+                .with_context(|| format!("Failed to bind collector socket {}", path))?;
+            tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => {
+                            tokio::spawn(serve_worker(stream, "unix".to_string(), hub.clone()));
+                        }
+                        Err(e) => eprintln!("Collector accept error: {}", e),
+                    }
+                }
+            });
+        }
+        #[cfg(not(unix))]
+        IpcAddr::Unix(_) => bail!("unix: IPC addresses are only supported on Unix"),
+    }
+    Ok(())
+}
+
+/// Replay the backlog after the worker's `since`, then forward live candles.
+async fn serve_worker<S>(stream: S, peer: String, hub: Arc<Hub>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (read, mut write) = tokio::io::split(stream);
+    let mut lines = BufReader::new(read).lines();
+    let hello: Hello = match lines.next_line().await {
+        Ok(Some(line)) => match serde_json::from_str(&line) {
+            Ok(hello) => hello,
+            Err(e) => {
+                eprintln!("Worker {} sent an invalid hello: {}", peer, e);
+                return;
+            }
+        },
+        _ => return,
+    };
+    println!(
+        "Worker {} connected, replaying candles after {}",
+        peer, hello.since
+    );
+    metrics::counter_inc("ipc_worker_connections_total", &[], 1);
+
+    // Subscribe before taking the backlog so no candle falls in between
+    let mut live = hub.live.subscribe();
+    let replay: Vec<Kline> = hub
+        .backlog
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|k| k.open_time > hello.since)
+        .cloned()
+        .collect();
+
+    let mut last_sent = hello.since;
+    let reason = 'session: {
+        for kline in replay {
+            if let Err(e) = send(&mut write, &hub, &kline).await {
+                break 'session e.to_string();
+            }
+            last_sent = kline.open_time;
+        }
+        loop {
+            tokio::select! {
+                received = live.recv() => match received {
+                    Ok(kline) if kline.open_time > last_sent => {
+                        if let Err(e) = send(&mut write, &hub, &kline).await {
+                            break 'session e.to_string();
+                        }
+                        last_sent = kline.open_time;
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        break 'session format!("lagging, {} candles behind", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break 'session "shutdown".to_string(),
+                },
+                line = lines.next_line() => match line {
+                    Ok(Some(_)) => {}
+                    Ok(None) => break 'session "closed by worker".to_string(),
+                    Err(e) => break 'session e.to_string(),
+                },
+            }
+        }
+    };
+    println!("Worker {} disconnected: {}", peer, reason);
+}
+
+async fn send(write: &mut (impl AsyncWrite + Unpin), hub: &Hub, kline: &Kline) -> Result<()> {
+    write.write_all(hub.line(kline)?.as_bytes()).await?;
+    write.flush().await?;
+    metrics::counter_inc("ipc_candles_sent_total", &[], 1);
+    Ok(())
+}
+
+/// Run as a worker's candle source: connect to the collector at `addr` and
+/// send every candle newer than `since` to `candles`. Reconnects with
+/// exponential backoff; returns an error if the collector streams another
+/// symbol or interval.
+pub async fn receive(
+    addr: String,
+    symbol: String,
+    interval: String,
+    mut since: i64,
+    candles: CandleSender,
+) -> Result<()> {
+    let target = IpcAddr::parse(&addr)?;
+    let stream = format!("ipc:{}", addr);
+    let mut backoff = MIN_BACKOFF;
+
+    loop {
+        let session = match &target {
+            IpcAddr::Tcp(host) => match tokio::net::TcpStream::connect(host).await {
+                Ok(conn) => {
+                    Some(session(conn, &stream, &symbol, &interval, &mut since, &candles).await)
+                }
+                Err(e) => {
+                    eprintln!("Connection to collector {} failed: {}", addr, e);
+                    None
+                }
+            },
+            #[cfg(unix)]
+            IpcAddr::Unix(path) => match tokio::net::UnixStream::connect(path).await {
+                Ok(conn) => {
+                    Some(session(conn, &stream, &symbol, &interval, &mut since, &candles).await)
+                }
+                Err(e) => {
+                    eprintln!("Connection to collector {} failed: {}", addr, e);
+                    None
+                }
+            },
+            #[cfg(not(unix))]
+            IpcAddr::Unix(_) => bail!("unix: IPC addresses are only supported on Unix"),
+        };
+
+        if let Some(outcome) = session {
+            let Some(Disconnect {
+                reason,
+                received_any,
+            }) = outcome?
+            else {
+                // The pipeline stopped listening
+                return Ok(());
+            };
+            println!("Disconnected from collector {}: {}", addr, reason);
+            events::emit(Event::Disconnected {
+                symbol: symbol.clone(),
+                stream: stream.clone(),
+                reason,
+            });
+            if received_any {
+                backoff = MIN_BACKOFF;
+            }
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+struct Disconnect {
+    reason: String,
+    received_any: bool,
+}
+
+/// One connection to the collector. `Ok(None)` when `candles` is closed.
+async fn session<S>(
+    conn: S,
+    stream: &str,
+    symbol: &str,
+    interval: &str,
+    since: &mut i64,
+    candles: &CandleSender,
+) -> Result<Option<Disconnect>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (read, mut write) = tokio::io::split(conn);
+    let mut hello = serde_json::to_string(&Hello { since: *since })?;
+    hello.push('\n');
+    if let Err(e) = write.write_all(hello.as_bytes()).await {
+        return Ok(Some(Disconnect {
+            reason: e.to_string(),
+            received_any: false,
+        }));
+    }
+    println!("Connected to collector, requesting candles after {}", since);
+    events::emit(Event::Connected {
+        symbol: symbol.to_string(),
+        stream: stream.to_string(),
+    });
+
+    let mut lines = BufReader::new(read).lines();
+    let mut received_any = false;
+    let reason = loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break "closed by collector".to_string(),
+            Err(e) => break e.to_string(),
+        };
+        let received = Instant::now();
+        received_any = true;
+        let candle: WireCandle = serde_json::from_str(&line)
+            .with_context(|| format!("Invalid candle from collector: {}", line))?;
+        if !candle.symbol.eq_ignore_ascii_case(symbol) || candle.interval != interval {
+            bail!(
+                "Collector streams {} {}, but this worker is configured for {} {}",
+                candle.symbol,
+                candle.interval,
+                symbol,
+                interval
+            );
+        }
+        if candle.open_time <= *since {
+            continue;
+        }
+        *since = candle.open_time;
+        metrics::counter_inc("ipc_candles_received_total", &[], 1);
+        if candles.send((candle.kline(), received)).await.is_err() {
+            return Ok(None);
+        }
+    };
+    Ok(Some(Disconnect {
+        reason,
+        received_any,
+    }))
+}
@@ -0,0 +1,403 @@
+//! `journal-report` subcommand: performance review of the signal audit trail.
+//!
+//! Strategies do not execute orders, so their audited signals are the trade
+//! log. Each strategy's signals on a stream become paper trades under the
+//! model of the summary reports – one unit long or short from a signal's
+//! price until the next signal of another side, without fees – and the
+//! stored raw candles in between give each trade's excursions:
+//!
+//! - MAE (maximum adverse excursion) – how far the price went against the
+//!   trade, in percent of the entry price;
+//! - MFE (maximum favorable excursion) – how far it went in its favor.
+//!
+//! Closed trades are broken down by strategy, by symbol and by the market
+//! regime of the entry row (`regime` column, with `[regime]` enabled):
+//! win rate, average win and loss, expectancy (mean return per trade),
+//! holding time and the MAE/MFE distributions.
+//!
+//! ```text
+//! cargo run --release journal-report m15[,h1] [--input signal_audit.jsonl]
+//!                                             [--from 2025-01-01T00:00:00Z] [--to ...]
+//!                                             [--output journal.md|journal.html]
+//! ```
+
+use crate::backfill::CandleReader;
+use crate::config::{CliArgs, Config};
+use crate::report::{self, Table};
+use crate::{live_stream, timezone, utils};
+use anyhow::{bail, Context, Result};
+use binance_streamer::kline::Kline;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Candles read from a raw log per chunk.
+const CANDLE_CHUNK: usize = 100_000;
+
+/// Statistics of a breakdown row, after its key column.
+const STAT_HEADERS: [&str; 12] = [
+    "Trades",
+    "Win %",
+    "Avg win %",
+    "Avg loss %",
+    "Expectancy %",
+    "Total %",
+    "Hold avg (h)",
+    "Hold p50 (h)",
+    "MAE p50 %",
+    "MAE p90 %",
+    "MFE p50 %",
+    "MFE p90 %",
+];
+
+/// The fields of an audit record this report reads.
+#[derive(Debug, Deserialize)]
+struct AuditLine {
+    symbol: String,
+    interval: String,
+    strategy: String,
+    side: String,
+    open_time: i64,
+    price: f64,
+    #[serde(default)]
+    features: Map<String, Value>,
+}
+
+/// A paper trade between two signals.
+#[derive(Debug)]
+struct Trade {
+    symbol: String,
+    interval: String,
+    strategy: String,
+    /// `regime` of the entry row, if it had one.
+    regime: Option<String>,
+    /// 1 long, -1 short.
+    direction: f64,
+    entry_time: i64,
+    entry_price: f64,
+    exit_time: i64,
+    return_pct: f64,
+    mae_pct: Option<f64>,
+    mfe_pct: Option<f64>,
+}
+
+fn direction(side: &str) -> Result<f64> {
+    Ok(match side {
+        "long" => 1.0,
+        "short" => -1.0,
+        "flat" => 0.0,
+        other => bail!("unknown side '{}'", other),
+    })
+}
+
+fn read_audit(path: &str, from: Option<i64>, to: Option<i64>) -> Result<Vec<AuditLine>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path))?;
+    let mut records = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<AuditLine>(&line) {
+            Ok(record) => {
+                if from.is_none_or(|from| record.open_time >= from)
+                    && to.is_none_or(|to| record.open_time < to)
+                {
+                    records.push(record);
+                }
+            }
+            Err(e) => eprintln!("Warning: skipping audit line in {}: {}", path, e),
+        }
+    }
+    Ok(records)
+}
+
+/// Paper trades of the signals, and the number of positions still open.
+fn trades(mut records: Vec<AuditLine>) -> Result<(Vec<Trade>, usize)> {
+    records.sort_by(|a, b| {
+        (&a.symbol, &a.interval, &a.strategy, a.open_time).cmp(&(
+            &b.symbol,
+            &b.interval,
+            &b.strategy,
+            b.open_time,
+        ))
+    });
+    let mut trades = Vec::new();
+    let mut open = 0;
+    let mut position: Option<(AuditLine, f64)> = None;
+    for record in records {
+        let side = direction(&record.side)?;
+        if let Some((entry, dir)) = position.take() {
+            let same_stream = (&entry.symbol, &entry.interval, &entry.strategy)
+                == (&record.symbol, &record.interval, &record.strategy);
+            if !same_stream {
+                open += 1;
+            } else if side == dir {
+                // A repeated signal of the open side keeps the position
+                position = Some((entry, dir));
+                continue;
+            } else {
+                trades.push(Trade {
+                    regime: entry
+                        .features
+                        .get("regime")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                    direction: dir,
+                    entry_time: entry.open_time,
+                    entry_price: entry.price,
+                    exit_time: record.open_time,
+                    return_pct: dir * (record.price / entry.price - 1.0) * 100.0,
+                    mae_pct: None,
+                    mfe_pct: None,
+                    symbol: entry.symbol,
+                    interval: entry.interval,
+                    strategy: entry.strategy,
+                });
+            }
+        }
+        if side != 0.0 {
+            position = Some((record, side));
+        }
+    }
+    open += position.is_some() as usize;
+    Ok((trades, open))
+}
+
+/// Candles of a raw log opening after `from`.
+fn read_candles(path: &str, from: i64) -> Result<Vec<Kline>> {
+    let mut reader = CandleReader::open(path)?;
+    let mut candles = Vec::new();
+    loop {
+        let chunk = reader.next_chunk(CANDLE_CHUNK)?;
+        if chunk.is_empty() {
+            break;
+        }
+        candles.extend(chunk.into_iter().filter(|k| k.open_time > from));
+    }
+    candles.sort_by_key(|k| k.open_time);
+    candles.dedup_by_key(|k| k.open_time);
+    Ok(candles)
+}
+
+/// Fill in the MAE and MFE of a trade from the candles after its entry up
+/// to and including its exit candle.
+fn excursions(trade: &mut Trade, candles: &[Kline]) {
+    let start = candles.partition_point(|k| k.open_time <= trade.entry_time);
+    let end = candles.partition_point(|k| k.open_time <= trade.exit_time);
+    if start >= end {
+        return;
+    }
+    let span = &candles[start..end];
+    let high = span.iter().map(|k| k.high).fold(f64::MIN, f64::max);
+    let low = span.iter().map(|k| k.low).fold(f64::MAX, f64::min);
+    let up = (high / trade.entry_price - 1.0) * 100.0;
+    let down = (1.0 - low / trade.entry_price) * 100.0;
+    let (adverse, favorable) = if trade.direction > 0.0 {
+        (down, up)
+    } else {
+        (up, down)
+    };
+    trade.mae_pct = Some(adverse.max(0.0));
+    trade.mfe_pct = Some(favorable.max(0.0));
+}
+
+/// Nearest-rank percentile of sorted values.
+fn percentile(sorted: &[f64], q: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((q * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+    Some(sorted[rank - 1])
+}
+
+/// One breakdown row: `key` then [`STAT_HEADERS`].
+fn stats_row(key: &str, trades: &[&Trade]) -> Vec<String> {
+    let n = trades.len() as f64;
+    let returns: Vec<f64> = trades.iter().map(|t| t.return_pct).collect();
+    let wins: Vec<f64> = returns.iter().copied().filter(|r| *r > 0.0).collect();
+    let losses: Vec<f64> = returns.iter().copied().filter(|r| *r <= 0.0).collect();
+    let mean = |values: &[f64]| {
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    };
+    let sorted = |values: Vec<f64>| {
+        let mut values = values;
+        values.sort_by(f64::total_cmp);
+        values
+    };
+    let hold = sorted(
+        trades
+            .iter()
+            .map(|t| (t.exit_time - t.entry_time) as f64 / 3_600_000.0)
+            .collect(),
+    );
+    let mae = sorted(trades.iter().filter_map(|t| t.mae_pct).collect());
+    let mfe = sorted(trades.iter().filter_map(|t| t.mfe_pct).collect());
+    let pct = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{:+.3}", v));
+    let plain = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{:.3}", v));
+    let hours = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{:.1}", v));
+    vec![
+        key.to_string(),
+        trades.len().to_string(),
+        format!("{:.1}", wins.len() as f64 / n * 100.0),
+        pct(mean(&wins)),
+        pct(mean(&losses)),
+        pct(mean(&returns)),
+        pct(Some(returns.iter().sum())),
+        hours(mean(&hold)),
+        hours(percentile(&hold, 0.5)),
+        plain(percentile(&mae, 0.5)),
+        plain(percentile(&mae, 0.9)),
+        plain(percentile(&mfe, 0.5)),
+        plain(percentile(&mfe, 0.9)),
+    ]
+}
+
+fn breakdown(
+    title: &'static str,
+    key_header: &'static str,
+    trades: &[Trade],
+    key: impl Fn(&Trade) -> String,
+) -> Table {
+    let mut groups: BTreeMap<String, Vec<&Trade>> = BTreeMap::new();
+    for trade in trades {
+        groups.entry(key(trade)).or_default().push(trade);
+    }
+    let mut headers = vec![key_header];
+    headers.extend(STAT_HEADERS);
+    Table {
+        title,
+        headers,
+        rows: groups
+            .iter()
+            .map(|(key, trades)| stats_row(key, trades))
+            .collect(),
+    }
+}
+
+pub fn run(cli: &CliArgs, config: &Config) -> Result<()> {
+    let stream_types: Vec<&str> = cli
+        .positional
+        .get(1)
+        .map_or("m15", String::as_str)
+        .split(',')
+        .map(str::trim)
+        .collect();
+    let from = cli.value("from").map(utils::parse_time_bound).transpose()?;
+    let to = cli.value("to").map(utils::parse_time_bound).transpose()?;
+
+    // Audit trails and raw logs of every configured pipeline
+    let mut audits = Vec::new();
+    let mut raw_logs = HashMap::new();
+    for stream_type in &stream_types {
+        let interval = live_stream::stream_interval(stream_type);
+        if interval.is_empty() {
+            bail!(
+                "journal-report expects candle stream types (m5, m15, ...), got '{}'",
+                stream_type
+            );
+        }
+        for symbol in config.symbols() {
+            let paths = config
+                .for_symbol(&symbol)
+                .output_paths(stream_type, interval)?;
+            audits.push(paths.audit);
+            raw_logs.insert((symbol, interval.to_string()), paths.raw_csv);
+        }
+    }
+    if let Some(input) = cli.value("input") {
+        audits = vec![input.to_string()];
+    }
+
+    let mut records = Vec::new();
+    for path in &audits {
+        if cli.value("input").is_none() && !Path::new(path).exists() {
+            continue;
+        }
+        records.extend(read_audit(path, from, to)?);
+    }
+    let signals = records.len();
+    if signals == 0 {
+        bail!(
+            "no audited signals in {} (is `signal_audit` on?)",
+            audits.join(", ")
+        );
+    }
+    let (mut trades, open) = trades(records)?;
+
+    // Candles are only read from the first entry on
+    let mut first_entry: HashMap<(String, String), i64> = HashMap::new();
+    for trade in &trades {
+        let first = first_entry
+            .entry((trade.symbol.clone(), trade.interval.clone()))
+            .or_insert(trade.entry_time);
+        *first = (*first).min(trade.entry_time);
+    }
+    let mut candles = HashMap::new();
+    for (key, first) in first_entry {
+        let loaded = match raw_logs.get(&key) {
+            Some(path) if Path::new(path).exists() => read_candles(path, first)?,
+            _ => Vec::new(),
+        };
+        candles.insert(key, loaded);
+    }
+    let mut without_candles = 0;
+    for trade in &mut trades {
+        let key = (trade.symbol.clone(), trade.interval.clone());
+        excursions(trade, &candles[&key]);
+        without_candles += trade.mae_pct.is_none() as usize;
+    }
+
+    let overview = Table {
+        title: "Overview",
+        headers: vec![
+            "Signals",
+            "Closed trades",
+            "Open positions",
+            "Trades without candles",
+        ],
+        rows: vec![vec![
+            signals.to_string(),
+            trades.len().to_string(),
+            open.to_string(),
+            without_candles.to_string(),
+        ]],
+    };
+    let tables = vec![
+        overview,
+        breakdown("By strategy", "Strategy", &trades, |t| t.strategy.clone()),
+        breakdown("By symbol", "Symbol", &trades, |t| {
+            format!("{} {}", t.symbol, t.interval)
+        }),
+        breakdown("By regime", "Regime", &trades, |t| {
+            t.regime.clone().unwrap_or_else(|| "-".to_string())
+        }),
+    ];
+    let title = format!(
+        "Trade journal, {} to {}",
+        from.map_or("start".to_string(), timezone::format_ms_secs),
+        to.map_or("now".to_string(), timezone::format_ms_secs)
+    );
+
+    match cli.value("output") {
+        Some(path) => {
+            let text = if path.ends_with(".html") {
+                report::render_html(&title, &tables)
+            } else {
+                report::render_markdown(&title, &tables)
+            };
+            std::fs::write(path, text).with_context(|| format!("Failed to write {}", path))?;
+            println!(
+                "Wrote the journal of {} trades ({} signals) to {}",
+                trades.len(),
+                signals,
+                path
+            );
+        }
+        None => print!("{}", report::render_markdown(&title, &tables)),
+    }
+    Ok(())
+}
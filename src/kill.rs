@@ -0,0 +1,626 @@
+//! Strategy kill criteria (`[kill]`).
+//!
+//! Every routed signal moves its strategy's paper position on the stream as
+//! in the trade journal (`journal-report`): long or short from the signal's
+//! price until a signal of another side, `flat` closing it. Here the position
+//! is weighted by the signal's `size` (the whole allocated capital without
+//! one) and marked to every candle close, so per (symbol, interval, strategy)
+//! the closed trades and the equity curve, in percent of the allocated
+//! capital, are known as they happen. The books are kept with `[kill]`
+//! disabled too, for `GET /strategies` and [`flatten`]. With it enabled, a
+//! strategy is disabled on a stream after `consecutive_losses` losing trades
+//! in a row, or once its equity is `max_drawdown_pct` below its peak;
+//! `[kill.strategies."<name>"]` replaces either limit for one strategy.
+//!
+//! A disabled strategy still evaluates every candle, so its state stays
+//! current, but its signals are held (`signals_suppressed_total` with reason
+//! `kill`), and a position it held is closed with a `flat` signal at the
+//! price that broke the limit. Disabling raises a `kill` alert, writes a
+//! `strategy_disabled` event and sets `strategy_disabled{symbol,interval,
+//! strategy}`; [`enable`] (`POST /control/strategies/{strategy}/enable`)
+//! lifts it with a fresh streak and peak. The books live in memory and are
+//! rebuilt at startup ([`restore`]) from the stream's signal audit trail, the
+//! trade journal of `journal-report`, marked to the stored candles: a losing
+//! streak or drawdown carries over a restart. Signals that are not audited
+//! (TradingView alerts, the `flat` signals of kills and flattening) are not
+//! replayed.
+
+use crate::alerts::{self, Level};
+use crate::config::KillConfig;
+use crate::events::{self, Event};
+use crate::metrics;
+use crate::strategy::{Side, Signal};
+use anyhow::{Context, Result};
+use binance_streamer::kline::Kline;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::{Mutex, OnceLock, RwLock};
+
+/// (symbol, interval, strategy)
+type Key = (String, String, String);
+
+/// A paper position.
+#[derive(Debug, Clone, Copy, Serialize)]
+struct Position {
+    /// 1 long, -1 short.
+    direction: f64,
+    /// Fraction of the allocated capital.
+    size: f64,
+    entry_price: f64,
+    last_price: f64,
+    /// Open time of the candle (or signal) last marked.
+    marked_at: i64,
+}
+
+impl Position {
+    /// Return so far, in percent of the allocated capital.
+    fn return_pct(&self) -> f64 {
+        self.direction * self.size * (self.last_price / self.entry_price - 1.0) * 100.0
+    }
+}
+
+/// The paper record of one strategy on one stream.
+#[derive(Debug, Default, Clone, Serialize)]
+struct Book {
+    position: Option<Position>,
+    /// Closed trades' returns, in percent of the allocated capital.
+    realized_pct: f64,
+    /// Highest equity (realized plus open) since start or re-enabling.
+    peak_pct: f64,
+    /// Losing trades in a row.
+    losses: u32,
+    /// Why the strategy is disabled on the stream.
+    disabled: Option<String>,
+}
+
+impl Book {
+    fn equity_pct(&self) -> f64 {
+        self.realized_pct + self.position.map_or(0.0, |p| p.return_pct())
+    }
+
+    /// A fresh losing streak and peak, at the current equity.
+    fn restart(&mut self) {
+        self.losses = 0;
+        self.peak_pct = self.equity_pct();
+    }
+
+    fn close(&mut self) {
+        if let Some(position) = self.position.take() {
+            let trade = position.return_pct();
+            self.realized_pct += trade;
+            self.losses = if trade < 0.0 { self.losses + 1 } else { 0 };
+        }
+    }
+
+    /// The limit the book breaks, if any.
+    fn broken(&mut self, (losses, drawdown): (u32, f64)) -> Option<String> {
+        let equity = self.equity_pct();
+        self.peak_pct = self.peak_pct.max(equity);
+        if losses > 0 && self.losses >= losses {
+            return Some(format!("{} losing trades in a row", self.losses));
+        }
+        let dropped = self.peak_pct - equity;
+        (drawdown > 0.0 && dropped >= drawdown).then(|| {
+            format!(
+                "drawdown of {:.2}% of its capital (limit {}%)",
+                dropped, drawdown
+            )
+        })
+    }
+}
+
+fn books() -> &'static Mutex<BTreeMap<Key, Book>> {
+    static BOOKS: OnceLock<Mutex<BTreeMap<Key, Book>>> = OnceLock::new();
+    BOOKS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+fn settings() -> &'static RwLock<KillConfig> {
+    static SETTINGS: OnceLock<RwLock<KillConfig>> = OnceLock::new();
+    SETTINGS.get_or_init(|| RwLock::new(KillConfig::default()))
+}
+
+/// Use `config` from now on.
+pub fn configure(config: &KillConfig) {
+    *settings().write().unwrap() = config.clone();
+}
+
+fn key(symbol: &str, interval: &str, strategy: &str) -> Key {
+    (
+        symbol.to_string(),
+        interval.to_string(),
+        strategy.to_string(),
+    )
+}
+
+/// Whether `strategy` is disabled on the stream.
+pub fn disabled(symbol: &str, interval: &str, strategy: &str) -> bool {
+    books()
+        .lock()
+        .unwrap()
+        .get(&key(symbol, interval, strategy))
+        .is_some_and(|book| book.disabled.is_some())
+}
+
+/// Disable the strategy of `key` for `reason`; the `flat` signal closing its
+/// position at `price`, if it held one.
+fn disable(
+    key: &Key,
+    book: &mut Book,
+    reason: String,
+    open_time: i64,
+    price: f64,
+) -> Option<Signal> {
+    let (symbol, interval, strategy) = key;
+    let held = book.position.is_some();
+    book.close();
+    book.disabled = Some(reason.clone());
+    metrics::gauge_set(
+        "strategy_disabled",
+        &[
+            ("symbol", symbol),
+            ("interval", interval),
+            ("strategy", strategy),
+        ],
+        1.0,
+    );
+    events::emit(Event::StrategyDisabled {
+        symbol: symbol.clone(),
+        interval: interval.clone(),
+        strategy: strategy.clone(),
+        reason: reason.clone(),
+    });
+    alerts::raise(
+        Level::Critical,
+        "kill",
+        &format!(
+            "{} disabled on {} {}: {}; its signals are held until it is re-enabled",
+            strategy, symbol, interval, reason
+        ),
+    );
+    held.then(|| Signal {
+        symbol: symbol.clone(),
+        interval: interval.clone(),
+        strategy: strategy.clone(),
+        side: Side::Flat,
+        open_time,
+        price,
+        size: None,
+        stop: None,
+    })
+}
+
+/// Book a routed signal; the `flat` signal to route after it if it disabled
+/// its strategy while in a position.
+pub fn signal(signal: &Signal) -> Option<Signal> {
+    let settings = settings().read().unwrap();
+    let key = key(&signal.symbol, &signal.interval, &signal.strategy);
+    let mut books = books().lock().unwrap();
+    let book = books.entry(key.clone()).or_default();
+    if book.disabled.is_some() {
+        return None;
+    }
+    let direction = match signal.side {
+        Side::Long => 1.0,
+        Side::Short => -1.0,
+        Side::Flat => 0.0,
+    };
+    if let Some(position) = &mut book.position {
+        position.last_price = signal.price;
+        position.marked_at = signal.open_time;
+        // A repeated signal of the open side keeps the position
+        if position.direction != direction {
+            book.close();
+        }
+    }
+    if direction != 0.0 && book.position.is_none() {
+        book.position = Some(Position {
+            direction,
+            size: signal.size.unwrap_or(1.0),
+            entry_price: signal.price,
+            last_price: signal.price,
+            marked_at: signal.open_time,
+        });
+    }
+    if !settings.enabled {
+        return None;
+    }
+    let reason = book.broken(settings.limits(&signal.strategy))?;
+    disable(&key, book, reason, signal.open_time, signal.price)
+}
+
+/// Mark the open positions on the stream to a candle's close; the `flat`
+/// signals of the strategies this disabled.
+pub fn mark(symbol: &str, interval: &str, open_time: i64, close: f64) -> Vec<Signal> {
+    let settings = settings().read().unwrap();
+    let mut flats = Vec::new();
+    let mut books = books().lock().unwrap();
+    for (key, book) in books.iter_mut() {
+        if key.0 != symbol || key.1 != interval || book.disabled.is_some() {
+            continue;
+        }
+        let Some(position) = &mut book.position else {
+            continue;
+        };
+        position.last_price = close;
+        position.marked_at = open_time;
+        if !settings.enabled {
+            continue;
+        }
+        if let Some(reason) = book.broken(settings.limits(&key.2)) {
+            flats.extend(disable(key, book, reason, open_time, close));
+        }
+    }
+    flats
+}
+
+/// Close the open paper positions (those of `symbol`, `interval` and
+/// `strategy` only, if given) at their last marked price; the `flat` signals
+/// to deliver. The strategies are not told and may signal again.
+pub fn flatten(
+    symbol: Option<&str>,
+    interval: Option<&str>,
+    strategy: Option<&str>,
+) -> Vec<Signal> {
+    let mut flats = Vec::new();
+    let mut books = books().lock().unwrap();
+    for ((s, i, name), book) in books.iter_mut() {
+        if symbol.is_some_and(|symbol| symbol != s)
+            || interval.is_some_and(|interval| interval != i)
+            || strategy.is_some_and(|strategy| strategy != name)
+        {
+            continue;
+        }
+        let Some(position) = book.position else {
+            continue;
+        };
+        book.close();
+        flats.push(Signal {
+            symbol: s.clone(),
+            interval: i.clone(),
+            strategy: name.clone(),
+            side: Side::Flat,
+            open_time: position.marked_at,
+            price: position.last_price,
+            size: None,
+            stop: None,
+        });
+    }
+    flats
+}
+
+/// The book of every strategy on every stream, for `GET /strategies`.
+pub fn report() -> Vec<serde_json::Value> {
+    books()
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|((symbol, interval, strategy), book)| {
+            serde_json::json!({
+                "symbol": symbol,
+                "interval": interval,
+                "strategy": strategy,
+                "equity_pct": book.equity_pct(),
+                "drawdown_pct": book.peak_pct - book.equity_pct(),
+                "book": book,
+            })
+        })
+        .collect()
+}
+
+/// Re-enable `strategy` on the streams it is disabled on (those of `symbol`
+/// and `interval` only, if given), with a fresh losing streak and peak;
+/// the streams re-enabled.
+pub fn enable(
+    strategy: &str,
+    symbol: Option<&str>,
+    interval: Option<&str>,
+) -> Vec<(String, String)> {
+    let mut enabled = Vec::new();
+    let mut books = books().lock().unwrap();
+    for ((s, i, name), book) in books.iter_mut() {
+        if name != strategy
+            || symbol.is_some_and(|symbol| symbol != s)
+            || interval.is_some_and(|interval| interval != i)
+            || book.disabled.take().is_none()
+        {
+            continue;
+        }
+        book.restart();
+        metrics::gauge_set(
+            "strategy_disabled",
+            &[("symbol", s), ("interval", i), ("strategy", name)],
+            0.0,
+        );
+        events::emit(Event::StrategyEnabled {
+            symbol: s.clone(),
+            interval: i.clone(),
+            strategy: name.clone(),
+        });
+        println!("[kill] {} re-enabled on {} {}", name, s, i);
+        enabled.push((s.clone(), i.clone()));
+    }
+    enabled
+}
+
+/// The fields of a signal audit record [`restore`] reads.
+#[derive(Debug, Deserialize)]
+struct Audited {
+    symbol: String,
+    interval: String,
+    strategy: String,
+    side: Side,
+    open_time: i64,
+    price: f64,
+    #[serde(default)]
+    size: Option<f64>,
+}
+
+/// Rebuild the books of the stream from its signal audit trail at `audit`
+/// (missing: nothing to rebuild) and its stored `candles`, oldest first: every
+/// audited signal is booked and every candle marked in the order they
+/// happened, a strategy breaking a limit is disabled again, and one with
+/// signals after it was disabled had been re-enabled. Books the stream
+/// already has (a pipeline restarted in the process) are kept; the number of
+/// signals booked.
+pub fn restore(audit: &str, symbol: &str, interval: &str, candles: &[Kline]) -> Result<usize> {
+    if books()
+        .lock()
+        .unwrap()
+        .keys()
+        .any(|(s, i, _)| s == symbol && i == interval)
+    {
+        return Ok(0);
+    }
+    let file = match File::open(audit) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e).with_context(|| format!("Failed to open {}", audit)),
+    };
+    let mut signals = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Audited>(&line) {
+            Ok(record) if record.symbol == symbol && record.interval == interval => {
+                signals.push(Signal {
+                    symbol: record.symbol,
+                    interval: record.interval,
+                    strategy: record.strategy,
+                    side: record.side,
+                    open_time: record.open_time,
+                    price: record.price,
+                    size: record.size,
+                    stop: None,
+                })
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Warning: skipping audit line in {}: {}", audit, e),
+        }
+    }
+    // A candle is marked when it closes, before the signals made on it
+    signals.sort_by_key(|s| s.open_time);
+    let mut pending = signals.iter().peekable();
+    let mut book_until = |open_time: i64| {
+        while let Some(signal) = pending.next_if(|s| s.open_time <= open_time) {
+            let key = key(&signal.symbol, &signal.interval, &signal.strategy);
+            if let Some(book) = books().lock().unwrap().get_mut(&key) {
+                if book.disabled.take().is_some() {
+                    book.restart();
+                    metrics::gauge_set(
+                        "strategy_disabled",
+                        &[
+                            ("symbol", &key.0),
+                            ("interval", &key.1),
+                            ("strategy", &key.2),
+                        ],
+                        0.0,
+                    );
+                }
+            }
+            self::signal(signal);
+        }
+    };
+    for candle in candles {
+        book_until(candle.open_time - 1);
+        mark(symbol, interval, candle.open_time, candle.close);
+        book_until(candle.open_time);
+    }
+    book_until(i64::MAX);
+    Ok(signals.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The settings are process-wide: tests configuring them run one at a time.
+    static SETTINGS_LOCK: Mutex<()> = Mutex::new(());
+
+    fn limits(consecutive_losses: u32, max_drawdown_pct: f64) -> KillConfig {
+        KillConfig {
+            enabled: true,
+            consecutive_losses,
+            max_drawdown_pct,
+            ..KillConfig::default()
+        }
+    }
+
+    /// A signal of `strategy` on a stream of its own.
+    fn routed(strategy: &str, side: Side, open_time: i64, price: f64) -> Signal {
+        Signal {
+            symbol: "KILLUSDT".to_string(),
+            interval: "15m".to_string(),
+            strategy: strategy.to_string(),
+            side,
+            open_time,
+            price,
+            size: None,
+            stop: None,
+        }
+    }
+
+    fn assert_flat(signal: &Signal, symbol: &str, open_time: i64, price: f64) {
+        assert_eq!(signal.symbol, symbol);
+        assert_eq!(signal.side, Side::Flat);
+        assert_eq!(signal.open_time, open_time);
+        assert_eq!(signal.price, price);
+        assert_eq!(signal.size, None);
+    }
+
+    #[test]
+    fn losing_streak_disables_until_enabled() {
+        let _lock = SETTINGS_LOCK.lock().unwrap();
+        configure(&limits(3, 0.0));
+        let strategy = "kill_test_streak";
+
+        // Two losing longs, then a winning one resets the streak
+        for (t, (entry, exit)) in [(100.0, 99.0), (100.0, 99.0), (100.0, 101.0)]
+            .into_iter()
+            .enumerate()
+        {
+            let t = t as i64 * 10;
+            assert!(signal(&routed(strategy, Side::Long, t, entry)).is_none());
+            assert!(signal(&routed(strategy, Side::Flat, t + 1, exit)).is_none());
+        }
+        assert!(!disabled("KILLUSDT", "15m", strategy));
+
+        // Three losing shorts; the third closes flat and needs no extra signal
+        for t in [100, 110, 120] {
+            assert!(signal(&routed(strategy, Side::Short, t, 100.0)).is_none());
+            assert!(signal(&routed(strategy, Side::Flat, t + 1, 101.0)).is_none());
+        }
+        assert!(disabled("KILLUSDT", "15m", strategy));
+        // Held: no position is opened while disabled
+        assert!(signal(&routed(strategy, Side::Long, 130, 100.0)).is_none());
+        assert!(flatten(None, None, Some(strategy)).is_empty());
+
+        assert!(enable(strategy, Some("OTHERUSDT"), None).is_empty());
+        assert_eq!(
+            enable(strategy, None, None),
+            vec![("KILLUSDT".to_string(), "15m".to_string())]
+        );
+        assert!(!disabled("KILLUSDT", "15m", strategy));
+        // A fresh streak: one more loss does not disable it again
+        signal(&routed(strategy, Side::Long, 140, 100.0));
+        signal(&routed(strategy, Side::Flat, 141, 99.0));
+        assert!(!disabled("KILLUSDT", "15m", strategy));
+
+        configure(&KillConfig::default());
+    }
+
+    #[test]
+    fn drawdown_disables_and_closes_the_position() {
+        let _lock = SETTINGS_LOCK.lock().unwrap();
+        configure(&limits(0, 10.0));
+        let strategy = "kill_test_drawdown";
+
+        assert!(signal(&routed(strategy, Side::Long, 0, 100.0)).is_none());
+        // Up 20%, then back to +11%: 9% below the peak
+        assert!(mark("KILLUSDT", "15m", 10, 120.0).is_empty());
+        assert!(mark("KILLUSDT", "15m", 20, 111.0).is_empty());
+        assert!(!disabled("KILLUSDT", "15m", strategy));
+
+        let flats = mark("KILLUSDT", "15m", 30, 109.0);
+        let closed: Vec<_> = flats.iter().filter(|s| s.strategy == strategy).collect();
+        assert_eq!(closed.len(), 1);
+        assert_flat(closed[0], "KILLUSDT", 30, 109.0);
+        assert!(disabled("KILLUSDT", "15m", strategy));
+        // Disabled books are no longer marked
+        assert!(mark("KILLUSDT", "15m", 40, 50.0)
+            .iter()
+            .all(|s| s.strategy != strategy));
+
+        enable(strategy, Some("KILLUSDT"), Some("15m"));
+        assert!(!disabled("KILLUSDT", "15m", strategy));
+        // The peak restarts at the current equity (+9%)
+        signal(&routed(strategy, Side::Short, 50, 100.0));
+        assert!(mark("KILLUSDT", "15m", 60, 101.0).is_empty());
+        assert!(!disabled("KILLUSDT", "15m", strategy));
+
+        configure(&KillConfig::default());
+    }
+
+    #[test]
+    fn flatten_closes_matching_positions_at_their_last_mark() {
+        let strategy = "kill_test_flatten";
+        let symbol = "FLATUSDT";
+        let mut long = routed(strategy, Side::Long, 0, 100.0);
+        long.symbol = symbol.to_string();
+        signal(&long);
+        mark(symbol, "15m", 10, 105.0);
+
+        assert!(flatten(Some(symbol), Some("1h"), None).is_empty());
+        let flats = flatten(Some(symbol), None, Some(strategy));
+        assert_eq!(flats.len(), 1);
+        assert_flat(&flats[0], symbol, 10, 105.0);
+        assert!(flatten(Some(symbol), None, Some(strategy)).is_empty());
+    }
+
+    #[test]
+    fn restore_rebuilds_the_books_from_the_audit_trail() {
+        let _lock = SETTINGS_LOCK.lock().unwrap();
+        configure(&limits(2, 0.0));
+        let dir = std::env::temp_dir().join(format!("kill_restore_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let audit = dir.join("signal_audit.jsonl");
+        let record = |strategy: &str, side: &str, open_time: i64, price: f64| {
+            serde_json::json!({
+                "symbol": "RSTUSDT",
+                "interval": "1m",
+                "strategy": strategy,
+                "model_version": "v1",
+                "side": side,
+                "open_time": open_time,
+                "price": price,
+                "features": {},
+            })
+            .to_string()
+        };
+        let lines = [
+            record("losing", "long", 1, 100.0),
+            record("losing", "flat", 2, 90.0),
+            record("losing", "long", 3, 100.0),
+            record("other", "long", 3, 100.0),
+            serde_json::json!({"symbol": "OTHERUSDT", "interval": "1m"}).to_string(),
+            "not json".to_string(),
+        ];
+        std::fs::write(&audit, lines.join("\n")).unwrap();
+        let candle = |open_time: i64, close: f64| Kline {
+            open_time,
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 1.0,
+            close_time: open_time + 59_999,
+        };
+        let candles = [candle(3, 100.0), candle(4, 80.0)];
+        let path = audit.to_str().unwrap();
+
+        assert_eq!(restore(path, "RSTUSDT", "1m", &candles).unwrap(), 4);
+        // The open position marked down to 80 is a loss once closed
+        assert!(!disabled("RSTUSDT", "1m", "losing"));
+        let flat = Signal {
+            symbol: "RSTUSDT".to_string(),
+            interval: "1m".to_string(),
+            ..routed("losing", Side::Flat, 5, 80.0)
+        };
+        assert!(signal(&flat).is_none());
+        assert!(disabled("RSTUSDT", "1m", "losing"));
+        assert!(!disabled("RSTUSDT", "1m", "other"));
+
+        // A pipeline restarted in the process keeps its books
+        assert_eq!(restore(path, "RSTUSDT", "1m", &candles).unwrap(), 0);
+        assert!(disabled("RSTUSDT", "1m", "losing"));
+        let missing = dir.join("missing.jsonl");
+        assert_eq!(
+            restore(missing.to_str().unwrap(), "NEWUSDT", "1m", &[]).unwrap(),
+            0
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+//! Latest state per symbol, shared with the HTTP API.
+//!
+//! The pipeline publishes the newest feature row after every candle, together
+//! with the paths of the raw candle snapshot and journal, so `/latest` and `/candles` can be
+//! answered without touching the streaming loop.
+
+use crate::timezone;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+#[derive(Debug, Clone)]
+pub struct SymbolState {
+    pub interval: String,
+    /// Most recent feature row (column name → value).
+    pub features: Map<String, Value>,
+    /// Parquet snapshot of the raw candle window.
+    pub raw_cache: String,
+    /// Candles closed since that snapshot.
+    pub raw_journal: String,
+    /// Parquet file holding the feature window (served over Arrow Flight).
+    #[cfg_attr(not(feature = "flight"), allow(dead_code))]
+    pub feature_parquet: String,
+}
+
+fn registry() -> &'static Mutex<HashMap<String, SymbolState>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<String, SymbolState>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// JSON form of a feature row, served on `/latest/{symbol}` and pushed to
+/// WebSocket subscribers.
+/// With `[[sessions]]` configured it also names the sessions open at the
+/// row's `open_time`.
+pub fn feature_message(symbol: &str, interval: &str, features: &Map<String, Value>) -> Value {
+    let mut message = serde_json::json!({
+        "symbol": symbol.to_uppercase(),
+        "interval": interval,
+        "features": features,
+    });
+    if timezone::has_sessions() {
+        if let Some(open_time) = features.get("open_time").and_then(Value::as_i64) {
+            message["sessions"] = timezone::active_sessions(open_time).into();
+        }
+    }
+    message
+}
+
+/// Replace the published state of `symbol`.
+pub fn publish(symbol: &str, state: SymbolState) {
+    registry()
+        .lock()
+        .unwrap()
+        .insert(symbol.to_uppercase(), state);
+}
+
+/// Every symbol with published state.
+#[cfg_attr(not(feature = "flight"), allow(dead_code))]
+pub fn symbols() -> Vec<String> {
+    let mut symbols: Vec<String> = registry().lock().unwrap().keys().cloned().collect();
+    symbols.sort();
+    symbols
+}
+
+/// Published state of `symbol` (case-insensitive), if any.
+pub fn get(symbol: &str) -> Option<SymbolState> {
+    registry()
+        .lock()
+        .unwrap()
+        .get(&symbol.to_uppercase())
+        .cloned()
+}
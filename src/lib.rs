@@ -0,0 +1,61 @@
+//! Reusable market-data pieces of the collector.
+//!
+//! - [`kline`] – the [`Kline`] candle type (Binance array format deserializer)
+//! - [`binance_client`] – paginated REST fetchers for historical candles
+//! - [`coinbase_client`] – the same fetchers for Coinbase Advanced Trade
+//! - [`bybit_client`] – the same fetchers for Bybit linear perpetuals
+//! - [`exchange`] – the [`MarketDataSource`](exchange::MarketDataSource)
+//!   trait, its adapters, and the configured [`Exchange`](exchange::Exchange)
+//! - [`error`] – the [`Error`] kinds of the clients, adapters and storage
+//! - [`data_storage`] – Parquet/CSV persistence, DataFrame conversions and
+//!   asof alignment of auxiliary series onto the candle grid
+//! - [`history`] – cached historical window with refresh and gap backfill
+//! - [`progress`] – throttled progress of long REST fetches
+//! - [`query`] – `LazyFrame` scans of the stored feature and candle datasets
+//! - [`tiers`] – the feature-history datasets across local disk and a cold
+//!   object store
+//! - [`window`] – the columnar ring buffer holding the rolling candle window,
+//!   and [`WindowStats`](window::WindowStats) rolling statistics over it
+//! - [`features`] – the feature pipeline ([`compute_features`]) and the
+//!   stateful [`FeatureEngine`]
+//! - [`strategy`] – the [`Strategy`] trait and the signals it produces
+//! - [`hooks`] – callbacks on the live pipeline's closed candles, feature rows
+//!   and signals
+//! - [`timezone`] – the display timezone of logged timestamps and trading sessions
+//!
+//! The collector binary (`main.rs`) adds the live stream, persistence
+//! guardrails and network endpoints on top; the bindings under `python/` and
+//! `ffi/` expose the feature pipeline to other languages.
+//!
+//! ```no_run
+//! # async fn demo() -> anyhow::Result<()> {
+//! use binance_streamer::{binance_client, compute_features};
+//!
+//! let klines = binance_client::fetch_latest_klines("BTCUSDT", "15m", 5_000).await?;
+//! let features = compute_features(&klines)?;
+//! println!("{}", features.tail(Some(1)));
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod binance_client;
+pub mod bybit_client;
+pub mod coinbase_client;
+pub mod data_storage;
+pub mod error;
+pub mod exchange;
+pub mod features;
+pub mod history;
+pub mod hooks;
+pub mod kline;
+pub mod progress;
+pub mod query;
+pub mod strategy;
+pub mod tiers;
+pub mod timezone;
+pub mod window;
+
+pub use error::Error;
+pub use features::{compute_features, FeatureEngine};
+pub use kline::Kline;
+pub use strategy::{Side, Signal, Strategy};
@@ -1,57 +1,1767 @@
-use crate::data_storage;
-use crate::features;
+use crate::alerts::{self, Level};
+use crate::anomaly::AnomalyDetector;
+use crate::audit;
+use crate::bus::{self, Update};
+use crate::calendar;
+use crate::clock;
+use crate::config::{
+    BackpressurePolicy, Config, FeatureWindowConfig, LogLevel, OutputPaths, Profile, Role,
+    StreamingLogFormat,
+};
+use crate::connection_stats::{self, ConnectionStats};
+use crate::consistency::ConsistencyCheck;
+use crate::control::{self, Command};
+use crate::data_storage::{self, ZstdJsonlLog};
+use crate::deadline::DeadlineGuard;
+use crate::disk_guard::{DiskGuard, PersistenceMode};
+use crate::events::{self, Event};
+use crate::exchange::{
+    self, BookQuote, Exchange, IntervalCandles, MarketDataSource, StreamEvent, StreamKind,
+    TradeCandles,
+};
+use crate::features::cross_exchange::{Basis, CrossExchange};
+use crate::features::{
+    self, ema, pivots, quality, regime, warmup, BookSpread, CandleQuality, EmaTracker, OrderFlow,
+    ReferenceCorrelation, RegimeConfig, UsdConversion,
+};
+use crate::heartbeat;
+use crate::history::HistoryStore;
+use crate::hooks;
+use crate::ipc;
+use crate::kill;
 use crate::kline::Kline;
+use crate::latest::{self, SymbolState};
+use crate::maintenance;
+use crate::memory_guard::{MemoryGuard, OPTIONAL_COLUMNS};
+use crate::metrics;
+use crate::pipeline_summary::{PipelineSummary, Saved};
+use crate::plugins;
+use crate::recorder::Recorder;
+use crate::scheduler;
+use crate::signals;
+use crate::strategy::{Side, Signal, Strategy};
+use crate::timezone;
 use crate::utils;
-use anyhow::Result;
-use chrono::{DateTime, Duration, Utc};
-use futures_util::future::join_all;
+use crate::watchdog::Watchdog;
+use crate::window::{KlineWindow, WindowStats};
+use anyhow::{bail, Result};
+use chrono::Utc;
+use futures_util::future::{join_all, BoxFuture, FutureExt};
 use futures_util::{SinkExt, StreamExt};
+use polars::prelude::{ChunkCompareIneq, DataFrame, PolarsResult};
 use serde_json::Value;
-use std::collections::VecDeque;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Instant;
-use tokio;
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
-use url::Url;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::task;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::{protocol::Message, Error as WsError};
 
-const HISTORICAL_COUNT: usize = 50_000;
-const FEATURE_WINDOW_SIZE: usize = 50000;
+/// Largest relative difference tolerated between incremental and batch EMAs.
+const EMA_TOLERANCE: f64 = 1e-9;
 
-fn format_time(ms: u64) -> String {
-    let seconds = (ms / 1000) as i64;
-    let nanos = ((ms % 1000) * 1_000_000) as u32;
-    DateTime::<Utc>::from_timestamp(seconds, nanos)
-        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S%.3f UTC").to_string())
-        .unwrap_or_else(|| "Invalid timestamp".to_string())
+/// Reconnect backoff bounds; the delay doubles after every failed session.
+pub const MIN_BACKOFF: std::time::Duration = std::time::Duration::from_secs(1);
+pub const MAX_BACKOFF: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Record the outcome of one save task in the latency histograms and the event log.
+fn record_save<E: std::fmt::Display>(
+    target: &str,
+    path: &str,
+    started: Instant,
+    result: &std::result::Result<(), E>,
+) -> Saved {
+    metrics::observe_stage(&format!("save_{}", target), started);
+    let ms = started.elapsed().as_secs_f64() * 1000.0;
+    events::emit(Event::Save {
+        target: target.to_string(),
+        path: path.to_string(),
+        ok: result.is_ok(),
+        duration_ms: ms,
+        error: result.as_ref().err().map(|e| e.to_string()),
+    });
+    Saved {
+        target: target.to_string(),
+        ok: result.is_ok(),
+        ms,
+    }
+}
+
+/// Publish the newest feature row for the HTTP API and the update bus.
+fn publish_latest(symbol: &str, interval: &str, features_df: &DataFrame, paths: &OutputPaths) {
+    let Some(last) = features_df.height().checked_sub(1) else {
+        return;
+    };
+    match data_storage::row_to_json(features_df, last) {
+        Ok(features) => {
+            bus::publish(Update::Features {
+                symbol: symbol.to_string(),
+                interval: interval.to_string(),
+                open_time: features
+                    .get("open_time")
+                    .and_then(Value::as_i64)
+                    .unwrap_or_default(),
+                values: features.clone(),
+            });
+            latest::publish(
+                symbol,
+                SymbolState {
+                    interval: interval.to_string(),
+                    features,
+                    raw_cache: paths.raw_cache.clone(),
+                    raw_journal: paths.raw_journal.clone(),
+                    feature_parquet: paths.feature_parquet.clone(),
+                },
+            );
+        }
+        Err(e) => eprintln!("Warning: failed to publish latest features: {}", e),
+    }
+}
+
+/// Rolling window and incremental EMA state. Advanced on the blocking pool,
+/// so feature computation never runs on the async reactor threads.
+struct FeatureState {
+    symbol: String,
+    raw_window: KlineWindow,
+    /// Candles of the raw window and the full feature window.
+    window_size: usize,
+    ema: EmaTracker,
+    /// Candles since the EMAs were last checked against a full recomputation.
+    ema_unchecked: u64,
+    ema_validate_every: u64,
+    /// Rules of the `regime` column, if enabled.
+    regime: Option<RegimeConfig>,
+    /// States joined with a second stream (cross-exchange, USD columns,
+    /// reference correlation, order flow, spread).
+    joined: Vec<Arc<Mutex<dyn Joined>>>,
+    /// Provenance of the window's candles, if the quality columns are on.
+    quality: Option<CandleQuality>,
+    /// Add the `warmup_flags` column.
+    warmup_flags: bool,
+}
+
+impl FeatureState {
+    /// Compare the incremental EMAs with a batch recomputation over the same
+    /// window. A mismatch raises an alert and rebuilds the state by replaying
+    /// the raw window.
+    fn validate_ema(&mut self) -> Result<()> {
+        let Some(row) = self.ema.last() else {
+            return Ok(());
+        };
+        let base = features::window_frame(&self.raw_window, self.window_size)?;
+        let batch = ema::add_ema_features(base)?;
+        let diff = ema::max_relative_diff(&row, &batch)?;
+        if let Some(diff) = diff {
+            metrics::gauge_set("ema_incremental_max_rel_diff", &[], diff);
+            if diff <= EMA_TOLERANCE {
+                return Ok(());
+            }
+        }
+        metrics::counter_inc("ema_resyncs_total", &[], 1);
+        alerts::raise(
+            Level::Warning,
+            "ema",
+            &format!(
+                "{}: incremental EMAs drifted from the batch computation (max relative diff {}), resyncing",
+                self.symbol,
+                diff.map_or("null mismatch".to_string(), |d| format!("{:e}", d))
+            ),
+        );
+        self.ema = EmaTracker::replay(&self.raw_window.to_klines(), self.window_size);
+        Ok(())
+    }
+
+    /// Replace stored candles with corrected ones and replay the EMAs over
+    /// the window; returns the candles the window held.
+    fn correct(&mut self, klines: &[Kline]) -> Vec<Kline> {
+        let replaced: Vec<Kline> = klines
+            .iter()
+            .filter(|k| self.raw_window.replace(k))
+            .cloned()
+            .collect();
+        if !replaced.is_empty() {
+            self.ema = EmaTracker::replay(&self.raw_window.to_klines(), self.window_size);
+        }
+        if let Some(quality) = &mut self.quality {
+            for kline in &replaced {
+                quality.replaced(kline.open_time);
+            }
+        }
+        replaced
+    }
+
+    /// Add a closed candle to the window and the EMAs, without computing features.
+    fn absorb(&mut self, new_kline: &Kline) {
+        // --- Update raw rolling window ---
+        let stage_start = Instant::now();
+        self.raw_window.push(new_kline);
+        metrics::observe_stage("window_update", stage_start);
+
+        // --- Advance the EMAs by one candle ---
+        let stage_start = Instant::now();
+        self.ema.update(new_kline);
+        metrics::observe_stage("ema_update", stage_start);
+
+        for joined in &self.joined {
+            joined.lock().unwrap().push_primary(new_kline);
+        }
+    }
+
+    /// Add a closed candle to the window and compute the feature frame of
+    /// the last `feature_window` candles (the whole window unless the memory
+    /// guard shrank it).
+    fn advance(&mut self, new_kline: &Kline, feature_window: usize) -> Result<DataFrame> {
+        self.absorb(new_kline);
+
+        // --- Compute features on the last feature_window of raw_window ---
+        let stage_start = Instant::now();
+        let base = features::window_frame(&self.raw_window, feature_window)?;
+        let mut features_df = features::compute_features_with_ema(base, &self.ema)?;
+        if let Some(regime) = &self.regime {
+            features_df = regime::add_regime_features(features_df, regime)?;
+        }
+        features_df = calendar::add_columns(features_df)?;
+        for joined in &self.joined {
+            features_df = joined.lock().unwrap().add_columns(features_df)?;
+        }
+        if let Some(quality) = &self.quality {
+            features_df = quality.add_columns(features_df)?;
+        }
+        if self.warmup_flags {
+            features_df = warmup::add_warmup_flags(features_df)?;
+        }
+        metrics::observe_stage("feature_compute", stage_start);
+
+        // The batch check needs the full window, so it waits while memory is short
+        self.ema_unchecked += 1;
+        if self.ema_validate_every > 0
+            && self.ema_unchecked >= self.ema_validate_every
+            && feature_window >= self.window_size
+        {
+            self.ema_unchecked = 0;
+            let stage_start = Instant::now();
+            self.validate_ema()?;
+            metrics::observe_stage("ema_validate", stage_start);
+        }
+        Ok(features_df)
+    }
+}
+
+/// State carried from one closed candle to the next: the feature state,
+/// output paths and the monitors that watch the pipeline.
+struct CandlePipeline<'a> {
+    symbol: &'a str,
+    interval: &'a str,
+    /// Where the candles come from, for its maintenance windows.
+    exchange: Exchange,
+    interval_minutes: i64,
+    paths: &'a OutputPaths,
+    /// Shared with the blocking task computing each candle's features.
+    state: Arc<Mutex<FeatureState>>,
+    store: HistoryStore,
+    /// When the raw Parquet cache was last rewritten (epoch ms, see
+    /// [`clock`]); in between, candles only go to the journal.
+    last_snapshot: i64,
+    snapshot_every: std::time::Duration,
+    disk_guard: DiskGuard,
+    memory_guard: MemoryGuard,
+    deadline: DeadlineGuard,
+    float32_features: bool,
+    /// The streaming log as compressed JSON lines; `None` appends CSV lines.
+    streaming_jsonl: Option<Arc<Mutex<ZstdJsonlLog>>>,
+    /// Run the saves one after another, in a fixed order.
+    deterministic: bool,
+    /// The `signal_only` profile: candles and features are not written.
+    signal_only: bool,
+    /// Candles the feature frame covers at most.
+    feature_window: usize,
+    /// Warm-up flags of the newest row, while some features are still
+    /// warming up.
+    warming: Option<u32>,
+    watchdog: Option<Watchdog>,
+    consistency: Option<ConsistencyCheck>,
+    anomaly: Option<AnomalyDetector>,
+    strategies: Vec<Box<dyn Strategy>>,
+    /// Append every signal to the audit trail.
+    signal_audit: bool,
+    /// Drop signals of candles closing inside a calendar event window.
+    suppress_around_events: bool,
+    feeds: Vec<ReferenceFeed>,
+    feature_windows: Vec<FeatureWindow>,
+    /// The table printed every `pipeline_summary_every` candles instead of
+    /// the per-candle lines.
+    summary: Option<PipelineSummary>,
+    /// Paused from the control endpoint: candles are only absorbed.
+    paused: bool,
+    /// Record how late candles arrive and are processed (live candles of a
+    /// fixed interval; replayed ones closed long ago).
+    close_lag: bool,
+}
+
+/// A `[[feature_windows]]` entry, recomputed every `every` candles over the
+/// newest `size` candles of the raw window and saved as its own snapshot.
+struct FeatureWindow {
+    config: FeatureWindowConfig,
+    path: String,
+    /// Candles since the last computation.
+    since: u64,
+}
+
+impl CandlePipeline<'_> {
+    /// Take in a candle whose feature row is skipped under the `coalesce`
+    /// backpressure policy, or while the pipeline is paused. It still enters
+    /// the window, the EMAs and the raw logs, so the next feature row is
+    /// exact.
+    async fn absorb(&mut self, kline: Kline) -> Result<()> {
+        if !self.screen(&kline)? {
+            return Ok(());
+        }
+        let state = Arc::clone(&self.state);
+        let absorbed = kline.clone();
+        task::spawn_blocking(move || state.lock().unwrap().absorb(&absorbed)).await?;
+
+        if !self.signal_only {
+            let started = Instant::now();
+            let (csv, journal) = tokio::join!(
+                data_storage::append_kline_to_csv_async(kline.clone(), self.paths.raw_csv.clone()),
+                data_storage::append_kline_to_journal_async(
+                    kline.clone(),
+                    self.paths.raw_journal.clone()
+                ),
+            );
+            for (target, path, result) in [
+                ("raw_csv", &self.paths.raw_csv, csv),
+                ("raw_journal", &self.paths.raw_journal, journal),
+            ] {
+                if let Err(e) = &result {
+                    eprintln!("Error saving coalesced kline to {}: {}", path, e);
+                }
+                let saved = record_save(target, path, started, &result);
+                if let Some(summary) = &mut self.summary {
+                    summary.saved(&saved);
+                }
+            }
+        }
+
+        bus::publish(Update::Candle {
+            symbol: self.symbol.to_string(),
+            interval: self.interval.to_string(),
+            kline: kline.clone(),
+        });
+        plugins::spawn(hooks::candle_closed(hooks::CandleClosed {
+            symbol: self.symbol.to_string(),
+            interval: self.interval.to_string(),
+            kline: kline.clone(),
+        }));
+        for flat in kill::mark(self.symbol, self.interval, kline.open_time, kline.close) {
+            signals::deliver(flat);
+        }
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.record(kline.open_time);
+        }
+        if let Some(consistency) = &self.consistency {
+            consistency.record(&kline);
+        }
+        if self.paused {
+            metrics::counter_inc("candles_paused_total", &[("symbol", self.symbol)], 1);
+            heartbeat::beat(&self.paths.heartbeat, kline.open_time);
+            return Ok(());
+        }
+        metrics::counter_inc("candles_coalesced_total", &[("symbol", self.symbol)], 1);
+        if let Some(summary) = &mut self.summary {
+            summary.coalesced();
+        }
+        Ok(())
+    }
+
+    /// Count and report a cycle that found `skipped` candles queued behind
+    /// the one it received.
+    fn report_coalesced(&self, skipped: usize, alert_threshold: usize) {
+        println!(
+            "[backpressure] {}: processing fell behind, coalesced {} queued candle(s) into the newest",
+            self.symbol, skipped
+        );
+        events::emit(Event::Coalesced {
+            symbol: self.symbol.to_string(),
+            interval: self.interval.to_string(),
+            skipped,
+        });
+        if alert_threshold > 0 && skipped >= alert_threshold {
+            alerts::raise(
+                Level::Warning,
+                "backpressure",
+                &format!(
+                    "{}: processing fell behind, skipped feature rows for {} candle(s)",
+                    self.symbol, skipped
+                ),
+            );
+        }
+    }
+
+    /// Write what a stopping pipeline still holds (see [`Self::flush`]).
+    async fn finish(&mut self) -> Result<()> {
+        self.log_summary();
+        self.flush().await
+    }
+
+    /// Write a raw cache snapshot (superseding the journal) and the pending
+    /// frame of the streaming log.
+    async fn flush(&mut self) -> Result<()> {
+        if !self.signal_only {
+            self.last_snapshot = clock::now_ms();
+            let state = Arc::clone(&self.state);
+            let store = self.store.clone();
+            task::spawn_blocking(move || {
+                store.snapshot(&state.lock().unwrap().raw_window.to_klines())
+            })
+            .await??;
+        }
+        if let Some(log) = self.streaming_jsonl.clone() {
+            task::spawn_blocking(move || log.lock().unwrap().flush()).await??;
+        }
+        Ok(())
+    }
+
+    /// Carry out a control endpoint command; what was done.
+    async fn control(&mut self, command: Command) -> Result<String> {
+        Ok(match command {
+            Command::Pause | Command::Resume => {
+                let pause = matches!(command, Command::Pause);
+                let changed = self.paused != pause;
+                self.paused = pause;
+                match (pause, changed) {
+                    (true, true) => "paused; candles are only absorbed",
+                    (false, true) => "resumed",
+                    (true, false) => "already paused",
+                    (false, false) => "not paused",
+                }
+                .to_string()
+            }
+            Command::Flush => {
+                self.flush().await?;
+                "raw cache snapshot and streaming log written".to_string()
+            }
+            Command::Rotate => {
+                // Pending rows belong to the file being rotated
+                if let Some(log) = self.streaming_jsonl.clone() {
+                    task::spawn_blocking(move || log.lock().unwrap().flush()).await??;
+                }
+                let mut rotated = Vec::new();
+                for path in [&self.paths.feature_csv, &self.paths.streaming_log] {
+                    if let Some(to) = utils::rotate_file(path)? {
+                        rotated.push(format!("{} → {}", path, to));
+                    }
+                }
+                if rotated.is_empty() {
+                    "nothing to rotate".to_string()
+                } else {
+                    rotated.join(", ")
+                }
+            }
+            Command::Reload(config) => {
+                self.strategies = config.build_strategies()?;
+                let loaded: Vec<String> = self
+                    .strategies
+                    .iter()
+                    .map(|s| format!("{} {}", s.name(), s.version()))
+                    .collect();
+                format!("strategies {}", loaded.join(", "))
+            }
+        })
+    }
+
+    /// Record how long after its nominal close (open time plus interval) a
+    /// candle reached `stage` (`arrival` or `processed`), at `at_ms` on the
+    /// system clock; the lag in ms, if recorded.
+    fn record_close_lag(&mut self, kline: &Kline, stage: &str, at_ms: i64) -> Option<f64> {
+        if !self.close_lag {
+            return None;
+        }
+        let lag = (at_ms - (kline.open_time + self.interval_minutes * 60_000)) as f64;
+        let labels = [
+            ("symbol", self.symbol),
+            ("interval", self.interval),
+            ("stage", stage),
+        ];
+        // A local clock behind the exchange's makes the lag negative
+        metrics::observe_ms(metrics::CLOSE_LAG, &labels, lag.max(0.0));
+        metrics::gauge_set("candle_close_lag_last_ms", &labels, lag);
+        if let Some(summary) = &mut self.summary {
+            summary.stage(
+                &format!("close_lag_{}", stage),
+                std::time::Duration::from_secs_f64(lag.max(0.0) / 1000.0),
+            );
+        }
+        Some(lag)
+    }
+
+    /// Print the summary of the candles since the last one, if any.
+    fn log_summary(&mut self) {
+        if let Some(summary) = &mut self.summary {
+            let state = self.state.lock().unwrap();
+            summary.log(
+                state.raw_window.len(),
+                state.window_size,
+                connection_stats::reconnects_of(self.symbol),
+            );
+        }
+    }
+
+    /// Apply the REST candles the consistency check queued in place of stored
+    /// ones: the window and EMAs take them, the raw CSV log is rewritten and
+    /// the raw cache snapshot falls due (unless the profile is signal-only).
+    /// Returns the oldest replaced open time, from which the feature-history
+    /// rows are rewritten.
+    async fn reconcile(&mut self) -> Result<Option<i64>> {
+        let Some(consistency) = &self.consistency else {
+            return Ok(None);
+        };
+        let mismatched = consistency.take_mismatches();
+        if !mismatched.is_empty() {
+            if let Some(quality) = &mut self.state.lock().unwrap().quality {
+                for open_time in mismatched {
+                    quality.mark(open_time, quality::MISMATCH);
+                }
+            }
+        }
+        let corrections = consistency.take_corrections();
+        if corrections.is_empty() {
+            return Ok(None);
+        }
+        let state = Arc::clone(&self.state);
+        let replaced =
+            task::spawn_blocking(move || state.lock().unwrap().correct(&corrections)).await?;
+        if replaced.is_empty() {
+            return Ok(None);
+        }
+        for kline in &replaced {
+            println!(
+                "[consistency] {}: replaced the {} candle of {} with the REST values",
+                self.symbol,
+                self.interval,
+                timezone::format_ms(kline.open_time)
+            );
+            events::emit(Event::CandleReconciled {
+                symbol: self.symbol.to_string(),
+                interval: self.interval.to_string(),
+                open_time: kline.open_time,
+                close: kline.close,
+                volume: kline.volume,
+            });
+        }
+        metrics::counter_inc(
+            "candles_reconciled_total",
+            &[("symbol", self.symbol)],
+            replaced.len() as u64,
+        );
+        // The snapshot supersedes the journal, so both are rewritten with it
+        self.last_snapshot = 0;
+        if self.signal_only {
+            return Ok(None);
+        }
+
+        let started = Instant::now();
+        let path = self.paths.raw_csv.clone();
+        let klines = replaced.clone();
+        let result = task::spawn_blocking(move || data_storage::rewrite_csv_klines(&path, &klines))
+            .await
+            .map_err(binance_streamer::Error::from)
+            .and_then(|r| r)
+            .map(|_| ());
+        if let Err(e) = &result {
+            eprintln!("Error rewriting {}: {}", self.paths.raw_csv, e);
+        }
+        let saved = record_save("raw_csv_rewrite", &self.paths.raw_csv, started, &result);
+        if let Some(summary) = &mut self.summary {
+            summary.saved(&saved);
+        }
+        Ok(replaced.iter().map(|k| k.open_time).min())
+    }
+
+    /// Follow the warm-up of a short window through the newest row of
+    /// `features_df`, until every feature has a value.
+    fn track_warmup(&mut self, features_df: &DataFrame) {
+        let Some(previous) = self.warming else {
+            return;
+        };
+        let flags = warmup::warmup_flags(&features_df.tail(Some(1)))
+            .first()
+            .copied()
+            .unwrap_or(0);
+        let labels = [("symbol", self.symbol), ("interval", self.interval)];
+        metrics::gauge_set("warmup_flags", &labels, f64::from(flags));
+        if flags == previous {
+            return;
+        }
+        let candles = self.state.lock().unwrap().raw_window.len();
+        if flags == 0 {
+            println!(
+                "[warmup] {}: every {} feature is warm after {} candles",
+                self.symbol, self.interval, candles
+            );
+            self.warming = None;
+        } else {
+            println!(
+                "[warmup] {}: {} candles, still warming up {}",
+                self.symbol,
+                candles,
+                warmup::warming_columns(flags).join(", ")
+            );
+            self.warming = Some(flags);
+        }
+    }
+
+    /// Whether `kline` passes the anomaly screen; a suspicious candle is
+    /// quarantined and never enters the window.
+    fn screen(&mut self, kline: &Kline) -> Result<bool> {
+        match &mut self.anomaly {
+            Some(anomaly) => anomaly.screen(kline),
+            None => Ok(true),
+        }
+    }
+
+    /// Run one closed candle through window update, features and persistence.
+    async fn process(&mut self, new_kline: Kline, received: Instant) -> Result<()> {
+        let arrived_ms = Utc::now().timestamp_millis() - received.elapsed().as_millis() as i64;
+        self.record_close_lag(&new_kline, "arrival", arrived_ms);
+        if !self.screen(&new_kline)? {
+            return Ok(());
+        }
+        for feed in &self.feeds {
+            feed.wait_for(self.symbol, new_kline.open_time).await;
+        }
+        clock::advance(new_kline.close_time);
+        // Joined streams are waited for, not worked on: the deadline starts here
+        let work_start = Instant::now();
+        let rewrite_from = self.reconcile().await?;
+        if maintenance::overlaps(self.exchange, new_kline.open_time, new_kline.close_time) {
+            if let Some(quality) = &mut self.state.lock().unwrap().quality {
+                quality.mark(new_kline.open_time, quality::MAINTENANCE);
+            }
+        }
+        let plan = self.memory_guard.check();
+        let feature_window = plan.feature_window.min(self.feature_window);
+        let state = Arc::clone(&self.state);
+        let kline = new_kline.clone();
+        let features_df =
+            task::spawn_blocking(move || state.lock().unwrap().advance(&kline, feature_window))
+                .await??;
+        let features_time = work_start.elapsed();
+        self.track_warmup(&features_df);
+
+        // --- Evaluate strategies on the new row ---
+        let stage_start = Instant::now();
+        let mut signals = Vec::new();
+        let mut audit_row = None;
+        // Signals are dropped inside calendar event windows and while the
+        // consistency check holds them
+        let hold = if self.suppress_around_events {
+            calendar::active_event(new_kline.close_time + 1).map(|event| {
+                (
+                    "calendar",
+                    format!(
+                        "inside the window of {} at {}",
+                        event.name,
+                        timezone::format_ms(event.time)
+                    ),
+                )
+            })
+        } else {
+            None
+        }
+        .or_else(|| {
+            self.consistency.as_ref().filter(|c| c.held()).map(|_| {
+                (
+                    "consistency",
+                    "while stored candles differ from REST".to_string(),
+                )
+            })
+        });
+        {
+            // Strategies read the raw window in place; nothing awaits meanwhile
+            let state = self.state.lock().unwrap();
+            let window = WindowStats::new(&state.raw_window);
+            for strategy in &mut self.strategies {
+                let side = strategy.evaluate(&features_df, &window)?;
+                // A flat decision is only a signal when it closes a position
+                if side != Side::Flat || strategy.exited() {
+                    let killed = kill::disabled(self.symbol, self.interval, strategy.name())
+                        .then(|| ("kill", "of a disabled strategy".to_string()));
+                    // Evaluated all the same, so the strategy's state stays current
+                    if let Some((reason, why)) = hold.as_ref().or(killed.as_ref()) {
+                        println!(
+                            "[{}] {}: suppressed {} {} signal {}",
+                            reason,
+                            self.symbol,
+                            strategy.name(),
+                            side.as_str(),
+                            why
+                        );
+                        metrics::counter_inc(
+                            "signals_suppressed_total",
+                            &[
+                                ("symbol", self.symbol),
+                                ("strategy", strategy.name()),
+                                ("reason", reason),
+                            ],
+                            1,
+                        );
+                        continue;
+                    }
+                    let signal = Signal {
+                        symbol: self.symbol.to_string(),
+                        interval: self.interval.to_string(),
+                        strategy: strategy.name().to_string(),
+                        side,
+                        open_time: new_kline.open_time,
+                        price: new_kline.close,
+                        size: strategy.size(),
+                        stop: strategy.stop(),
+                    };
+                    // The feature row is converted once, for the first signal
+                    if self.signal_audit && audit_row.is_none() {
+                        let last = features_df.height().saturating_sub(1);
+                        audit_row = Some(data_storage::row_to_json(&features_df, last)?);
+                    }
+                    if let Some(row) = &audit_row {
+                        if let Err(e) =
+                            audit::record(&self.paths.audit, strategy.as_ref(), &signal, row)
+                        {
+                            alerts::raise(
+                                Level::Warning,
+                                "audit",
+                                &format!("{}: failed to record signal: {}", self.symbol, e),
+                            );
+                        }
+                    }
+                    signals.push(signal);
+                }
+            }
+        }
+        metrics::observe_stage("strategy", stage_start);
+        let strategy_time = stage_start.elapsed();
+
+        let saving_start = Instant::now();
+        let persistence = if self.signal_only {
+            PersistenceMode::SignalsOnly
+        } else if self.deterministic {
+            PersistenceMode::Full
+        } else {
+            self.disk_guard.check()
+        };
+        // Over the deadline, the optional outputs go first
+        let degraded = self.deadline.degraded();
+
+        // Strategies and subscribers get full precision; storage may not
+        let stored_df = if self.float32_features {
+            data_storage::downcast_features(&features_df)?
+        } else {
+            features_df.clone()
+        };
+
+        let mut saves: Vec<BoxFuture<'static, Saved>> = Vec::new();
+
+        // 0. Recompute and save the feature windows that are due
+        for window in &mut self.feature_windows {
+            window.since += 1;
+            if window.since < window.config.every {
+                continue;
+            }
+            if degraded {
+                // Still due, so it is computed once candles are on time again
+                self.deadline
+                    .skip(&format!("features_{}", window.config.name));
+                continue;
+            }
+            window.since = 0;
+            let state = Arc::clone(&self.state);
+            let config = window.config.clone();
+            let path = window.path.clone();
+            let float32 = self.float32_features;
+            saves.push(
+                async move {
+                    let started = Instant::now();
+                    let target = format!("features_{}", config.name);
+                    let save_path = path.clone();
+                    let result = task::spawn_blocking(move || {
+                        let df = {
+                            let state = state.lock().unwrap();
+                            features::window_frame(&state.raw_window, config.size)?
+                        };
+                        let df = features::add_groups(df, config.groups())?;
+                        let mut df = if float32 {
+                            data_storage::downcast_features(&df)?
+                        } else {
+                            df
+                        };
+                        data_storage::save_dataframe_parquet(&mut df, &save_path)?;
+                        Ok(())
+                    })
+                    .await
+                    .map_err(anyhow::Error::from)
+                    .and_then(|r| r);
+                    if let Err(e) = &result {
+                        eprintln!("Error saving feature window {}: {}", target, e);
+                    }
+                    record_save(&target, &path, started, &result)
+                }
+                .boxed(),
+            );
+        }
+
+        if persistence != PersistenceMode::SignalsOnly {
+            // 1. Save feature parquet (clone DataFrame; lean while over the memory budget)
+            let df_clone = if plan.drop_optional {
+                stored_df.drop_many(OPTIONAL_COLUMNS)
+            } else {
+                stored_df.clone()
+            };
+            let path = self.paths.feature_parquet.clone();
+            saves.push(
+                async move {
+                    let started = Instant::now();
+                    let result =
+                        data_storage::save_dataframe_parquet_async(df_clone, path.clone()).await;
+                    if let Err(e) = &result {
+                        eprintln!("Error saving feature parquet: {}", e);
+                    }
+                    record_save("feature_parquet", &path, started, &result)
+                }
+                .boxed(),
+            );
+
+            // 1b. Append the new row to the point-in-time feature history, with
+            //     the rows of reconciled candles rewritten
+            let df_clone = stored_df.clone();
+            let path = self.paths.feature_history.clone();
+            saves.push(
+                async move {
+                    let started = Instant::now();
+                    let result = match rewrite_from {
+                        Some(from) => {
+                            let dir = path.clone();
+                            task::spawn_blocking(move || {
+                                // Rows as they looked at their close: no right pivot runs
+                                let df = pivots::add_pivot_features_as_of_close(df_clone)?;
+                                let open_times = df.column("open_time")?.i64()?.clone();
+                                let rows = df.filter(&open_times.gt_eq(from))?;
+                                data_storage::append_feature_history_rows(&rows, &dir)?;
+                                Ok(())
+                            })
+                            .await
+                            .map_err(anyhow::Error::from)
+                            .and_then(|r| r)
+                        }
+                        None => {
+                            data_storage::append_feature_history_row_async(df_clone, path.clone())
+                                .await
+                                .map_err(anyhow::Error::from)
+                        }
+                    };
+                    if let Err(e) = &result {
+                        eprintln!("Error appending feature history: {}", e);
+                    }
+                    record_save("feature_history", &path, started, &result)
+                }
+                .boxed(),
+            );
+        }
+
+        // 2. Save feature CSV (optional, skipped when disk is low or over the deadline)
+        if persistence == PersistenceMode::Full && degraded {
+            self.deadline.skip("feature_csv");
+            self.deadline.skip(if self.streaming_jsonl.is_some() {
+                "streaming_jsonl"
+            } else {
+                "streaming_csv"
+            });
+        } else if persistence == PersistenceMode::Full {
+            let df_clone = stored_df.clone();
+            let path = self.paths.feature_csv.clone();
+            saves.push(
+                async move {
+                    let started = Instant::now();
+                    let result =
+                        data_storage::append_features_row_to_csv_async(df_clone, path.clone())
+                            .await;
+                    if let Err(e) = &result {
+                        eprintln!("Error saving feature CSV: {}", e);
+                    }
+                    record_save("feature_csv", &path, started, &result)
+                }
+                .boxed(),
+            );
+
+            // 3. Append feature row to streaming log
+            let df_clone = stored_df.clone();
+            let path = self.paths.streaming_log.clone();
+            let jsonl = self.streaming_jsonl.clone();
+            saves.push(
+                async move {
+                    let started = Instant::now();
+                    let (target, result) = match jsonl {
+                        Some(log) => (
+                            "streaming_jsonl",
+                            task::spawn_blocking(move || log.lock().unwrap().append_row(&df_clone))
+                                .await
+                                .map_err(binance_streamer::Error::from)
+                                .and_then(|r| r),
+                        ),
+                        None => (
+                            "streaming_csv",
+                            data_storage::append_features_row_to_csv_async(df_clone, path.clone())
+                                .await,
+                        ),
+                    };
+                    if let Err(e) = &result {
+                        eprintln!("Error appending feature row: {}", e);
+                    }
+                    record_save(target, &path, started, &result)
+                }
+                .boxed(),
+            );
+        }
+
+        if persistence != PersistenceMode::SignalsOnly {
+            // 4. Append raw kline to raw CSV
+            let kline_clone = new_kline.clone();
+            let path = self.paths.raw_csv.clone();
+            saves.push(
+                async move {
+                    let started = Instant::now();
+                    let result =
+                        data_storage::append_kline_to_csv_async(kline_clone, path.clone()).await;
+                    if let Err(e) = &result {
+                        eprintln!("Error appending raw kline: {}", e);
+                    }
+                    record_save("raw_csv", &path, started, &result)
+                }
+                .boxed(),
+            );
+
+            // 5. Journal the raw kline; rewrite the full Parquet cache only when a
+            //    snapshot is due (the snapshot then supersedes the journal)
+            let now = clock::now_ms();
+            if now - self.last_snapshot >= self.snapshot_every.as_millis() as i64 {
+                self.last_snapshot = now;
+                let state = Arc::clone(&self.state);
+                let store = self.store.clone();
+                let path = self.paths.raw_cache.clone();
+                saves.push(
+                    async move {
+                        let started = Instant::now();
+                        let result = task::spawn_blocking(move || {
+                            store.snapshot(&state.lock().unwrap().raw_window.to_klines())
+                        })
+                        .await
+                        .map_err(binance_streamer::Error::from)
+                        .and_then(|r| r);
+                        if let Err(e) = &result {
+                            eprintln!("Error saving raw Parquet: {}", e);
+                        }
+                        record_save("raw_parquet", &path, started, &result)
+                    }
+                    .boxed(),
+                );
+            } else {
+                let kline_clone = new_kline.clone();
+                let path = self.paths.raw_journal.clone();
+                saves.push(
+                    async move {
+                        let started = Instant::now();
+                        let result =
+                            data_storage::append_kline_to_journal_async(kline_clone, path.clone())
+                                .await;
+                        if let Err(e) = &result {
+                            eprintln!("Error journaling raw kline: {}", e);
+                        }
+                        record_save("raw_journal", &path, started, &result)
+                    }
+                    .boxed(),
+                );
+            }
+        }
+
+        // Wait for all saves to complete; concurrently unless the run must be reproducible
+        let mut saved = Vec::with_capacity(saves.len());
+        if self.deterministic {
+            for save in saves {
+                saved.push(save.await);
+            }
+        } else {
+            for result in join_all(saves.into_iter().map(tokio::spawn)).await {
+                // A panicked save is a failed one; which output it wrote is lost
+                saved.push(result.unwrap_or_else(|e| {
+                    eprintln!("Error: a save task of the candle failed: {}", e);
+                    Saved {
+                        target: "panicked".to_string(),
+                        ok: false,
+                        ms: 0.0,
+                    }
+                }));
+            }
+        }
+        metrics::observe_stage("save_all", saving_start);
+        if let Some(summary) = &mut self.summary {
+            summary.stage("features", features_time);
+            summary.stage("strategy", strategy_time);
+            summary.stage("save_all", saving_start.elapsed());
+            for saved in &saved {
+                summary.saved(saved);
+            }
+        }
+        self.deadline.record(
+            new_kline.open_time,
+            work_start.elapsed(),
+            &[
+                ("features", features_time),
+                ("strategy", strategy_time),
+                ("save_all", saving_start.elapsed()),
+            ],
+        );
+        bus::publish(Update::Candle {
+            symbol: self.symbol.to_string(),
+            interval: self.interval.to_string(),
+            kline: new_kline.clone(),
+        });
+        publish_latest(self.symbol, self.interval, &features_df, self.paths);
+        plugins::spawn(hooks::candle_closed(hooks::CandleClosed {
+            symbol: self.symbol.to_string(),
+            interval: self.interval.to_string(),
+            kline: new_kline.clone(),
+        }));
+        if hooks::wants_features() {
+            plugins::spawn(hooks::features_ready(hooks::FeaturesReady {
+                symbol: self.symbol.to_string(),
+                interval: self.interval.to_string(),
+                kline: new_kline.clone(),
+                row: features_df.tail(Some(1)),
+            }));
+        }
+        let signal_count = signals.len();
+        for signal in signals {
+            signals::route(signal);
+        }
+        for flat in kill::mark(
+            self.symbol,
+            self.interval,
+            new_kline.open_time,
+            new_kline.close,
+        ) {
+            signals::deliver(flat);
+        }
+        let close_lag_ms =
+            self.record_close_lag(&new_kline, "processed", Utc::now().timestamp_millis());
+
+        // --- Print basic kline info ---
+        let open_time_str = timezone::format_ms(new_kline.open_time);
+        let close_time_str = timezone::format_ms(new_kline.close_time);
+        let nominal_end = timezone::format_ms(new_kline.open_time + self.interval_minutes * 60_000);
+
+        metrics::observe_stage("total", received);
+        events::emit(Event::CandleProcessed {
+            symbol: self.symbol.to_string(),
+            interval: self.interval.to_string(),
+            open_time: new_kline.open_time,
+            close_time: new_kline.close_time,
+            close: new_kline.close,
+            volume: new_kline.volume,
+            latency_ms: received.elapsed().as_secs_f64() * 1000.0,
+            close_lag_ms,
+        });
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.record(new_kline.open_time);
+        }
+        if let Some(consistency) = &self.consistency {
+            consistency.record(&new_kline);
+        }
+        heartbeat::beat(&self.paths.heartbeat, new_kline.open_time);
+        metrics::counter_inc("candles_processed_total", &[], 1);
+        let state = self.state.lock().unwrap();
+        metrics::gauge_set("raw_window_size", &[], state.raw_window.len() as f64);
+
+        let level = control::log_level();
+        if let Some(summary) = &mut self.summary {
+            summary.stage("total", received.elapsed());
+            if summary.candle(new_kline.open_time, signal_count) {
+                if level == LogLevel::Warn {
+                    summary.clear();
+                } else {
+                    summary.log(
+                        state.raw_window.len(),
+                        state.window_size,
+                        connection_stats::reconnects_of(self.symbol),
+                    );
+                }
+            }
+            if level != LogLevel::Debug {
+                return Ok(());
+            }
+        }
+        if level == LogLevel::Warn {
+            return Ok(());
+        }
+        println!(
+            "Kline | Open: {} | Close (actual): {} | Nominal End: {} | High: {} | Low: {} | ClosePrice: {} | Volume: {}",
+            open_time_str,
+            close_time_str,
+            nominal_end,
+            new_kline.high,
+            new_kline.low,
+            new_kline.close,
+            new_kline.volume
+        );
+        if timezone::has_sessions() {
+            let sessions = timezone::active_sessions(new_kline.open_time);
+            println!(
+                "Sessions: {}",
+                if sessions.is_empty() {
+                    "none".to_string()
+                } else {
+                    sessions.join(", ")
+                }
+            );
+        }
+        utils::print_memory_usage();
+
+        utils::log_memory_breakdown(&state.raw_window, &features_df);
+        Ok(())
+    }
+}
+
+/// Feature state joined by `open_time` with the events of a second live
+/// stream.
+trait Joined: Send {
+    fn push_primary(&mut self, kline: &Kline);
+    fn push_reference(&mut self, event: &StreamEvent);
+    fn has_reference(&self, open_time: i64) -> bool;
+    fn add_columns(&self, df: DataFrame) -> PolarsResult<DataFrame>;
+}
+
+impl Joined for CrossExchange {
+    fn push_primary(&mut self, kline: &Kline) {
+        CrossExchange::push_primary(self, kline)
+    }
+    fn push_reference(&mut self, event: &StreamEvent) {
+        if let StreamEvent::Candle(kline) = event {
+            CrossExchange::push_reference(self, kline)
+        }
+    }
+    fn has_reference(&self, open_time: i64) -> bool {
+        CrossExchange::has_reference(self, open_time)
+    }
+    fn add_columns(&self, df: DataFrame) -> PolarsResult<DataFrame> {
+        CrossExchange::add_columns(self, df)
+    }
+}
+
+impl Joined for UsdConversion {
+    fn push_primary(&mut self, kline: &Kline) {
+        UsdConversion::push_primary(self, kline)
+    }
+    fn push_reference(&mut self, event: &StreamEvent) {
+        if let StreamEvent::Candle(kline) = event {
+            UsdConversion::push_reference(self, kline)
+        }
+    }
+    fn has_reference(&self, open_time: i64) -> bool {
+        UsdConversion::has_reference(self, open_time)
+    }
+    fn add_columns(&self, df: DataFrame) -> PolarsResult<DataFrame> {
+        UsdConversion::add_columns(self, df)
+    }
+}
+
+impl Joined for OrderFlow {
+    // Rows come from the trades alone
+    fn push_primary(&mut self, _kline: &Kline) {}
+    fn push_reference(&mut self, event: &StreamEvent) {
+        if let StreamEvent::Trade {
+            price,
+            qty,
+            time,
+            taker_buy: Some(taker_buy),
+        } = *event
+        {
+            OrderFlow::push_trade(self, price, qty, time, taker_buy)
+        }
+    }
+    fn has_reference(&self, open_time: i64) -> bool {
+        OrderFlow::is_complete(self, open_time)
+    }
+    fn add_columns(&self, df: DataFrame) -> PolarsResult<DataFrame> {
+        OrderFlow::add_columns(self, df)
+    }
+}
+
+impl Joined for ReferenceCorrelation {
+    fn push_primary(&mut self, kline: &Kline) {
+        ReferenceCorrelation::push_primary(self, kline)
+    }
+    fn push_reference(&mut self, event: &StreamEvent) {
+        if let StreamEvent::Candle(kline) = event {
+            ReferenceCorrelation::push_reference(self, kline)
+        }
+    }
+    fn has_reference(&self, open_time: i64) -> bool {
+        ReferenceCorrelation::has_reference(self, open_time)
+    }
+    fn add_columns(&self, df: DataFrame) -> PolarsResult<DataFrame> {
+        ReferenceCorrelation::add_columns(self, df)
+    }
+}
+
+/// [`BookSpread`] that hands its sampled quotes to the writer of the quote
+/// history.
+struct SampledSpread {
+    spread: BookSpread,
+    quotes: Option<mpsc::Sender<BookQuote>>,
+}
+
+impl SampledSpread {
+    /// The spread state, its sampled quotes appended to the CSV at `path`
+    /// (none if empty) by a task of their own, so no file I/O runs under the
+    /// state's lock.
+    fn new(spread: BookSpread, path: &str) -> Self {
+        let quotes = (!path.is_empty()).then(|| {
+            let (quotes, mut received) = mpsc::channel::<BookQuote>(CANDLE_QUEUE);
+            let path = path.to_string();
+            tokio::spawn(async move {
+                while let Some(quote) = received.recv().await {
+                    if let Err(e) =
+                        data_storage::append_quote_to_csv_async(quote, path.clone()).await
+                    {
+                        eprintln!("Error saving quote to {}: {}", path, e);
+                    }
+                }
+            });
+            quotes
+        });
+        Self { spread, quotes }
+    }
+}
+
+impl Joined for SampledSpread {
+    // Rows come from the quotes alone
+    fn push_primary(&mut self, _kline: &Kline) {}
+    fn push_reference(&mut self, event: &StreamEvent) {
+        let StreamEvent::Quote(quote) = event else {
+            return;
+        };
+        if !self.spread.push_quote(quote) {
+            return;
+        }
+        if let Some(quotes) = &self.quotes {
+            if let Err(e) = quotes.try_send(*quote) {
+                eprintln!("Dropped a sampled quote from the quote history: {}", e);
+            }
+        }
+    }
+    fn has_reference(&self, open_time: i64) -> bool {
+        self.spread.is_complete(open_time)
+    }
+    fn add_columns(&self, df: DataFrame) -> PolarsResult<DataFrame> {
+        self.spread.add_columns(df)
+    }
+}
+
+/// A second live stream whose events go into a [`Joined`] state: candles of
+/// the same interval from the reference venue of the cross-exchange features
+/// or the conversion pair of the USD columns, or the trades or best bid/ask
+/// quotes of the symbol for the order-flow and spread features. Dropping the
+/// feed closes the stream.
+struct ReferenceFeed {
+    state: Arc<Mutex<dyn Joined>>,
+    reference: exchange::Exchange,
+    symbol: String,
+    interval: String,
+    history: usize,
+    join_wait: std::time::Duration,
+    /// Counter of candles computed without their reference candle.
+    missed_metric: &'static str,
+    source: task::JoinHandle<Result<()>>,
+}
+
+impl Drop for ReferenceFeed {
+    fn drop(&mut self) {
+        self.source.abort();
+    }
+}
+
+impl ReferenceFeed {
+    /// Connect to `config.cross_exchange.reference`, if set. Its frames go
+    /// to the same `recorder` as the primary stream's.
+    fn cross_exchange(
+        stream_type: &str,
+        config: &Config,
+        recorder: Option<Recorder>,
+    ) -> Result<Option<Self>> {
+        let cross = &config.cross_exchange;
+        let interval = stream_interval(stream_type);
+        let Some(reference) = cross.reference.filter(|_| !interval.is_empty()) else {
+            return Ok(None);
+        };
+        let basis = match (config.exchange.is_perpetual(), reference.is_perpetual()) {
+            (true, false) => Basis::PrimaryIsPerp,
+            (false, true) => Basis::ReferenceIsPerp,
+            _ => Basis::None,
+        };
+        let state = Arc::new(Mutex::new(CrossExchange::new(
+            exchange::interval_ms(interval)?,
+            cross.correlation_window,
+            config.window_size(interval),
+            basis,
+        )));
+
+        let mut reference_config = config.clone();
+        reference_config.exchange = reference;
+        if let Some(symbol) = &cross.symbol {
+            reference_config.symbol = symbol.clone();
+        }
+        Ok(Some(Self::spawn(
+            StreamKind::Klines(interval.to_string()),
+            reference_config,
+            state,
+            cross.history,
+            cross.join_wait_ms,
+            "cross_exchange_missed_total",
+            recorder,
+        )))
+    }
+
+    /// Connect to the `[usd]` conversion pair, if set.
+    fn usd(stream_type: &str, config: &Config, recorder: Option<Recorder>) -> Option<Self> {
+        let usd = &config.usd;
+        let pair = usd
+            .pair
+            .as_ref()
+            .filter(|_| !stream_interval(stream_type).is_empty())?;
+        let state = Arc::new(Mutex::new(UsdConversion::new(
+            config.window_size(stream_interval(stream_type)),
+            usd.invert,
+        )));
+        let mut reference_config = config.clone();
+        reference_config.symbol = pair.clone();
+        Some(Self::spawn(
+            StreamKind::Klines(stream_interval(stream_type).to_string()),
+            reference_config,
+            state,
+            usd.history,
+            usd.join_wait_ms,
+            "usd_conversion_missed_total",
+            recorder,
+        ))
+    }
+
+    /// Connect to the `[correlation]` reference symbol, if set and not the
+    /// pipeline's own symbol.
+    fn correlation(
+        stream_type: &str,
+        config: &Config,
+        recorder: Option<Recorder>,
+    ) -> Result<Option<Self>> {
+        let correlation = &config.correlation;
+        let interval = stream_interval(stream_type);
+        let Some(reference) = correlation
+            .reference
+            .as_ref()
+            .filter(|r| !interval.is_empty() && **r != config.symbol)
+        else {
+            return Ok(None);
+        };
+        let state = Arc::new(Mutex::new(ReferenceCorrelation::new(
+            exchange::interval_ms(interval)?,
+            correlation.window,
+            config.window_size(interval),
+        )));
+        let mut reference_config = config.clone();
+        reference_config.symbol = reference.clone();
+        Ok(Some(Self::spawn(
+            StreamKind::Klines(interval.to_string()),
+            reference_config,
+            state,
+            correlation.history,
+            correlation.join_wait_ms,
+            "correlation_missed_total",
+            recorder,
+        )))
+    }
+
+    /// Stream the symbol's trades into the `[order_flow]` state, if enabled.
+    fn order_flow(
+        stream_type: &str,
+        config: &Config,
+        recorder: Option<Recorder>,
+    ) -> Result<Option<Self>> {
+        let flow = &config.order_flow;
+        let interval = stream_interval(stream_type);
+        if !flow.enabled || interval.is_empty() {
+            return Ok(None);
+        }
+        let state = Arc::new(Mutex::new(OrderFlow::new(
+            exchange::interval_ms(interval)?,
+            config.window_size(interval),
+            flow.block_notional,
+        )));
+        Ok(Some(Self::spawn(
+            StreamKind::AggTrades,
+            config.clone(),
+            state,
+            0,
+            flow.join_wait_ms,
+            "order_flow_missed_total",
+            recorder,
+        )))
+    }
+
+    /// Stream the symbol's best bid and ask into the `[spread]` state, if
+    /// enabled; sampled quotes go to `path`.
+    fn spread(
+        stream_type: &str,
+        config: &Config,
+        path: &str,
+        recorder: Option<Recorder>,
+    ) -> Result<Option<Self>> {
+        let spread = &config.spread;
+        let interval = stream_interval(stream_type);
+        if !spread.enabled || interval.is_empty() {
+            return Ok(None);
+        }
+        let state = Arc::new(Mutex::new(SampledSpread::new(
+            BookSpread::new(
+                exchange::interval_ms(interval)?,
+                config.window_size(interval),
+                spread.sample_ms as i64,
+            ),
+            path,
+        )));
+        Ok(Some(Self::spawn(
+            StreamKind::BookTicker,
+            config.clone(),
+            state,
+            0,
+            spread.join_wait_ms,
+            "spread_missed_total",
+            recorder,
+        )))
+    }
+
+    /// Stream `kind` of `reference_config.symbol` on
+    /// `reference_config.exchange` into `state`.
+    fn spawn(
+        kind: StreamKind,
+        reference_config: Config,
+        state: Arc<Mutex<dyn Joined>>,
+        history: usize,
+        join_wait_ms: u64,
+        missed_metric: &'static str,
+        recorder: Option<Recorder>,
+    ) -> Self {
+        let reference = reference_config.exchange;
+        let symbol = reference_config.symbol.clone();
+        let interval = match &kind {
+            StreamKind::Klines(interval) => interval.clone(),
+            _ => String::new(),
+        };
+        let (events, mut received) = mpsc::channel::<StreamEvent>(CANDLE_QUEUE);
+        let collect = tokio::spawn(connect(
+            kind,
+            reference_config,
+            Sink::Events(events),
+            recorder,
+        ));
+        let feed_state = Arc::clone(&state);
+        let source = tokio::spawn(async move {
+            while let Some(event) = received.recv().await {
+                feed_state.lock().unwrap().push_reference(&event);
+            }
+            collect.await?
+        });
+
+        Self {
+            state,
+            reference,
+            symbol,
+            interval,
+            history,
+            join_wait: std::time::Duration::from_millis(join_wait_ms),
+            missed_metric,
+            source,
+        }
+    }
+
+    /// Load the primary `history` (its last `window` candles) and the newest
+    /// reference candles, so the latest rows start out joined. A failed
+    /// reference fetch only leaves the historical rows null.
+    async fn warm_up(&self, history: &[Kline], window: usize) {
+        if self.history > 0 {
+            match self
+                .reference
+                .source()
+                .fetch_latest_klines(&self.symbol, &self.interval, self.history)
+                .await
+            {
+                Ok(klines) => {
+                    let mut state = self.state.lock().unwrap();
+                    for kline in klines {
+                        state.push_reference(&StreamEvent::Candle(kline));
+                    }
+                }
+                Err(e) => eprintln!(
+                    "Warning: failed to load {} history from {}: {}",
+                    self.symbol,
+                    self.reference.as_str(),
+                    e
+                ),
+            }
+        }
+        let mut state = self.state.lock().unwrap();
+        let skip = history.len().saturating_sub(window);
+        for kline in &history[skip..] {
+            state.push_primary(kline);
+        }
+    }
+
+    /// Give the reference candle opening at `open_time` up to `join_wait` to
+    /// arrive; a row computed without it keeps null joined values.
+    async fn wait_for(&self, symbol: &str, open_time: i64) {
+        let deadline = Instant::now() + self.join_wait;
+        while !self.state.lock().unwrap().has_reference(open_time) {
+            if Instant::now() >= deadline {
+                metrics::counter_inc(self.missed_metric, &[("symbol", symbol)], 1);
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+    }
+}
+
+/// A closed candle and the instant its message was received.
+pub type CandleSender = mpsc::Sender<(Kline, Instant)>;
+
+/// Candles queued between the connection and the pipeline.
+const CANDLE_QUEUE: usize = 1024;
+
+/// Merge the live candles buffered during warm-up into the loaded history:
+/// newer candles are appended (and logged to the raw CSV and journal, which
+/// the history load has already written), a candle with the same open time
+/// replaces the stored one, older ones are dropped. Returns how many candles
+/// at the end of `history` are live ones.
+fn merge_buffered(
+    history: &mut Vec<Kline>,
+    received: &mut mpsc::Receiver<(Kline, Instant)>,
+    paths: Option<&OutputPaths>,
+) -> usize {
+    let mut live = 0;
+    while let Ok((kline, _)) = received.try_recv() {
+        let last_open_time = history.last().map_or(i64::MIN, |k| k.open_time);
+        if kline.open_time < last_open_time {
+            continue;
+        }
+        for (path, result) in paths.into_iter().flat_map(|paths| {
+            [
+                (
+                    &paths.raw_csv,
+                    data_storage::append_kline_to_csv(&kline, &paths.raw_csv),
+                ),
+                (
+                    &paths.raw_journal,
+                    data_storage::append_kline_to_journal(&kline, &paths.raw_journal),
+                ),
+            ]
+        }) {
+            if let Err(e) = result {
+                eprintln!("Error saving buffered kline to {}: {}", path, e);
+            }
+        }
+        // Replacing a live candle leaves the count as it is
+        if kline.open_time != last_open_time || live == 0 {
+            live += 1;
+        }
+        if kline.open_time == last_open_time {
+            history.pop();
+        }
+        history.push(kline);
+    }
+    live
 }
 
 /// Run the live stream.
-/// - `raw_window` – initial raw data window (50k)
-/// - `raw_cache_file` – Parquet file for raw rolling window (overwritten)
-/// - `raw_csv_file` – CSV file for raw data (appended)
-/// - `feature_parquet` – Parquet file for feature‑enriched window (overwritten)
-/// - `feature_csv` – CSV file for feature‑enriched window (overwritten)
+/// - `config` – runtime configuration (symbol, disk guardrails, ...)
+/// - `history` – loads the initial raw data window (`[window]` candles)
+/// - `paths` – resolved output files (raw Parquet/CSV, feature Parquet/CSV logs)
+/// - `store` – the raw candle cache, snapshotted every `raw_snapshot_secs`
+///
+/// Candles come from the Binance WebSocket (see [`collect`]), from a shared
+/// base stream with `derive_from` (see [`derive`]), from a separate
+/// collector process with `[ipc] role = "worker"`, or from `replay` (see
+/// [`replay`]), in which case the run ends after the last one. The source is
+/// started before `history` is awaited, so candles closing during a cold
+/// start are buffered and merged into the window instead of being missed.
+/// It runs in its own task, so feature computation and persistence never
+/// stall the connection. Pipeline errors and a failed source are returned to
+/// the caller.
 pub async fn run(
     stream_type: &str,
-    mut raw_window: VecDeque<Kline>, // was Vec<Kline>
-    raw_cache_file: &str,
-    raw_csv_file: &str,
-    feature_parquet: &str,
-    feature_csv: &str,
-    streaming_csv_file: &str,
+    config: &Config,
+    history: impl Future<Output = Result<Vec<Kline>>>,
+    paths: &OutputPaths,
+    store: HistoryStore,
+    replay_klines: Option<Vec<Kline>>,
 ) -> Result<()> {
     let start = Instant::now();
+    let symbol = config.symbol.as_str();
+    let interval = stream_interval(stream_type);
+    let window_size = config.window_size(interval);
+    let signal_only = config.profile == Profile::SignalOnly;
+    // Where candles and features are written; nowhere in the signal-only profile
+    let persisted = (!signal_only).then_some(paths);
+
+    // Connect first; the source buffers candles while history loads
+    let (candles, mut received) = mpsc::channel(CANDLE_QUEUE);
+    let replaying = replay_klines.is_some();
+    let recorder = if config.record && !replaying && !paths.recording.is_empty() {
+        Some(Recorder::spawn(&paths.recording)?)
+    } else {
+        None
+    };
+    let source = match (replay_klines, config.ipc.role) {
+        (Some(klines), _) => tokio::spawn(replay(klines, candles)),
+        // The collector replays what it holds; duplicates are merged below
+        (None, Role::Worker) => tokio::spawn(ipc::receive(
+            config.ipc.addr.clone().unwrap_or_default(),
+            symbol.to_string(),
+            interval.to_string(),
+            0,
+            candles,
+        )),
+        (None, _) => match &config.derive_from {
+            Some(base) if !interval.is_empty() => tokio::spawn(derive(
+                stream_type.to_string(),
+                base.clone(),
+                config.clone(),
+                candles,
+                recorder.clone(),
+            )),
+            _ => tokio::spawn(collect(
+                stream_type.to_string(),
+                config.clone(),
+                candles,
+                recorder.clone(),
+            )),
+        },
+    };
+    // A replay has no second stream to join
+    let mut feeds = Vec::new();
+    if !replaying {
+        feeds.extend(ReferenceFeed::cross_exchange(
+            stream_type,
+            config,
+            recorder.clone(),
+        )?);
+        feeds.extend(ReferenceFeed::usd(stream_type, config, recorder.clone()));
+        feeds.extend(ReferenceFeed::correlation(
+            stream_type,
+            config,
+            recorder.clone(),
+        )?);
+        feeds.extend(ReferenceFeed::order_flow(
+            stream_type,
+            config,
+            recorder.clone(),
+        )?);
+        feeds.extend(ReferenceFeed::spread(
+            stream_type,
+            config,
+            &paths.book_ticker,
+            recorder,
+        )?);
+    }
+    let mut raw_window = match history.await {
+        Ok(history) => history,
+        Err(e) => {
+            source.abort();
+            return Err(e);
+        }
+    };
+    if let Some(last) = raw_window.last() {
+        clock::advance(last.close_time);
+    }
+    // Replayed candles are all meant for the pipeline, not the warm-up
+    let mut live = 0;
+    if !replaying {
+        live = merge_buffered(&mut raw_window, &mut received, persisted);
+        if live > 0 {
+            println!(
+                "Merged {} live candle(s) received while loading history.",
+                live
+            );
+        }
+        // The kill books of the strategies as they stood before a restart
+        match kill::restore(&paths.audit, symbol, interval, &raw_window) {
+            Ok(0) => {}
+            Ok(restored) => println!(
+                "Rebuilt the kill books from {} audited signal(s).",
+                restored
+            ),
+            Err(e) => eprintln!("Warning: failed to rebuild the kill books: {}", e),
+        }
+    }
+    // Every candle but the merged live ones was loaded rather than streamed
+    let quality = config.quality.enabled.then(|| {
+        let mut quality = CandleQuality::new(
+            &config.quality,
+            exchange::interval_ms(interval).unwrap_or(0),
+            window_size,
+        );
+        for kline in &raw_window[..raw_window.len() - live] {
+            quality.mark(kline.open_time, quality::REST);
+        }
+        quality
+    });
+
+    if !feeds.is_empty() {
+        utils::measure_time_async("warm up reference feeds", async {
+            for feed in &feeds {
+                feed.warm_up(&raw_window, window_size).await;
+            }
+        })
+        .await;
+    }
 
     // Build initial feature window
     let feature_slice = utils::measure_time("collect feature slice", || {
         raw_window
             .iter()
-            .skip(raw_window.len().saturating_sub(FEATURE_WINDOW_SIZE))
+            .skip(raw_window.len().saturating_sub(window_size))
             .cloned()
             .collect::<Vec<Kline>>()
     });
 
     let mut features_df =
-        utils::measure_time("features", || features::compute_features(&feature_slice))?;
+        utils::try_measure_time("features", || features::compute_features(&feature_slice))?;
+    for feed in &feeds {
+        features_df = feed.state.lock().unwrap().add_columns(features_df)?;
+    }
+    features_df = calendar::add_columns(features_df)?;
+    if let Some(quality) = &quality {
+        features_df = quality.add_columns(features_df)?;
+    }
+    if config.warmup_flags {
+        features_df = warmup::add_warmup_flags(features_df)?;
+    }
+    // Features a short window cannot compute yet are followed until warm
+    let warming = warmup::warmup_flags(&features_df.tail(Some(1)))
+        .first()
+        .copied()
+        .filter(|flags| *flags != 0);
+    let mut stored_df = if config.float32_features {
+        data_storage::downcast_features(&features_df)?
+    } else {
+        features_df.clone()
+    };
 
     println!(
         "Initial features computed, shape: {:?}",
@@ -59,260 +1769,887 @@ pub async fn run(
     );
 
     // Save initial features
+    if let Some(paths) = persisted {
+        let saving_start = Instant::now();
 
-    let saving_start = Instant::now();
+        utils::try_measure_time("save feature parquet", || {
+            data_storage::save_dataframe_parquet(&mut stored_df, &paths.feature_parquet)
+        })?;
 
-    utils::measure_time("save feature parquet", || {
-        data_storage::save_dataframe_parquet(&mut features_df, feature_parquet)
-    })?;
-
-    utils::measure_time("save feature csv", || {
-        data_storage::save_dataframe_csv_to_path(&mut features_df, feature_csv)
-    })?;
+        utils::try_measure_time("save feature csv", || {
+            data_storage::save_dataframe_csv_to_path(&stored_df, &paths.feature_csv)
+        })?;
 
-    let saving_elapsed = saving_start.elapsed();
+        let saving_elapsed = saving_start.elapsed();
 
-    println!(
-        "saving took: {:.2} ms",
-        saving_elapsed.as_secs_f64() * 1000.0
-    );
+        println!(
+            "saving took: {:.2} ms",
+            saving_elapsed.as_secs_f64() * 1000.0
+        );
+    }
+    drop(stored_df);
 
     let tail = features_df.tail(Some(1));
     println!("Latest features: {:?}", tail);
 
-    let stream_name = match stream_type {
-        "trade" => "btcusdt@trade",
-        "m5" => "btcusdt@kline_5m",
-        "m15" => "btcusdt@kline_15m",
-        _ => unreachable!(),
-    };
-
-    let interval_minutes = match stream_type {
-        "m5" => 5,
-        "m15" => 15,
-        _ => 0,
-    };
+    if !interval.is_empty() {
+        publish_latest(symbol, interval, &features_df, paths);
+    }
 
-    let url_str = format!("wss://stream.binance.com:9443/ws/{}", stream_name);
-    let url = Url::parse(&url_str)?;
+    let interval_minutes = exchange::interval_ms(interval).map_or(0, |ms| ms / 60_000);
 
-    println!("Connecting to Binance WebSocket: {}", url);
-    let (ws_stream, _) = connect_async(url).await?;
-    println!("Connected! Streaming '{}'", stream_name);
     println!("Loaded {} historical klines for context.", raw_window.len());
+    if interval_minutes > 0 {
+        println!(
+            "Rolling window: {} {} candles ({:.1} days)",
+            window_size,
+            interval,
+            (window_size as i64 * interval_minutes) as f64 / 1440.0
+        );
+        metrics::gauge_set(
+            "raw_window_size",
+            &[("symbol", symbol), ("interval", interval)],
+            window_size as f64,
+        );
+    }
+    if interval_minutes > 0 {
+        // The history store extends a short window; a replay or a young
+        // listing may still be short
+        let required = ema::warmup_candles(interval_minutes * 60_000).min(window_size);
+        if raw_window.len() < required {
+            alerts::raise(
+                Level::Warning,
+                "ema",
+                &format!(
+                    "{}: only {} of the {} {} candles needed to warm every EMA timeframe; the slow higher-timeframe EMAs stay null until enough candles arrive",
+                    symbol,
+                    raw_window.len(),
+                    required,
+                    interval
+                ),
+            );
+        }
+    }
+
+    let anomaly = config.anomaly.enabled.then(|| {
+        let seed = raw_window.len().saturating_sub(config.anomaly.lookback + 1);
+        AnomalyDetector::new(
+            symbol,
+            interval,
+            &config.anomaly,
+            &paths.quarantine,
+            raw_window[seed..].iter().map(|k| k.close),
+        )
+    });
+
+    let raw_window = KlineWindow::from_klines(&raw_window, window_size);
+
+    // Replayed candles are not expected on the wall clock
+    let watchdog = (interval_minutes > 0 && !replaying).then(|| {
+        Watchdog::spawn(
+            symbol,
+            interval_minutes * 60 * 1000,
+            config.watchdog_grace_secs as i64 * 1000,
+            raw_window.last().map_or(0, |k| k.open_time),
+        )
+    });
+
+    // A replay has nothing live to check against
+    let checked = config.consistency.every_secs > 0 || config.scheduler.reconciliation.enabled();
+    let consistency = (!interval.is_empty() && !replaying && checked).then(|| {
+        ConsistencyCheck::spawn(
+            symbol,
+            interval,
+            config.exchange,
+            Arc::clone(&store.source),
+            &config.consistency,
+        )
+    });
+
+    scheduler::track_feature_history(symbol, interval, &paths.feature_history);
+
+    // Watch the filesystem the append logs actually live on
+    let data_dir = std::path::Path::new(&paths.raw_csv)
+        .parent()
+        .and_then(|p| p.to_str())
+        .filter(|p| !p.is_empty())
+        .unwrap_or(".");
+    let disk_guard = DiskGuard::new(
+        data_dir,
+        &[&paths.raw_csv, &paths.feature_csv, &paths.streaming_log],
+        &config.disk,
+    );
+
+    let ema = utils::try_measure_time("ema state", || {
+        EmaTracker::new(&feature_slice, &features_df, window_size)
+    })?;
+    drop(feature_slice);
+
+    let mut pipeline = CandlePipeline {
+        symbol,
+        interval,
+        exchange: config.exchange,
+        interval_minutes,
+        paths,
+        state: Arc::new(Mutex::new(FeatureState {
+            symbol: symbol.to_string(),
+            raw_window,
+            window_size,
+            ema,
+            ema_unchecked: 0,
+            ema_validate_every: config.ema_validate_every,
+            regime: config.regime.enabled.then(|| config.regime.clone()),
+            joined: feeds.iter().map(|f| Arc::clone(&f.state)).collect(),
+            quality,
+            warmup_flags: config.warmup_flags,
+        })),
+        store,
+        last_snapshot: clock::now_ms(),
+        snapshot_every: std::time::Duration::from_secs(config.raw_snapshot_secs),
+        disk_guard,
+        memory_guard: MemoryGuard::new(&config.memory, window_size),
+        deadline: DeadlineGuard::new(symbol, interval, &config.deadline),
+        float32_features: config.float32_features,
+        streaming_jsonl: (config.streaming_log.format == StreamingLogFormat::JsonlZst).then(|| {
+            Arc::new(Mutex::new(ZstdJsonlLog::new(
+                &paths.streaming_log,
+                config.streaming_log.level,
+                config.streaming_log.frame_rows,
+            )))
+        }),
+        deterministic: config.deterministic.enabled,
+        signal_only,
+        feature_window: if signal_only {
+            config.signal_only.feature_window.min(window_size)
+        } else {
+            window_size
+        },
+        warming,
+        watchdog,
+        consistency,
+        anomaly,
+        strategies: config.build_strategies()?,
+        signal_audit: config.signal_audit,
+        suppress_around_events: config.calendar.suppress_signals,
+        feeds,
+        feature_windows: config
+            .feature_windows
+            .iter()
+            .zip(&paths.feature_windows)
+            .map(|(window, path)| FeatureWindow {
+                config: window.clone(),
+                path: path.clone(),
+                // The first candle computes every window
+                since: window.every - 1,
+            })
+            .collect(),
+        summary: PipelineSummary::new(symbol, interval, config.pipeline_summary_every),
+        paused: false,
+        close_lag: interval_minutes > 0 && !replaying,
+    };
 
-    let initial_elapsed = start.elapsed();
     println!(
         "starting streamer took: {:.2} ms",
-        initial_elapsed.as_secs_f64() * 1000.0
+        start.elapsed().as_secs_f64() * 1000.0
     );
     utils::print_memory_usage();
+    utils::log_memory_breakdown(&pipeline.state.lock().unwrap().raw_window, &features_df);
 
-    utils::log_memory_breakdown(&raw_window, &features_df);
+    let backpressure = &config.backpressure;
+    // A replay queues candles as fast as it can read them; none may be coalesced
+    let policy = if replaying {
+        BackpressurePolicy::Queue
+    } else {
+        backpressure.policy
+    };
+    let mut last_open_time = pipeline
+        .state
+        .lock()
+        .unwrap()
+        .raw_window
+        .last()
+        .map_or(i64::MIN, |k| k.open_time);
+    let mut stop = stop_signal(symbol);
+    let mut commands = control::register(symbol, interval);
+    loop {
+        let mut next = tokio::select! {
+            next = received.recv() => match next {
+                Some(next) => next,
+                None => break,
+            },
+            Some(request) = commands.recv() => {
+                let outcome = pipeline.control(request.command).await;
+                // The requester may have given up waiting
+                let _ = request.reply.send(outcome.map_err(|e| format!("{:#}", e)));
+                continue;
+            }
+            true = async { stop.wait_for(|stop| *stop).await.is_ok() } => {
+                source.abort();
+                pipeline.finish().await?;
+                println!("{} {} pipeline stopped", symbol, interval);
+                return Ok(());
+            }
+        };
+        // A collector replay can repeat candles merged during warm-up
+        if next.0.open_time <= last_open_time {
+            continue;
+        }
+        if pipeline.paused {
+            last_open_time = next.0.open_time;
+            pipeline.absorb(next.0).await?;
+            continue;
+        }
+        if policy == BackpressurePolicy::Coalesce {
+            let mut skipped = 0;
+            while let Ok(newer) = received.try_recv() {
+                if newer.0.open_time <= next.0.open_time {
+                    continue;
+                }
+                let (older, _) = std::mem::replace(&mut next, newer);
+                pipeline.absorb(older).await?;
+                skipped += 1;
+            }
+            if skipped > 0 {
+                pipeline.report_coalesced(skipped, backpressure.alert_threshold);
+            }
+        }
+        metrics::gauge_set(
+            "candle_queue_depth",
+            &[("symbol", symbol)],
+            received.len() as f64,
+        );
+        let (new_kline, received_at) = next;
+        last_open_time = new_kline.open_time;
+        pipeline.process(new_kline, received_at).await?;
+    }
+    // The source dropped its sender: it either failed or was shut down
+    pipeline.log_summary();
+    source.await?
+}
 
-    let (mut write, mut read) = ws_stream.split();
+/// Send stored candles to `candles`, in order, as if they had just closed.
+pub async fn replay(klines: Vec<Kline>, candles: CandleSender) -> Result<()> {
+    for kline in klines {
+        if candles.send((kline, Instant::now())).await.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
 
-    while let Some(message) = read.next().await {
-        match message? {
-            Message::Text(text) => {
-                let data: Value = serde_json::from_str(&text)?;
+/// Binance stream interval of a stream type ("" for trades).
+pub fn stream_interval(stream_type: &str) -> &'static str {
+    match stream_type {
+        "m1" => "1m",
+        "m5" => "5m",
+        "m15" => "15m",
+        "h1" => "1h",
+        "h4" => "4h",
+        _ => "",
+    }
+}
 
-                match stream_type {
-                    "trade" => {
-                        if let (Some(price), Some(qty), Some(time)) =
-                            (data["p"].as_str(), data["q"].as_str(), data["T"].as_u64())
-                        {
-                            let time_str = format_time(time);
-                            println!(
-                                "Trade | Time: {} | Price: {} | Qty: {}",
-                                time_str, price, qty
-                            );
-                        }
-                    }
-                    "m5" | "m15" => {
-                        if let Some(kline) = data["k"].as_object() {
-                            // Only process closed candles
-                            if let Some(is_closed) = kline["x"].as_bool() {
-                                if !is_closed {
-                                    continue;
-                                }
-                            }
+/// Stop requests, by symbol (see [`request_stop`]).
+fn stop_requests() -> &'static Mutex<HashMap<String, watch::Sender<bool>>> {
+    static REQUESTS: OnceLock<Mutex<HashMap<String, watch::Sender<bool>>>> = OnceLock::new();
+    REQUESTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
 
-                            if let (
-                                Some(open_str),
-                                Some(high_str),
-                                Some(low_str),
-                                Some(close_str),
-                                Some(volume_str),
-                                Some(open_time),
-                                Some(close_time),
-                            ) = (
-                                kline["o"].as_str(),
-                                kline["h"].as_str(),
-                                kline["l"].as_str(),
-                                kline["c"].as_str(),
-                                kline["v"].as_str(),
-                                kline["t"].as_u64(),
-                                kline["T"].as_u64(),
-                            ) {
-                                let message_start = Instant::now();
-
-                                let open_time_ms = open_time as i64;
-                                let close_time_ms = close_time as i64;
-
-                                let open = open_str.parse::<f64>()?;
-                                let high = high_str.parse::<f64>()?;
-                                let low = low_str.parse::<f64>()?;
-                                let close = close_str.parse::<f64>()?;
-                                let volume = volume_str.parse::<f64>()?;
-
-                                let new_kline = Kline {
-                                    open_time: open_time_ms,
-                                    open,
-                                    high,
-                                    low,
-                                    close,
-                                    volume,
-                                    close_time: close_time_ms,
-                                };
-
-                                // --- Update raw rolling window (50k) ---
-
-                                raw_window.push_back(new_kline.clone());
-                                if raw_window.len() > HISTORICAL_COUNT {
-                                    raw_window.pop_front();
-                                }
+fn stop_signal(symbol: &str) -> watch::Receiver<bool> {
+    stop_requests()
+        .lock()
+        .unwrap()
+        .entry(symbol.to_string())
+        .or_insert_with(|| watch::channel(false).0)
+        .subscribe()
+}
 
-                                // --- Compute features on the last FEATURE_WINDOW_SIZE of raw_window ---
-                                let feature_slice =
-                                    utils::measure_time("collect feature slice", || {
-                                        raw_window
-                                            .iter()
-                                            .skip(
-                                                raw_window
-                                                    .len()
-                                                    .saturating_sub(FEATURE_WINDOW_SIZE),
-                                            )
-                                            .cloned()
-                                            .collect::<Vec<Kline>>()
-                                    });
-
-                                let features_df = utils::measure_time("compute features", || {
-                                    features::compute_features(&feature_slice)
-                                })?;
-
-                                let saving_start = Instant::now();
-
-                                let mut handles = Vec::new();
-
-                                // 1. Save feature parquet (clone DataFrame)
-                                let df_clone = features_df.clone();
-                                let path = feature_parquet.to_string();
-                                handles.push(tokio::spawn(async move {
-                                    if let Err(e) =
-                                        data_storage::save_dataframe_parquet_async(df_clone, path)
-                                            .await
-                                    {
-                                        eprintln!("Error saving feature parquet: {}", e);
-                                    }
-                                }));
-
-                                // 2. Save feature CSV (optional, but if you keep it)
-                                let df_clone = features_df.clone();
-                                let path = feature_csv.to_string();
-                                handles.push(tokio::spawn(async move {
-                                    if let Err(e) = data_storage::append_features_row_to_csv_async(
-                                        df_clone, path,
-                                    )
-                                    .await
-                                    {
-                                        eprintln!("Error saving feature CSV: {}", e);
-                                    }
-                                }));
-
-                                // 3. Append feature row to streaming log
-                                let df_clone = features_df.clone();
-                                let path = streaming_csv_file.to_string();
-                                handles.push(tokio::spawn(async move {
-                                    if let Err(e) = data_storage::append_features_row_to_csv_async(
-                                        df_clone, path,
-                                    )
-                                    .await
-                                    {
-                                        eprintln!("Error appending feature row: {}", e);
-                                    }
-                                }));
-
-                                // 4. Append raw kline to raw CSV
-                                let kline_clone = new_kline.clone();
-                                let path = raw_csv_file.to_string();
-                                handles.push(tokio::spawn(async move {
-                                    if let Err(e) =
-                                        data_storage::append_kline_to_csv_async(kline_clone, path)
-                                            .await
-                                    {
-                                        eprintln!("Error appending raw kline: {}", e);
-                                    }
-                                }));
-
-                                // 5. Save raw Parquet cache (collect raw window into Vec first)
-                                let raw_slice: Vec<Kline> = raw_window.iter().cloned().collect();
-                                let path = raw_cache_file.to_string();
-                                handles.push(tokio::spawn(async move {
-                                    if let Err(e) =
-                                        data_storage::save_klines_to_parquet_async(raw_slice, path)
-                                            .await
-                                    {
-                                        eprintln!("Error saving raw Parquet: {}", e);
-                                    }
-                                }));
+fn stop_requested(symbol: &str) -> bool {
+    stop_requests()
+        .lock()
+        .unwrap()
+        .get(symbol)
+        .is_some_and(|stop| *stop.borrow())
+}
 
-                                // Wait for all spawned tasks to complete
-                                join_all(handles).await;
+/// Ask every pipeline of `symbol` to stop: each finishes the candle in
+/// hand, closes its streams, writes a final raw cache snapshot and flushes
+/// its streaming log, then returns `Ok`. A pipeline still loading its
+/// history stops once it is live.
+pub fn request_stop(symbol: &str) {
+    stop_requests()
+        .lock()
+        .unwrap()
+        .entry(symbol.to_string())
+        .or_insert_with(|| watch::channel(false).0)
+        .send_replace(true);
+    let prefix = format!("{}:", symbol);
+    base_streams()
+        .lock()
+        .unwrap()
+        .retain(|key, _| !key.starts_with(&prefix));
+}
 
-                                let saving_elapsed = saving_start.elapsed();
+/// Let pipelines of `symbol` run again after [`request_stop`].
+pub fn clear_stop(symbol: &str) {
+    if let Some(stop) = stop_requests().lock().unwrap().get(symbol) {
+        stop.send_replace(false);
+    }
+}
 
-                                println!(
-                                    "saving took: {:.2} ms",
-                                    saving_elapsed.as_secs_f64() * 1000.0
-                                );
+type BaseSender = broadcast::Sender<(Kline, Instant)>;
 
-                                // --- Print basic kline info ---
-                                let open_time_str = format_time(open_time);
-                                let close_time_str = format_time(close_time);
-                                let nominal_end =
-                                    DateTime::<Utc>::from_timestamp_millis(open_time_ms)
-                                        .map(|dt| dt + Duration::minutes(interval_minutes))
-                                        .map(|dt| {
-                                            dt.format("%Y-%m-%d %H:%M:%S%.3f UTC").to_string()
-                                        })
-                                        .unwrap_or_else(|| "Invalid".to_string());
+/// Open base streams of `derive_from`, by symbol and stream type.
+fn base_streams() -> &'static Mutex<HashMap<String, BaseSender>> {
+    static STREAMS: OnceLock<Mutex<HashMap<String, BaseSender>>> = OnceLock::new();
+    STREAMS.get_or_init(|| Mutex::new(HashMap::new()))
+}
 
-                                let message_elapsed = message_start.elapsed();
+/// Subscribe to the `base` stream of `config.symbol`, connecting it on first
+/// use. The stream stays open for the rest of the process, so pipelines that
+/// restart pick it up again instead of reconnecting.
+fn subscribe_base(
+    base: &str,
+    config: &Config,
+    recorder: Option<Recorder>,
+) -> broadcast::Receiver<(Kline, Instant)> {
+    let key = format!("{}:{}", config.symbol, base);
+    let mut streams = base_streams().lock().unwrap();
+    if let Some(sender) = streams.get(&key) {
+        return sender.subscribe();
+    }
+    let (sender, subscriber) = broadcast::channel(CANDLE_QUEUE);
+    streams.insert(key.clone(), sender.clone());
 
-                                println!(
-                                    "Kline | Open: {} | Close (actual): {} | Nominal End: {} | High: {} | Low: {} | ClosePrice: {} | Volume: {}",
-                                    open_time_str, close_time_str, nominal_end, high, low, close, volume
-                                );
-                                println!(
-                                    "new message took: {:.2} ms",
-                                    message_elapsed.as_secs_f64() * 1000.0
-                                );
-                                utils::print_memory_usage();
+    let (candles, mut received) = mpsc::channel(CANDLE_QUEUE);
+    let stream = collect(base.to_string(), config.clone(), candles, recorder);
+    let name = key.clone();
+    tokio::spawn(async move {
+        if let Err(e) = stream.await {
+            eprintln!("Base stream {} failed: {:#}", name, e);
+        }
+    });
+    let symbol = config.symbol.clone();
+    tokio::spawn(async move {
+        while let Some(candle) = received.recv().await {
+            // Nobody subscribed: every pipeline is restarting and the candle is
+            // dropped, or the symbol was stopped and the stream closes
+            if sender.send(candle).is_err() && stop_requested(&symbol) {
+                break;
+            }
+        }
+        // Subscribers see the stream close; the next one reconnects it
+        base_streams().lock().unwrap().remove(&key);
+    });
+    subscriber
+}
+
+/// Build `stream_type` candles from the shared `base` stream of
+/// `config.symbol` and send them to `candles`. The base candles of the first
+/// bucket that were closed before the stream connected are fetched over REST,
+/// so the first candle is complete. Candles missing base candles are still
+/// sent, with a `derive` alert. Returns when `candles` is closed.
+pub async fn derive(
+    stream_type: String,
+    base: String,
+    config: Config,
+    candles: CandleSender,
+    recorder: Option<Recorder>,
+) -> Result<()> {
+    let symbol = config.symbol.as_str();
+    let base_interval = stream_interval(&base);
+    let mut builder = IntervalCandles::new(
+        exchange::interval_ms(base_interval)?,
+        exchange::interval_ms(stream_interval(&stream_type))?,
+    )?;
+    let mut received = subscribe_base(&base, &config, recorder);
+    println!(
+        "Deriving {} candles of {} from the {} stream",
+        stream_type, symbol, base
+    );
+
+    let mut first = true;
+    loop {
+        let (kline, received_at) = match received.recv().await {
+            Ok(candle) => candle,
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                eprintln!(
+                    "[derive] {} {}: fell {} base candle(s) behind",
+                    symbol, stream_type, missed
+                );
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => {
+                bail!("The {} base stream of {} closed", base, symbol);
+            }
+        };
+        let mut base_klines = Vec::new();
+        if std::mem::take(&mut first) {
+            let bucket = builder.bucket(kline.open_time);
+            if bucket < kline.open_time {
+                let source = config.exchange.source();
+                match source
+                    .fetch_klines_range(symbol, base_interval, bucket, kline.open_time - 1)
+                    .await
+                {
+                    Ok(fetched) => base_klines = fetched,
+                    Err(e) => eprintln!(
+                        "[derive] {}: fetching the start of the first {} candle failed: {}",
+                        symbol, stream_type, e
+                    ),
+                }
+            }
+        }
+        base_klines.push(kline);
+
+        for base_kline in &base_klines {
+            for (derived, parts) in builder.push(base_kline) {
+                if parts < builder.parts() {
+                    alerts::raise(
+                        Level::Warning,
+                        "derive",
+                        &format!(
+                            "{}: {} candle {} built from {} of {} {} candles",
+                            symbol,
+                            stream_type,
+                            timezone::format_ms(derived.open_time),
+                            parts,
+                            builder.parts(),
+                            base
+                        ),
+                    );
+                }
+                if candles.send((derived, received_at)).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// `closed` preceded by the `kind` candles from open time `from` up to its
+/// first one, fetched over REST: the candles a stream built from trades missed
+/// while reconnecting, since a new [`TradeCandles`] skips the bucket it
+/// starts in.
+async fn with_session_gap(
+    source: &dyn MarketDataSource,
+    symbol: &str,
+    kind: &StreamKind,
+    from: Option<i64>,
+    closed: Vec<Kline>,
+) -> Vec<Kline> {
+    let (Some(from), Some(first), StreamKind::Klines(interval)) = (from, closed.first(), kind)
+    else {
+        return closed;
+    };
+    if first.open_time <= from {
+        return closed;
+    }
+    let until = first.open_time;
+    match source
+        .fetch_klines_range(symbol, interval, from, until - 1)
+        .await
+    {
+        Ok(fetched) => {
+            let mut gap: Vec<Kline> = fetched
+                .into_iter()
+                .filter(|k| k.open_time >= from && k.open_time < until)
+                .collect();
+            println!(
+                "Backfilled {} {} {} candle(s) missed while reconnecting",
+                gap.len(),
+                symbol,
+                interval
+            );
+            gap.extend(closed);
+            gap
+        }
+        Err(e) => {
+            eprintln!(
+                "Backfilling the {} {} candles missed while reconnecting failed: {}",
+                symbol, interval, e
+            );
+            closed
+        }
+    }
+}
+
+/// Where [`connect`] delivers what a stream decodes.
+enum Sink {
+    /// Closed candles for a pipeline; trades are only logged.
+    Candles(CandleSender),
+    /// Closed candles, trades and quotes, for a [`ReferenceFeed`].
+    Events(mpsc::Sender<StreamEvent>),
+}
+
+impl Sink {
+    /// Deliver a closed candle; false once the receiver is gone.
+    async fn candle(&self, kline: Kline, received: Instant) -> bool {
+        match self {
+            Sink::Candles(candles) => candles.send((kline, received)).await.is_ok(),
+            Sink::Events(events) => events.send(StreamEvent::Candle(kline)).await.is_ok(),
+        }
+    }
+}
+
+/// Own the WebSocket of `config.exchange` and send every closed candle to
+/// `candles`. Trades are only logged. Returns when `candles` is closed;
+/// WebSocket failures and frames the source rejects end the session, which is
+/// retried with exponential backoff. Candles built from trades that a
+/// reconnect missed are fetched over REST. With a `recorder`, every text
+/// frame is recorded before it is decoded.
+pub async fn collect(
+    stream_type: String,
+    config: Config,
+    candles: CandleSender,
+    recorder: Option<Recorder>,
+) -> Result<()> {
+    let kind = match stream_interval(&stream_type) {
+        "" => StreamKind::Trades,
+        interval => StreamKind::Klines(interval.to_string()),
+    };
+    connect(kind, config, Sink::Candles(candles), recorder).await
+}
+
+/// The connection loop of [`collect`], for any stream `kind` and [`Sink`].
+async fn connect(
+    kind: StreamKind,
+    config: Config,
+    sink: Sink,
+    recorder: Option<Recorder>,
+) -> Result<()> {
+    let symbol = config.symbol.as_str();
+    let source = config.exchange.source();
+    let request = source.stream_request(symbol, &kind)?;
+    let interval_ms = match &kind {
+        StreamKind::Klines(interval) if request.trade_candles => {
+            Some(exchange::interval_ms(interval)?)
+        }
+        _ => None,
+    };
+    let stream_name = request.name.as_str();
+
+    let stats =
+        ConnectionStats::spawn_shared(std::time::Duration::from_secs(config.metrics_summary_secs));
+    let mut first_connect = true;
+    let mut backoff = MIN_BACKOFF;
+    // Open time of the next candle built from trades, across sessions
+    let mut next_open: Option<i64> = None;
+
+    loop {
+        println!("Connecting to {} WebSocket: {}", source.name(), request.url);
+        let mut ws_stream = match connect_async(request.url.as_str()).await {
+            Ok((ws_stream, _)) => ws_stream,
+            Err(e) => {
+                eprintln!("Connection to '{}' failed: {}", stream_name, e);
+                if matches!(&e, WsError::Http(response) if response.status() == 503) {
+                    maintenance::detected(config.exchange, "WebSocket handshake refused with 503");
+                }
+                stats.lock().unwrap().on_disconnect(&e.to_string());
+                tokio::time::sleep(maintenance::retry_delay(config.exchange).unwrap_or(backoff))
+                    .await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                continue;
+            }
+        };
+        let mut subscribed = Ok(());
+        for frame in &request.subscribe {
+            subscribed = subscribed.and(ws_stream.send(Message::Text(frame.clone())).await);
+        }
+        if let Err(e) = subscribed {
+            eprintln!("Subscribing to '{}' failed: {}", stream_name, e);
+            stats.lock().unwrap().on_disconnect(&e.to_string());
+            tokio::time::sleep(maintenance::retry_delay(config.exchange).unwrap_or(backoff)).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+            continue;
+        }
+        println!("Connected! Streaming '{}'", stream_name);
+        if let Some(recorder) = &recorder {
+            recorder.connected(config.exchange, stream_name);
+        }
+        stats.lock().unwrap().on_connect();
+        events::emit(Event::Connected {
+            symbol: symbol.to_string(),
+            stream: stream_name.to_string(),
+        });
+        if std::mem::take(&mut first_connect) {
+            heartbeat::ready();
+        } else {
+            connection_stats::reconnected(symbol);
+        }
+
+        let (mut write, mut read) = ws_stream.split();
+        let mut builder =
+            interval_ms.map(|ms| TradeCandles::new(ms, Utc::now().timestamp_millis()));
+        // The new builder skips the bucket it starts in
+        let mut gap_from = next_open;
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(1));
+        let mut last_keepalive = Instant::now();
+        let mut received_any = false;
 
-                                utils::log_memory_breakdown(&raw_window, &features_df);
+        let reason = loop {
+            let message = tokio::select! {
+                message = read.next() => message,
+                _ = ticker.tick() => {
+                    if let Some(frame) = &request.keepalive {
+                        if last_keepalive.elapsed() >= exchange::KEEPALIVE_INTERVAL {
+                            last_keepalive = Instant::now();
+                            if let Err(e) = write.send(Message::Text(frame.clone())).await {
+                                break e.to_string();
                             }
                         }
                     }
-                    _ => unreachable!(),
+                    let mut closed = builder
+                        .as_mut()
+                        .map(|b| b.tick(Utc::now().timestamp_millis()))
+                        .unwrap_or_default();
+                    if !closed.is_empty() {
+                        closed =
+                            with_session_gap(&*source, symbol, &kind, gap_from.take(), closed)
+                                .await;
+                    }
+                    for kline in closed {
+                        next_open = interval_ms.map(|ms| kline.open_time + ms);
+                        if !sink.candle(kline, Instant::now()).await {
+                            return Ok(());
+                        }
+                    }
+                    continue;
                 }
+            };
+            let message = match message {
+                Some(Ok(message)) => message,
+                Some(Err(e)) => break e.to_string(),
+                None => break "stream ended".to_string(),
+            };
+            // Data on a new session ends a maintenance
+            if !received_any && !matches!(message, Message::Close(_)) {
+                maintenance::recovered(config.exchange);
             }
-            Message::Ping(payload) => {
-                write.send(Message::Pong(payload)).await?;
+            received_any = true;
+            stats.lock().unwrap().on_message(stream_name);
+
+            match message {
+                Message::Text(text) => {
+                    let received = Instant::now();
+                    if let Some(recorder) = &recorder {
+                        recorder.frame(config.exchange, stream_name, &text);
+                    }
+                    let decoded = match source.parse_message(&text) {
+                        Ok(decoded) => decoded,
+                        Err(e) => break e.to_string(),
+                    };
+                    for event in decoded {
+                        let mut closed = match (event, builder.as_mut()) {
+                            (
+                                StreamEvent::Trade {
+                                    price, qty, time, ..
+                                },
+                                Some(builder),
+                            ) => builder.trade(price, qty, time),
+                            (
+                                StreamEvent::Trade {
+                                    price,
+                                    qty,
+                                    time,
+                                    taker_buy,
+                                },
+                                None,
+                            ) => {
+                                if let Sink::Events(events) = &sink {
+                                    let trade = StreamEvent::Trade {
+                                        price,
+                                        qty,
+                                        time,
+                                        taker_buy,
+                                    };
+                                    if events.send(trade).await.is_err() {
+                                        return Ok(());
+                                    }
+                                    continue;
+                                }
+                                println!(
+                                    "Trade | Time: {} | Price: {} | Qty: {}",
+                                    timezone::format_ms(time),
+                                    price,
+                                    qty
+                                );
+                                events::emit(Event::Trade {
+                                    symbol: symbol.to_string(),
+                                    price,
+                                    qty,
+                                    trade_time: time,
+                                });
+                                continue;
+                            }
+                            (StreamEvent::Candle(kline), _) => vec![kline],
+                            (StreamEvent::Quote(quote), _) => {
+                                if let Sink::Events(events) = &sink {
+                                    if events.send(StreamEvent::Quote(quote)).await.is_err() {
+                                        return Ok(());
+                                    }
+                                }
+                                continue;
+                            }
+                        };
+                        if builder.is_some() && !closed.is_empty() {
+                            closed =
+                                with_session_gap(&*source, symbol, &kind, gap_from.take(), closed)
+                                    .await;
+                        }
+                        for kline in closed {
+                            metrics::observe_stage("parse", received);
+                            next_open = interval_ms.map(|ms| kline.open_time + ms);
+                            if !sink.candle(kline, received).await {
+                                return Ok(());
+                            }
+                        }
+                    }
+                }
+                Message::Ping(payload) => {
+                    if let Err(e) = write.send(Message::Pong(payload)).await {
+                        break e.to_string();
+                    }
+                }
+                Message::Close(frame) => {
+                    if let Some(f) = &frame {
+                        if maintenance::is_maintenance_close(u16::from(f.code), &f.reason) {
+                            maintenance::detected(
+                                config.exchange,
+                                &format!("stream closed with {} {}", f.code, f.reason),
+                            );
+                        }
+                    }
+                    break frame
+                        .map(|f| format!("closed by server: {} {}", f.code, f.reason))
+                        .unwrap_or_else(|| "closed by server".to_string());
+                }
+                _ => {}
             }
-            _ => {}
+        };
+
+        println!("Disconnected from '{}': {}", stream_name, reason);
+        stats.lock().unwrap().on_disconnect(&reason);
+        events::emit(Event::Disconnected {
+            symbol: symbol.to_string(),
+            stream: stream_name.to_string(),
+            reason,
+        });
+
+        // A session that delivered data resets the backoff
+        if received_any {
+            backoff = MIN_BACKOFF;
         }
+        let delay = maintenance::retry_delay(config.exchange).unwrap_or(backoff);
+        println!("Reconnecting in {:.0}s...", delay.as_secs_f64());
+        tokio::time::sleep(delay).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
     }
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::future::{BoxFuture, FutureExt};
+
+    const M15_MS: i64 = 15 * 60 * 1000;
+
+    fn kline(open_time: i64) -> Kline {
+        Kline {
+            open_time,
+            open: 1.0,
+            high: 1.0,
+            low: 1.0,
+            close: 1.0,
+            volume: 1.0,
+            close_time: open_time + M15_MS - 1,
+        }
+    }
+
+    /// REST history of one M15 candle per bucket from 0.
+    #[derive(Debug)]
+    struct History;
+
+    impl MarketDataSource for History {
+        fn name(&self) -> &'static str {
+            "history"
+        }
+
+        fn fetch_klines_range<'a>(
+            &'a self,
+            _symbol: &'a str,
+            _interval: &'a str,
+            start_time: i64,
+            end_time: i64,
+        ) -> BoxFuture<'a, binance_streamer::error::Result<Vec<Kline>>> {
+            let first = start_time.div_euclid(M15_MS) * M15_MS;
+            let klines = (first..=end_time).step_by(M15_MS as usize).map(kline);
+            async move { Ok(klines.collect()) }.boxed()
+        }
+
+        fn fetch_latest_klines<'a>(
+            &'a self,
+            _symbol: &'a str,
+            _interval: &'a str,
+            _count: usize,
+        ) -> BoxFuture<'a, binance_streamer::error::Result<Vec<Kline>>> {
+            unreachable!()
+        }
+
+        fn stream_request(
+            &self,
+            _symbol: &str,
+            _kind: &StreamKind,
+        ) -> binance_streamer::error::Result<exchange::StreamRequest> {
+            unreachable!()
+        }
+
+        fn parse_message(&self, _text: &str) -> binance_streamer::error::Result<Vec<StreamEvent>> {
+            unreachable!()
+        }
+    }
+
+    fn open_times(klines: &[Kline]) -> Vec<i64> {
+        klines.iter().map(|k| k.open_time / M15_MS).collect()
+    }
+
+    /// `history` after merging `buffered`, as open times, and the live count.
+    fn merge(history: &[i64], buffered: &[i64]) -> (Vec<i64>, usize) {
+        let mut history: Vec<Kline> = history.iter().map(|&t| kline(t * M15_MS)).collect();
+        let (sender, mut received) = mpsc::channel(CANDLE_QUEUE);
+        for &t in buffered {
+            sender
+                .try_send((kline(t * M15_MS), Instant::now()))
+                .unwrap();
+        }
+        let live = merge_buffered(&mut history, &mut received, None);
+        (open_times(&history), live)
+    }
+
+    #[test]
+    fn merge_counts_the_live_candles_at_the_end() {
+        // Appended, replacing the newest stored candle, or older and dropped
+        assert_eq!(merge(&[1, 2, 3], &[4, 5]), (vec![1, 2, 3, 4, 5], 2));
+        assert_eq!(merge(&[1, 2, 3], &[3, 4]), (vec![1, 2, 3, 4], 2));
+        assert_eq!(merge(&[1, 2, 3], &[2, 3]), (vec![1, 2, 3], 1));
+        // A revised live candle is still one
+        assert_eq!(merge(&[1, 2, 3], &[4, 4, 5, 5]), (vec![1, 2, 3, 4, 5], 2));
+        assert_eq!(merge(&[], &[7, 7]), (vec![7], 1));
+        assert_eq!(merge(&[], &[]), (vec![], 0));
+    }
+
+    #[tokio::test]
+    async fn session_gap_is_fetched_before_the_first_built_candle() {
+        let kind = StreamKind::Klines("15m".to_string());
+        let built = vec![kline(5 * M15_MS), kline(6 * M15_MS)];
+
+        let filled = with_session_gap(&History, "BTC-USD", &kind, Some(2 * M15_MS), built.clone());
+        assert_eq!(open_times(&filled.await), vec![2, 3, 4, 5, 6]);
+
+        // First session, or no candle missed
+        let first = with_session_gap(&History, "BTC-USD", &kind, None, built.clone());
+        assert_eq!(open_times(&first.await), vec![5, 6]);
+        let seamless = with_session_gap(&History, "BTC-USD", &kind, Some(5 * M15_MS), built);
+        assert_eq!(open_times(&seamless.await), vec![5, 6]);
+    }
 }
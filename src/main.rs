@@ -1,131 +1,503 @@
-mod binance_client;
-mod data_storage;
-mod features;
-mod kline;
+mod alerts;
+mod anomaly;
+mod audit;
+mod backfill;
+mod bench;
+mod bus;
+mod calendar;
+mod clock;
+mod config;
+mod connection_stats;
+mod consistency;
+mod control;
+mod crash;
+mod deadline;
+mod determinism;
+mod diff;
+mod disk_guard;
+mod emit;
+mod events;
+#[cfg(feature = "flight")]
+mod flight;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod heartbeat;
+mod ipc;
+mod journal;
+mod kill;
+mod latest;
 mod live_stream;
+mod maintenance;
+mod manifest;
+mod memory_guard;
+mod metrics;
+mod nats_sink;
+mod pipeline_summary;
+#[cfg(feature = "plot")]
+mod plot;
+mod plugins;
+mod portfolio;
+mod recorder;
+mod redis_sink;
+mod report;
+mod sample;
+mod scheduler;
+mod server;
+mod signals;
+mod supervisor;
+mod tradingview;
+mod universe;
 mod utils;
+mod watchdog;
+mod webhooks;
+mod ws_server;
+mod zmq_pub;
 
-use anyhow::Result;
-use chrono::{Duration, Utc};
-use std::collections::VecDeque;
+use crate::manifest::RunManifest;
+use anyhow::{bail, Context, Result};
+use binance_streamer::exchange::recorded::{self, Recorded};
+use binance_streamer::exchange::{MarketDataSource, StreamKind};
+use binance_streamer::history::HistoryStore;
+use binance_streamer::{
+    data_storage, exchange, features, history, hooks, kline, progress, strategy, timezone, window,
+};
+use chrono::Duration;
 use std::path::Path;
+use std::sync::Arc;
 
-const HISTORICAL_COUNT: usize = 50_000;
-const SYMBOL: &str = "BTCUSDT";
 const LATEST_TIME_BEFORE_CACHE_REFRESH: i64 = 24;
+/// Candles of a replayed dataset loaded as history before the replay starts.
+const DEFAULT_REPLAY_WARMUP: usize = 1000;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args: Vec<String> = std::env::args().collect();
+    let cli = config::CliArgs::parse(std::env::args().skip(1))?;
+    let config = config::Config::load(&cli)?;
+    determinism::apply(&config.deterministic);
+    events::set_minimal(config.profile == config::Profile::SignalOnly);
+    utils::set_record_timings(config.timing_metrics);
+    features::ema::set_semantics(config.ema);
+    data_storage::set_decimal_format(config.decimals.clone());
+    maintenance::configure(&config.maintenance);
+    kill::configure(&config.kill);
+    control::set_log_level(config.log_level);
+    timezone::set_display(timezone::parse_tz(&config.timezone)?);
+    timezone::set_sessions(config.trading_sessions()?);
+    progress::set_report_every(std::time::Duration::from_secs(config.fetch_progress_secs));
+    crash::install(&config);
 
-    match args.get(1).map(String::as_str) {
+    let result = run(&cli, &config).await;
+    if let Err(e) = &result {
+        crash::report_error(e);
+    }
+    result
+}
+
+async fn run(cli: &config::CliArgs, config: &config::Config) -> Result<()> {
+    let resume = cli.has("resume");
+    // First, so that stdout mode silences everything printed afterwards
+    emit::spawn(&config.emit).await?;
+
+    if let Some(addr) = &config.http_addr {
+        server::spawn(addr, &config.tradingview, &config.control).await?;
+    }
+    if let Some(addr) = &config.ws_addr {
+        ws_server::spawn(addr).await?;
+    }
+    if let Some(addr) = &config.grpc_addr {
+        #[cfg(feature = "grpc")]
+        grpc::spawn(addr).await?;
+        #[cfg(not(feature = "grpc"))]
+        anyhow::bail!(
+            "grpc_addr {} is set but this build lacks the `grpc` feature",
+            addr
+        );
+    }
+    if let Some(endpoint) = &config.zmq_addr {
+        zmq_pub::spawn(endpoint).await?;
+    }
+    redis_sink::spawn(&config.redis).await?;
+    nats_sink::spawn(&config.nats).await?;
+    webhooks::spawn(&config.webhooks)?;
+
+    match cli.positional.first().map(String::as_str) {
         Some("fetch-historical") => { /* unchanged */ }
+        Some("bench") => bench::run(cli, config.deterministic.seed)?,
+        Some("replay") => {
+            // The replayed pipeline computes the calendar columns too
+            calendar::spawn(&config.calendar).await?;
+            replay(cli, config).await?
+        }
+        // Polars blocks; keep it off the async workers
+        Some("backfill-features") => tokio::task::block_in_place(|| backfill::run(cli, config))?,
+        Some("diff-features") => tokio::task::block_in_place(|| diff::run(cli))?,
+        Some("journal-report") => tokio::task::block_in_place(|| journal::run(cli, config))?,
+        Some("make-sample") => {
+            tokio::task::block_in_place(|| sample::run(cli, config.deterministic.seed))?
+        }
+        Some("plot") => {
+            // Parquet scanning blocks; polars must not run on an async worker
+            #[cfg(feature = "plot")]
+            tokio::task::block_in_place(|| plot::run(cli, &config.output_paths("m15", "15m")?))?;
+            #[cfg(not(feature = "plot"))]
+            anyhow::bail!("`plot` needs a build with the `plot` feature (--features plot)");
+        }
         _ => {
-            // Determine interval from stream type
-            let stream_type = args.get(1).map(String::as_str).unwrap_or("trade");
-            let (interval, cache_file, csv_file) = match stream_type {
-                "m5" => (
-                    "5m",
-                    "data/m5_latest_50000.parquet",
-                    "data/m5_latest_50000_raw.csv",
-                ),
-                "m15" => (
-                    "15m",
-                    "data/m15_latest_50000.parquet",
-                    "data/m15_latest_50000_raw.csv",
-                ),
-                "trade" => ("", "", ""), // no historical for trade stream
-                _ => {
-                    eprintln!("Unknown stream type. Use 'trade', 'm5', or 'm15'.");
+            // One pipeline per stream type, e.g. "m15,h1,h4"
+            let stream_types: Vec<String> = cli
+                .positional
+                .first()
+                .map_or("trade", String::as_str)
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .collect();
+            for stream_type in &stream_types {
+                if stream_type != "trade" && live_stream::stream_interval(stream_type).is_empty() {
+                    eprintln!(
+                        "Unknown stream type '{}'. Use 'trade', 'm1', 'm5', 'm15', 'h1' or 'h4'.",
+                        stream_type
+                    );
                     std::process::exit(1);
                 }
-            };
+                let interval = live_stream::stream_interval(stream_type);
+                if !interval.is_empty() {
+                    config.check_window(interval)?;
+                }
+            }
+            if stream_types.len() > 1
+                && !config.path_template.contains("{stream}")
+                && !config.path_template.contains("{interval}")
+            {
+                bail!(
+                    "Several stream types need the {{stream}} or {{interval}} placeholder in path_template '{}'",
+                    config.path_template
+                );
+            }
+            if let Some(base) = &config.derive_from {
+                let base_ms = exchange::interval_ms(live_stream::stream_interval(base))?;
+                for stream_type in stream_types.iter().filter(|s| *s != "trade") {
+                    let ms = exchange::interval_ms(live_stream::stream_interval(stream_type))?;
+                    if ms % base_ms != 0 {
+                        bail!(
+                            "{} candles cannot be derived from {} (derive_from)",
+                            stream_type,
+                            base
+                        );
+                    }
+                }
+            }
+            let stream_type = stream_types[0].as_str();
+            let interval = live_stream::stream_interval(stream_type);
 
-            let (feature_parquet, feature_csv) = match stream_type {
-                "m5" => ("data/m5_features.parquet", "data/m5_features.csv"),
-                "m15" => ("data/m15_features.parquet", "data/m15_features.csv"),
-                _ => ("", ""), // not used for trade
-            };
+            let mut config = config.clone();
+            if config.universe.enabled {
+                config.symbols = universe::initial(&config).await?;
+            }
+            let symbols = config.symbols();
 
-            let streaming_csv_file = match stream_type {
-                "m5" => "data/m5_streaming_features.csv",
-                "m15" => "data/m15_streaming_features.csv",
-                _ => "", // not used for trade
-            };
+            // A collector only forwards candles; workers own history and outputs
+            if config.ipc.role == config::Role::Collector {
+                return ipc::serve(stream_type, &config.for_symbol(&symbols[0])).await;
+            }
+
+            report::spawn(&config)?;
+            calendar::spawn(&config.calendar).await?;
+            scheduler::spawn(&config)?;
 
-            let historical = if stream_type == "m5" || stream_type == "m15" {
-                let vec = utils::measure_time_async(
-                    "load_or_fetch_historical",
-                    load_or_fetch_historical(interval, cache_file, csv_file),
+            // One process-wide event log (events carry their symbol): the first pipeline's
+            if !interval.is_empty() {
+                let paths = config
+                    .for_symbol(&symbols[0])
+                    .output_paths(stream_type, interval)?;
+                events::init(&paths.events)?;
+            }
+
+            portfolio::spawn(&config, &stream_types)?;
+
+            // With several stream types a pipeline is supervised as "SYMBOL/stream"
+            let pipelines_of = |symbol: &str| -> Vec<String> {
+                if stream_types.len() == 1 {
+                    vec![symbol.to_string()]
+                } else {
+                    stream_types
+                        .iter()
+                        .map(|s| format!("{}/{}", symbol, s))
+                        .collect()
+                }
+            };
+            let default_stream = stream_types[0].clone();
+            let pipeline_config = config.clone();
+            let pipeline = move |pipeline: String, restarts| {
+                let (symbol, stream_type) = match pipeline.split_once('/') {
+                    Some((symbol, stream_type)) => (symbol.to_string(), stream_type.to_string()),
+                    None => (pipeline, default_stream.clone()),
+                };
+                // After a failure, continue from the snapshot instead of refetching
+                run_pipeline(
+                    stream_type,
+                    pipeline_config.for_symbol(&symbol),
+                    resume || restarts > 0,
                 )
-                .await?;
-                VecDeque::from(vec)
-            } else {
-                VecDeque::new()
             };
-
-            live_stream::run(
-                stream_type,
-                historical,
-                cache_file,
-                csv_file,
-                feature_parquet,
-                feature_csv,
-                streaming_csv_file,
-            )
-            .await?;
+            if config.universe.enabled {
+                universe::run(&config, symbols, pipelines_of, pipeline).await?;
+            } else {
+                let pipelines: Vec<String> = symbols.iter().flat_map(|s| pipelines_of(s)).collect();
+                supervisor::run(&pipelines, &config.supervisor, pipeline).await?;
+            }
         }
     }
     Ok(())
 }
 
-/// Load cached historical data if it exists and is fresh; otherwise fetch from Binance.
-async fn load_or_fetch_historical(
-    interval: &str,
-    cache_file: &str,
-    csv_file: &str,
-) -> Result<Vec<kline::Kline>> {
-    // Ensure data directory exists
-    if let Some(parent) = Path::new(cache_file).parent() {
-        std::fs::create_dir_all(parent)?;
-    }
+/// Load the history of `config.symbol` and run its live stream.
+async fn run_pipeline(stream_type: String, config: config::Config, resume: bool) -> Result<()> {
+    let stream_type = stream_type.as_str();
+    let interval = live_stream::stream_interval(stream_type);
+    let config = &config;
 
-    let should_fetch = if Path::new(cache_file).exists() {
-        let metadata = std::fs::metadata(cache_file)?;
-        let modified = metadata.modified()?;
-        let age = Utc::now().signed_duration_since(chrono::DateTime::<Utc>::from(modified));
-        age > Duration::hours(LATEST_TIME_BEFORE_CACHE_REFRESH)
+    // Trade streams write nothing, so they keep empty paths
+    let paths = if interval.is_empty() {
+        config::OutputPaths::default()
     } else {
-        true
+        config.output_paths(stream_type, interval)?
     };
 
-    let klines = if should_fetch {
-        println!(
-            "Fetching latest {} {} candles from Binance...",
-            HISTORICAL_COUNT, interval
-        );
-        let klines =
-            binance_client::fetch_latest_klines(SYMBOL, interval, HISTORICAL_COUNT).await?;
-        println!("Fetched {} klines. Saving to cache...", klines.len());
-        data_storage::save_klines_to_parquet(&klines, cache_file)?;
-        klines
+    let store = history_store(config, &paths, config.window_size(interval));
+    // Awaited by the pipeline once its candle source is connected
+    let loader = store.clone();
+    let symbol = config.symbol.clone();
+    let snapshot_exists = Path::new(&paths.raw_cache).exists();
+    let signal_only = config.profile == config::Profile::SignalOnly;
+    let manifest = if interval.is_empty() || signal_only {
+        None
     } else {
-        println!("Loading cached historical data from {}", cache_file);
-        data_storage::load_klines_from_parquet(cache_file)?
+        Some((
+            RunManifest::new("live", stream_type, config)?,
+            paths.manifest.clone(),
+        ))
+    };
+    let history = async move {
+        let klines = if interval.is_empty() {
+            Vec::new()
+        } else if signal_only {
+            // Nothing is cached: the window comes straight from the exchange
+            utils::try_measure_time_async(
+                "fetch_historical",
+                loader
+                    .source
+                    .fetch_latest_klines(&symbol, interval, loader.count),
+            )
+            .await?
+        } else if resume && snapshot_exists {
+            utils::try_measure_time_async("resume_from_snapshot", loader.resume(&symbol, interval))
+                .await?
+        } else {
+            if resume {
+                println!(
+                    "No snapshot found at {}, falling back to a full load.",
+                    loader.cache_file
+                );
+            }
+            utils::try_measure_time_async(
+                "load_or_fetch_historical",
+                loader.load_or_fetch(&symbol, interval),
+            )
+            .await?
+        };
+        if let Some((mut manifest, path)) = manifest {
+            manifest
+                .data
+                .push(manifest::DataRange::of("history", None, &klines)?);
+            if let Err(e) = manifest.save(&path) {
+                eprintln!(
+                    "Warning: failed to write the run manifest {}: {:#}",
+                    path, e
+                );
+            }
+        }
+        Ok(klines)
     };
 
-    // Write initial CSV only if the file does NOT already exist
-    if !Path::new(csv_file).exists() {
-        println!("Writing initial historical data to CSV: {}", csv_file);
-        if let Err(e) = data_storage::save_klines_to_csv(&klines, csv_file) {
-            eprintln!("Warning: failed to write initial CSV: {}", e);
+    live_stream::run(stream_type, config, history, &paths, store, None).await
+}
+
+/// The raw candle cache behind `paths`, holding `count` candles.
+fn history_store(
+    config: &config::Config,
+    paths: &config::OutputPaths,
+    count: usize,
+) -> HistoryStore {
+    HistoryStore {
+        cache_file: paths.raw_cache.clone(),
+        journal_file: paths.raw_journal.clone(),
+        csv_file: paths.raw_csv.clone(),
+        count,
+        max_age: Duration::hours(LATEST_TIME_BEFORE_CACHE_REFRESH),
+        source: config.exchange.source(),
+    }
+}
+
+/// `replay <m5|m15> --input <candles.parquet> [--warmup N]`: run the first
+/// `warmup` candles of a stored dataset as history and feed the rest through
+/// the live pipeline of `config.symbol`, then print a hash of the outputs.
+/// With `--deterministic` two replays of the same dataset into fresh data
+/// directories print the same hash.
+///
+/// `replay <m5|m15> --session <session.jsonl.zst> --input <candles.parquet>`
+/// instead feeds the candles a `--record`ed session produced, decoded from
+/// its raw frames, with the `--input` candles before the session as history.
+/// The exchange resolves to an offline [`Recorded`] source.
+///
+/// Either way the run's [`RunManifest`] is written next to its outputs;
+/// `replay --manifest <run_manifest.json>` repeats that run and fails unless
+/// it reproduces the recorded output hash.
+async fn replay(cli: &config::CliArgs, config: &config::Config) -> Result<()> {
+    let previous = cli.value("manifest").map(RunManifest::load).transpose()?;
+    // Flags win over what the manifest recorded
+    let recorded = |role: &str| -> Option<&manifest::DataRange> {
+        previous.as_ref().and_then(|m| m.range(role))
+    };
+    let stream_type = cli
+        .positional
+        .get(1)
+        .map(String::as_str)
+        .or(previous.as_ref().map(|m| m.stream.as_str()))
+        .unwrap_or("m15");
+    let interval = live_stream::stream_interval(stream_type);
+    if interval.is_empty() {
+        bail!(
+            "replay expects a candle stream type (m5, m15, ...), got '{}'",
+            stream_type
+        );
+    }
+    let input = cli
+        .value("input")
+        .or(recorded("input").and_then(|d| d.path.as_deref()))
+        .context("replay needs --input <candles.parquet>")?;
+    let session = cli
+        .value("session")
+        .or(recorded("session").and_then(|d| d.path.as_deref()));
+    for (role, path) in [("input", Some(input)), ("session", session)] {
+        let (Some(path), Some(expected)) = (path, recorded(role).and_then(|d| d.sha256.as_ref()))
+        else {
+            continue;
+        };
+        if manifest::sha256_file(path)? != *expected {
+            bail!(
+                "{} is not the {} the manifest's run read (SHA-256 differs)",
+                path,
+                role
+            );
         }
+    }
+    config.check_window(interval)?;
+    let mut history = data_storage::load_klines_from_parquet(input)?;
+    let mut manifest = RunManifest::new("replay", stream_type, config)?;
+    manifest
+        .data
+        .push(manifest::DataRange::of("input", Some(input), &history)?);
+
+    let mut venue = None;
+    let replayed = if let Some(session) = session {
+        let frames = recorded::load_session(session)?;
+        let kind = StreamKind::Klines(interval.to_string());
+        let source = Recorded::new(config.exchange, history);
+        let request = source.stream_request(&config.symbol, &kind)?;
+        recorded::ensure_stream(&frames, &request.name)?;
+        let replayed = recorded::session_klines(&frames, &source, &request, &kind)?;
+        let first = replayed
+            .first()
+            .with_context(|| format!("{} holds no closed {} candles", session, interval))?;
+        history = source
+            .fetch_klines_range(&config.symbol, interval, i64::MIN, first.open_time - 1)
+            .await?;
+        history = history.split_off(history.len().saturating_sub(config.window_size(interval)));
+        if history.is_empty() {
+            bail!("{} has no candles before the recording starts", input);
+        }
+        println!(
+            "Replaying {} candles decoded from {} frames of '{}' in {}",
+            replayed.len(),
+            frames.len(),
+            request.name,
+            session
+        );
+        manifest.data.push(manifest::DataRange::of(
+            "session",
+            Some(session),
+            &replayed,
+        )?);
+        venue = Some(Arc::new(source));
+        replayed
     } else {
+        let warmup: usize = match (cli.value("warmup"), recorded("history")) {
+            (Some(v), _) => v.parse().context("--warmup expects a candle count")?,
+            (None, Some(history)) => history.candles,
+            (None, None) => DEFAULT_REPLAY_WARMUP,
+        };
+        if warmup == 0 || warmup >= history.len() {
+            bail!(
+                "--warmup {} leaves nothing to replay from {} candles",
+                warmup,
+                history.len()
+            );
+        }
+        let replayed = history.split_off(warmup);
         println!(
-            "CSV file {} already exists, skipping initial write.",
-            csv_file
+            "Replaying {} candles from {} after {} of warm-up",
+            replayed.len(),
+            input,
+            warmup
+        );
+        replayed
+    };
+
+    let paths = config.output_paths(stream_type, interval)?;
+    if Path::new(&paths.streaming_log).exists() || Path::new(&paths.feature_history).exists() {
+        eprintln!(
+            "Warning: {} already holds outputs; appended files will not match a fresh replay",
+            config.data_dir
         );
     }
+    events::init(&paths.events)?;
+    let count = replayed.len();
+    manifest
+        .data
+        .push(manifest::DataRange::of("history", None, &history)?);
+    manifest
+        .data
+        .push(manifest::DataRange::of("replayed", None, &replayed)?);
+
+    let mut store = history_store(config, &paths, config.window_size(interval));
+    if let Some(venue) = venue {
+        store.source = venue;
+    }
+    let loader = store.clone();
+    let history = async move {
+        loader.snapshot(&history)?;
+        data_storage::save_klines_to_csv(&history, &loader.csv_file)?;
+        Ok(history)
+    };
+    live_stream::run(stream_type, config, history, &paths, store, Some(replayed)).await?;
 
-    Ok(klines)
+    println!("Replayed {} candles. Output hashes:", count);
+    let (outputs, hash) = determinism::hash_outputs(&paths)?;
+    println!("Output hash: {}", hash);
+    if !config.deterministic.enabled {
+        println!("(run with --deterministic for a reproducible hash)");
+    }
+    manifest.outputs = outputs;
+    manifest.output_hash = Some(hash.clone());
+    manifest.save(&paths.manifest)?;
+    match previous.and_then(|m| m.output_hash) {
+        Some(expected) if expected != hash => bail!(
+            "Output hash {} differs from {} in the manifest: the run was not reproduced",
+            hash,
+            expected
+        ),
+        Some(_) => println!("Reproduced the manifest's output hash."),
+        None => {}
+    }
+    Ok(())
 }
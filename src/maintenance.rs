@@ -0,0 +1,154 @@
+//! Exchange maintenance mode (`[maintenance]`).
+//!
+//! An exchange under maintenance refuses the WebSocket handshake with HTTP
+//! 503, closes open streams with code 1012 (service restart) or 1013 (try
+//! again later), and answers REST calls with 503 or Binance -1016. Any of
+//! these puts the exchange in maintenance mode until a new stream session
+//! delivers data or a REST call succeeds again. Meanwhile:
+//!
+//! - the alert sources of `mute` (missed candles, failed REST checks,
+//!   restarts, ...) are only logged and counted in
+//!   `alerts_suppressed_total{source}`; one `maintenance` alert says why;
+//! - streams reconnect every `retry_secs` instead of backing off from 1 s;
+//! - `exchange_maintenance{exchange}` is 1, and the event log gets a
+//!   `maintenance_started` and a `maintenance_ended` event with the window;
+//! - candles overlapping the window get the `maintenance` quality flag.
+
+use crate::alerts::{self, Level};
+use crate::clock;
+use crate::config::MaintenanceConfig;
+use crate::events::{self, Event};
+use crate::metrics;
+use binance_streamer::exchange::Exchange;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::Duration;
+
+/// Ended windows kept for the quality flags, per exchange.
+const WINDOWS_KEPT: usize = 64;
+
+#[derive(Default)]
+struct State {
+    /// Start (epoch ms) of the current maintenance, by exchange.
+    active: BTreeMap<&'static str, i64>,
+    /// Ended maintenance windows (from, to), by exchange, oldest first.
+    ended: BTreeMap<&'static str, VecDeque<(i64, i64)>>,
+}
+
+fn state() -> &'static Mutex<State> {
+    static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+    STATE.get_or_init(|| Mutex::new(State::default()))
+}
+
+fn settings() -> &'static RwLock<MaintenanceConfig> {
+    static SETTINGS: OnceLock<RwLock<MaintenanceConfig>> = OnceLock::new();
+    SETTINGS.get_or_init(|| RwLock::new(MaintenanceConfig::default()))
+}
+
+/// Use `config` from now on.
+pub fn configure(config: &MaintenanceConfig) {
+    *settings().write().unwrap() = config.clone();
+}
+
+/// Whether a WebSocket close frame announces maintenance.
+pub fn is_maintenance_close(code: u16, reason: &str) -> bool {
+    matches!(code, 1012 | 1013) || reason.to_lowercase().contains("maintenance")
+}
+
+/// `exchange` reported maintenance (`reason`); enters maintenance mode unless
+/// already in it.
+pub fn detected(exchange: Exchange, reason: &str) {
+    if !settings().read().unwrap().enabled {
+        return;
+    }
+    let name = exchange.as_str();
+    let from = clock::now_ms();
+    if state().lock().unwrap().active.insert(name, from).is_some() {
+        return;
+    }
+    metrics::gauge_set("exchange_maintenance", &[("exchange", name)], 1.0);
+    events::emit(Event::MaintenanceStarted {
+        exchange: name.to_string(),
+        reason: reason.to_string(),
+    });
+    alerts::raise(
+        Level::Warning,
+        "maintenance",
+        &format!(
+            "{} is under maintenance ({}); muting {} alerts and retrying every {}s",
+            name,
+            reason,
+            settings().read().unwrap().mute.join("/"),
+            settings().read().unwrap().retry_secs
+        ),
+    );
+}
+
+/// `exchange` answered normally again; ends its maintenance mode, if any.
+pub fn recovered(exchange: Exchange) {
+    let name = exchange.as_str();
+    let to = clock::now_ms();
+    let from = {
+        let mut state = state().lock().unwrap();
+        let Some(from) = state.active.remove(name) else {
+            return;
+        };
+        let ended = state.ended.entry(name).or_default();
+        ended.push_back((from, to));
+        if ended.len() > WINDOWS_KEPT {
+            ended.pop_front();
+        }
+        from
+    };
+    metrics::gauge_set("exchange_maintenance", &[("exchange", name)], 0.0);
+    events::emit(Event::MaintenanceEnded {
+        exchange: name.to_string(),
+        from,
+        to,
+    });
+    println!(
+        "[maintenance] {} is back after {:.0}s",
+        name,
+        (to - from) as f64 / 1000.0
+    );
+}
+
+/// Whether `exchange` is in maintenance mode.
+pub fn active(exchange: Exchange) -> bool {
+    state()
+        .lock()
+        .unwrap()
+        .active
+        .contains_key(exchange.as_str())
+}
+
+/// The reconnect delay while `exchange` is in maintenance.
+pub fn retry_delay(exchange: Exchange) -> Option<Duration> {
+    active(exchange).then(|| Duration::from_secs(settings().read().unwrap().retry_secs))
+}
+
+/// Whether an alert from `source` is held back: some exchange is in
+/// maintenance and `source` is muted.
+pub fn mutes(source: &str) -> bool {
+    // try_lock: a stuck lock must not hold back alerts
+    let any_active = state()
+        .try_lock()
+        .is_ok_and(|state| !state.active.is_empty());
+    any_active && settings().read().unwrap().mute.iter().any(|s| s == source)
+}
+
+/// Whether `[open_time, close_time]` overlaps a maintenance window of
+/// `exchange`, the current one included.
+pub fn overlaps(exchange: Exchange, open_time: i64, close_time: i64) -> bool {
+    let state = state().lock().unwrap();
+    let name = exchange.as_str();
+    state
+        .active
+        .get(name)
+        .is_some_and(|from| close_time >= *from)
+        || state.ended.get(name).is_some_and(|ended| {
+            ended
+                .iter()
+                .any(|(from, to)| open_time <= *to && close_time >= *from)
+        })
+}
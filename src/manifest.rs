@@ -0,0 +1,160 @@
+//! Run manifests (`run_manifest.json` next to a run's outputs).
+//!
+//! A manifest records what a run depended on: the effective configuration,
+//! the strategies with their parameters and versions, the crate version and
+//! git commit, a fingerprint of the feature code, and the candle ranges it
+//! read (with the SHA-256 of input files). A replay also records the hash of
+//! every output; a live run writes its manifest once the history is loaded.
+//!
+//! `replay --manifest <run_manifest.json>` runs the recorded replay again:
+//! the configuration, stream, input, warm-up and session come from the
+//! manifest (flags still override, e.g. `--data-dir` to write elsewhere), the
+//! inputs must hash as recorded, and the command fails unless the outputs
+//! hash as recorded too.
+
+use crate::bench;
+use crate::config::Config;
+use crate::determinism::DEFAULT_SEED;
+use anyhow::{Context, Result};
+use binance_streamer::features;
+use binance_streamer::kline::Kline;
+use chrono::Utc;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Candles of the synthetic series the feature fingerprint is computed on.
+const FINGERPRINT_CANDLES: usize = 2_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyRecord {
+    pub name: String,
+    pub version: String,
+    pub params: Value,
+}
+
+/// Candles a run read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataRange {
+    /// `input`, `session`, `history` or `replayed`.
+    pub role: String,
+    pub path: Option<String>,
+    /// SHA-256 of the file at `path`.
+    pub sha256: Option<String>,
+    pub candles: usize,
+    /// `open_time` of the first and last candle (epoch ms).
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+impl DataRange {
+    /// The range of `klines`, read from `path` if any.
+    pub fn of(role: &str, path: Option<&str>, klines: &[Kline]) -> Result<Self> {
+        Ok(Self {
+            role: role.to_string(),
+            path: path.map(str::to_string),
+            sha256: path.map(sha256_file).transpose()?,
+            candles: klines.len(),
+            from: klines.first().map(|k| k.open_time),
+            to: klines.last().map(|k| k.open_time),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunManifest {
+    /// `replay` or `live`.
+    pub command: String,
+    /// Stream type, e.g. "m15".
+    pub stream: String,
+    pub symbol: String,
+    /// Command line, without the program name.
+    pub args: Vec<String>,
+    /// Wall-clock start of the run (epoch ms).
+    pub started: i64,
+    pub version: String,
+    /// Commit the binary was built from, when built from a git checkout.
+    pub git_commit: Option<String>,
+    /// The effective configuration, after every override.
+    pub config: Value,
+    /// SHA-256 of the features computed over a fixed synthetic series: any
+    /// change to the feature code changes it.
+    pub feature_fingerprint: String,
+    pub strategies: Vec<StrategyRecord>,
+    pub data: Vec<DataRange>,
+    /// SHA-256 per output file (replays).
+    #[serde(default)]
+    pub outputs: BTreeMap<String, String>,
+    /// Hash over every output (replays).
+    #[serde(default)]
+    pub output_hash: Option<String>,
+}
+
+impl RunManifest {
+    /// The manifest of a run of `command` on `stream` with `config`; data
+    /// ranges and output hashes are added by the caller.
+    pub fn new(command: &str, stream: &str, config: &Config) -> Result<Self> {
+        let strategies = config
+            .build_strategies()?
+            .iter()
+            .map(|s| StrategyRecord {
+                name: s.name().to_string(),
+                version: s.version(),
+                params: s.params(),
+            })
+            .collect();
+        Ok(Self {
+            command: command.to_string(),
+            stream: stream.to_string(),
+            symbol: config.symbol.clone(),
+            args: std::env::args().skip(1).collect(),
+            started: Utc::now().timestamp_millis(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_commit: option_env!("GIT_COMMIT").map(str::to_string),
+            config: serde_json::to_value(config)?,
+            feature_fingerprint: feature_fingerprint()?,
+            strategies,
+            data: Vec::new(),
+            outputs: BTreeMap::new(),
+            output_hash: None,
+        })
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read manifest {}", path))?;
+        serde_json::from_str(&text).with_context(|| format!("Failed to parse manifest {}", path))
+    }
+
+    /// Write the manifest to `path`, through a temporary file.
+    pub fn save(&self, path: &str) -> Result<()> {
+        let tmp = format!("{}.tmp", path);
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        println!("Wrote the run manifest to {}", path);
+        Ok(())
+    }
+
+    /// The recorded data range of `role`.
+    pub fn range(&self, role: &str) -> Option<&DataRange> {
+        self.data.iter().find(|d| d.role == role)
+    }
+}
+
+/// SHA-256 of the file at `path`.
+pub fn sha256_file(path: &str) -> Result<String> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path))?;
+    Ok(format!("{:x}", Sha256::digest(bytes)))
+}
+
+/// SHA-256 of the CSV of the features of [`FINGERPRINT_CANDLES`] synthetic
+/// candles.
+fn feature_fingerprint() -> Result<String> {
+    let klines = bench::synthetic_klines(FINGERPRINT_CANDLES, DEFAULT_SEED);
+    let mut df = features::compute_features(&klines)?;
+    let mut csv = Vec::new();
+    CsvWriter::new(&mut csv).finish(&mut df)?;
+    Ok(format!("{:x}", Sha256::digest(csv)))
+}
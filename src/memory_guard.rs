@@ -0,0 +1,122 @@
+//! Resident memory budget for the live pipeline.
+//!
+//! RSS is sampled before every candle. Over budget, the pipeline sheds
+//! memory instead of growing until the OOM killer ends the run: the optional
+//! pivot side columns are dropped from the feature snapshot and the feature
+//! window is halved on every over-budget candle, down to `min_feature_window`.
+//! Once RSS is back below `recover_ratio` of the budget the window doubles
+//! again, and the full snapshot returns when it reaches its configured size.
+
+use crate::alerts::{self, Level};
+use crate::config::MemoryConfig;
+use crate::metrics;
+use memory_stats::memory_stats;
+
+const MB: u64 = 1024 * 1024;
+
+/// Feature columns left out of the snapshot while over budget; the pivot
+/// strengths they are derived into are kept.
+pub const OPTIONAL_COLUMNS: [&str; 4] = [
+    "pivot_high_left",
+    "pivot_high_right",
+    "pivot_low_left",
+    "pivot_low_right",
+];
+
+/// How the next candle's features are computed and stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryPlan {
+    /// Candles in the feature frame.
+    pub feature_window: usize,
+    /// Leave [`OPTIONAL_COLUMNS`] out of the feature snapshot.
+    pub drop_optional: bool,
+}
+
+pub struct MemoryGuard {
+    config: MemoryConfig,
+    full_window: usize,
+    window: usize,
+    /// Set once the smallest window is reached while still over budget, so
+    /// the critical alert fires once per episode.
+    exhausted: bool,
+}
+
+impl MemoryGuard {
+    pub fn new(config: &MemoryConfig, full_window: usize) -> Self {
+        Self {
+            config: config.clone(),
+            full_window,
+            window: full_window,
+            exhausted: false,
+        }
+    }
+
+    /// Sample RSS, shrink or grow the window, and return the plan for the
+    /// next candle.
+    pub fn check(&mut self) -> MemoryPlan {
+        if let Some(budget_mb) = self.config.budget_mb {
+            match memory_stats() {
+                Some(stats) => self.adapt(stats.physical_mem as u64, budget_mb * MB),
+                None => eprintln!("[Memory] Unable to obtain memory stats on this platform."),
+            }
+        }
+        metrics::gauge_set("feature_window_size", &[], self.window as f64);
+        metrics::gauge_set("memory_reduced", &[], self.reduced() as u8 as f64);
+        self.plan()
+    }
+
+    fn plan(&self) -> MemoryPlan {
+        MemoryPlan {
+            feature_window: self.window,
+            drop_optional: self.reduced(),
+        }
+    }
+
+    fn reduced(&self) -> bool {
+        self.window < self.full_window
+    }
+
+    fn adapt(&mut self, rss: u64, budget: u64) {
+        metrics::gauge_set("process_rss_bytes", &[], rss as f64);
+        let usage = format!("RSS {} MB, budget {} MB", rss / MB, budget / MB);
+        let min_window = self.config.min_feature_window.min(self.full_window);
+
+        if rss > budget {
+            if self.window > min_window {
+                self.window = (self.window / 2).max(min_window);
+                alerts::raise(
+                    Level::Warning,
+                    "memory",
+                    &format!(
+                        "{}; feature window shrunk to {} candles, optional columns dropped",
+                        usage, self.window
+                    ),
+                );
+            } else if !self.exhausted {
+                self.exhausted = true;
+                alerts::raise(
+                    Level::Critical,
+                    "memory",
+                    &format!(
+                        "{} with the feature window already at its minimum ({} candles)",
+                        usage, self.window
+                    ),
+                );
+            }
+        } else if self.reduced() && (rss as f64) < budget as f64 * self.config.recover_ratio {
+            self.exhausted = false;
+            self.window = (self.window * 2).min(self.full_window);
+            if self.reduced() {
+                println!(
+                    "[Memory] {}; feature window grown to {}",
+                    usage, self.window
+                );
+            } else {
+                println!(
+                    "[Memory] {}; full feature window and columns restored",
+                    usage
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! In-process metrics registry (histograms, counters, gauges).
+//!
+//! Everything lives in one global registry so any module can record without
+//! threading a handle around. `render_prometheus` produces the text exposition
+//! format served on `/metrics`, including the progress of REST candle fetches;
+//! `log_latency_summary` prints p50/p95/p99.
+
+use binance_streamer::progress;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+/// Upper bounds (ms) of the latency histogram buckets; an implicit +Inf bucket follows.
+const LATENCY_BUCKETS_MS: &[f64] = &[
+    0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0,
+    10000.0, 30000.0, 60000.0, 300000.0,
+];
+
+/// Name of the per-candle stage latency histogram (label `stage`).
+pub const STAGE_LATENCY: &str = "candle_stage_latency_ms";
+
+/// Name of the histogram of how long after its nominal close a candle
+/// arrived and was processed (labels `symbol`, `interval`, `stage`).
+pub const CLOSE_LAG: &str = "candle_close_lag_ms";
+
+/// Name of the histogram of operations timed with `utils::measure_time` and
+/// friends (labels `label`, `result`).
+pub const TIMED_LATENCY: &str = "timed_operation_ms";
+
+/// Metric identity: name plus sorted label pairs.
+type Key = (String, Vec<(String, String)>);
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    counts: Vec<u64>, // one per bucket + the +Inf bucket
+    count: u64,
+    sum: f64,
+    max: f64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            counts: vec![0; LATENCY_BUCKETS_MS.len() + 1],
+            count: 0,
+            sum: 0.0,
+            max: 0.0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        let idx = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&b| value <= b)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.counts[idx] += 1;
+        self.count += 1;
+        self.sum += value;
+        self.max = self.max.max(value);
+    }
+
+    /// Estimate a quantile by linear interpolation inside the matching bucket.
+    fn quantile(&self, q: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let rank = q * self.count as f64;
+        let mut cumulative = 0u64;
+        for (i, &c) in self.counts.iter().enumerate() {
+            if c == 0 {
+                continue;
+            }
+            if (cumulative + c) as f64 >= rank {
+                let lower = if i == 0 {
+                    0.0
+                } else {
+                    LATENCY_BUCKETS_MS[i - 1]
+                };
+                let upper = LATENCY_BUCKETS_MS.get(i).copied().unwrap_or(self.max);
+                let fraction = (rank - cumulative as f64) / c as f64;
+                return (lower + (upper - lower) * fraction).min(self.max);
+            }
+            cumulative += c;
+        }
+        self.max
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    histograms: BTreeMap<Key, Histogram>,
+    counters: BTreeMap<Key, u64>,
+    gauges: BTreeMap<Key, f64>,
+}
+
+fn registry() -> &'static Mutex<Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(Registry::default()))
+}
+
+fn key(name: &str, labels: &[(&str, &str)]) -> Key {
+    let mut labels: Vec<(String, String)> = labels
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    labels.sort();
+    (name.to_string(), labels)
+}
+
+/// Record one observation (in milliseconds) into a histogram.
+pub fn observe_ms(name: &str, labels: &[(&str, &str)], value_ms: f64) {
+    let mut reg = registry().lock().unwrap();
+    reg.histograms
+        .entry(key(name, labels))
+        .or_insert_with(Histogram::new)
+        .observe(value_ms);
+}
+
+/// Record the elapsed time since `start` for a pipeline stage.
+pub fn observe_stage(stage: &str, start: Instant) {
+    observe_ms(
+        STAGE_LATENCY,
+        &[("stage", stage)],
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+}
+
+/// Increment a counter.
+pub fn counter_inc(name: &str, labels: &[(&str, &str)], by: u64) {
+    let mut reg = registry().lock().unwrap();
+    *reg.counters.entry(key(name, labels)).or_insert(0) += by;
+}
+
+/// Set a gauge to an absolute value.
+pub fn gauge_set(name: &str, labels: &[(&str, &str)], value: f64) {
+    let mut reg = registry().lock().unwrap();
+    reg.gauges.insert(key(name, labels), value);
+}
+
+fn format_labels(labels: &[(String, String)], extra: Option<(&str, String)>) -> String {
+    let mut parts: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, v))
+        .collect();
+    if let Some((k, v)) = extra {
+        parts.push(format!("{}=\"{}\"", k, v));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", parts.join(","))
+    }
+}
+
+/// Mirror the state of every REST candle fetch into `fetch_*` gauges.
+fn record_fetch_progress() {
+    for status in progress::statuses() {
+        let labels = [
+            ("exchange", status.exchange),
+            ("symbol", status.symbol.as_str()),
+            ("interval", status.interval.as_str()),
+        ];
+        gauge_set("fetch_candles", &labels, status.rows as f64);
+        gauge_set("fetch_candles_per_sec", &labels, status.rows_per_sec);
+        gauge_set("fetch_progress_ratio", &labels, status.fraction);
+        gauge_set("fetch_reached_ms", &labels, status.reached as f64);
+        gauge_set("fetch_eta_seconds", &labels, status.eta_secs.unwrap_or(0.0));
+        gauge_set("fetch_active", &labels, f64::from(u8::from(status.active)));
+    }
+}
+
+/// Render every metric in the Prometheus text exposition format.
+pub fn render_prometheus() -> String {
+    record_fetch_progress();
+    let reg = registry().lock().unwrap();
+    let mut out = String::new();
+
+    for ((name, labels), value) in &reg.counters {
+        let _ = writeln!(out, "{}{} {}", name, format_labels(labels, None), value);
+    }
+    for ((name, labels), value) in &reg.gauges {
+        let _ = writeln!(out, "{}{} {}", name, format_labels(labels, None), value);
+    }
+    for ((name, labels), h) in &reg.histograms {
+        let mut cumulative = 0;
+        for (i, c) in h.counts.iter().enumerate() {
+            cumulative += c;
+            let le = LATENCY_BUCKETS_MS
+                .get(i)
+                .map(|b| b.to_string())
+                .unwrap_or_else(|| "+Inf".to_string());
+            let _ = writeln!(
+                out,
+                "{}_bucket{} {}",
+                name,
+                format_labels(labels, Some(("le", le))),
+                cumulative
+            );
+        }
+        let _ = writeln!(out, "{}_sum{} {}", name, format_labels(labels, None), h.sum);
+        let _ = writeln!(
+            out,
+            "{}_count{} {}",
+            name,
+            format_labels(labels, None),
+            h.count
+        );
+    }
+    out
+}
+
+/// Print p50/p95/p99 of every histogram observed so far.
+pub fn log_latency_summary() {
+    let reg = registry().lock().unwrap();
+    if reg.histograms.is_empty() {
+        return;
+    }
+    println!("=== Latency summary (ms) ===");
+    for ((name, labels), h) in &reg.histograms {
+        let label = labels
+            .iter()
+            .map(|(_, v)| v.as_str())
+            .collect::<Vec<_>>()
+            .join("/");
+        println!(
+            "{:<40} n={:<6} p50={:>9.2} p95={:>9.2} p99={:>9.2} max={:>9.2}",
+            if label.is_empty() {
+                name.clone()
+            } else {
+                label
+            },
+            h.count,
+            h.quantile(0.50),
+            h.quantile(0.95),
+            h.quantile(0.99),
+            h.max
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Optional NATS sink for feeding a central data platform.
+//!
+//! Every closed candle is published as JSON on `{prefix}.candles.{SYMBOL}` and
+//! every feature row (same shape as `GET /latest/{symbol}`) on
+//! `{prefix}.features.{SYMBOL}`. Messages are queued locally before sending;
+//! while the server is unreachable up to `buffer` of them are kept (oldest
+//! dropped first) and delivered in order once the client has reconnected.
+
+use crate::alerts::{self, Level};
+use crate::bus::{self, Update};
+use crate::config::NatsConfig;
+use crate::{latest, metrics};
+use anyhow::{Context, Result};
+use async_nats::connection::State;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// How often a non-empty buffer is retried when no new updates arrive.
+const RETRY_EVERY: Duration = Duration::from_secs(1);
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Subject and JSON payload for one bus update, or `None` if the sink ignores it.
+fn message(prefix: &str, update: Update) -> Option<(String, String)> {
+    match update {
+        Update::Candle {
+            symbol,
+            interval,
+            kline,
+        } => {
+            let symbol = symbol.to_uppercase();
+            let payload = json!({ "symbol": symbol, "interval": interval, "candle": kline });
+            Some((
+                format!("{}.candles.{}", prefix, symbol),
+                payload.to_string(),
+            ))
+        }
+        Update::Features {
+            symbol,
+            interval,
+            values,
+            ..
+        } => {
+            let symbol = symbol.to_uppercase();
+            let payload = latest::feature_message(&symbol, &interval, &values);
+            Some((
+                format!("{}.features.{}", prefix, symbol),
+                payload.to_string(),
+            ))
+        }
+        Update::Signal(_) => None,
+    }
+}
+
+struct Outbox {
+    client: async_nats::Client,
+    pending: VecDeque<(String, String)>,
+    capacity: usize,
+    failing: bool,
+}
+
+impl Outbox {
+    fn push(&mut self, message: (String, String)) {
+        if self.pending.len() >= self.capacity {
+            self.pending.pop_front();
+            metrics::counter_inc("nats_dropped_messages_total", &[], 1);
+        }
+        self.pending.push_back(message);
+        metrics::gauge_set("nats_buffered_messages", &[], self.pending.len() as f64);
+    }
+
+    /// Send buffered messages in order; anything not confirmed by a flush stays queued.
+    async fn drain(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let result = if self.client.connection_state() != State::Connected {
+            Err(anyhow::anyhow!("not connected"))
+        } else {
+            self.send_all().await
+        };
+        match result {
+            Ok(sent) => {
+                self.pending.drain(..sent);
+                metrics::counter_inc("nats_published_total", &[], sent as u64);
+                if self.failing {
+                    self.failing = false;
+                    println!("NATS sink recovered");
+                }
+            }
+            Err(e) => {
+                metrics::counter_inc("nats_publish_errors_total", &[], 1);
+                if !self.failing {
+                    self.failing = true;
+                    alerts::raise(
+                        Level::Warning,
+                        "nats",
+                        &format!(
+                            "publish failed, buffering up to {} messages: {:#}",
+                            self.capacity, e
+                        ),
+                    );
+                }
+            }
+        }
+        metrics::gauge_set("nats_buffered_messages", &[], self.pending.len() as f64);
+    }
+
+    async fn send_all(&self) -> Result<usize> {
+        for (subject, payload) in &self.pending {
+            self.client
+                .publish(subject.clone(), payload.clone().into())
+                .await?;
+        }
+        tokio::time::timeout(FLUSH_TIMEOUT, self.client.flush())
+            .await
+            .context("flush timed out")??;
+        Ok(self.pending.len())
+    }
+}
+
+/// Connect to NATS and forward bus updates in a background task.
+/// An unreachable server at startup is an error; later outages are buffered.
+pub async fn spawn(config: &NatsConfig) -> Result<()> {
+    let Some(url) = &config.url else {
+        return Ok(());
+    };
+    let client = async_nats::ConnectOptions::new()
+        .name("binance_streamer")
+        .connection_timeout(Duration::from_secs(5))
+        .connect(url.as_str())
+        .await
+        .with_context(|| format!("Failed to connect to NATS at {}", url))?;
+    println!(
+        "Publishing candles and feature rows to NATS ({}.candles.<SYMBOL>, {}.features.<SYMBOL>)",
+        config.prefix, config.prefix
+    );
+
+    let prefix = config.prefix.clone();
+    let mut outbox = Outbox {
+        client,
+        pending: VecDeque::new(),
+        capacity: config.buffer.max(1),
+        failing: false,
+    };
+    let mut updates = bus::subscribe();
+    let mut retry = tokio::time::interval(RETRY_EVERY);
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                received = updates.recv() => match received {
+                    Ok(update) => {
+                        if let Some(message) = message(&prefix, update) {
+                            outbox.push(message);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        metrics::counter_inc("nats_skipped_updates_total", &[], skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = retry.tick() => {}
+            }
+            outbox.drain().await;
+        }
+    });
+    Ok(())
+}
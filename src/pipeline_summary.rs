@@ -0,0 +1,164 @@
+//! Compact pipeline log (`pipeline_summary_every`).
+//!
+//! By default every closed candle prints its own block of lines – the candle,
+//! the active sessions, the memory usage and breakdown – interleaved with
+//! those of every other pipeline. With `pipeline_summary_every = N` a
+//! pipeline prints nothing per candle; every N candles (and when it stops) it
+//! prints one table instead: the candles covered, signals and coalesced
+//! candles, the window occupancy, the RSS, the reconnects of the symbol's
+//! streams since start, then per stage (the close lags included) and per
+//! output the runs, failed saves and the mean and max latency.
+
+use crate::timezone;
+use memory_stats::memory_stats;
+use std::time::Duration;
+
+/// One save of an output of a candle.
+pub struct Saved {
+    pub target: String,
+    pub ok: bool,
+    pub ms: f64,
+}
+
+#[derive(Default, Clone, Copy)]
+struct Timing {
+    runs: u64,
+    failed: u64,
+    total_ms: f64,
+    max_ms: f64,
+}
+
+impl Timing {
+    fn add(&mut self, ms: f64, ok: bool) {
+        self.runs += 1;
+        self.failed += u64::from(!ok);
+        self.total_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+    }
+}
+
+/// What a pipeline did since its last summary.
+pub struct PipelineSummary {
+    symbol: String,
+    interval: String,
+    every: u64,
+    candles: u64,
+    coalesced: u64,
+    signals: u64,
+    /// Open times of the first and last candle covered.
+    span: Option<(i64, i64)>,
+    /// Stages, then outputs, in the order first seen.
+    stages: Vec<(String, Timing)>,
+    outputs: Vec<(String, Timing)>,
+}
+
+fn add(rows: &mut Vec<(String, Timing)>, name: &str, ms: f64, ok: bool) {
+    match rows.iter_mut().find(|(n, _)| n == name) {
+        Some((_, timing)) => timing.add(ms, ok),
+        None => {
+            let mut timing = Timing::default();
+            timing.add(ms, ok);
+            rows.push((name.to_string(), timing));
+        }
+    }
+}
+
+impl PipelineSummary {
+    /// A summary of every `every` candles; `None` for 0 (per-candle lines).
+    pub fn new(symbol: &str, interval: &str, every: u64) -> Option<Self> {
+        (every > 0).then(|| Self {
+            symbol: symbol.to_string(),
+            interval: interval.to_string(),
+            every,
+            candles: 0,
+            coalesced: 0,
+            signals: 0,
+            span: None,
+            stages: Vec::new(),
+            outputs: Vec::new(),
+        })
+    }
+
+    pub fn stage(&mut self, name: &str, elapsed: Duration) {
+        add(&mut self.stages, name, elapsed.as_secs_f64() * 1000.0, true);
+    }
+
+    pub fn saved(&mut self, saved: &Saved) {
+        add(&mut self.outputs, &saved.target, saved.ms, saved.ok);
+    }
+
+    /// A candle taken in without a feature row.
+    pub fn coalesced(&mut self) {
+        self.coalesced += 1;
+    }
+
+    /// A processed candle and the signals it raised; whether a summary is due.
+    pub fn candle(&mut self, open_time: i64, signals: usize) -> bool {
+        self.candles += 1;
+        self.signals += signals as u64;
+        self.span = Some(
+            self.span
+                .map_or((open_time, open_time), |(first, _)| (first, open_time)),
+        );
+        self.candles >= self.every
+    }
+
+    /// Print the summary, if it covers any candle, and start the next one.
+    pub fn log(&mut self, window_len: usize, window_size: usize, reconnects: u64) {
+        let Some((first, last)) = self.span else {
+            return;
+        };
+        let rss = memory_stats().map_or("n/a".to_string(), |ms| {
+            format!("{:.1} MB", ms.physical_mem as f64 / (1024.0 * 1024.0))
+        });
+        let mut text = format!(
+            "[summary] {} {}: {} candles {} → {} | {} signals | {} coalesced | window {}/{} ({:.0}%) | RSS {} | reconnects {}\n",
+            self.symbol,
+            self.interval,
+            self.candles,
+            timezone::format_ms(first),
+            timezone::format_ms(last),
+            self.signals,
+            self.coalesced,
+            window_len,
+            window_size,
+            window_len as f64 * 100.0 / window_size.max(1) as f64,
+            rss,
+            reconnects
+        );
+        let width = self
+            .stages
+            .iter()
+            .chain(&self.outputs)
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0)
+            .max("stage/output".len());
+        text.push_str(&format!(
+            "  {:<width$} {:>6} {:>6} {:>9} {:>9}\n",
+            "stage/output", "runs", "failed", "avg ms", "max ms"
+        ));
+        for (name, timing) in self.stages.iter().chain(&self.outputs) {
+            text.push_str(&format!(
+                "  {:<width$} {:>6} {:>6} {:>9.2} {:>9.2}\n",
+                name,
+                timing.runs,
+                timing.failed,
+                timing.total_ms / timing.runs.max(1) as f64,
+                timing.max_ms
+            ));
+        }
+        print!("{}", text);
+        self.clear();
+    }
+
+    /// Start the next summary without printing this one.
+    pub fn clear(&mut self) {
+        self.candles = 0;
+        self.coalesced = 0;
+        self.signals = 0;
+        self.span = None;
+        self.stages.clear();
+        self.outputs.clear();
+    }
+}
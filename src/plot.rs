@@ -0,0 +1,370 @@
+//! `plot` subcommand: render candles with overlays to PNG or SVG (feature `plot`).
+//!
+//! ```text
+//! cargo run --release --features plot plot [--input data/m15_features.parquet]
+//!     [--from 2025-01-01T00:00:00Z] [--to <ms|RFC 3339>] [--output chart.png|chart.svg]
+//!     [--overlays ema50_m15,ema200_m15,bollinger,pivots,signals]
+//!     [--events data/m15_events.jsonl] [--pivot-strength 10] [--size 1600x900]
+//! ```
+//! Overlays are any float column of the feature frame (drawn as a line) plus
+//! three computed ones: `bollinger` (20-period, 2σ on close), `pivots`
+//! (markers on candles whose pivot strength reaches `--pivot-strength`) and
+//! `signals` (strategy signals read from the event log). Without `--from` the
+//! last 200 candles up to `--to` are drawn.
+
+use crate::config::{CliArgs, OutputPaths};
+use crate::{data_storage, timezone, utils};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::DateTime;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use polars::prelude::*;
+use serde_json::Value;
+use std::io::{BufRead, BufReader};
+
+const DEFAULT_OVERLAYS: &str = "ema50_m15,ema200_m15,bollinger,pivots,signals";
+const DEFAULT_CANDLES: usize = 200;
+const DEFAULT_PIVOT_STRENGTH: u32 = 10;
+const BOLLINGER_PERIOD: usize = 20;
+const BOLLINGER_K: f64 = 2.0;
+const LINE_COLORS: &[RGBColor] = &[
+    RGBColor(31, 119, 180),
+    RGBColor(255, 127, 14),
+    RGBColor(148, 103, 189),
+    RGBColor(140, 86, 75),
+    RGBColor(227, 119, 194),
+    RGBColor(23, 190, 207),
+];
+
+/// The candles being drawn, plus everything overlaid on them.
+struct Chart {
+    title: String,
+    open_time: Vec<i64>,
+    open: Vec<f64>,
+    high: Vec<f64>,
+    low: Vec<f64>,
+    close: Vec<f64>,
+    lines: Vec<(String, Vec<Option<f64>>)>,
+    bollinger: Option<[Vec<Option<f64>>; 3]>,
+    /// (candle index, price, is_high)
+    pivots: Vec<(usize, f64, bool)>,
+    /// (candle index, price, is_long)
+    signals: Vec<(usize, f64, bool)>,
+}
+
+fn f64_column(df: &DataFrame, name: &str) -> Result<Vec<Option<f64>>> {
+    let column = df
+        .column(name)
+        .with_context(|| format!("Unknown overlay column '{}'", name))?
+        .cast(&DataType::Float64)?;
+    Ok(column.f64()?.into_iter().collect())
+}
+
+fn values(df: &DataFrame, name: &str) -> Result<Vec<f64>> {
+    Ok(f64_column(df, name)?
+        .into_iter()
+        .map(|v| v.unwrap_or(f64::NAN))
+        .collect())
+}
+
+/// Rolling mean ± `BOLLINGER_K` standard deviations of `close` (middle, upper, lower).
+fn bollinger(close: &[f64]) -> [Vec<Option<f64>>; 3] {
+    let mut bands = [
+        vec![None; close.len()],
+        vec![None; close.len()],
+        vec![None; close.len()],
+    ];
+    for end in BOLLINGER_PERIOD..=close.len() {
+        let window = &close[end - BOLLINGER_PERIOD..end];
+        let mean = window.iter().sum::<f64>() / BOLLINGER_PERIOD as f64;
+        let var = window.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / BOLLINGER_PERIOD as f64;
+        let sd = var.sqrt();
+        bands[0][end - 1] = Some(mean);
+        bands[1][end - 1] = Some(mean + BOLLINGER_K * sd);
+        bands[2][end - 1] = Some(mean - BOLLINGER_K * sd);
+    }
+    bands
+}
+
+/// Strategy signals from the event log within `[from, to]` (open_time, side is long).
+fn read_signals(path: &str, from: i64, to: i64) -> Result<Vec<(i64, f64, bool)>> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open event log {}", path))?;
+    let mut signals = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let Ok(event) = serde_json::from_str::<Value>(&line?) else {
+            continue;
+        };
+        if event["event"] != "signal" {
+            continue;
+        }
+        let (Some(open_time), Some(price)) = (event["open_time"].as_i64(), event["price"].as_f64())
+        else {
+            continue;
+        };
+        if (from..=to).contains(&open_time) {
+            signals.push((open_time, price, event["side"] == "long"));
+        }
+    }
+    Ok(signals)
+}
+
+fn draw<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, chart: &Chart) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    let err = |e: DrawingAreaErrorKind<DB::ErrorType>| anyhow!("Drawing failed: {}", e);
+    root.fill(&WHITE).map_err(err)?;
+
+    let n = chart.open_time.len();
+    let (mut y_min, mut y_max) = chart
+        .low
+        .iter()
+        .zip(&chart.high)
+        .filter(|(l, h)| l.is_finite() && h.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), (l, h)| {
+            (lo.min(*l), hi.max(*h))
+        });
+    let overlay_values = chart
+        .lines
+        .iter()
+        .map(|(_, v)| v)
+        .chain(chart.bollinger.iter().flatten());
+    for v in overlay_values.flatten().flatten() {
+        y_min = y_min.min(*v);
+        y_max = y_max.max(*v);
+    }
+    let pad = (y_max - y_min).max(f64::EPSILON) * 0.03;
+
+    let mut ctx = ChartBuilder::on(&root)
+        .caption(&chart.title, ("sans-serif", 22))
+        .margin(12)
+        .x_label_area_size(40)
+        .y_label_area_size(70)
+        .build_cartesian_2d(-1f64..n as f64, (y_min - pad)..(y_max + pad))
+        .map_err(err)?;
+    let times = &chart.open_time;
+    let label = |x: &f64| {
+        let i = x.round();
+        if i < 0.0 || i as usize >= n {
+            return String::new();
+        }
+        DateTime::from_timestamp_millis(times[i as usize])
+            .map(|t| {
+                t.with_timezone(&timezone::display())
+                    .format("%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default()
+    };
+    ctx.configure_mesh()
+        .x_labels(10)
+        .x_label_formatter(&label)
+        .y_labels(10)
+        .light_line_style(WHITE.mix(0.0))
+        .draw()
+        .map_err(err)?;
+
+    // Wider charts get wider bodies; leave a gap between neighbours
+    let width = ((root.dim_in_pixel().0 as f64 / n.max(1) as f64) * 0.6).clamp(1.0, 20.0) as u32;
+    ctx.draw_series((0..n).map(|i| {
+        CandleStick::new(
+            i as f64,
+            chart.open[i],
+            chart.high[i],
+            chart.low[i],
+            chart.close[i],
+            RGBColor(38, 166, 154).filled(),
+            RGBColor(239, 83, 80).filled(),
+            width,
+        )
+    }))
+    .map_err(err)?;
+
+    // Null runs (warm-up) split a line into separate segments
+    let segments = |v: &[Option<f64>]| -> Vec<Vec<(f64, f64)>> {
+        let mut out = vec![Vec::new()];
+        for (i, value) in v.iter().enumerate() {
+            match value {
+                Some(y) if y.is_finite() => out.last_mut().unwrap().push((i as f64, *y)),
+                _ if !out.last().unwrap().is_empty() => out.push(Vec::new()),
+                _ => {}
+            }
+        }
+        out
+    };
+
+    for (k, (name, v)) in chart.lines.iter().enumerate() {
+        let color = LINE_COLORS[k % LINE_COLORS.len()];
+        for (s, points) in segments(v).into_iter().enumerate() {
+            let series = ctx
+                .draw_series(LineSeries::new(points, color.stroke_width(2)))
+                .map_err(err)?;
+            if s == 0 {
+                series
+                    .label(name.as_str())
+                    .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], color));
+            }
+        }
+    }
+    if let Some(bands) = &chart.bollinger {
+        let color = RGBColor(120, 120, 120);
+        for (b, band) in bands.iter().enumerate() {
+            let style = if b == 0 {
+                color.stroke_width(1)
+            } else {
+                color.mix(0.7).stroke_width(1)
+            };
+            for (s, points) in segments(band).into_iter().enumerate() {
+                let series = ctx
+                    .draw_series(LineSeries::new(points, style))
+                    .map_err(err)?;
+                if b == 0 && s == 0 {
+                    series
+                        .label("bollinger(20, 2)")
+                        .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], color));
+                }
+            }
+        }
+    }
+
+    ctx.draw_series(chart.pivots.iter().map(|&(i, price, is_high)| {
+        let color = if is_high { RED } else { BLUE };
+        Circle::new((i as f64, price), 4, color.stroke_width(2))
+    }))
+    .map_err(err)?;
+    ctx.draw_series(chart.signals.iter().map(|&(i, price, is_long)| {
+        let (color, size) = if is_long {
+            (RGBColor(0, 150, 0), 9)
+        } else {
+            (RGBColor(200, 0, 0), -9)
+        };
+        // Triangles point up for longs and down for shorts
+        Polygon::new(
+            vec![
+                (i as f64, price),
+                (i as f64 - 0.8, price - size as f64 * pad / 9.0),
+                (i as f64 + 0.8, price - size as f64 * pad / 9.0),
+            ],
+            color.filled(),
+        )
+    }))
+    .map_err(err)?;
+
+    if !chart.lines.is_empty() || chart.bollinger.is_some() {
+        ctx.configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .position(SeriesLabelPosition::UpperLeft)
+            .draw()
+            .map_err(err)?;
+    }
+    root.present().map_err(err)?;
+    Ok(())
+}
+
+fn parse_size(value: &str) -> Result<(u32, u32)> {
+    let (w, h) = value
+        .split_once('x')
+        .context("--size expects WIDTHxHEIGHT, e.g. 1600x900")?;
+    Ok((w.parse()?, h.parse()?))
+}
+
+pub fn run(cli: &CliArgs, paths: &OutputPaths) -> Result<()> {
+    let input = cli.value("input").unwrap_or(&paths.feature_parquet);
+    let output = cli.value("output").unwrap_or("chart.png");
+    let size = parse_size(cli.value("size").unwrap_or("1600x900"))?;
+    let pivot_strength = match cli.value("pivot-strength") {
+        Some(v) => v.parse().context("--pivot-strength must be an integer")?,
+        None => DEFAULT_PIVOT_STRENGTH,
+    };
+    let from = cli.value("from").map(utils::parse_time_bound).transpose()?;
+    let to = cli.value("to").map(utils::parse_time_bound).transpose()?;
+
+    // Load everything up to `to` so the Bollinger bands are warm at `from`
+    let df = data_storage::load_dataframe_range(input, None, to)
+        .with_context(|| format!("Failed to load {}", input))?;
+    let open_time: Vec<i64> = df.column("open_time")?.i64()?.into_no_null_iter().collect();
+    let start = match from {
+        Some(from) => open_time.partition_point(|&t| t < from),
+        None => open_time.len().saturating_sub(DEFAULT_CANDLES),
+    };
+    if start >= open_time.len() {
+        bail!("No candles in the requested range of {}", input);
+    }
+    let close_all = values(&df, "close")?;
+    let df = df.slice(start as i64, open_time.len() - start);
+    let open_time = open_time[start..].to_vec();
+    let (first, last) = (open_time[0], *open_time.last().unwrap());
+
+    let mut chart = Chart {
+        title: format!(
+            "{} – {} ({} candles)",
+            DateTime::from_timestamp_millis(first).unwrap_or_default(),
+            DateTime::from_timestamp_millis(last).unwrap_or_default(),
+            open_time.len()
+        ),
+        open: values(&df, "open")?,
+        high: values(&df, "high")?,
+        low: values(&df, "low")?,
+        close: values(&df, "close")?,
+        open_time,
+        lines: Vec::new(),
+        bollinger: None,
+        pivots: Vec::new(),
+        signals: Vec::new(),
+    };
+
+    let overlays = cli.value("overlays").unwrap_or(DEFAULT_OVERLAYS);
+    for overlay in overlays.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        match overlay {
+            "bollinger" => {
+                chart.bollinger = Some(bollinger(&close_all).map(|band| band[start..].to_vec()));
+            }
+            "pivots" => {
+                for (column, price, is_high) in [
+                    ("pivot_high_strength", &chart.high, true),
+                    ("pivot_low_strength", &chart.low, false),
+                ] {
+                    for (i, strength) in f64_column(&df, column)?.iter().enumerate() {
+                        if strength.is_some_and(|s| s >= pivot_strength as f64) {
+                            chart.pivots.push((i, price[i], is_high));
+                        }
+                    }
+                }
+            }
+            "signals" => {
+                let events = cli.value("events").unwrap_or(&paths.events);
+                match read_signals(events, first, last) {
+                    Ok(signals) => {
+                        for (open_time, price, is_long) in signals {
+                            if let Ok(i) = chart.open_time.binary_search(&open_time) {
+                                chart.signals.push((i, price, is_long));
+                            }
+                        }
+                    }
+                    // Only chosen explicitly is a missing event log an error
+                    Err(e) if cli.value("overlays").is_some() => return Err(e),
+                    Err(_) => {}
+                }
+            }
+            column => chart
+                .lines
+                .push((column.to_string(), f64_column(&df, column)?)),
+        }
+    }
+
+    if output.ends_with(".svg") {
+        draw(SVGBackend::new(output, size).into_drawing_area(), &chart)?;
+    } else {
+        draw(BitMapBackend::new(output, size).into_drawing_area(), &chart)?;
+    }
+    println!(
+        "Wrote {} ({} candles, {} pivots, {} signals)",
+        output,
+        chart.open_time.len(),
+        chart.pivots.len(),
+        chart.signals.len()
+    );
+    Ok(())
+}
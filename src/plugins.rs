@@ -0,0 +1,31 @@
+//! Runs the callbacks registered through [`binance_streamer::hooks`].
+//!
+//! Every call is its own task, so the pipeline never waits for a hook. An
+//! error or panic raises a `hooks` alert and counts in
+//! `hook_errors_total{point,hook}`; `hook_calls_total{point,hook}` counts
+//! every run.
+
+use crate::alerts::{self, Level};
+use crate::hooks::HookCall;
+use crate::metrics;
+
+/// Start `calls` without waiting for them.
+pub fn spawn(calls: Vec<HookCall>) {
+    for call in calls {
+        tokio::spawn(async move {
+            let labels = [("point", call.point), ("hook", call.hook.as_str())];
+            metrics::counter_inc("hook_calls_total", &labels, 1);
+            let error = match tokio::spawn(call.future).await {
+                Ok(Ok(())) => return,
+                Ok(Err(e)) => format!("{:#}", e),
+                Err(e) => format!("panicked: {}", e),
+            };
+            metrics::counter_inc("hook_errors_total", &labels, 1);
+            alerts::raise(
+                Level::Warning,
+                "hooks",
+                &format!("{} hook '{}' failed: {}", call.point, call.hook, error),
+            );
+        });
+    }
+}
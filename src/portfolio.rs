@@ -0,0 +1,160 @@
+//! Basket features of a multi-symbol run (`[portfolio]`).
+//!
+//! Listens to the feature rows every pipeline publishes on the update bus
+//! and feeds them, per interval, into a [`PortfolioFeatures`]. Every finished
+//! basket row rewrites `portfolio_features.parquet` (the path template with
+//! `portfolio` as the symbol; not in the signal-only profile), sets the `portfolio_*{interval}` gauges and
+//! runs the `portfolio_ready` hooks, where allocation strategies get the
+//! basket frame together with the newest feature row of every member.
+
+use crate::alerts::{self, Level};
+use crate::bus::{self, Update};
+use crate::config::{Config, Profile};
+use crate::features::portfolio::PortfolioRow;
+use crate::features::PortfolioFeatures;
+use crate::{data_storage, hooks, live_stream, metrics, plugins};
+use anyhow::Result;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use tokio::sync::broadcast;
+
+/// Symbol placeholder of the basket's output paths.
+const PORTFOLIO_SYMBOL: &str = "portfolio";
+
+/// Basket state of one interval.
+struct Basket {
+    features: PortfolioFeatures,
+    /// `None` in the signal-only profile.
+    path: Option<String>,
+    /// Newest feature row of every member.
+    members: BTreeMap<String, Map<String, Value>>,
+}
+
+/// Start aggregating the `stream_types` candle pipelines of `config.symbols`
+/// when there are several symbols and `[portfolio]` is enabled.
+pub fn spawn(config: &Config, stream_types: &[String]) -> Result<()> {
+    let symbols = config.symbols();
+    if !config.portfolio.enabled || symbols.len() < 2 {
+        return Ok(());
+    }
+    let basket_config = config.for_symbol(PORTFOLIO_SYMBOL);
+    let mut baskets = BTreeMap::new();
+    for stream_type in stream_types {
+        let interval = live_stream::stream_interval(stream_type);
+        if interval.is_empty() {
+            continue;
+        }
+        let path = (config.profile != Profile::SignalOnly).then(|| {
+            basket_config.resolve_path(stream_type, interval, "portfolio_features.parquet")
+        });
+        if let Some(dir) = path
+            .as_deref()
+            .and_then(|p| std::path::Path::new(p).parent())
+        {
+            std::fs::create_dir_all(dir)?;
+        }
+        println!(
+            "Aggregating {} {} symbols{}",
+            symbols.len(),
+            interval,
+            path.as_ref()
+                .map_or(String::new(), |path| format!(" into {}", path))
+        );
+        baskets.insert(
+            interval.to_string(),
+            Basket {
+                features: PortfolioFeatures::new(
+                    &symbols,
+                    config.portfolio.rsi_period,
+                    config.portfolio.rows,
+                ),
+                path,
+                members: BTreeMap::new(),
+            },
+        );
+    }
+
+    let mut updates = bus::subscribe();
+    tokio::spawn(async move {
+        loop {
+            let update = match updates.recv().await {
+                Ok(update) => update,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let Update::Features {
+                symbol,
+                interval,
+                open_time,
+                values,
+            } = update
+            else {
+                continue;
+            };
+            let Some(basket) = baskets.get_mut(&interval) else {
+                continue;
+            };
+            let Some(close) = values.get("close").and_then(Value::as_f64) else {
+                continue;
+            };
+            let ema200 = values.get("ema200_m15").and_then(Value::as_f64);
+            let finished = basket.features.push(&symbol, open_time, close, ema200);
+            basket.members.insert(symbol, values);
+            if !finished.is_empty() {
+                publish(&interval, basket, &finished).await;
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Save the basket frame, set the gauges to the newest of the `finished`
+/// rows and run the hooks for each.
+async fn publish(interval: &str, basket: &Basket, finished: &[PortfolioRow]) {
+    let row = finished[finished.len() - 1];
+    let labels = [("interval", interval)];
+    metrics::gauge_set("portfolio_symbols", &labels, row.symbols as f64);
+    for (name, value) in [
+        ("portfolio_breadth_ema200", row.breadth_ema200),
+        ("portfolio_avg_rsi", row.avg_rsi),
+        ("portfolio_return_dispersion", row.return_dispersion),
+    ] {
+        if let Some(value) = value {
+            metrics::gauge_set(name, &labels, value);
+        }
+    }
+
+    let frame = match basket.features.frame() {
+        Ok(frame) => frame,
+        Err(e) => {
+            eprintln!("Failed to build the {} basket frame: {:#}", interval, e);
+            return;
+        }
+    };
+    if let Some(path) = &basket.path {
+        let mut snapshot = frame.clone();
+        let target = path.clone();
+        let saved = tokio::task::spawn_blocking(move || {
+            data_storage::save_dataframe_parquet(&mut snapshot, &target)
+        })
+        .await
+        .map_err(binance_streamer::Error::from)
+        .and_then(|result| result);
+        if let Err(e) = saved {
+            alerts::raise(
+                Level::Warning,
+                "portfolio",
+                &format!("Failed to save {}: {}", path, e),
+            );
+        }
+    }
+
+    for &row in finished {
+        plugins::spawn(hooks::portfolio_ready(hooks::PortfolioReady {
+            interval: interval.to_string(),
+            row,
+            frame: frame.clone(),
+            members: basket.members.clone(),
+        }));
+    }
+}
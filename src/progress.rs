@@ -0,0 +1,180 @@
+//! Throttled progress of long REST candle fetches.
+//!
+//! A multi-year fetch runs for hours in batches of a few hundred candles;
+//! instead of one line per batch, [`FetchProgress`] prints at most one line
+//! per [`report_every`] period (candles, candles/s, the date reached and an
+//! ETA) and a summary at the end. The newest state of every fetch is kept in
+//! a registry ([`statuses`]) the collector exposes on `/metrics`.
+
+use crate::timezone;
+use std::collections::BTreeMap;
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+/// Progress of one fetch, as last reported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchStatus {
+    pub exchange: &'static str,
+    pub symbol: String,
+    pub interval: String,
+    /// Candles fetched so far.
+    pub rows: usize,
+    pub rows_per_sec: f64,
+    /// Close time (epoch ms) of the newest candle fetched so far.
+    pub reached: i64,
+    /// Share of the requested time range covered, 0 to 1.
+    pub fraction: f64,
+    /// Estimated seconds left; `None` before the first batch and once the
+    /// fetch has ended.
+    pub eta_secs: Option<f64>,
+    /// Whether the fetch is still running.
+    pub active: bool,
+}
+
+type Key = (&'static str, String, String);
+
+fn report_period() -> &'static RwLock<Duration> {
+    static PERIOD: OnceLock<RwLock<Duration>> = OnceLock::new();
+    PERIOD.get_or_init(|| RwLock::new(Duration::from_secs(10)))
+}
+
+fn registry() -> &'static RwLock<BTreeMap<Key, FetchStatus>> {
+    static REGISTRY: OnceLock<RwLock<BTreeMap<Key, FetchStatus>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Print fetch progress at most once per `period` from now on (10 s by
+/// default); zero prints every batch.
+pub fn set_report_every(period: Duration) {
+    *report_period().write().unwrap() = period;
+}
+
+/// The current progress report period.
+pub fn report_every() -> Duration {
+    *report_period().read().unwrap()
+}
+
+/// The newest state of every fetch since start-up, by exchange, symbol and
+/// interval.
+pub fn statuses() -> Vec<FetchStatus> {
+    registry().read().unwrap().values().cloned().collect()
+}
+
+/// Reporter of one paginated fetch between `start` and `end` (epoch ms).
+pub struct FetchProgress {
+    status: FetchStatus,
+    start: i64,
+    end: i64,
+    started: Instant,
+    last_report: Option<Instant>,
+}
+
+impl FetchProgress {
+    pub fn new(exchange: &'static str, symbol: &str, interval: &str, start: i64, end: i64) -> Self {
+        let progress = Self {
+            status: FetchStatus {
+                exchange,
+                symbol: symbol.to_string(),
+                interval: interval.to_string(),
+                rows: 0,
+                rows_per_sec: 0.0,
+                reached: start,
+                fraction: 0.0,
+                eta_secs: None,
+                active: true,
+            },
+            start,
+            end,
+            started: Instant::now(),
+            last_report: None,
+        };
+        progress.publish();
+        progress
+    }
+
+    fn publish(&self) {
+        let status = &self.status;
+        registry().write().unwrap().insert(
+            (
+                status.exchange,
+                status.symbol.clone(),
+                status.interval.clone(),
+            ),
+            status.clone(),
+        );
+    }
+
+    /// Count a batch of `rows` candles, the newest closing at `reached`.
+    pub fn advance(&mut self, rows: usize, reached: i64) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let status = &mut self.status;
+        status.rows += rows;
+        status.reached = status.reached.max(reached);
+        status.rows_per_sec = if elapsed > 0.0 {
+            status.rows as f64 / elapsed
+        } else {
+            0.0
+        };
+        status.fraction = if self.end > self.start {
+            ((status.reached - self.start) as f64 / (self.end - self.start) as f64).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        status.eta_secs =
+            (status.fraction > 0.0).then(|| elapsed * (1.0 - status.fraction) / status.fraction);
+        self.publish();
+
+        let due = self
+            .last_report
+            .is_none_or(|last| last.elapsed() >= report_every());
+        if due {
+            self.last_report = Some(Instant::now());
+            let status = &self.status;
+            println!(
+                "[fetch] {} {} {}: {} candles ({:.0}/s), reached {} ({:.1}%), ETA {}",
+                status.exchange,
+                status.symbol,
+                status.interval,
+                status.rows,
+                status.rows_per_sec,
+                timezone::format_ms(status.reached),
+                status.fraction * 100.0,
+                status.eta_secs.map_or("-".to_string(), format_secs)
+            );
+        }
+    }
+
+    /// Mark the fetch done and print its summary.
+    pub fn finish(mut self) {
+        self.status.fraction = 1.0;
+        let status = &self.status;
+        println!(
+            "[fetch] {} {} {}: fetched {} candles in {} ({:.0}/s)",
+            status.exchange,
+            status.symbol,
+            status.interval,
+            status.rows,
+            format_secs(self.started.elapsed().as_secs_f64()),
+            status.rows_per_sec
+        );
+    }
+}
+
+/// A fetch that ended, finished or failed, is no longer active.
+impl Drop for FetchProgress {
+    fn drop(&mut self) {
+        self.status.active = false;
+        self.status.eta_secs = None;
+        self.publish();
+    }
+}
+
+/// `95` → "1m35s", `4000` → "1h06m".
+fn format_secs(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
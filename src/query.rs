@@ -0,0 +1,211 @@
+//! Lazy queries over the stored datasets.
+//!
+//! Every function here returns a polars [`LazyFrame`] instead of a
+//! materialized frame, so callers can chain their own filters, projections
+//! and aggregations and have polars push them down into the Parquet scan:
+//!
+//! ```no_run
+//! # fn demo() -> anyhow::Result<()> {
+//! use binance_streamer::query::{load_features_lazy, DataLayout, TimeRange};
+//! use polars::prelude::*;
+//!
+//! let strong_highs = load_features_lazy(
+//!     &DataLayout::default(),
+//!     "BTCUSDT",
+//!     "15m",
+//!     TimeRange::since(1_735_689_600_000),
+//! )?
+//! .filter(col("pivot_high_strength").gt_eq(lit(10)))
+//! .select([col("open_time"), col("close"), col("ema200_h1")])
+//! .collect()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Paths follow the same `path_template` as the collector, described by a
+//! [`DataLayout`].
+
+use crate::error::Result;
+use polars::prelude::*;
+
+/// Default layout keeps the historical `data/m15_features.csv` style names.
+pub const DEFAULT_PATH_TEMPLATE: &str = "{data_dir}/{stream}_{file}";
+
+/// Where the collector writes its files: the `data_dir` and `path_template`
+/// of its configuration.
+#[derive(Debug, Clone)]
+pub struct DataLayout {
+    pub data_dir: String,
+    /// Placeholders: `{data_dir}`, `{symbol}`, `{interval}`, `{stream}` and `{file}`.
+    pub path_template: String,
+}
+
+impl Default for DataLayout {
+    fn default() -> Self {
+        Self {
+            data_dir: "data".to_string(),
+            path_template: DEFAULT_PATH_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl DataLayout {
+    /// Resolve a file name against the path template.
+    pub fn resolve(&self, symbol: &str, stream: &str, interval: &str, file: &str) -> String {
+        self.path_template
+            .replace("{data_dir}", &self.data_dir)
+            .replace("{symbol}", symbol)
+            .replace("{interval}", interval)
+            .replace("{stream}", stream)
+            .replace("{file}", file)
+    }
+
+    /// Feature Parquet snapshot of `symbol` on `interval` (e.g. "15m").
+    pub fn features_path(&self, symbol: &str, interval: &str) -> String {
+        self.resolve(
+            &symbol.to_uppercase(),
+            &stream_name(interval),
+            interval,
+            "features.parquet",
+        )
+    }
+
+    /// Directory of the append-only feature-history dataset (one row per
+    /// live candle, as computed at the time).
+    pub fn feature_history_dir(&self, symbol: &str, interval: &str) -> String {
+        self.resolve(
+            &symbol.to_uppercase(),
+            &stream_name(interval),
+            interval,
+            "feature_history",
+        )
+    }
+
+    /// Raw candle Parquet cache holding a window of `count` candles.
+    pub fn candles_path(&self, symbol: &str, interval: &str, count: usize) -> String {
+        self.resolve(
+            &symbol.to_uppercase(),
+            &stream_name(interval),
+            interval,
+            &format!("latest_{}.parquet", count),
+        )
+    }
+}
+
+/// Stream name of a Binance interval as used in file names ("15m" → "m15").
+pub fn stream_name(interval: &str) -> String {
+    let unit_at = interval
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(interval.len());
+    let (count, unit) = interval.split_at(unit_at);
+    format!("{}{}", unit, count)
+}
+
+/// Inclusive bounds on `open_time` (epoch ms); `None` leaves a side open.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeRange {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+impl TimeRange {
+    /// Every row.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    pub fn between(from: i64, to: i64) -> Self {
+        Self {
+            from: Some(from),
+            to: Some(to),
+        }
+    }
+
+    pub fn since(from: i64) -> Self {
+        Self {
+            from: Some(from),
+            to: None,
+        }
+    }
+
+    pub fn until(to: i64) -> Self {
+        Self {
+            from: None,
+            to: Some(to),
+        }
+    }
+}
+
+/// Lazily scan a Parquet file with an `open_time` column, restricted to `range`.
+pub fn scan_range(path: &str, range: TimeRange) -> Result<LazyFrame> {
+    let mut lf = LazyFrame::scan_parquet(path.into(), Default::default())?;
+    if let Some(from) = range.from {
+        lf = lf.filter(col("open_time").gt_eq(lit(from)));
+    }
+    if let Some(to) = range.to {
+        lf = lf.filter(col("open_time").lt_eq(lit(to)));
+    }
+    Ok(lf)
+}
+
+/// Stored feature rows of `symbol` on `interval` within `range`.
+pub fn load_features_lazy(
+    layout: &DataLayout,
+    symbol: &str,
+    interval: &str,
+    range: TimeRange,
+) -> Result<LazyFrame> {
+    scan_range(&layout.features_path(symbol, interval), range)
+}
+
+/// Point-in-time feature rows of `symbol` on `interval` within `range`: every
+/// row exactly as the live pipeline computed it when its candle closed. The
+/// `date` partition column of the dataset is included.
+pub fn load_feature_history_lazy(
+    layout: &DataLayout,
+    symbol: &str,
+    interval: &str,
+    range: TimeRange,
+) -> Result<LazyFrame> {
+    scan_feature_history(&layout.feature_history_dir(symbol, interval), range)
+}
+
+/// Lazily scan the feature-history dataset in `dir`, restricted to `range`.
+pub fn scan_feature_history(dir: &str, range: TimeRange) -> Result<LazyFrame> {
+    scan_range(&format!("{}/date=*/*.parquet", dir), range)
+}
+
+/// Lazily scan the feature-history datasets in `dirs` as one (a dataset and
+/// the cache of its cold partitions, say), restricted to `range`. Directories
+/// without a partition are skipped.
+pub fn scan_feature_history_dirs(dirs: &[&str], range: TimeRange) -> Result<LazyFrame> {
+    let mut frames = Vec::new();
+    for dir in dirs {
+        let partitioned = std::fs::read_dir(dir).is_ok_and(|entries| {
+            entries
+                .flatten()
+                .any(|entry| entry.file_name().to_string_lossy().starts_with("date="))
+        });
+        if partitioned {
+            frames.push(scan_feature_history(dir, range)?);
+        }
+    }
+    match frames.len() {
+        // Fails as a scan of the first directory does
+        0 => scan_feature_history(dirs.first().copied().unwrap_or_default(), range),
+        1 => Ok(frames.remove(0)),
+        _ => Ok(concat(frames, UnionArgs::default())?),
+    }
+}
+
+/// Stored raw candles of `symbol` on `interval` within `range`, from the
+/// cache of the newest `count` candles.
+pub fn load_candles_lazy(
+    layout: &DataLayout,
+    symbol: &str,
+    interval: &str,
+    count: usize,
+    range: TimeRange,
+) -> Result<LazyFrame> {
+    scan_range(&layout.candles_path(symbol, interval, count), range)
+}
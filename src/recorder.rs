@@ -0,0 +1,101 @@
+//! Session recorder (`--record`): appends every raw WebSocket frame with its
+//! receive time to a zstd-compressed JSON-lines file, read back by
+//! `exchange::recorded` for `replay --session`.
+//!
+//! Frames are handed to a writer thread, so the stream never waits on
+//! compression or disk. The writer closes its zstd frame every
+//! [`FRAME_SECS`]; a killed process loses at most that much of the tail.
+//! Every run appends new zstd frames, which a reader decodes as one stream.
+
+use crate::exchange::recorded::RecordedFrame;
+use crate::exchange::Exchange;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Seconds of frames per zstd frame.
+const FRAME_SECS: u64 = 10;
+
+/// zstd compression level of recordings.
+const LEVEL: i32 = 3;
+
+/// Handle to a recording; clones write to the same file.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    frames: mpsc::Sender<RecordedFrame>,
+}
+
+impl Recorder {
+    /// Start recording to `path` (appended to if it exists).
+    pub fn spawn(path: &str) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open recording {}", path))?;
+        let (frames, received) = mpsc::channel();
+        let target = path.to_string();
+        std::thread::Builder::new()
+            .name("recorder".to_string())
+            .spawn(move || {
+                if let Err(e) = write_frames(file, received) {
+                    eprintln!("Recording to {} stopped: {}", target, e);
+                }
+            })?;
+        println!("Recording raw frames to {}", path);
+        Ok(Self { frames })
+    }
+
+    /// Record a text frame received on `stream` just now.
+    pub fn frame(&self, exchange: Exchange, stream: &str, text: &str) {
+        self.send(exchange, stream, false, text);
+    }
+
+    /// Record that `stream` (re)connected.
+    pub fn connected(&self, exchange: Exchange, stream: &str) {
+        self.send(exchange, stream, true, "");
+    }
+
+    fn send(&self, exchange: Exchange, stream: &str, connect: bool, text: &str) {
+        // A failed writer has already reported why
+        let _ = self.frames.send(RecordedFrame {
+            t: Utc::now().timestamp_millis(),
+            exchange,
+            stream: stream.to_string(),
+            connect,
+            text: text.to_string(),
+        });
+    }
+}
+
+/// Compress frames into `file` until every [`Recorder`] is dropped.
+fn write_frames(file: File, frames: mpsc::Receiver<RecordedFrame>) -> Result<()> {
+    let mut encoder = zstd::stream::write::Encoder::new(file, LEVEL)?;
+    let mut pending = false;
+    let mut frame_started = Instant::now();
+    loop {
+        match frames.recv_timeout(Duration::from_secs(1)) {
+            Ok(frame) => {
+                serde_json::to_writer(&mut encoder, &frame)?;
+                encoder.write_all(b"\n")?;
+                if !pending {
+                    pending = true;
+                    frame_started = Instant::now();
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        if pending && frame_started.elapsed() >= Duration::from_secs(FRAME_SECS) {
+            let mut file = encoder.finish()?;
+            file.flush()?;
+            encoder = zstd::stream::write::Encoder::new(file, LEVEL)?;
+            pending = false;
+        }
+    }
+    encoder.finish()?.flush()?;
+    Ok(())
+}
@@ -0,0 +1,118 @@
+//! Optional Redis sink.
+//!
+//! For every feature row on the update bus the sink PUBLISHes the JSON row
+//! (same shape as `GET /latest/{symbol}`) on `{prefix}:features:{SYMBOL}` and
+//! SETs it under `{prefix}:latest:{SYMBOL}`, in one pipelined round trip.
+//! Signals are published on `{prefix}:signals:{SYMBOL}`. The connection
+//! manager reconnects on its own; while Redis is unreachable updates are
+//! dropped and one alert is raised per outage.
+
+use crate::alerts::{self, Level};
+use crate::bus::{self, Update};
+use crate::config::RedisConfig;
+use crate::{latest, metrics};
+use anyhow::{Context, Result};
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Commands for one bus update, or `None` if the sink ignores it.
+fn commands(prefix: &str, update: Update) -> Option<redis::Pipeline> {
+    let mut pipe = redis::pipe();
+    match update {
+        Update::Features {
+            symbol,
+            interval,
+            values,
+            ..
+        } => {
+            let symbol = symbol.to_uppercase();
+            let json = latest::feature_message(&symbol, &interval, &values).to_string();
+            pipe.cmd("PUBLISH")
+                .arg(format!("{}:features:{}", prefix, symbol))
+                .arg(&json)
+                .ignore()
+                .cmd("SET")
+                .arg(format!("{}:latest:{}", prefix, symbol))
+                .arg(&json)
+                .ignore();
+        }
+        Update::Signal(signal) => {
+            let json = serde_json::to_string(&signal).ok()?;
+            pipe.cmd("PUBLISH")
+                .arg(format!(
+                    "{}:signals:{}",
+                    prefix,
+                    signal.symbol.to_uppercase()
+                ))
+                .arg(json)
+                .ignore();
+        }
+        Update::Candle { .. } => return None,
+    }
+    Some(pipe)
+}
+
+/// Connect to Redis and forward bus updates in a background task.
+/// An unreachable server at startup is an error; later outages are retried.
+pub async fn spawn(config: &RedisConfig) -> Result<()> {
+    let Some(url) = &config.url else {
+        return Ok(());
+    };
+    let client =
+        redis::Client::open(url.as_str()).with_context(|| format!("Invalid Redis URL {}", url))?;
+    // Bounded timeouts so a stalled server cannot hold up the sink indefinitely
+    let options = ConnectionManagerConfig::new()
+        .set_number_of_retries(3)
+        .set_factor(2)
+        .set_max_delay(5_000)
+        .set_connection_timeout(Duration::from_secs(5))
+        .set_response_timeout(Duration::from_secs(5));
+    let mut conn = ConnectionManager::new_with_config(client, options)
+        .await
+        .with_context(|| format!("Failed to connect to Redis at {}", url))?;
+    println!(
+        "Publishing feature rows to Redis ({}:features:<SYMBOL>)",
+        config.prefix
+    );
+
+    let prefix = config.prefix.clone();
+    let mut updates = bus::subscribe();
+    tokio::spawn(async move {
+        let mut failing = false;
+        loop {
+            let update = match updates.recv().await {
+                Ok(update) => update,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    metrics::counter_inc("redis_skipped_updates_total", &[], skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let Some(pipe) = commands(&prefix, update) else {
+                continue;
+            };
+            match pipe.query_async::<()>(&mut conn).await {
+                Ok(()) => {
+                    metrics::counter_inc("redis_published_total", &[], 1);
+                    if failing {
+                        failing = false;
+                        println!("Redis sink recovered");
+                    }
+                }
+                Err(e) => {
+                    metrics::counter_inc("redis_publish_errors_total", &[], 1);
+                    if !failing {
+                        failing = true;
+                        alerts::raise(
+                            Level::Warning,
+                            "redis",
+                            &format!("publish failed, dropping updates until it recovers: {}", e),
+                        );
+                    }
+                }
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,618 @@
+//! Daily and weekly summary reports.
+//!
+//! While reports are enabled, every candle, feature row and signal on the
+//! update bus and every alert is tallied per period. When a period ends
+//! (midnight, or Monday 00:00, in the display timezone) its summary is
+//! written to `{dir}/{daily|weekly}-{date}.md` (or `.html`): candles
+//! collected and candles missing per stream, min/mean/max of every feature,
+//! signals per strategy and side, the paper return of following each
+//! strategy's signals (split by market regime with `[regime]` enabled), and
+//! the most frequent alerts. Webhooks with
+//! `on = "report"` receive the Markdown text. The `[scheduler.report]` job
+//! writes the running summaries to the same files before their period ends,
+//! marked "so far".
+//!
+//! The paper return assumes one unit long or short from each signal's price
+//! until the next signal, marked to every close, without fees. There is no
+//! live execution, so there is no live PnL to report.
+
+use crate::alerts::Level;
+use crate::bus::{self, Update};
+use crate::clock;
+use crate::config::{Config, ReportConfig, ReportFormat, WebhookConfig, WebhookEvent};
+use crate::timezone;
+use crate::webhooks;
+use anyhow::Result;
+use binance_streamer::exchange;
+use binance_streamer::strategy::Side;
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::sync::{Mutex, OnceLock};
+use tokio::sync::broadcast;
+
+/// Columns of a feature row that are not features.
+const NOT_FEATURES: [&str; 4] = ["index", "open_time", "close_time", "datetime"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Period {
+    Daily,
+    Weekly,
+}
+
+impl Period {
+    fn as_str(self) -> &'static str {
+        match self {
+            Period::Daily => "daily",
+            Period::Weekly => "weekly",
+        }
+    }
+
+    /// Start (epoch ms) of the first period after the one containing `ms`.
+    fn next_start(self, ms: i64) -> i64 {
+        let tz = timezone::display();
+        let local = DateTime::<Utc>::from_timestamp_millis(ms)
+            .unwrap_or_default()
+            .with_timezone(&tz)
+            .date_naive();
+        let days = match self {
+            Period::Daily => 1,
+            Period::Weekly => 7 - local.weekday().num_days_from_monday() as i64,
+        };
+        let start = (local + Duration::days(days)).and_time(NaiveTime::MIN);
+        // A midnight skipped by a DST change starts the day an hour later
+        tz.from_local_datetime(&start)
+            .earliest()
+            .or_else(|| {
+                tz.from_local_datetime(&(start + Duration::hours(1)))
+                    .earliest()
+            })
+            .map_or(ms + 86_400_000, |t| t.timestamp_millis())
+    }
+}
+
+/// min/mean/max of one feature column.
+#[derive(Debug, Clone, Copy)]
+struct Stat {
+    count: u64,
+    nulls: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for Stat {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            nulls: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct StreamSummary {
+    candles: u64,
+    /// Candles missing between consecutive received ones.
+    missing: u64,
+    features: BTreeMap<String, Stat>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct AlertSummary {
+    count: u64,
+    last_message: String,
+}
+
+/// What happened during one period.
+#[derive(Debug, Clone)]
+struct Summary {
+    period: Period,
+    start_ms: i64,
+    end_ms: i64,
+    /// By (symbol, interval).
+    streams: BTreeMap<(String, String), StreamSummary>,
+    /// By (strategy, side).
+    signals: BTreeMap<(String, String), u64>,
+    /// Paper return in percent, by (symbol, interval, strategy).
+    paper: BTreeMap<(String, String, String), f64>,
+    /// Paper return in percent, by (symbol, interval, strategy, regime).
+    paper_by_regime: BTreeMap<(String, String, String, String), f64>,
+    /// By (level, source).
+    alerts: BTreeMap<(String, String), AlertSummary>,
+}
+
+impl Summary {
+    fn new(period: Period, start_ms: i64) -> Self {
+        Self {
+            period,
+            start_ms,
+            end_ms: period.next_start(start_ms),
+            streams: BTreeMap::new(),
+            signals: BTreeMap::new(),
+            paper: BTreeMap::new(),
+            paper_by_regime: BTreeMap::new(),
+            alerts: BTreeMap::new(),
+        }
+    }
+}
+
+/// A strategy's paper position on one stream.
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    /// 1 long, -1 short.
+    direction: f64,
+    last_price: f64,
+}
+
+#[derive(Default)]
+struct ReportState {
+    summaries: Vec<Summary>,
+    /// Newest candle open time by (symbol, interval), across periods.
+    last_open_time: HashMap<(String, String), i64>,
+    /// Open paper positions by (symbol, interval, strategy), across periods.
+    positions: HashMap<(String, String, String), Position>,
+    /// `regime` of the newest feature row by (symbol, interval).
+    regimes: HashMap<(String, String), String>,
+}
+
+fn state() -> &'static Mutex<ReportState> {
+    static STATE: OnceLock<Mutex<ReportState>> = OnceLock::new();
+    STATE.get_or_init(|| Mutex::new(ReportState::default()))
+}
+
+impl ReportState {
+    fn candle(&mut self, symbol: &str, interval: &str, open_time: i64, close: f64) {
+        let key = (symbol.to_string(), interval.to_string());
+        let missing = match (
+            self.last_open_time.insert(key.clone(), open_time),
+            exchange::interval_ms(interval),
+        ) {
+            (Some(last), Ok(ms)) if open_time > last + ms => ((open_time - last) / ms - 1) as u64,
+            _ => 0,
+        };
+        let mut returns = Vec::new();
+        for ((s, i, strategy), position) in self.positions.iter_mut() {
+            if s == symbol && i == interval {
+                let change = position.direction * (close / position.last_price - 1.0);
+                position.last_price = close;
+                returns.push((strategy.clone(), change * 100.0));
+            }
+        }
+        // The candle's move happened in the regime of the row before it
+        let regime = self.regimes.get(&key);
+        for summary in &mut self.summaries {
+            let stream = summary.streams.entry(key.clone()).or_default();
+            stream.candles += 1;
+            stream.missing += missing;
+            for (strategy, change) in &returns {
+                *summary
+                    .paper
+                    .entry((key.0.clone(), key.1.clone(), strategy.clone()))
+                    .or_default() += change;
+                if let Some(regime) = regime {
+                    *summary
+                        .paper_by_regime
+                        .entry((
+                            key.0.clone(),
+                            key.1.clone(),
+                            strategy.clone(),
+                            regime.clone(),
+                        ))
+                        .or_default() += change;
+                }
+            }
+        }
+    }
+
+    fn features(&mut self, symbol: &str, interval: &str, values: &Map<String, Value>) {
+        let key = (symbol.to_string(), interval.to_string());
+        if let Some(regime) = values.get("regime").and_then(Value::as_str) {
+            self.regimes.insert(key.clone(), regime.to_string());
+        }
+        for summary in &mut self.summaries {
+            let stream = summary.streams.entry(key.clone()).or_default();
+            for (name, value) in values {
+                if NOT_FEATURES.contains(&name.as_str()) {
+                    continue;
+                }
+                let stat = stream.features.entry(name.clone()).or_default();
+                match value.as_f64() {
+                    Some(v) => {
+                        stat.count += 1;
+                        stat.sum += v;
+                        stat.min = stat.min.min(v);
+                        stat.max = stat.max.max(v);
+                    }
+                    None if value.is_null() => stat.nulls += 1,
+                    None => {}
+                }
+            }
+        }
+    }
+
+    fn signal(&mut self, symbol: &str, interval: &str, strategy: &str, side: Side, price: f64) {
+        let key = (
+            symbol.to_string(),
+            interval.to_string(),
+            strategy.to_string(),
+        );
+        let direction = match side {
+            Side::Long => 1.0,
+            Side::Short => -1.0,
+            Side::Flat => 0.0,
+        };
+        if direction == 0.0 {
+            self.positions.remove(&key);
+        } else {
+            self.positions.insert(
+                key.clone(),
+                Position {
+                    direction,
+                    last_price: price,
+                },
+            );
+        }
+        for summary in &mut self.summaries {
+            *summary
+                .signals
+                .entry((strategy.to_string(), side.as_str().to_string()))
+                .or_default() += 1;
+            // Listed with a zero return until the first candle after it
+            summary.paper.entry(key.clone()).or_default();
+        }
+    }
+
+    fn alert(&mut self, level: Level, source: &str, message: &str) {
+        for summary in &mut self.summaries {
+            let alert = summary
+                .alerts
+                .entry((level.as_str().to_string(), source.to_string()))
+                .or_default();
+            alert.count += 1;
+            alert.last_message = message.to_string();
+        }
+    }
+
+    /// Take the summaries of the periods that ended by `now_ms`, starting
+    /// the next ones.
+    fn finish(&mut self, now_ms: i64) -> Vec<Summary> {
+        let mut finished = Vec::new();
+        for summary in &mut self.summaries {
+            while summary.end_ms <= now_ms {
+                let next = Summary::new(summary.period, summary.end_ms);
+                finished.push(std::mem::replace(summary, next));
+            }
+        }
+        finished
+    }
+}
+
+/// Count an alert in the running reports (no-op while reports are off).
+pub fn record_alert(level: Level, source: &str, message: &str) {
+    // try_lock: alerts may be raised while the report is being written
+    if let Ok(mut state) = state().try_lock() {
+        state.alert(level, source, message);
+    }
+}
+
+/// A titled table of a report.
+pub struct Table {
+    pub title: &'static str,
+    pub headers: Vec<&'static str>,
+    pub rows: Vec<Vec<String>>,
+}
+
+fn tables(summary: &Summary, top_alerts: usize) -> Vec<Table> {
+    let mut streams = Table {
+        title: "Candles",
+        headers: vec!["Symbol", "Interval", "Collected", "Missing"],
+        rows: Vec::new(),
+    };
+    let mut features = Table {
+        title: "Features",
+        headers: vec![
+            "Symbol", "Interval", "Feature", "Min", "Mean", "Max", "Nulls",
+        ],
+        rows: Vec::new(),
+    };
+    for ((symbol, interval), stream) in &summary.streams {
+        streams.rows.push(vec![
+            symbol.clone(),
+            interval.clone(),
+            stream.candles.to_string(),
+            stream.missing.to_string(),
+        ]);
+        for (name, stat) in &stream.features {
+            let number = |v: f64| {
+                if stat.count > 0 {
+                    format!("{:.6}", v)
+                } else {
+                    "-".to_string()
+                }
+            };
+            features.rows.push(vec![
+                symbol.clone(),
+                interval.clone(),
+                name.clone(),
+                number(stat.min),
+                number(stat.sum / stat.count.max(1) as f64),
+                number(stat.max),
+                stat.nulls.to_string(),
+            ]);
+        }
+    }
+    let signals = Table {
+        title: "Signals",
+        headers: vec!["Strategy", "Side", "Count"],
+        rows: summary
+            .signals
+            .iter()
+            .map(|((strategy, side), count)| {
+                vec![strategy.clone(), side.clone(), count.to_string()]
+            })
+            .collect(),
+    };
+    let paper = Table {
+        title: "Paper return",
+        headers: vec!["Symbol", "Interval", "Strategy", "Return %"],
+        rows: summary
+            .paper
+            .iter()
+            .map(|((symbol, interval, strategy), pct)| {
+                vec![
+                    symbol.clone(),
+                    interval.clone(),
+                    strategy.clone(),
+                    format!("{:+.3}", pct),
+                ]
+            })
+            .collect(),
+    };
+    let mut by_count: Vec<_> = summary.alerts.iter().collect();
+    by_count.sort_by_key(|(_, alert)| std::cmp::Reverse(alert.count));
+    let alerts = Table {
+        title: "Top alerts",
+        headers: vec!["Level", "Source", "Count", "Last message"],
+        rows: by_count
+            .into_iter()
+            .take(top_alerts)
+            .map(|((level, source), alert)| {
+                vec![
+                    level.clone(),
+                    source.clone(),
+                    alert.count.to_string(),
+                    alert.last_message.clone(),
+                ]
+            })
+            .collect(),
+    };
+    let mut tables = vec![streams, features, signals, paper];
+    if !summary.paper_by_regime.is_empty() {
+        tables.push(Table {
+            title: "Paper return by regime",
+            headers: vec!["Symbol", "Interval", "Strategy", "Regime", "Return %"],
+            rows: summary
+                .paper_by_regime
+                .iter()
+                .map(|((symbol, interval, strategy, regime), pct)| {
+                    vec![
+                        symbol.clone(),
+                        interval.clone(),
+                        strategy.clone(),
+                        regime.clone(),
+                        format!("{:+.3}", pct),
+                    ]
+                })
+                .collect(),
+        });
+    }
+    tables.push(alerts);
+    tables
+}
+
+fn title(summary: &Summary, now_ms: i64) -> String {
+    let mut title = format!(
+        "{} summary, {} to {}",
+        match summary.period {
+            Period::Daily => "Daily",
+            Period::Weekly => "Weekly",
+        },
+        timezone::format_ms_secs(summary.start_ms),
+        timezone::format_ms_secs(summary.end_ms)
+    );
+    if now_ms < summary.end_ms {
+        let _ = write!(
+            title,
+            " (so far, as of {})",
+            timezone::format_ms_secs(now_ms)
+        );
+    }
+    title
+}
+
+/// `tables` as a Markdown document titled `title`.
+pub fn render_markdown(title: &str, tables: &[Table]) -> String {
+    let cell = |text: &str| text.replace('|', "\\|").replace('\n', " ");
+    let mut out = format!("# {}\n", title);
+    for table in tables {
+        let _ = write!(out, "\n## {}\n\n", table.title);
+        if table.rows.is_empty() {
+            out.push_str("None.\n");
+            continue;
+        }
+        let _ = writeln!(out, "| {} |", table.headers.join(" | "));
+        let _ = writeln!(out, "|{}", "---|".repeat(table.headers.len()));
+        for row in &table.rows {
+            let cells: Vec<String> = row.iter().map(|c| cell(c)).collect();
+            let _ = writeln!(out, "| {} |", cells.join(" | "));
+        }
+    }
+    out
+}
+
+/// `tables` as an HTML page titled `title`.
+pub fn render_html(title: &str, tables: &[Table]) -> String {
+    let escape = |text: &str| {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+    let title = escape(title);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title></head><body>\n<h1>{0}</h1>\n",
+        title
+    );
+    for table in tables {
+        let _ = writeln!(out, "<h2>{}</h2>", table.title);
+        if table.rows.is_empty() {
+            out.push_str("<p>None.</p>\n");
+            continue;
+        }
+        out.push_str("<table border=\"1\">\n<tr>");
+        for header in &table.headers {
+            let _ = write!(out, "<th>{}</th>", header);
+        }
+        out.push_str("</tr>\n");
+        for row in &table.rows {
+            out.push_str("<tr>");
+            for cell in row {
+                let _ = write!(out, "<td>{}</td>", escape(cell));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body></html>\n");
+    out
+}
+
+/// Write `summary` to `dir` and push it to the report webhooks.
+async fn publish(summary: Summary, config: &ReportConfig, dir: &str, hooks: &[WebhookConfig]) {
+    let tables = tables(&summary, config.top_alerts);
+    let title = title(&summary, clock::now_ms());
+    let markdown = render_markdown(&title, &tables);
+    let (text, extension) = match config.format {
+        ReportFormat::Markdown => (markdown.clone(), "md"),
+        ReportFormat::Html => (render_html(&title, &tables), "html"),
+    };
+    let date = DateTime::<Utc>::from_timestamp_millis(summary.start_ms)
+        .unwrap_or_default()
+        .with_timezone(&timezone::display())
+        .format("%Y-%m-%d");
+    let path = format!("{}/{}-{}.{}", dir, summary.period.as_str(), date, extension);
+    let written = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&path, text));
+    match written {
+        Ok(()) => println!(
+            "[report] {} summary written to {}",
+            summary.period.as_str(),
+            path
+        ),
+        Err(e) => eprintln!("[report] writing {} failed: {}", path, e),
+    }
+    let fields = json!({
+        "event": "report",
+        "period": summary.period.as_str(),
+        "from": summary.start_ms,
+        "to": summary.end_ms,
+        "path": path,
+        "text": markdown,
+    });
+    if let Value::Object(fields) = fields {
+        webhooks::push(hooks, WebhookEvent::Report, fields).await;
+    }
+}
+
+/// Directory of the report files.
+fn report_dir(config: &Config) -> String {
+    config
+        .report
+        .dir
+        .clone()
+        .unwrap_or_else(|| format!("{}/reports", config.data_dir))
+}
+
+/// Write the running summaries as they stand, to the files their period's
+/// end overwrites (the `[scheduler.report]` job). Webhooks only receive the
+/// finished summaries.
+pub async fn write_running(config: &Config) {
+    let running = state().lock().unwrap().summaries.clone();
+    let dir = report_dir(config);
+    for summary in running {
+        publish(summary, &config.report, &dir, &[]).await;
+    }
+}
+
+/// Start collecting for the enabled `[report]` periods and write each
+/// summary when its period ends.
+pub fn spawn(config: &Config) -> Result<()> {
+    let report = config.report.clone();
+    let mut periods = Vec::new();
+    if report.daily {
+        periods.push(Period::Daily);
+    }
+    if report.weekly {
+        periods.push(Period::Weekly);
+    }
+    if periods.is_empty() {
+        return Ok(());
+    }
+    let dir = report_dir(config);
+    std::fs::create_dir_all(&dir)?;
+    let now = clock::now_ms();
+    state().lock().unwrap().summaries = periods.iter().map(|p| Summary::new(*p, now)).collect();
+    let hooks: Vec<WebhookConfig> = config
+        .webhooks
+        .iter()
+        .filter(|h| h.on == WebhookEvent::Report)
+        .cloned()
+        .collect();
+    println!(
+        "Writing {} summary reports to {}",
+        periods
+            .iter()
+            .map(|p| p.as_str())
+            .collect::<Vec<_>>()
+            .join(" and "),
+        dir
+    );
+
+    let mut updates = bus::subscribe();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(30));
+        loop {
+            tokio::select! {
+                update = updates.recv() => {
+                    let update = match update {
+                        Ok(update) => update,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+                    let mut state = state().lock().unwrap();
+                    match update {
+                        Update::Candle { symbol, interval, kline } => {
+                            state.candle(&symbol, &interval, kline.open_time, kline.close)
+                        }
+                        Update::Features { symbol, interval, values, .. } => {
+                            state.features(&symbol, &interval, &values)
+                        }
+                        Update::Signal(s) => {
+                            state.signal(&s.symbol, &s.interval, &s.strategy, s.side, s.price)
+                        }
+                    }
+                }
+                _ = ticker.tick() => {}
+            }
+            // The pinned clock of a deterministic run follows the candles
+            let finished = state().lock().unwrap().finish(clock::now_ms());
+            for summary in finished {
+                publish(summary, &report, &dir, &hooks).await;
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,370 @@
+//! `make-sample` subcommand: a small candle dataset that can be shared.
+//!
+//! Bug reports, tests and benchmarks need candles, but not the multi-GB
+//! private archive they were collected into. From `--input` (a raw candle
+//! Parquet file, raw CSV log or journal) this writes `--rows` candles
+//! (default 2,000) to `--output` – CSV if it ends in `.csv`, Parquet
+//! otherwise:
+//!
+//! - by default the consecutive candles from `--from`, or the newest ones;
+//! - with `--synthetic` a new path resampled from the input in blocks of
+//!   `--block` candles (default 96): every candle's log return, gap to the
+//!   previous close, wicks and volume are drawn together, so the return,
+//!   range and volume distributions and the volatility clustering survive
+//!   while no price path of the archive does. The draw is reproducible from
+//!   `--seed`.
+//!
+//! Without `--input` the `bench` random walk stands in for the archive.
+//! `--start` moves the sample in time (its first open time, on the interval
+//! grid), `--price` rescales its prices to that first open and `--volume` its
+//! volumes to that median, so neither the period nor the market shows. A
+//! table compares the statistics of the input and of the sample.
+//!
+//! ```text
+//! cargo run --release make-sample --output sample.parquet
+//!     [--input data/m15_latest_50000.parquet] [--rows 2000] [--from ...]
+//!     [--synthetic [--block 96]] [--seed 42]
+//!     [--start 2020-01-01T00:00:00Z] [--price 100] [--volume 1000]
+//! ```
+
+use crate::backfill::CandleReader;
+use crate::bench;
+use crate::config::CliArgs;
+use crate::determinism::SeededRng;
+use crate::report::{self, Table};
+use crate::{timezone, utils};
+use anyhow::{bail, Context, Result};
+use binance_streamer::data_storage;
+use binance_streamer::kline::Kline;
+use std::collections::VecDeque;
+
+const DEFAULT_ROWS: usize = 2_000;
+const DEFAULT_BLOCK: usize = 96;
+/// Candles read from the input at a time.
+const CHUNK: usize = 100_000;
+
+/// One candle relative to the previous close, in log terms.
+#[derive(Debug, Clone, Copy)]
+struct Shape {
+    /// Open against the previous close.
+    gap: f64,
+    /// Close against the open.
+    body: f64,
+    /// High against the higher of open and close.
+    upper: f64,
+    /// Lower of open and close against the low.
+    lower: f64,
+    volume: f64,
+}
+
+/// The shapes and extent of a candle series, fed one candle at a time.
+#[derive(Default)]
+struct Profile {
+    shapes: Vec<Shape>,
+    candles: usize,
+    first: Option<Kline>,
+    last: Option<Kline>,
+    /// Smallest step between open times.
+    interval_ms: Option<i64>,
+}
+
+impl Profile {
+    fn of(klines: &[Kline]) -> Self {
+        let mut profile = Profile::default();
+        for kline in klines {
+            profile.push(kline);
+        }
+        profile
+    }
+
+    fn push(&mut self, kline: &Kline) {
+        let valid = |k: &Kline| {
+            [k.open, k.high, k.low, k.close]
+                .iter()
+                .all(|p| p.is_finite() && *p > 0.0)
+        };
+        if let Some(prev) = &self.last {
+            let step = kline.open_time - prev.open_time;
+            if step > 0 {
+                self.interval_ms = Some(self.interval_ms.map_or(step, |i| i.min(step)));
+            }
+        }
+        if valid(kline) {
+            let prev_close = self
+                .last
+                .as_ref()
+                .filter(|prev| valid(prev))
+                .map_or(kline.open, |prev| prev.close);
+            let (top, bottom) = (kline.open.max(kline.close), kline.open.min(kline.close));
+            self.shapes.push(Shape {
+                gap: (kline.open / prev_close).ln(),
+                body: (kline.close / kline.open).ln(),
+                upper: (kline.high / top).ln().max(0.0),
+                lower: (bottom / kline.low).ln().max(0.0),
+                volume: kline.volume.max(0.0),
+            });
+        }
+        self.candles += 1;
+        if self.first.is_none() {
+            self.first = Some(kline.clone());
+        }
+        self.last = Some(kline.clone());
+    }
+
+    fn median_volume(&self) -> f64 {
+        median(self.shapes.iter().map(|s| s.volume).collect())
+    }
+
+    /// Statistic rows: extent, return and range sizes, volume, clustering.
+    fn stats(&self) -> Vec<String> {
+        let returns: Vec<f64> = self.shapes.iter().map(|s| s.gap + s.body).collect();
+        let mean = returns.iter().sum::<f64>() / returns.len().max(1) as f64;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>()
+            / returns.len().saturating_sub(1).max(1) as f64;
+        let range = self
+            .shapes
+            .iter()
+            .map(|s| ((s.upper + s.lower + s.body.abs()).exp() - 1.0) * 100.0)
+            .sum::<f64>()
+            / self.shapes.len().max(1) as f64;
+        let time = |k: &Option<Kline>| {
+            k.as_ref()
+                .map_or("-".into(), |k| timezone::format_ms_secs(k.open_time))
+        };
+        vec![
+            self.candles.to_string(),
+            time(&self.first),
+            time(&self.last),
+            format!("{:.4}", variance.sqrt() * 100.0),
+            format!("{:.4}", range),
+            format!("{:.4}", self.median_volume()),
+            format!("{:.3}", lag1_autocorrelation(&returns)),
+        ]
+    }
+}
+
+const STAT_NAMES: [&str; 7] = [
+    "Candles",
+    "First open",
+    "Last open",
+    "Return std (%)",
+    "Mean high/low range (%)",
+    "Median volume",
+    "|Return| lag-1 autocorrelation",
+];
+
+fn median(mut values: Vec<f64>) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mid = values.len() / 2;
+    *values.select_nth_unstable_by(mid, f64::total_cmp).1
+}
+
+/// Lag-1 autocorrelation of the absolute returns: how much volatility
+/// clusters.
+fn lag1_autocorrelation(returns: &[f64]) -> f64 {
+    let abs: Vec<f64> = returns.iter().map(|r| r.abs()).collect();
+    if abs.len() < 3 {
+        return 0.0;
+    }
+    let mean = abs.iter().sum::<f64>() / abs.len() as f64;
+    let variance: f64 = abs.iter().map(|a| (a - mean).powi(2)).sum();
+    if variance == 0.0 {
+        return 0.0;
+    }
+    let covariance: f64 = abs.windows(2).map(|w| (w[0] - mean) * (w[1] - mean)).sum();
+    covariance / variance
+}
+
+/// Read `path` through, profiling every candle and keeping the `rows`
+/// consecutive candles from `from`, or the newest.
+fn read_input(path: &str, rows: usize, from: Option<i64>) -> Result<(Profile, Vec<Kline>)> {
+    let mut reader = CandleReader::open(path)?;
+    let mut profile = Profile::default();
+    let mut window = VecDeque::with_capacity(rows);
+    loop {
+        let chunk = reader.next_chunk(CHUNK)?;
+        if chunk.is_empty() {
+            break;
+        }
+        for kline in &chunk {
+            profile.push(kline);
+            match from {
+                Some(from) if kline.open_time < from || window.len() >= rows => {}
+                Some(_) => window.push_back(kline.clone()),
+                None => {
+                    if window.len() == rows {
+                        window.pop_front();
+                    }
+                    window.push_back(kline.clone());
+                }
+            }
+        }
+    }
+    Ok((profile, window.into()))
+}
+
+/// `rows` candles resampled from the shapes of `source` in blocks of
+/// `block`, starting at its first open time and price.
+fn resample(source: &Profile, rows: usize, block: usize, seed: u64) -> Result<Vec<Kline>> {
+    let (Some(first), Some(interval_ms)) = (&source.first, source.interval_ms) else {
+        bail!("--synthetic needs an input of at least two candles");
+    };
+    let shapes = &source.shapes;
+    if shapes.is_empty() {
+        bail!("--synthetic found no candle with valid prices in the input");
+    }
+    let mut rng = SeededRng::new(seed);
+    let mut klines = Vec::with_capacity(rows);
+    let mut close = first.open;
+    while klines.len() < rows {
+        let start = ((rng.next_unit() * shapes.len() as f64) as usize).min(shapes.len() - 1);
+        for shape in shapes[start..].iter().take(block) {
+            if klines.len() == rows {
+                break;
+            }
+            // The first candle opens at the source's price
+            let open = if klines.is_empty() {
+                close
+            } else {
+                close * shape.gap.exp()
+            };
+            close = open * shape.body.exp();
+            let open_time = first.open_time + klines.len() as i64 * interval_ms;
+            klines.push(Kline {
+                open_time,
+                open,
+                high: open.max(close) * shape.upper.exp(),
+                low: open.min(close) * (-shape.lower).exp(),
+                close,
+                volume: shape.volume,
+                close_time: open_time + interval_ms - 1,
+            });
+        }
+    }
+    Ok(klines)
+}
+
+/// Move `klines` to open at `start`, rescale their prices to open at `price`
+/// and their volumes to a median of `volume`.
+fn anonymize(
+    klines: &mut [Kline],
+    interval_ms: i64,
+    start: Option<i64>,
+    price: Option<f64>,
+    volume: Option<f64>,
+) -> Result<()> {
+    let Some(first) = klines.first().cloned() else {
+        return Ok(());
+    };
+    if let Some(start) = start {
+        if start.rem_euclid(interval_ms) != 0 {
+            bail!(
+                "--start must be on the {} ms interval grid of the candles",
+                interval_ms
+            );
+        }
+        let shift = start - first.open_time;
+        for kline in klines.iter_mut() {
+            kline.open_time += shift;
+            kline.close_time += shift;
+        }
+    }
+    if let Some(price) = price {
+        if first.open <= 0.0 {
+            bail!("--price needs a sample whose first open is positive");
+        }
+        let factor = price / first.open;
+        for kline in klines.iter_mut() {
+            kline.open *= factor;
+            kline.high *= factor;
+            kline.low *= factor;
+            kline.close *= factor;
+        }
+    }
+    if let Some(volume) = volume {
+        let current = median(klines.iter().map(|k| k.volume).collect());
+        if current <= 0.0 {
+            bail!("--volume needs a sample whose median volume is positive");
+        }
+        for kline in klines.iter_mut() {
+            kline.volume *= volume / current;
+        }
+    }
+    Ok(())
+}
+
+fn positive<T: std::str::FromStr + PartialOrd + Default>(
+    cli: &CliArgs,
+    flag: &str,
+) -> Result<Option<T>> {
+    let Some(value) = cli.value(flag) else {
+        return Ok(None);
+    };
+    match value.parse::<T>() {
+        Ok(v) if v > T::default() => Ok(Some(v)),
+        _ => bail!("--{} must be a positive number, got '{}'", flag, value),
+    }
+}
+
+pub fn run(cli: &CliArgs, seed: u64) -> Result<()> {
+    let output = cli
+        .value("output")
+        .context("make-sample needs --output <sample.parquet|sample.csv>")?;
+    let rows = positive(cli, "rows")?.unwrap_or(DEFAULT_ROWS);
+    let block = positive(cli, "block")?.unwrap_or(DEFAULT_BLOCK);
+    let price = positive::<f64>(cli, "price")?;
+    let volume = positive::<f64>(cli, "volume")?;
+    let from = cli.value("from").map(utils::parse_time_bound).transpose()?;
+    let start = cli
+        .value("start")
+        .map(utils::parse_time_bound)
+        .transpose()?;
+
+    let (source, window) = match cli.value("input") {
+        Some(path) => {
+            println!("Profiling {}", path);
+            read_input(path, rows, from)?
+        }
+        None => {
+            println!("No --input: sampling the synthetic bench random walk");
+            let klines = bench::synthetic_klines(rows, seed);
+            (Profile::of(&klines), klines)
+        }
+    };
+    let Some(interval_ms) = source.interval_ms else {
+        bail!("The input has fewer than two candles");
+    };
+    let mut sample = if cli.has("synthetic") {
+        resample(&source, rows, block, seed)?
+    } else {
+        window
+    };
+    if sample.is_empty() {
+        bail!("No candle of the input opens at or after --from");
+    }
+    anonymize(&mut sample, interval_ms, start, price, volume)?;
+
+    if output.ends_with(".csv") {
+        data_storage::save_klines_to_csv(&sample, output)?;
+    } else {
+        data_storage::save_klines_to_parquet(&sample, output)?;
+    }
+
+    let (input_stats, sample_stats) = (source.stats(), Profile::of(&sample).stats());
+    let table = Table {
+        title: "Statistics",
+        headers: vec!["Statistic", "Input", "Sample"],
+        rows: STAT_NAMES
+            .iter()
+            .zip(input_stats.into_iter().zip(sample_stats))
+            .map(|(name, (input, sample))| vec![name.to_string(), input, sample])
+            .collect(),
+    };
+    print!(
+        "{}",
+        report::render_markdown(&format!("Sample written to {}", output), &[table])
+    );
+    Ok(())
+}
@@ -0,0 +1,458 @@
+//! In-process scheduler of the periodic maintenance jobs (`[scheduler]`).
+//!
+//! Every job runs every `every_secs`, or once a day at `at` (in the display
+//! timezone):
+//!
+//! - `latency_summary` – the p50/p95/p99 latency log, every
+//!   `metrics_summary_secs`;
+//! - `memory` – the process RSS and virtual memory;
+//! - `compaction` – every partition of the feature-history datasets merged
+//!   into its `data.parquet`, the current day's too (the pipeline itself only
+//!   compacts a day once the next one starts);
+//! - `retention` – feature-history partitions older than `keep_days` deleted;
+//! - `reconciliation` – the `[consistency]` REST check of every pipeline;
+//! - `report` – the running `[report]` summaries written as they stand;
+//! - `drift` – the population stability index of every feature over the
+//!   last `recent_days` against the `baseline_days` before them, with a
+//!   `drift` alert naming the features past `threshold`, the cold partitions
+//!   of those days fetched back first;
+//! - `tiering` – feature-history partitions older than `[storage]`
+//!   `warm_days`, then the oldest while the datasets exceed `max_local_mb`,
+//!   moved to the cold store (today's stays).
+//!
+//! A job still running when it is due again is skipped rather than started
+//! twice. Runs are counted in `scheduler_runs_total{job,outcome}` (`ok`,
+//! `failed`, `skipped`) and timed in `scheduler_job_ms{job}`; a failed run
+//! raises a `scheduler` alert.
+
+use crate::alerts::{self, Level};
+use crate::config::{Config, DriftJob, JobSchedule};
+use crate::{consistency, metrics, report, timezone, utils};
+use anyhow::Result;
+use binance_streamer::data_storage;
+use binance_streamer::query::TimeRange;
+use chrono::{Days, NaiveTime, TimeZone, Utc};
+use polars::prelude::*;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+const DAY_MS: i64 = 86_400_000;
+
+/// Feature-history datasets of the pipelines started, by directory, with
+/// their (symbol, interval).
+fn datasets() -> &'static Mutex<BTreeMap<String, (String, String)>> {
+    static DATASETS: OnceLock<Mutex<BTreeMap<String, (String, String)>>> = OnceLock::new();
+    DATASETS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// Include the feature-history dataset in `dir` of `symbol`'s `interval`
+/// pipeline in the dataset jobs.
+pub fn track_feature_history(symbol: &str, interval: &str, dir: &str) {
+    datasets()
+        .lock()
+        .unwrap()
+        .insert(dir.to_string(), (symbol.to_string(), interval.to_string()));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Job {
+    LatencySummary,
+    Memory,
+    Compaction,
+    Retention,
+    Reconciliation,
+    Report,
+    Drift,
+    Tiering,
+}
+
+impl Job {
+    fn as_str(self) -> &'static str {
+        match self {
+            Job::LatencySummary => "latency_summary",
+            Job::Memory => "memory",
+            Job::Compaction => "compaction",
+            Job::Retention => "retention",
+            Job::Reconciliation => "reconciliation",
+            Job::Report => "report",
+            Job::Drift => "drift",
+            Job::Tiering => "tiering",
+        }
+    }
+}
+
+/// Start the timer of every scheduled job.
+pub fn spawn(config: &Config) -> Result<()> {
+    let scheduler = &config.scheduler;
+    let latency_summary = JobSchedule {
+        every_secs: config.metrics_summary_secs,
+        at: None,
+    };
+    let jobs = [
+        (Job::LatencySummary, &latency_summary),
+        (Job::Memory, &scheduler.memory),
+        (Job::Compaction, &scheduler.compaction),
+        (Job::Retention, &scheduler.retention.schedule),
+        (Job::Reconciliation, &scheduler.reconciliation),
+        (Job::Report, &scheduler.report),
+        (Job::Drift, &scheduler.drift.schedule),
+        (Job::Tiering, &scheduler.tiering),
+    ];
+    let shared = Arc::new(config.clone());
+    for (job, schedule) in jobs {
+        if !schedule.enabled() {
+            continue;
+        }
+        let at = schedule.at_time()?;
+        // The latency summary predates the scheduler and stays quiet
+        if job != Job::LatencySummary {
+            match (&schedule.at, schedule.every_secs) {
+                (Some(at), _) => println!("[scheduler] {} daily at {}", job.as_str(), at),
+                (None, secs) => println!("[scheduler] {} every {}s", job.as_str(), secs),
+            }
+        }
+        tokio::spawn(timer(job, schedule.every_secs, at, Arc::clone(&shared)));
+    }
+    Ok(())
+}
+
+/// Time until the next `at` in the display timezone.
+fn until_next(at: NaiveTime) -> Duration {
+    let tz = timezone::display();
+    let now = Utc::now().with_timezone(&tz);
+    // A time skipped by a DST change moves to the next day
+    for days in 0..=2 {
+        let Some(day) = now.date_naive().checked_add_days(Days::new(days)) else {
+            break;
+        };
+        if let Some(next) = tz.from_local_datetime(&day.and_time(at)).earliest() {
+            if next > now {
+                return (next - now).to_std().unwrap_or_default();
+            }
+        }
+    }
+    Duration::from_millis(DAY_MS as u64)
+}
+
+/// Run `job` whenever it is due, skipping the runs that would overlap.
+async fn timer(job: Job, every_secs: u64, at: Option<NaiveTime>, config: Arc<Config>) {
+    let mut ticker = (every_secs > 0).then(|| {
+        let mut ticker = tokio::time::interval(Duration::from_secs(every_secs));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        ticker
+    });
+    if let Some(ticker) = &mut ticker {
+        ticker.tick().await; // first tick fires immediately
+    }
+    let mut last: Option<JoinHandle<()>> = None;
+    loop {
+        match (&mut ticker, at) {
+            (Some(ticker), _) => {
+                ticker.tick().await;
+            }
+            (None, Some(at)) => tokio::time::sleep(until_next(at)).await,
+            (None, None) => return,
+        }
+        if last.as_ref().is_some_and(|run| !run.is_finished()) {
+            eprintln!(
+                "[scheduler] {} is still running; skipping this run",
+                job.as_str()
+            );
+            metrics::counter_inc(
+                "scheduler_runs_total",
+                &[("job", job.as_str()), ("outcome", "skipped")],
+                1,
+            );
+            continue;
+        }
+        let config = Arc::clone(&config);
+        last = Some(tokio::spawn(async move {
+            let started = Instant::now();
+            let outcome = match run(job, &config).await {
+                Ok(()) => "ok",
+                Err(e) => {
+                    alerts::raise(
+                        Level::Warning,
+                        "scheduler",
+                        &format!("{} job failed: {:#}", job.as_str(), e),
+                    );
+                    "failed"
+                }
+            };
+            metrics::observe_ms(
+                "scheduler_job_ms",
+                &[("job", job.as_str())],
+                started.elapsed().as_secs_f64() * 1000.0,
+            );
+            metrics::counter_inc(
+                "scheduler_runs_total",
+                &[("job", job.as_str()), ("outcome", outcome)],
+                1,
+            );
+        }));
+    }
+}
+
+/// The tracked feature-history datasets.
+fn tracked() -> Vec<(String, (String, String))> {
+    datasets()
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(dir, stream)| (dir.clone(), stream.clone()))
+        .collect()
+}
+
+async fn run(job: Job, config: &Config) -> Result<()> {
+    match job {
+        Job::LatencySummary => metrics::log_latency_summary(),
+        Job::Memory => utils::print_memory_usage(),
+        Job::Compaction => {
+            for (dir, _) in tracked() {
+                let target = dir.clone();
+                let partitions = tokio::task::spawn_blocking(move || {
+                    data_storage::compact_feature_history(&target)
+                })
+                .await??;
+                println!("[scheduler] compacted {} partitions of {}", partitions, dir);
+            }
+        }
+        Job::Retention => {
+            let keep_days = config.scheduler.retention.keep_days;
+            let keep_from = Utc::now().date_naive() - Days::new(keep_days - 1);
+            for (dir, _) in tracked() {
+                let target = dir.clone();
+                let pruned = tokio::task::spawn_blocking(move || {
+                    data_storage::prune_feature_history(&target, keep_from)
+                })
+                .await??;
+                if pruned > 0 {
+                    println!(
+                        "[scheduler] deleted {} feature-history partitions before {} from {}",
+                        pruned, keep_from, dir
+                    );
+                }
+            }
+        }
+        Job::Reconciliation => consistency::check_now(),
+        Job::Report => report::write_running(config).await,
+        Job::Drift => {
+            let now = Utc::now().timestamp_millis();
+            let drift = &config.scheduler.drift;
+            let from = now - (drift.recent_days + drift.baseline_days) as i64 * DAY_MS;
+            let tiers = config.tiered_storage()?;
+            for (dir, (symbol, interval)) in tracked() {
+                tiers.hydrate(&dir, TimeRange::since(from)).await?;
+                let cache = tiers.cache_of(&dir);
+                let job = drift.clone();
+                let target = dir.clone();
+                let scores =
+                    tokio::task::spawn_blocking(move || drift_scores(&target, &cache, &job, now))
+                        .await??;
+                check_drift(&symbol, &interval, &scores, drift);
+            }
+        }
+        Job::Tiering => tiering(config).await?,
+    }
+    Ok(())
+}
+
+/// Bytes of the files of a partition.
+fn partition_bytes(partition: &Path) -> u64 {
+    std::fs::read_dir(partition)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| entry.metadata().ok())
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Move the feature-history partitions older than `warm_days`, then the
+/// oldest across datasets while they exceed `max_local_mb`, to the cold
+/// store. Each is compacted, uploaded and checked before it is deleted.
+async fn tiering(config: &Config) -> Result<()> {
+    let storage = &config.storage;
+    let tiers = config.tiered_storage()?;
+    let Some(cold) = tiers.cold() else {
+        return Ok(());
+    };
+    let today = Utc::now().date_naive();
+    let warm_from = today - Days::new(storage.warm_days - 1);
+
+    let mut partitions = Vec::new();
+    for (dir, stream) in tracked() {
+        let target = dir.clone();
+        tokio::task::spawn_blocking(move || data_storage::compact_feature_history(&target))
+            .await??;
+        for (day, partition) in data_storage::feature_history_partitions(&dir)? {
+            let bytes = partition_bytes(&partition);
+            partitions.push((day, bytes, partition, dir.clone(), stream.clone()));
+        }
+    }
+    partitions.sort();
+    let budget = storage.max_local_mb * 1024 * 1024;
+    let mut local: u64 = partitions.iter().map(|p| p.1).sum();
+    let mut moved = BTreeMap::new();
+    for (day, bytes, partition, dir, (symbol, interval)) in partitions {
+        let over = budget > 0 && local > budget;
+        let file = partition.join("data.parquet");
+        // Not compacted: rows are being written to it
+        if day >= today || (day >= warm_from && !over) || !file.exists() {
+            continue;
+        }
+        let uploaded = cold.put_partition(&tiers.dataset(&dir), day, &file).await?;
+        let target = partition.clone();
+        let removed = tokio::task::spawn_blocking(move || {
+            data_storage::remove_feature_history_partition(&target, uploaded)
+        })
+        .await??;
+        if !removed {
+            eprintln!(
+                "[scheduler] {} changed while it was uploaded; kept on local disk",
+                partition.display()
+            );
+            continue;
+        }
+        local -= bytes;
+        metrics::counter_inc(
+            "tiering_partitions_moved_total",
+            &[("symbol", &symbol), ("interval", &interval)],
+            1,
+        );
+        *moved.entry(dir).or_insert(0) += 1;
+    }
+    metrics::gauge_set("feature_history_local_bytes", &[], local as f64);
+    for (dir, count) in moved {
+        println!(
+            "[scheduler] moved {} feature-history partitions of {} to the cold store",
+            count, dir
+        );
+    }
+    if budget > 0 && local > budget {
+        alerts::raise(
+            Level::Warning,
+            "tiering",
+            &format!(
+                "Feature history holds {:.0} MB on local disk after tiering, over max_local_mb = {} (today's partitions stay)",
+                local as f64 / (1024.0 * 1024.0),
+                storage.max_local_mb
+            ),
+        );
+    }
+    Ok(())
+}
+
+/// The population stability index of every checked feature of the dataset
+/// in `dir` (with its cold partitions cached in `cache`): its last
+/// `recent_days` to `now_ms` against the `baseline_days` before them.
+fn drift_scores(
+    dir: &str,
+    cache: &Path,
+    job: &DriftJob,
+    now_ms: i64,
+) -> Result<Vec<(String, f64)>> {
+    if !Path::new(dir).is_dir() && !cache.is_dir() {
+        return Ok(Vec::new());
+    }
+    let recent_from = now_ms - job.recent_days as i64 * DAY_MS;
+    let baseline_from = recent_from - job.baseline_days as i64 * DAY_MS;
+    let cache = cache.to_string_lossy();
+    let df = data_storage::read_feature_history(&[dir, &cache], TimeRange::since(baseline_from))?;
+    let recent = df.column("open_time")?.i64()?.gt_eq(recent_from);
+    let mut scores = Vec::new();
+    for column in df.columns() {
+        let name = column.name().as_str();
+        let checked = if job.features.is_empty() {
+            column.dtype().is_float()
+        } else {
+            job.features.iter().any(|feature| feature == name)
+        };
+        if !checked {
+            continue;
+        }
+        let values = column.cast(&DataType::Float64)?;
+        let (mut baseline, mut current) = (Vec::new(), Vec::new());
+        for (value, is_recent) in values.f64()?.into_iter().zip(&recent) {
+            match value.filter(|v| v.is_finite()) {
+                Some(v) if is_recent == Some(true) => current.push(v),
+                Some(v) => baseline.push(v),
+                None => {}
+            }
+        }
+        if let Some(score) = psi(&baseline, &current, job.bins) {
+            scores.push((name.to_string(), score));
+        }
+    }
+    Ok(scores)
+}
+
+/// Population stability index of `recent` against `baseline`, over `bins`
+/// quantile bins of the baseline; `None` with fewer baseline values than
+/// bins or no recent value.
+fn psi(baseline: &[f64], recent: &[f64], bins: usize) -> Option<f64> {
+    if baseline.len() < bins.max(1) || recent.is_empty() {
+        return None;
+    }
+    let mut sorted = baseline.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let mut edges: Vec<f64> = (1..bins).map(|i| sorted[i * sorted.len() / bins]).collect();
+    edges.dedup();
+    // An empty bin would make the index infinite
+    let shares = |values: &[f64]| -> Vec<f64> {
+        let mut counts = vec![0usize; edges.len() + 1];
+        for v in values {
+            counts[edges.partition_point(|edge| edge <= v)] += 1;
+        }
+        counts
+            .iter()
+            .map(|&count| (count as f64 / values.len() as f64).max(1e-4))
+            .collect()
+    };
+    let (expected, actual) = (shares(&sorted), shares(recent));
+    Some(
+        expected
+            .iter()
+            .zip(&actual)
+            .map(|(e, a)| (a - e) * (a / e).ln())
+            .sum(),
+    )
+}
+
+/// Set the drift gauge of a stream and alert on the features past the
+/// threshold.
+fn check_drift(symbol: &str, interval: &str, scores: &[(String, f64)], job: &DriftJob) {
+    let Some(max) = scores.iter().map(|(_, score)| *score).reduce(f64::max) else {
+        return;
+    };
+    metrics::gauge_set(
+        "feature_drift_psi_max",
+        &[("symbol", symbol), ("interval", interval)],
+        max,
+    );
+    let drifted: Vec<String> = scores
+        .iter()
+        .filter(|(_, score)| *score >= job.threshold)
+        .map(|(name, score)| format!("{} ({:.2})", name, score))
+        .collect();
+    if !drifted.is_empty() {
+        alerts::raise(
+            Level::Warning,
+            "drift",
+            &format!(
+                "{} {}: {} of {} features drifted over the last {} days: {}",
+                symbol,
+                interval,
+                drifted.len(),
+                scores.len(),
+                job.recent_days,
+                drifted.join(", ")
+            ),
+        );
+    }
+}
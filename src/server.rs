@@ -0,0 +1,121 @@
+//! Embedded HTTP server (axum).
+//!
+//! - `GET /metrics` – Prometheus text exposition
+//! - `GET /status` – state, restart count and last error of every symbol pipeline
+//! - `GET /latest/{symbol}` – most recent feature row as JSON
+//! - `GET /candles/{symbol}?from=&to=` – raw candles with `open_time` in
+//!   `[from, to]`; bounds are epoch milliseconds or RFC 3339 timestamps
+//! - `GET /strategies` – the `[kill]` paper record of every strategy on every
+//!   stream: position, equity, drawdown, losing streak, why it is disabled
+//! - `POST /webhooks/tradingview` – TradingView alerts as signals (only with
+//!   `[tradingview] enabled = true`, see `tradingview.rs`)
+//! - `POST /control/...` – authenticated runtime operations: pause, resume,
+//!   flush, rotate, reload, flatten, log level, re-enabling a strategy (only
+//!   with `[control] enabled = true`, see `control.rs`)
+
+use crate::config::{ControlConfig, TradingViewConfig};
+use crate::{control, history, kill, latest, metrics, supervisor, tradingview, utils};
+use anyhow::{Context, Result};
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// Routes served by the embedded HTTP server.
+fn router(tv_config: &TradingViewConfig, control_config: &ControlConfig) -> Router {
+    let mut router = Router::new()
+        .route("/metrics", get(|| async { metrics::render_prometheus() }))
+        .route("/status", get(|| async { Json(supervisor::report()) }))
+        .route("/latest/{symbol}", get(latest_features))
+        .route("/candles/{symbol}", get(candles))
+        .route("/strategies", get(|| async { Json(kill::report()) }));
+    if tv_config.enabled {
+        router = router.merge(
+            Router::new()
+                .route("/webhooks/tradingview", post(tradingview::receive))
+                .with_state(Arc::new(tv_config.clone())),
+        );
+    }
+    if control_config.enabled {
+        router = router.merge(control::router(control_config));
+    }
+    router
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, Json(json!({ "error": message.into() }))).into_response()
+}
+
+fn unknown_symbol(symbol: &str) -> Response {
+    error(
+        StatusCode::NOT_FOUND,
+        format!("No data for symbol {}", symbol),
+    )
+}
+
+async fn latest_features(Path(symbol): Path<String>) -> Response {
+    match latest::get(&symbol) {
+        Some(state) => Json(latest::feature_message(
+            &symbol,
+            &state.interval,
+            &state.features,
+        ))
+        .into_response(),
+        None => unknown_symbol(&symbol),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CandleRange {
+    from: Option<String>,
+    to: Option<String>,
+}
+
+async fn candles(Path(symbol): Path<String>, Query(range): Query<CandleRange>) -> Response {
+    let Some(state) = latest::get(&symbol) else {
+        return unknown_symbol(&symbol);
+    };
+    let bound = |v: &Option<String>| v.as_deref().map(utils::parse_time_bound).transpose();
+    let (from, to) = match (bound(&range.from), bound(&range.to)) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(e), _) | (_, Err(e)) => return error(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+
+    // Parquet scanning is blocking work; keep it off the async workers
+    let result = tokio::task::spawn_blocking(move || {
+        history::load_range(&state.raw_cache, &state.raw_journal, from, to)
+    })
+    .await;
+    match result {
+        Ok(Ok(klines)) => Json(klines).into_response(),
+        Ok(Err(e)) => error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Bind `addr` and serve in a background task.
+/// Binding errors are returned immediately; later serve errors are logged.
+pub async fn spawn(
+    addr: &str,
+    tv_config: &TradingViewConfig,
+    control_config: &ControlConfig,
+) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind HTTP server on {}", addr))?;
+    println!("HTTP server listening on http://{}", addr);
+    // The control routes check the peer address
+    let service =
+        router(tv_config, control_config).into_make_service_with_connect_info::<SocketAddr>();
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, service).await {
+            eprintln!("HTTP server error: {}", e);
+        }
+    });
+    Ok(())
+}
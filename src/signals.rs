@@ -0,0 +1,81 @@
+//! Single path every signal takes, whatever produced it.
+//!
+//! Strategy decisions from the candle pipeline and externally received alerts
+//! (TradingView webhooks) are logged, counted, written to the event log and
+//! published on the update bus here, so downstream consumers cannot tell the
+//! sources apart except by the `strategy` name. The registered signal hooks
+//! run last. Signals of a strategy its `[kill]` limits disabled are held
+//! here, whatever their source.
+
+use crate::bus::{self, Update};
+use crate::events::{self, Event};
+use crate::hooks;
+use crate::kill;
+use crate::metrics;
+use crate::plugins;
+use crate::strategy::Signal;
+
+pub fn route(signal: Signal) {
+    if kill::disabled(&signal.symbol, &signal.interval, &signal.strategy) {
+        println!(
+            "[kill] {}: held {} {} signal of a disabled strategy",
+            signal.symbol,
+            signal.strategy,
+            signal.side.as_str()
+        );
+        metrics::counter_inc(
+            "signals_suppressed_total",
+            &[
+                ("symbol", &signal.symbol),
+                ("strategy", &signal.strategy),
+                ("reason", "kill"),
+            ],
+            1,
+        );
+        return;
+    }
+    let flat = kill::signal(&signal);
+    deliver(signal);
+    if let Some(flat) = flat {
+        deliver(flat);
+    }
+}
+
+/// Log, count and publish a signal, bypassing the `[kill]` check (for the
+/// `flat` signals closing the position of a strategy it disabled, or of a
+/// `POST /control/flatten`).
+pub fn deliver(signal: Signal) {
+    println!(
+        "Signal | {} {} {} @ {}{}{}",
+        signal.strategy,
+        signal.side.as_str(),
+        signal.symbol,
+        signal.price,
+        signal
+            .size
+            .map_or(String::new(), |size| format!(" size {:.4}", size)),
+        signal
+            .stop
+            .map_or(String::new(), |stop| format!(" stop {}", stop))
+    );
+    metrics::counter_inc(
+        "signals_total",
+        &[
+            ("strategy", &signal.strategy),
+            ("side", signal.side.as_str()),
+        ],
+        1,
+    );
+    events::emit(Event::Signal {
+        symbol: signal.symbol.clone(),
+        interval: signal.interval.clone(),
+        strategy: signal.strategy.clone(),
+        side: signal.side.as_str().to_string(),
+        open_time: signal.open_time,
+        price: signal.price,
+        size: signal.size,
+        stop: signal.stop,
+    });
+    bus::publish(Update::Signal(signal.clone()));
+    plugins::spawn(hooks::signal(signal));
+}
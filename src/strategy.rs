@@ -0,0 +1,404 @@
+//! Strategies turn the feature frame of each closed candle into a decision.
+//!
+//! A strategy sees the feature DataFrame (last row = the candle that just
+//! closed) and [`WindowStats`] over the raw candle window, for rolling
+//! highs/lows, percentiles and the like without going through the DataFrame,
+//! and returns a [`Side`]. The pipeline wraps non-flat decisions into
+//! [`Signal`]s, logs them as events and publishes them on the update bus.
+//!
+//! Two built-in strategies serve as references: [`EmaCross`], always in the
+//! market, and [`SrBreakout`], which sizes its entries and manages its exits
+//! through [`Strategy::size`], [`Strategy::stop`] and [`Strategy::exited`].
+//! [`from_params`] builds either from its kind and parameters, as written in
+//! a parameter file.
+
+use crate::window::WindowStats;
+use anyhow::{bail, Context, Result};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Flat,
+    Long,
+    Short,
+}
+
+impl Side {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Side::Flat => "flat",
+            Side::Long => "long",
+            Side::Short => "short",
+        }
+    }
+}
+
+/// A non-flat decision of one strategy on one closed candle.
+#[derive(Debug, Clone, Serialize)]
+pub struct Signal {
+    pub symbol: String,
+    pub interval: String,
+    pub strategy: String,
+    pub side: Side,
+    pub open_time: i64,
+    /// Close price of the candle the decision was made on.
+    pub price: f64,
+    /// Position size as a fraction of equity, when the strategy sizes it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<f64>,
+    /// Protective stop price of the position, when the strategy sets one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<f64>,
+}
+
+pub trait Strategy: Send {
+    fn name(&self) -> &str;
+
+    /// Decide on the newest candle. `features` is the full feature window,
+    /// `window` the raw candle window it was computed from.
+    fn evaluate(&mut self, features: &DataFrame, window: &WindowStats) -> Result<Side>;
+
+    /// Parameters the decisions depend on, kept in the signal audit trail.
+    fn params(&self) -> Value {
+        Value::Null
+    }
+
+    /// Version of the model or rules behind the decisions; the crate version
+    /// unless the strategy carries its own (e.g. a model file hash).
+    fn version(&self) -> String {
+        env!("CARGO_PKG_VERSION").to_string()
+    }
+
+    /// Size of the position the newest decision opened, as a fraction of
+    /// equity; `None` leaves sizing to whoever acts on the signal.
+    fn size(&self) -> Option<f64> {
+        None
+    }
+
+    /// Protective stop price of the position the strategy holds.
+    fn stop(&self) -> Option<f64> {
+        None
+    }
+
+    /// Whether the newest `Flat` decision closed a position (e.g. its stop
+    /// was hit), so it is routed as a `flat` signal. Strategies that are
+    /// always in the market never exit.
+    fn exited(&self) -> bool {
+        false
+    }
+}
+
+/// Last two non-null values of a float column (previous, current).
+fn last_two(features: &DataFrame, column: &str) -> Result<Option<(f64, f64)>> {
+    let values = features.column(column)?.f64()?;
+    let n = values.len();
+    if n < 2 {
+        return Ok(None);
+    }
+    Ok(values.get(n - 2).zip(values.get(n - 1)))
+}
+
+/// Long when the fast EMA crosses above the slow one, short on the opposite
+/// cross, flat otherwise.
+pub struct EmaCross {
+    name: String,
+    fast: String,
+    slow: String,
+}
+
+impl EmaCross {
+    pub fn new(fast: &str, slow: &str) -> Self {
+        Self {
+            name: format!("ema_cross_{}_{}", fast, slow),
+            fast: fast.to_string(),
+            slow: slow.to_string(),
+        }
+    }
+}
+
+impl Strategy for EmaCross {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn params(&self) -> Value {
+        json!({ "fast": self.fast, "slow": self.slow })
+    }
+
+    fn evaluate(&mut self, features: &DataFrame, _window: &WindowStats) -> Result<Side> {
+        let (Some((fast_prev, fast)), Some((slow_prev, slow))) = (
+            last_two(features, &self.fast)?,
+            last_two(features, &self.slow)?,
+        ) else {
+            return Ok(Side::Flat);
+        };
+        Ok(if fast_prev <= slow_prev && fast > slow {
+            Side::Long
+        } else if fast_prev >= slow_prev && fast < slow {
+            Side::Short
+        } else {
+            Side::Flat
+        })
+    }
+}
+
+/// Parameters of [`SrBreakout`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SrBreakoutParams {
+    /// Candles searched for touches of a resistance zone.
+    pub lookback: usize,
+    /// Smallest `pivot_high_strength` of a swing high counted as a touch.
+    pub pivot_strength: u32,
+    /// Touches a zone needs.
+    pub min_touches: usize,
+    /// Zone height in ATRs: touches at most this far apart form one zone.
+    pub zone_atr: f64,
+    /// How far above the zone a close confirms the breakout, in ATRs.
+    pub confirm_atr: f64,
+    /// Candles of the ATR (mean true range).
+    pub atr_period: usize,
+    /// Stop distance below the close in ATRs, trailed as the price rises.
+    pub stop_atr: f64,
+    /// Share of equity lost when the initial stop is hit; the size follows.
+    pub risk: f64,
+}
+
+impl Default for SrBreakoutParams {
+    fn default() -> Self {
+        Self {
+            lookback: 500,
+            pivot_strength: 10,
+            min_touches: 3,
+            zone_atr: 0.5,
+            confirm_atr: 0.25,
+            atr_period: 14,
+            stop_atr: 2.0,
+            risk: 0.01,
+        }
+    }
+}
+
+/// An open [`SrBreakout`] position.
+#[derive(Debug, Clone, Copy)]
+struct BreakoutPosition {
+    size: f64,
+    stop: f64,
+}
+
+/// Long on a confirmed breakout of a multi-touch resistance zone, with an
+/// ATR stop.
+///
+/// Swing highs of at least `pivot_strength` candles on both sides (the
+/// `pivot_high_strength` feature) within the last `lookback` candles are the
+/// touches; touches at most `zone_atr` ATRs apart form a zone, and a zone of
+/// `min_touches` is resistance. A candle closing more than `confirm_atr`
+/// ATRs above the top of such a zone, after a close that did not, is a
+/// breakout: the strategy goes long with the stop `stop_atr` ATRs below the
+/// close, sized so that hitting it loses `risk` of equity (at most all of
+/// it). The stop trails the close at the same distance and never moves down;
+/// the candle whose low reaches it exits the position.
+pub struct SrBreakout {
+    params: SrBreakoutParams,
+    position: Option<BreakoutPosition>,
+    exited: bool,
+}
+
+impl SrBreakout {
+    pub fn new(params: SrBreakoutParams) -> Self {
+        Self {
+            params,
+            position: None,
+            exited: false,
+        }
+    }
+
+    /// Mean true range of the newest `atr_period` candles of `window`.
+    fn atr(&self, window: &WindowStats) -> Option<f64> {
+        let n = self.params.atr_period + 1;
+        let window = window.window();
+        let (highs, lows, closes) = (window.highs(n), window.lows(n), window.closes(n));
+        if closes.len() < n {
+            return None;
+        }
+        let ranges = (1..n).map(|i| {
+            let previous = closes[i - 1];
+            (highs[i] - lows[i])
+                .max((highs[i] - previous).abs())
+                .max((lows[i] - previous).abs())
+        });
+        Some(ranges.sum::<f64>() / (n - 1) as f64)
+    }
+
+    /// Tops of the resistance zones among the touches before the newest
+    /// candle, lowest first.
+    fn zones(&self, features: &DataFrame, atr: f64) -> Result<Vec<f64>> {
+        let height = features.height();
+        let from = height.saturating_sub(self.params.lookback + 1);
+        let highs = features.column("high")?.f64()?;
+        let strengths = features.column("pivot_high_strength")?.u32()?;
+        let mut touches: Vec<f64> = (from..height.saturating_sub(1))
+            .filter(|&i| strengths.get(i).unwrap_or(0) >= self.params.pivot_strength)
+            .filter_map(|i| highs.get(i))
+            .collect();
+        touches.sort_by(f64::total_cmp);
+        // Each zone starts at the lowest touch not in the one below
+        let height = self.params.zone_atr * atr;
+        let mut tops = Vec::new();
+        let mut start = 0;
+        while start < touches.len() {
+            let end = touches[start..].partition_point(|&t| t <= touches[start] + height) + start;
+            if end - start >= self.params.min_touches {
+                tops.push(touches[end - 1]);
+            }
+            start = end;
+        }
+        Ok(tops)
+    }
+}
+
+impl Strategy for SrBreakout {
+    fn name(&self) -> &str {
+        "sr_breakout"
+    }
+
+    fn params(&self) -> Value {
+        serde_json::to_value(self.params).unwrap_or_default()
+    }
+
+    fn size(&self) -> Option<f64> {
+        self.position.map(|p| p.size)
+    }
+
+    fn stop(&self) -> Option<f64> {
+        self.position.map(|p| p.stop)
+    }
+
+    fn exited(&self) -> bool {
+        self.exited
+    }
+
+    fn evaluate(&mut self, features: &DataFrame, window: &WindowStats) -> Result<Side> {
+        self.exited = false;
+        let (Some((previous, close)), Some((_, low))) =
+            (last_two(features, "close")?, last_two(features, "low")?)
+        else {
+            return Ok(Side::Flat);
+        };
+        let Some(atr) = self.atr(window).filter(|atr| *atr > 0.0) else {
+            return Ok(Side::Flat);
+        };
+
+        if let Some(position) = &mut self.position {
+            if low <= position.stop {
+                self.position = None;
+                self.exited = true;
+            } else {
+                position.stop = position.stop.max(close - self.params.stop_atr * atr);
+            }
+            return Ok(Side::Flat);
+        }
+
+        let confirm = self.params.confirm_atr * atr;
+        let broken = self
+            .zones(features, atr)?
+            .into_iter()
+            .any(|top| previous <= top + confirm && close > top + confirm);
+        if !broken {
+            return Ok(Side::Flat);
+        }
+        let stop = close - self.params.stop_atr * atr;
+        self.position = Some(BreakoutPosition {
+            size: (self.params.risk * close / (close - stop)).min(1.0),
+            stop,
+        });
+        Ok(Side::Long)
+    }
+}
+
+/// The built-in strategy `kind` with `params`; parameters left out take
+/// their defaults. `ema_cross` takes the `fast` and `slow` EMA columns
+/// (`ema50_m15` and `ema200_m15`), `sr_breakout` the fields of
+/// [`SrBreakoutParams`].
+pub fn from_params(kind: &str, params: &Map<String, Value>) -> Result<Box<dyn Strategy>> {
+    let text = |key: &str, default: &str| -> Result<String> {
+        match params.get(key) {
+            None => Ok(default.to_string()),
+            Some(value) => value
+                .as_str()
+                .map(str::to_string)
+                .with_context(|| format!("{} parameter '{}' must be a string", kind, key)),
+        }
+    };
+    let number = |key: &str, default: f64| -> Result<f64> {
+        match params.get(key) {
+            None => Ok(default),
+            Some(value) => value
+                .as_f64()
+                .filter(|v| *v > 0.0)
+                .with_context(|| format!("{} parameter '{}' must be a positive number", kind, key)),
+        }
+    };
+    let count = |key: &str, default: usize| -> Result<usize> {
+        match params.get(key) {
+            None => Ok(default),
+            Some(value) => value
+                .as_u64()
+                .filter(|v| *v > 0)
+                .map(|v| v as usize)
+                .with_context(|| {
+                    format!("{} parameter '{}' must be a positive integer", kind, key)
+                }),
+        }
+    };
+    let known: &[&str] = match kind {
+        "ema_cross" => &["fast", "slow"],
+        "sr_breakout" => &[
+            "lookback",
+            "pivot_strength",
+            "min_touches",
+            "zone_atr",
+            "confirm_atr",
+            "atr_period",
+            "stop_atr",
+            "risk",
+        ],
+        other => bail!("Unknown strategy kind '{}' (ema_cross, sr_breakout)", other),
+    };
+    if let Some(key) = params.keys().find(|key| !known.contains(&key.as_str())) {
+        bail!(
+            "Unknown {} parameter '{}' (expected {:?})",
+            kind,
+            key,
+            known
+        );
+    }
+    if kind == "ema_cross" {
+        return Ok(Box::new(EmaCross::new(
+            &text("fast", "ema50_m15")?,
+            &text("slow", "ema200_m15")?,
+        )));
+    }
+    let defaults = SrBreakoutParams::default();
+    let risk = number("risk", defaults.risk)?;
+    if risk > 1.0 {
+        bail!("sr_breakout parameter 'risk' is a share of equity, at most 1");
+    }
+    Ok(Box::new(SrBreakout::new(SrBreakoutParams {
+        lookback: count("lookback", defaults.lookback)?,
+        pivot_strength: count("pivot_strength", defaults.pivot_strength as usize)? as u32,
+        min_touches: count("min_touches", defaults.min_touches)?,
+        zone_atr: number("zone_atr", defaults.zone_atr)?,
+        confirm_atr: number("confirm_atr", defaults.confirm_atr)?,
+        atr_period: count("atr_period", defaults.atr_period)?,
+        stop_atr: number("stop_atr", defaults.stop_atr)?,
+        risk,
+    })))
+}
+
+/// Strategies run on every closed candle unless configured otherwise.
+pub fn default_strategies() -> Vec<Box<dyn Strategy>> {
+    vec![Box::new(EmaCross::new("ema50_m15", "ema200_m15"))]
+}
@@ -0,0 +1,202 @@
+//! Supervision of the per-symbol pipelines.
+//!
+//! Every symbol runs in its own task, so an error or a panic in one pipeline
+//! never takes the others down. A failed pipeline is restarted after an
+//! exponential backoff (reset once it has run for `stable_secs`), until
+//! `max_restarts` is reached or restarts are disabled. A pipeline failing
+//! with a [`binance_streamer::Error::Config`] error is not restarted: the
+//! next attempt would fail the same way.
+//!
+//! With a `[universe]` the set of pipelines changes at run time: the
+//! universe manager starts each one through [`supervise`] and marks the ones
+//! it stopped as [`PipelineState::Retired`].
+//!
+//! The state of every pipeline is kept in a registry, served on
+//! `GET /status`, logged on each change and mirrored in the `pipeline_up` and
+//! `pipeline_restarts_total` metrics.
+
+use crate::alerts::{self, Level};
+use crate::clock;
+use crate::config::SupervisorConfig;
+use crate::metrics;
+use anyhow::{bail, Result};
+use binance_streamer::Error;
+use futures_util::future::join_all;
+use serde::Serialize;
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineState {
+    Running,
+    /// Failed, waiting for the backoff before the next attempt.
+    Restarting,
+    /// Returned without error; not restarted.
+    Stopped,
+    /// Failed and out of restarts.
+    Failed,
+    /// Stopped by the universe manager after its symbol left the universe.
+    Retired,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineStatus {
+    pub state: PipelineState,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+    /// Epoch ms of the last state change.
+    pub since: i64,
+}
+
+fn registry() -> &'static Mutex<BTreeMap<String, PipelineStatus>> {
+    static REGISTRY: OnceLock<Mutex<BTreeMap<String, PipelineStatus>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// Status of every supervised pipeline, by symbol.
+pub fn report() -> BTreeMap<String, PipelineStatus> {
+    registry().lock().unwrap().clone()
+}
+
+fn set_state(symbol: &str, state: PipelineState, restarts: u32, error: Option<String>) {
+    let summary = {
+        let mut reg = registry().lock().unwrap();
+        let status = reg.entry(symbol.to_string()).or_insert(PipelineStatus {
+            state,
+            restarts,
+            last_error: None,
+            since: 0,
+        });
+        status.state = state;
+        status.restarts = restarts;
+        status.since = clock::now_ms();
+        if error.is_some() {
+            status.last_error = error;
+        }
+        reg.iter()
+            .map(|(symbol, s)| format!("{}={:?}", symbol, s.state))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let up = if state == PipelineState::Running {
+        1.0
+    } else {
+        0.0
+    };
+    metrics::gauge_set("pipeline_up", &[("symbol", symbol)], up);
+    println!("[supervisor] {}", summary);
+}
+
+/// Record that `pipeline` was stopped because its symbol left the universe.
+pub fn retired(pipeline: &str) {
+    let restarts = registry()
+        .lock()
+        .unwrap()
+        .get(pipeline)
+        .map_or(0, |s| s.restarts);
+    set_state(pipeline, PipelineState::Retired, restarts, None);
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string())
+}
+
+/// Run `pipeline(symbol, restarts)` for every symbol in its own supervised
+/// task. Returns once every pipeline has stopped for good; an error names the
+/// pipelines that failed.
+pub async fn run<F, Fut>(symbols: &[String], config: &SupervisorConfig, pipeline: F) -> Result<()>
+where
+    F: Fn(String, u32) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let tasks = symbols
+        .iter()
+        .map(|symbol| tokio::spawn(supervise(symbol.clone(), config.clone(), pipeline.clone())));
+    let mut failed = Vec::new();
+    for (symbol, result) in symbols.iter().zip(join_all(tasks).await) {
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => failed.push(format!("{:#}", e)),
+            Err(e) => failed.push(format!("{}: supervisor task failed: {}", symbol, e)),
+        }
+    }
+    if !failed.is_empty() {
+        bail!("{}", failed.join("; "));
+    }
+    Ok(())
+}
+
+/// Run `pipeline(symbol, restarts)`, restarting it on failure, until it
+/// returns `Ok` or runs out of restarts.
+pub async fn supervise<F, Fut>(symbol: String, config: SupervisorConfig, pipeline: F) -> Result<()>
+where
+    F: Fn(String, u32) -> Fut,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let min_backoff = Duration::from_secs(config.min_backoff_secs);
+    let max_backoff = Duration::from_secs(config.max_backoff_secs);
+    let mut backoff = min_backoff;
+    let mut restarts = 0;
+    loop {
+        set_state(&symbol, PipelineState::Running, restarts, None);
+        let started = Instant::now();
+        let (error, permanent) = match tokio::spawn(pipeline(symbol.clone(), restarts)).await {
+            Ok(Ok(())) => {
+                set_state(&symbol, PipelineState::Stopped, restarts, None);
+                return Ok(());
+            }
+            Ok(Err(e)) => (
+                format!("{:#}", e),
+                matches!(e.downcast_ref::<Error>(), Some(Error::Config(_))),
+            ),
+            Err(e) if e.is_panic() => (format!("panic: {}", panic_message(e.into_panic())), false),
+            Err(e) => (e.to_string(), false),
+        };
+
+        if permanent
+            || !config.restart
+            || (config.max_restarts > 0 && restarts >= config.max_restarts)
+        {
+            alerts::raise(
+                Level::Critical,
+                "supervisor",
+                &format!("{} pipeline failed, not restarting: {}", symbol, error),
+            );
+            set_state(
+                &symbol,
+                PipelineState::Failed,
+                restarts,
+                Some(error.clone()),
+            );
+            bail!("{}: {}", symbol, error);
+        }
+
+        if started.elapsed() >= Duration::from_secs(config.stable_secs) {
+            backoff = min_backoff;
+        }
+        restarts += 1;
+        metrics::counter_inc("pipeline_restarts_total", &[("symbol", &symbol)], 1);
+        alerts::raise(
+            Level::Warning,
+            "supervisor",
+            &format!(
+                "{} pipeline failed, restarting in {}s (restart #{}): {}",
+                symbol,
+                backoff.as_secs(),
+                restarts,
+                error
+            ),
+        );
+        set_state(&symbol, PipelineState::Restarting, restarts, Some(error));
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(max_backoff);
+    }
+}
@@ -0,0 +1,352 @@
+//! Storage tiers of the feature-history datasets.
+//!
+//! - hot: the rolling candle window of a running pipeline, in memory;
+//! - warm: the daily partitions (`date=YYYY-MM-DD/`) of a dataset on local
+//!   disk;
+//! - cold: partitions moved to an object store ([`ColdStore`]), under
+//!   `<prefix>/<dataset>/date=YYYY-MM-DD/data.parquet`, where the dataset is
+//!   its directory relative to the data directory.
+//!
+//! The collector's `tiering` job moves the partitions between local disk and
+//! the cold store. [`TieredStorage`] reads a dataset across both: the cold
+//! partitions a query needs and local disk lacks are fetched into a cache
+//! directory of the same layout, bounded in size by evicting the partitions
+//! read least recently, and scanned together with the local ones:
+//!
+//! ```no_run
+//! # async fn demo() -> binance_streamer::error::Result<()> {
+//! use binance_streamer::query::{DataLayout, TimeRange};
+//! use binance_streamer::tiers::{ColdStore, TieredStorage};
+//!
+//! let cold = ColdStore::open("file:///mnt/archive/bot", &Default::default())?;
+//! let tiers = TieredStorage::new(DataLayout::default(), Some(cold), "data/cold_cache", 1024);
+//! let rows = tiers
+//!     .load_feature_history_lazy("BTCUSDT", "15m", TimeRange::since(1_704_067_200_000))
+//!     .await?
+//!     .collect()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::data_storage;
+use crate::error::{Error, Result};
+use crate::query::{self, DataLayout, TimeRange};
+use chrono::NaiveDate;
+use futures_util::TryStreamExt;
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectStore, ObjectStoreExt};
+use polars::prelude::LazyFrame;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+const DAY_MS: i64 = 86_400_000;
+
+/// The object store holding the cold partitions.
+#[derive(Debug, Clone)]
+pub struct ColdStore {
+    store: Arc<dyn ObjectStore>,
+    /// Path of the cold tier in the store, without surrounding slashes.
+    prefix: String,
+}
+
+impl ColdStore {
+    /// Open the store at `url`: `file:///mnt/archive` (a local or mounted
+    /// directory), `gs://bucket/prefix`, `az://container/prefix` or an
+    /// `https://` WebDAV server. `options` are the store's settings
+    /// (`google_service_account`, `azure_storage_account_key`, ...).
+    pub fn open(url: &str, options: &BTreeMap<String, String>) -> Result<Self> {
+        let parsed = url::Url::parse(url)
+            .map_err(|e| Error::Config(format!("invalid object store URL '{}': {}", url, e)))?;
+        let (store, prefix) = object_store::parse_url_opts(&parsed, options)
+            .map_err(|e| Error::Config(format!("object store '{}': {}", url, e)))?;
+        Ok(Self {
+            store: Arc::from(store),
+            prefix: prefix.as_ref().trim_matches('/').to_string(),
+        })
+    }
+
+    fn dataset_path(&self, dataset: &str) -> Result<ObjectPath> {
+        let path = [self.prefix.as_str(), dataset.trim_matches('/')]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("/");
+        ObjectPath::parse(&path).map_err(storage)
+    }
+
+    fn partition_path(&self, dataset: &str, day: NaiveDate) -> Result<ObjectPath> {
+        let partition = format!("date={}", day.format("%Y-%m-%d"));
+        Ok(self
+            .dataset_path(dataset)?
+            .child(partition.as_str())
+            .child("data.parquet"))
+    }
+
+    /// Days of the partitions of `dataset` in the store, oldest first.
+    pub async fn days(&self, dataset: &str) -> Result<Vec<NaiveDate>> {
+        let prefix = self.dataset_path(dataset)?;
+        let objects: Vec<_> = self
+            .store
+            .list(Some(&prefix))
+            .try_collect()
+            .await
+            .map_err(storage)?;
+        let mut days: Vec<NaiveDate> = objects
+            .iter()
+            .filter_map(|object| {
+                object
+                    .location
+                    .parts()
+                    .filter_map(|part| {
+                        let day = part.as_ref().strip_prefix("date=")?;
+                        NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()
+                    })
+                    .next()
+            })
+            .collect();
+        days.sort();
+        days.dedup();
+        Ok(days)
+    }
+
+    /// Upload the compacted `file` as the `day` partition of `dataset` and
+    /// check the store holds all of it; the bytes uploaded.
+    pub async fn put_partition(&self, dataset: &str, day: NaiveDate, file: &Path) -> Result<u64> {
+        let location = self.partition_path(dataset, day)?;
+        let bytes = std::fs::read(file)?;
+        let len = bytes.len() as u64;
+        self.store
+            .put(&location, bytes.into())
+            .await
+            .map_err(storage)?;
+        let stored = self.store.head(&location).await.map_err(storage)?.size;
+        if stored != len {
+            return Err(Error::Storage(
+                format!(
+                    "{} holds {} bytes after uploading {}",
+                    location, stored, len
+                )
+                .into(),
+            ));
+        }
+        Ok(len)
+    }
+
+    /// Download the `day` partition of `dataset` into `partition`; the bytes
+    /// downloaded.
+    pub async fn get_partition(
+        &self,
+        dataset: &str,
+        day: NaiveDate,
+        partition: &Path,
+    ) -> Result<u64> {
+        let location = self.partition_path(dataset, day)?;
+        let bytes = self
+            .store
+            .get(&location)
+            .await
+            .map_err(storage)?
+            .bytes()
+            .await
+            .map_err(storage)?;
+        std::fs::create_dir_all(partition)?;
+        let tmp = partition.join("data.parquet.tmp");
+        std::fs::write(&tmp, &bytes)?;
+        std::fs::rename(&tmp, partition.join("data.parquet"))?;
+        Ok(bytes.len() as u64)
+    }
+}
+
+fn storage(e: impl std::error::Error + Send + Sync + 'static) -> Error {
+    Error::Storage(Box::new(e))
+}
+
+/// Name of the feature-history dataset in `dir` in the cold store and the
+/// cache: `dir` relative to `data_dir`.
+pub fn dataset_name(data_dir: &str, dir: &str) -> String {
+    let relative = Path::new(dir)
+        .strip_prefix(data_dir)
+        .unwrap_or(Path::new(dir));
+    relative
+        .components()
+        .filter_map(|component| match component {
+            std::path::Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Whether the UTC `day` has candles within `range`.
+fn overlaps(day: NaiveDate, range: TimeRange) -> bool {
+    let start = day
+        .and_time(chrono::NaiveTime::MIN)
+        .and_utc()
+        .timestamp_millis();
+    range.from.is_none_or(|from| start + DAY_MS > from) && range.to.is_none_or(|to| start <= to)
+}
+
+/// The feature-history datasets across the warm and cold tiers.
+#[derive(Debug, Clone)]
+pub struct TieredStorage {
+    layout: DataLayout,
+    cold: Option<ColdStore>,
+    cache_dir: PathBuf,
+    cache_bytes: u64,
+}
+
+impl TieredStorage {
+    /// The datasets of `layout`, with their cold partitions in `cold` (local
+    /// disk only if `None`) cached under `cache_dir`, up to `cache_mb`.
+    pub fn new(
+        layout: DataLayout,
+        cold: Option<ColdStore>,
+        cache_dir: impl Into<PathBuf>,
+        cache_mb: u64,
+    ) -> Self {
+        Self {
+            layout,
+            cold,
+            cache_dir: cache_dir.into(),
+            cache_bytes: cache_mb * 1024 * 1024,
+        }
+    }
+
+    pub fn cold(&self) -> Option<&ColdStore> {
+        self.cold.as_ref()
+    }
+
+    /// Name of the dataset in `dir` in the cold store.
+    pub fn dataset(&self, dir: &str) -> String {
+        dataset_name(&self.layout.data_dir, dir)
+    }
+
+    /// The cache directory of the dataset in `dir`.
+    pub fn cache_of(&self, dir: &str) -> PathBuf {
+        self.cache_dir.join(self.dataset(dir))
+    }
+
+    /// Fetch the cold partitions of the dataset in `dir` overlapping `range`
+    /// that neither local disk nor the cache holds, then evict the cache down
+    /// to its size (never the partitions of `range`); the partitions fetched.
+    pub async fn hydrate(&self, dir: &str, range: TimeRange) -> Result<usize> {
+        let Some(cold) = &self.cold else {
+            return Ok(0);
+        };
+        let dataset = self.dataset(dir);
+        let cache = self.cache_of(dir);
+        let local: HashSet<NaiveDate> = data_storage::feature_history_partitions(dir)?
+            .into_iter()
+            .map(|(day, _)| day)
+            .collect();
+        // A partition back on local disk (backfilled again) wins over its copy
+        for (day, partition) in data_storage::feature_history_partitions(&cache.to_string_lossy())?
+        {
+            if local.contains(&day) {
+                std::fs::remove_dir_all(partition)?;
+            }
+        }
+
+        let (mut fetched, mut bytes) = (0, 0);
+        let mut used = HashSet::new();
+        for day in cold.days(&dataset).await? {
+            if local.contains(&day) || !overlaps(day, range) {
+                continue;
+            }
+            let partition = cache.join(format!("date={}", day.format("%Y-%m-%d")));
+            let file = partition.join("data.parquet");
+            if file.exists() {
+                // The modification time orders the evictions
+                File::options()
+                    .write(true)
+                    .open(&file)?
+                    .set_modified(SystemTime::now())?;
+            } else {
+                bytes += cold.get_partition(&dataset, day, &partition).await?;
+                fetched += 1;
+            }
+            used.insert(partition);
+        }
+        if fetched > 0 {
+            println!(
+                "Fetched {} cold feature-history partitions of {} ({:.1} MB) into {}",
+                fetched,
+                dataset,
+                bytes as f64 / (1024.0 * 1024.0),
+                cache.display()
+            );
+        }
+        self.evict(&used)?;
+        Ok(fetched)
+    }
+
+    /// Delete the cached partitions read least recently, but those in `keep`,
+    /// until the cache fits its size.
+    fn evict(&self, keep: &HashSet<PathBuf>) -> Result<()> {
+        let mut cached = Vec::new();
+        cached_partitions(&self.cache_dir, &mut cached)?;
+        let mut total: u64 = cached.iter().map(|(_, bytes, _)| bytes).sum();
+        cached.sort();
+        for (_, bytes, partition) in cached {
+            if total <= self.cache_bytes {
+                break;
+            }
+            if keep.contains(&partition) {
+                continue;
+            }
+            std::fs::remove_dir_all(&partition)?;
+            total -= bytes;
+        }
+        Ok(())
+    }
+
+    /// Lazily scan the dataset in `dir` within `range` across the tiers,
+    /// fetching the cold partitions it needs first.
+    pub async fn scan_feature_history(&self, dir: &str, range: TimeRange) -> Result<LazyFrame> {
+        self.hydrate(dir, range).await?;
+        let cache = self.cache_of(dir);
+        query::scan_feature_history_dirs(&[&cache.to_string_lossy(), dir], range)
+    }
+
+    /// [`query::load_feature_history_lazy`] across the tiers.
+    pub async fn load_feature_history_lazy(
+        &self,
+        symbol: &str,
+        interval: &str,
+        range: TimeRange,
+    ) -> Result<LazyFrame> {
+        let dir = self.layout.feature_history_dir(symbol, interval);
+        self.scan_feature_history(&dir, range).await
+    }
+}
+
+/// The cached partitions under `dir`: when last read, their size and path.
+fn cached_partitions(dir: &Path, out: &mut Vec<(SystemTime, u64, PathBuf)>) -> Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if !path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with("date="))
+        {
+            cached_partitions(&path, out)?;
+            continue;
+        }
+        let (mut bytes, mut read) = (0, SystemTime::UNIX_EPOCH);
+        for file in std::fs::read_dir(&path)? {
+            let metadata = file?.metadata()?;
+            bytes += metadata.len();
+            read = read.max(metadata.modified()?);
+        }
+        out.push((read, bytes, path));
+    }
+    Ok(())
+}
@@ -0,0 +1,157 @@
+//! Display timezone and trading sessions.
+//!
+//! Candles, files and events keep UTC epoch milliseconds; only what is
+//! printed for people goes through [`format_ms`] in the display timezone
+//! (UTC unless [`set_display`] picked another). [`Session`]s are local
+//! trading hours such as the New York cash session, evaluated in their own
+//! timezone so daylight saving time moves them with the exchange.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Datelike, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::sync::{OnceLock, RwLock};
+
+fn display_tz() -> &'static RwLock<Tz> {
+    static DISPLAY: OnceLock<RwLock<Tz>> = OnceLock::new();
+    DISPLAY.get_or_init(|| RwLock::new(Tz::UTC))
+}
+
+fn sessions() -> &'static RwLock<Vec<Session>> {
+    static SESSIONS: OnceLock<RwLock<Vec<Session>>> = OnceLock::new();
+    SESSIONS.get_or_init(|| RwLock::new(Vec::new()))
+}
+
+/// An IANA timezone name such as "Europe/Paris" or "UTC".
+pub fn parse_tz(name: &str) -> Result<Tz> {
+    name.parse::<Tz>()
+        .map_err(|_| anyhow::anyhow!("Unknown timezone '{}' (expected e.g. Europe/Paris)", name))
+}
+
+/// Timezone timestamps are displayed in from now on.
+pub fn set_display(tz: Tz) {
+    *display_tz().write().unwrap() = tz;
+}
+
+/// The display timezone.
+pub fn display() -> Tz {
+    *display_tz().read().unwrap()
+}
+
+/// `ms` (epoch) in the display timezone, to the millisecond.
+pub fn format_ms(ms: i64) -> String {
+    format_with(ms, "%Y-%m-%d %H:%M:%S%.3f %Z")
+}
+
+/// `ms` (epoch) in the display timezone, to the second.
+pub fn format_ms_secs(ms: i64) -> String {
+    format_with(ms, "%Y-%m-%d %H:%M:%S %Z")
+}
+
+fn format_with(ms: i64, format: &str) -> String {
+    DateTime::<Utc>::from_timestamp_millis(ms)
+        .map(|dt| dt.with_timezone(&display()).format(format).to_string())
+        .unwrap_or_else(|| format!("Invalid({})", ms))
+}
+
+/// A `[[sessions]]` table.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SessionConfig {
+    pub name: String,
+    /// Timezone of `start` and `end`; the display timezone if unset.
+    pub timezone: Option<String>,
+    /// Local opening time, "HH:MM".
+    pub start: String,
+    /// Local closing time, "HH:MM"; before `start` for sessions past midnight.
+    pub end: String,
+    /// Days the session opens on ("mon" … "sun"); every day if empty.
+    #[serde(default)]
+    pub days: Vec<String>,
+}
+
+/// Recurring local trading hours.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub name: String,
+    tz: Tz,
+    start: NaiveTime,
+    end: NaiveTime,
+    days: Vec<Weekday>,
+}
+
+impl Session {
+    /// Parse a session, with `default_tz` for one without a timezone.
+    pub fn from_config(config: &SessionConfig, default_tz: Tz) -> Result<Self> {
+        let time = |value: &str| {
+            NaiveTime::parse_from_str(value, "%H:%M")
+                .with_context(|| format!("session '{}': bad time '{}'", config.name, value))
+        };
+        let tz = match &config.timezone {
+            Some(name) => parse_tz(name)?,
+            None => default_tz,
+        };
+        let days = config
+            .days
+            .iter()
+            .map(|day| {
+                day.parse::<Weekday>()
+                    .map_err(|_| anyhow::anyhow!("session '{}': bad day '{}'", config.name, day))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let (start, end) = (time(&config.start)?, time(&config.end)?);
+        if start == end {
+            bail!("session '{}' starts and ends at {}", config.name, start);
+        }
+        Ok(Self {
+            name: config.name.clone(),
+            tz,
+            start,
+            end,
+            days,
+        })
+    }
+
+    /// Whether the session is open at `ms` (epoch). A session past midnight
+    /// belongs to the day it opened on.
+    pub fn contains(&self, ms: i64) -> bool {
+        let Some(utc) = DateTime::<Utc>::from_timestamp_millis(ms) else {
+            return false;
+        };
+        let local = utc.with_timezone(&self.tz);
+        let time = local.time();
+        let opened_on = if self.start < self.end {
+            if time < self.start || time >= self.end {
+                return false;
+            }
+            local.weekday()
+        } else if time >= self.start {
+            local.weekday()
+        } else if time < self.end {
+            local.weekday().pred()
+        } else {
+            return false;
+        };
+        self.days.is_empty() || self.days.contains(&opened_on)
+    }
+}
+
+/// Replace the configured sessions.
+pub fn set_sessions(list: Vec<Session>) {
+    *sessions().write().unwrap() = list;
+}
+
+/// Whether any sessions are configured.
+pub fn has_sessions() -> bool {
+    !sessions().read().unwrap().is_empty()
+}
+
+/// Names of the sessions open at `ms` (epoch), in configuration order.
+pub fn active_sessions(ms: i64) -> Vec<String> {
+    sessions()
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|s| s.contains(ms))
+        .map(|s| s.name.clone())
+        .collect()
+}
@@ -0,0 +1,198 @@
+//! TradingView alert webhooks as an external signal source.
+//!
+//! `POST /webhooks/tradingview` on the HTTP server accepts the JSON body of a
+//! TradingView alert, maps it to a [`Signal`] through the configurable field
+//! names of `[tradingview]` (dotted paths reach into nested objects) and
+//! routes it exactly like a strategy decision, its interval in the
+//! collector's form ("15" → "15m", "240" → "4h"). TradingView cannot set
+//! headers, so the shared secret (required) travels in the body
+//! (`secret_field`) and is compared in constant time.
+//!
+//! A matching alert message in TradingView looks like:
+//! `{"secret": "...", "ticker": "{{ticker}}", "interval": "{{interval}}",
+//!   "action": "{{strategy.order.action}}", "price": {{close}}, "time": "{{time}}"}`
+
+use crate::config::TradingViewConfig;
+use crate::strategy::{Side, Signal};
+use crate::{control, exchange, metrics, signals, utils};
+use anyhow::{bail, Context, Result};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Value at a dotted path such as `strategy.order.action`.
+fn field<'a>(body: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(body, |value, key| value.get(key))
+}
+
+/// A string field (numbers are accepted and formatted).
+fn text(body: &Value, path: &str) -> Option<String> {
+    match field(body, path)? {
+        Value::String(s) => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// A numeric field (TradingView placeholders inside quotes arrive as strings).
+fn number(body: &Value, path: &str) -> Option<f64> {
+    match field(body, path)? {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// The collector's interval ("15m", "4h", "1d", "1w") of a TradingView
+/// `{{interval}}`: minutes ("15", "240"), days or weeks ("D", "1D", "W"). The
+/// collector's own form passes as it is; months and seconds are unsupported.
+fn interval(value: &str) -> Result<String> {
+    let interval = match value.parse::<i64>() {
+        Ok(minutes) if minutes > 0 && minutes % 1440 == 0 => format!("{}d", minutes / 1440),
+        Ok(minutes) if minutes > 0 && minutes % 60 == 0 => format!("{}h", minutes / 60),
+        Ok(minutes) => format!("{}m", minutes),
+        // "M" stays upper case: a month, not a minute
+        Err(_) => match value.replace('D', "d").replace('W', "w") {
+            unit if unit == "d" || unit == "w" => format!("1{}", unit),
+            interval => interval,
+        },
+    };
+    if exchange::interval_ms(&interval).is_err() {
+        bail!("unsupported interval '{}'", value);
+    }
+    Ok(interval)
+}
+
+/// Convert an alert body into a signal according to the configured schema.
+fn parse_alert(config: &TradingViewConfig, body: &Value) -> Result<Signal> {
+    let symbol = text(body, &config.symbol_field)
+        .with_context(|| format!("missing '{}'", config.symbol_field))?;
+    // "BINANCE:BTCUSDT" → "BTCUSDT"
+    let symbol = symbol.rsplit(':').next().unwrap_or(&symbol).to_uppercase();
+
+    let action = text(body, &config.side_field)
+        .with_context(|| format!("missing '{}'", config.side_field))?
+        .to_lowercase();
+    let matches = |values: &[String]| values.iter().any(|v| v.eq_ignore_ascii_case(&action));
+    let side = if matches(&config.long_values) {
+        Side::Long
+    } else if matches(&config.short_values) {
+        Side::Short
+    } else if matches(&config.flat_values) {
+        Side::Flat
+    } else {
+        bail!("unknown {} '{}'", config.side_field, action);
+    };
+
+    let price = number(body, &config.price_field)
+        .with_context(|| format!("missing or non-numeric '{}'", config.price_field))?;
+    let open_time = match text(body, &config.time_field) {
+        Some(time) => utils::parse_time_bound(&time)?,
+        None => chrono::Utc::now().timestamp_millis(),
+    };
+    let interval = interval(
+        &text(body, &config.interval_field)
+            .with_context(|| format!("missing '{}'", config.interval_field))?,
+    )?;
+    let strategy = config
+        .strategy_field
+        .as_deref()
+        .and_then(|path| text(body, path))
+        .map(|name| format!("tradingview:{}", name))
+        .unwrap_or_else(|| "tradingview".to_string());
+
+    Ok(Signal {
+        symbol,
+        interval,
+        strategy,
+        side,
+        open_time,
+        price,
+        size: None,
+        stop: None,
+    })
+}
+
+fn reject(status: StatusCode, reason: &str, message: String) -> Response {
+    metrics::counter_inc("tradingview_alerts_total", &[("result", reason)], 1);
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+/// Handler for `POST /webhooks/tradingview`.
+pub async fn receive(
+    State(config): State<Arc<TradingViewConfig>>,
+    Json(body): Json<Value>,
+) -> Response {
+    let given = text(&body, &config.secret_field).unwrap_or_default();
+    if !control::authorized(&given, config.secret.as_deref().unwrap_or_default()) {
+        return reject(
+            StatusCode::UNAUTHORIZED,
+            "unauthorized",
+            "invalid secret".to_string(),
+        );
+    }
+    let signal = match parse_alert(&config, &body) {
+        Ok(signal) => signal,
+        Err(e) => return reject(StatusCode::BAD_REQUEST, "invalid", format!("{:#}", e)),
+    };
+    metrics::counter_inc("tradingview_alerts_total", &[("result", "accepted")], 1);
+    let accepted = json!({ "accepted": &signal });
+    signals::route(signal);
+    Json(accepted).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intervals_map_to_the_collector_form() {
+        for (tradingview, collector) in [
+            ("1", "1m"),
+            ("15", "15m"),
+            ("60", "1h"),
+            ("240", "4h"),
+            ("1440", "1d"),
+            ("D", "1d"),
+            ("1D", "1d"),
+            ("3D", "3d"),
+            ("W", "1w"),
+            ("15m", "15m"),
+            ("4h", "4h"),
+        ] {
+            assert_eq!(interval(tradingview).unwrap(), collector, "{}", tradingview);
+        }
+        for unsupported in ["", "0", "-5", "1M", "M", "15S", "abc"] {
+            assert!(interval(unsupported).is_err(), "{}", unsupported);
+        }
+    }
+
+    #[test]
+    fn alerts_become_signals_of_the_collector_interval() {
+        let config = TradingViewConfig::default();
+        let body = json!({
+            "ticker": "BINANCE:btcusdt",
+            "interval": "15",
+            "action": "BUY",
+            "price": "42000.5",
+            "time": "2024-01-01T00:15:00Z",
+        });
+        let signal = parse_alert(&config, &body).unwrap();
+        assert_eq!(signal.symbol, "BTCUSDT");
+        assert_eq!(signal.interval, "15m");
+        assert_eq!(signal.strategy, "tradingview");
+        assert_eq!(signal.side, Side::Long);
+        assert_eq!(signal.price, 42000.5);
+        assert_eq!(signal.open_time, 1_704_068_100_000);
+
+        let mut unknown = body.clone();
+        unknown["interval"] = json!("1M");
+        assert!(parse_alert(&config, &unknown).is_err());
+        let mut missing = body;
+        missing.as_object_mut().unwrap().remove("interval");
+        assert!(parse_alert(&config, &missing).is_err());
+    }
+}
@@ -0,0 +1,207 @@
+//! Top-N universe (`[universe]`): the symbols a deployment runs, picked and
+//! rotated by 24h quote volume.
+//!
+//! Every `refresh_mins` the exchange's 24h tickers are ranked: symbols
+//! quoted in `quote`, not excluded and above `min_quote_volume`, by
+//! descending quote volume. A member stays while it ranks within
+//! `top + buffer`; the free places go to the best-ranked non-members. Entrants
+//! get their supervised pipelines; the pipelines of dropped symbols are asked
+//! to stop ([`live_stream::request_stop`]), flush and return, and are aborted
+//! after `stop_timeout_secs`. A failed ranking keeps the current members.
+
+use crate::alerts::{self, Level};
+use crate::config::{Config, UniverseConfig};
+use crate::{live_stream, maintenance, metrics, supervisor};
+use anyhow::{bail, Result};
+use futures_util::future::join_all;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Supervised tasks of one symbol's pipelines, with their names.
+type Pipelines = Vec<(String, JoinHandle<Result<()>>)>;
+
+/// Symbols of the 24h `volumes` eligible for the universe, best first.
+pub fn rank(volumes: Vec<(String, f64)>, config: &UniverseConfig) -> Vec<String> {
+    let mut eligible: Vec<(String, f64)> = volumes
+        .into_iter()
+        .filter(|(symbol, volume)| {
+            symbol.ends_with(&config.quote)
+                && symbol.len() > config.quote.len()
+                && !config.exclude.contains(symbol)
+                && volume.is_finite()
+                && *volume >= config.min_quote_volume
+        })
+        .collect();
+    eligible.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    eligible.into_iter().map(|(symbol, _)| symbol).collect()
+}
+
+/// The members after a ranking: the current `members` still within
+/// `top + buffer`, then the best-ranked others, `top` at most, in rank order.
+pub fn select(members: &[String], ranked: &[String], config: &UniverseConfig) -> Vec<String> {
+    let keep = config.top + config.buffer;
+    let mut selected: Vec<&String> = ranked
+        .iter()
+        .take(keep)
+        .filter(|symbol| members.contains(symbol))
+        .take(config.top)
+        .collect();
+    for symbol in ranked {
+        if selected.len() >= config.top {
+            break;
+        }
+        if !selected.contains(&symbol) {
+            selected.push(symbol);
+        }
+    }
+    let mut selected: Vec<String> = selected.into_iter().cloned().collect();
+    selected.sort_by_key(|symbol| ranked.iter().position(|s| s == symbol));
+    selected
+}
+
+/// Fetch and rank the exchange's 24h volumes.
+async fn fetch_ranking(config: &Config) -> Result<Vec<String>> {
+    let volumes = match config.exchange.source().fetch_quote_volumes().await {
+        Ok(volumes) => {
+            maintenance::recovered(config.exchange);
+            volumes
+        }
+        Err(e) => {
+            if e.is_maintenance() {
+                maintenance::detected(config.exchange, &format!("REST answered {}", e));
+            }
+            return Err(e.into());
+        }
+    };
+    Ok(rank(volumes, &config.universe))
+}
+
+/// The first members, for the pipelines started at launch.
+pub async fn initial(config: &Config) -> Result<Vec<String>> {
+    let members = select(&[], &fetch_ranking(config).await?, &config.universe);
+    if members.is_empty() {
+        bail!(
+            "[universe] no {} symbol of {} qualifies",
+            config.universe.quote,
+            config.exchange.as_str()
+        );
+    }
+    println!(
+        "[universe] top {} {} symbols by 24h volume: {}",
+        members.len(),
+        config.universe.quote,
+        members.join(", ")
+    );
+    Ok(members)
+}
+
+/// Run the pipelines of `members`, named by `pipelines_of` (one per stream
+/// type), and keep the set up to date with every re-ranking. Runs for the
+/// life of the process.
+pub async fn run<N, F, Fut>(
+    config: &Config,
+    members: Vec<String>,
+    pipelines_of: N,
+    pipeline: F,
+) -> Result<()>
+where
+    N: Fn(&str) -> Vec<String>,
+    F: Fn(String, u32) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let universe = &config.universe;
+    let mut running: BTreeMap<String, Pipelines> = BTreeMap::new();
+    let mut wanted = members;
+    let mut ticker = tokio::time::interval(Duration::from_secs(universe.refresh_mins * 60));
+    ticker.tick().await; // first tick fires immediately
+    loop {
+        let current: Vec<String> = running.keys().cloned().collect();
+        let retiring: Vec<String> = current
+            .iter()
+            .filter(|s| !wanted.contains(s))
+            .cloned()
+            .collect();
+        let entering: Vec<String> = wanted
+            .iter()
+            .filter(|s| !running.contains_key(*s))
+            .cloned()
+            .collect();
+        if !retiring.is_empty() || !entering.is_empty() {
+            println!(
+                "[universe] retiring [{}], adding [{}]",
+                retiring.join(", "),
+                entering.join(", ")
+            );
+        }
+
+        let stops = retiring.iter().map(|symbol| {
+            live_stream::request_stop(symbol);
+            let tasks = running.remove(symbol).unwrap_or_default();
+            retire(symbol.clone(), tasks, universe.stop_timeout_secs)
+        });
+        join_all(stops.collect::<Vec<_>>()).await;
+        for symbol in entering {
+            live_stream::clear_stop(&symbol);
+            let tasks = pipelines_of(&symbol)
+                .into_iter()
+                .map(|name| {
+                    let task = tokio::spawn(supervisor::supervise(
+                        name.clone(),
+                        config.supervisor.clone(),
+                        pipeline.clone(),
+                    ));
+                    (name, task)
+                })
+                .collect();
+            metrics::counter_inc("universe_changes_total", &[("change", "added")], 1);
+            running.insert(symbol, tasks);
+        }
+        metrics::gauge_set("universe_members", &[], running.len() as f64);
+
+        ticker.tick().await;
+        match fetch_ranking(config).await {
+            Ok(ranked) if !ranked.is_empty() => {
+                let current: Vec<String> = running.keys().cloned().collect();
+                wanted = select(&current, &ranked, universe);
+            }
+            Ok(_) => alerts::raise(
+                Level::Warning,
+                "universe",
+                "No symbol qualified in the ranking; keeping the current members",
+            ),
+            Err(e) => alerts::raise(
+                Level::Warning,
+                "universe",
+                &format!("Ranking failed, keeping the current members: {:#}", e),
+            ),
+        }
+    }
+}
+
+/// Wait for the stopping pipelines of `symbol` to flush and return, aborting
+/// those still running after `timeout_secs`.
+async fn retire(symbol: String, tasks: Pipelines, timeout_secs: u64) {
+    for (name, mut task) in tasks {
+        match tokio::time::timeout(Duration::from_secs(timeout_secs), &mut task).await {
+            Ok(Ok(Ok(()))) => {}
+            Ok(Ok(Err(e))) => eprintln!("[universe] {} ended with an error: {:#}", name, e),
+            Ok(Err(e)) => eprintln!("[universe] {} task failed: {}", name, e),
+            Err(_) => {
+                task.abort();
+                alerts::raise(
+                    Level::Warning,
+                    "universe",
+                    &format!(
+                        "{} did not stop within {}s and was aborted",
+                        name, timeout_secs
+                    ),
+                );
+            }
+        }
+        supervisor::retired(&name);
+    }
+    metrics::counter_inc("universe_changes_total", &[("change", "retired")], 1);
+    println!("[universe] {} retired", symbol);
+}
@@ -1,8 +1,11 @@
 //! Utility functions for monitoring and debugging.
 
+use crate::metrics;
+use anyhow::{Context, Result};
+use chrono::DateTime;
 use memory_stats::memory_stats;
-use std::collections::VecDeque;
 use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
 
 /// Print current memory usage (RSS) in MB to stdout.
@@ -21,17 +24,18 @@ pub fn print_memory_usage() {
 }
 
 use crate::kline::Kline;
+use crate::window::KlineWindow;
 use polars::prelude::DataFrame;
 
 /// Log estimated memory usage of key data structures, with values in MB.
-pub fn log_memory_breakdown(raw_window: &VecDeque<Kline>, df: &DataFrame) {
+pub fn log_memory_breakdown(raw_window: &KlineWindow, df: &DataFrame) {
     // Size of a single Kline (stack size; Kline has no heap allocations)
     let kline_size = std::mem::size_of::<Kline>(); // typically 72 bytes (7 f64 + 2 i64)
 
-    // Raw window: capacity vs used
+    // Raw window: capacity vs used (columns are mirrored, hence twice the capacity)
     let raw_cap = raw_window.capacity();
     let raw_used = raw_window.len();
-    let raw_allocated = raw_cap * kline_size;
+    let raw_allocated = raw_window.allocated_bytes();
     let raw_used_bytes = raw_used * kline_size;
 
     // DataFrame – rough estimate (rows × cols × 8 bytes per cell, ignoring strings/overhead)
@@ -62,13 +66,84 @@ pub fn log_memory_breakdown(raw_window: &VecDeque<Kline>, df: &DataFrame) {
     );
 }
 
+/// Whether [`measure_time`] and friends record into the metrics registry.
+static RECORD_TIMINGS: AtomicBool = AtomicBool::new(true);
+
+/// Record timed operations as `timed_operation_ms{label,result}` from now
+/// on (the default), or only print them.
+pub fn set_record_timings(on: bool) {
+    RECORD_TIMINGS.store(on, Ordering::Relaxed);
+}
+
+/// One timed operation, with its span when built with the `tracing`
+/// feature.
+struct Timing<'a> {
+    label: &'a str,
+    start: Instant,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl<'a> Timing<'a> {
+    fn start(label: &'a str) -> Self {
+        Self {
+            label,
+            start: Instant::now(),
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "timed",
+                label,
+                duration_ms = tracing::field::Empty,
+                ok = tracing::field::Empty
+            ),
+        }
+    }
+
+    /// Run `f` inside the span.
+    fn run<T>(&self, f: impl FnOnce() -> T) -> T {
+        #[cfg(feature = "tracing")]
+        return self.span.in_scope(f);
+        #[cfg(not(feature = "tracing"))]
+        f()
+    }
+
+    /// Print the duration and record it with the outcome.
+    fn finish(self, ok: bool) {
+        let ms = self.start.elapsed().as_secs_f64() * 1000.0;
+        println!(
+            "{} took: {:.2} ms{}",
+            self.label,
+            ms,
+            if ok { "" } else { " (failed)" }
+        );
+        if RECORD_TIMINGS.load(Ordering::Relaxed) {
+            let result = if ok { "ok" } else { "error" };
+            metrics::observe_ms(
+                metrics::TIMED_LATENCY,
+                &[("label", self.label), ("result", result)],
+                ms,
+            );
+        }
+        #[cfg(feature = "tracing")]
+        self.span.record("duration_ms", ms).record("ok", ok);
+    }
+}
+
 /// Measure the execution time of a closure and print it with a label.
 /// Returns the value returned by the closure.
 pub fn measure_time<T, F: FnOnce() -> T>(label: &str, f: F) -> T {
-    let start = Instant::now();
-    let result = f();
-    let elapsed = start.elapsed();
-    println!("{} took: {:.2} ms", label, elapsed.as_secs_f64() * 1000.0);
+    let timing = Timing::start(label);
+    let result = timing.run(f);
+    timing.finish(true);
+    result
+}
+
+/// [`measure_time`] of a fallible closure; an `Err` is recorded as a
+/// failure.
+pub fn try_measure_time<T, E, F: FnOnce() -> Result<T, E>>(label: &str, f: F) -> Result<T, E> {
+    let timing = Timing::start(label);
+    let result = timing.run(f);
+    timing.finish(result.is_ok());
     result
 }
 
@@ -76,9 +151,59 @@ pub async fn measure_time_async<F, T>(label: &str, f: F) -> T
 where
     F: Future<Output = T>,
 {
-    let start = Instant::now();
+    let timing = Timing::start(label);
+    #[cfg(feature = "tracing")]
+    let f = tracing::Instrument::instrument(f, timing.span.clone());
+    let result = f.await;
+    timing.finish(true);
+    result
+}
+
+/// [`measure_time_async`] of a fallible future; an `Err` is recorded as a
+/// failure.
+pub async fn try_measure_time_async<F, T, E>(label: &str, f: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let timing = Timing::start(label);
+    #[cfg(feature = "tracing")]
+    let f = tracing::Instrument::instrument(f, timing.span.clone());
     let result = f.await;
-    let elapsed = start.elapsed();
-    println!("{} took: {:.2} ms", label, elapsed.as_secs_f64() * 1000.0);
+    timing.finish(result.is_ok());
     result
 }
+
+/// Parse a range bound given as epoch milliseconds or an RFC 3339 timestamp.
+pub fn parse_time_bound(value: &str) -> Result<i64> {
+    if let Ok(ms) = value.parse::<i64>() {
+        return Ok(ms);
+    }
+    let time = DateTime::parse_from_rfc3339(value).with_context(|| {
+        format!(
+            "Invalid time '{}': expected epoch milliseconds or RFC 3339",
+            value
+        )
+    })?;
+    Ok(time.timestamp_millis())
+}
+
+/// Rename `path` to `<name>.<UTC time>.<extensions>` beside it, so the next
+/// append starts a new file; the new name, or `None` without a file.
+pub fn rotate_file(path: &str) -> Result<Option<String>> {
+    let path = std::path::Path::new(path);
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return Ok(None);
+    };
+    if !path.exists() {
+        return Ok(None);
+    }
+    let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%S%3f");
+    let rotated = match name.split_once('.') {
+        Some((stem, extensions)) => format!("{}.{}.{}", stem, stamp, extensions),
+        None => format!("{}.{}", name, stamp),
+    };
+    let rotated = path.with_file_name(rotated);
+    std::fs::rename(path, &rotated)
+        .with_context(|| format!("Failed to rotate {}", path.display()))?;
+    Ok(Some(rotated.to_string_lossy().into_owned()))
+}
@@ -0,0 +1,89 @@
+//! Missed-candle watchdog.
+//!
+//! Only closed candles are processed, so a stalled or silently broken stream
+//! shows up as "no new row". A background task checks that the candle after
+//! the last processed one arrived by `its open_time + interval + grace`, and
+//! raises an alert (once per late candle) when it did not.
+
+use crate::alerts::{self, Level};
+use crate::timezone;
+use chrono::Utc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the deadline is checked.
+const CHECK_PERIOD: Duration = Duration::from_secs(10);
+
+/// Handle used by the stream to report processed candles.
+#[derive(Clone)]
+pub struct Watchdog {
+    last_open_time: Arc<AtomicI64>,
+}
+
+impl Watchdog {
+    /// Start the watchdog task. `last_open_time` is the newest candle already
+    /// in the window (0 if none; checking starts with the first recorded candle).
+    pub fn spawn(symbol: &str, interval_ms: i64, grace_ms: i64, last_open_time: i64) -> Self {
+        let handle = Watchdog {
+            last_open_time: Arc::new(AtomicI64::new(last_open_time)),
+        };
+        let last = handle.last_open_time.clone();
+        let symbol = symbol.to_string();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(CHECK_PERIOD);
+            // Open time of the candle we already alerted about
+            let mut alerted_for: Option<i64> = None;
+            loop {
+                ticker.tick().await;
+                // Every handle dropped: the pipeline is gone (e.g. restarted)
+                if Arc::strong_count(&last) == 1 {
+                    break;
+                }
+                let last_good = last.load(Ordering::Relaxed);
+                if last_good == 0 {
+                    continue;
+                }
+
+                let expected_open = last_good + interval_ms;
+                if let Some(missed) = alerted_for {
+                    if expected_open > missed {
+                        println!(
+                            "[watchdog] {} candles resumed, last good candle now {}",
+                            symbol,
+                            timezone::format_ms_secs(last_good)
+                        );
+                        alerted_for = None;
+                    }
+                }
+
+                let deadline = expected_open + interval_ms + grace_ms;
+                let now = Utc::now().timestamp_millis();
+                if now > deadline && alerted_for.is_none() {
+                    let missing = (now - grace_ms - expected_open) / interval_ms;
+                    alerts::raise(
+                        Level::Critical,
+                        "watchdog",
+                        &format!(
+                            "{}: candle opening at {} not processed (deadline {}, {} candle(s) missing); last good candle {}",
+                            symbol,
+                            timezone::format_ms_secs(expected_open),
+                            timezone::format_ms_secs(deadline),
+                            missing.max(1),
+                            timezone::format_ms_secs(last_good)
+                        ),
+                    );
+                    alerted_for = Some(expected_open);
+                }
+            }
+        });
+
+        handle
+    }
+
+    /// Record that the candle opening at `open_time` was fully processed.
+    pub fn record(&self, open_time: i64) {
+        self.last_open_time.fetch_max(open_time, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,166 @@
+//! Outgoing webhooks fired on closed candles, strategy signals or summary
+//! reports.
+//!
+//! Each `[[webhooks]]` entry gets its own task and bus subscription, so a
+//! slow endpoint only delays its own deliveries. The request body is the
+//! event as flat JSON, or the entry's `template` with `{field}` placeholders
+//! replaced by the event's fields (e.g. `"{symbol} {side} at {price}"`).
+//! Failed deliveries are retried with exponential backoff; once the retries
+//! are exhausted the event is dropped and an alert is raised.
+
+use crate::alerts::{self, Level};
+use crate::bus::{self, Update};
+use crate::config::{WebhookConfig, WebhookEvent};
+use crate::metrics;
+use anyhow::{bail, Result};
+use serde_json::{json, Map, Value};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Flat fields of an update, or `None` if `event` does not select it.
+fn fields(event: WebhookEvent, update: Update) -> Option<Map<String, Value>> {
+    let value = match (event, update) {
+        (
+            WebhookEvent::Candle,
+            Update::Candle {
+                symbol,
+                interval,
+                kline,
+            },
+        ) => json!({
+            "event": "candle",
+            "symbol": symbol.to_uppercase(),
+            "interval": interval,
+            "open_time": kline.open_time,
+            "open": kline.open,
+            "high": kline.high,
+            "low": kline.low,
+            "close": kline.close,
+            "volume": kline.volume,
+            "close_time": kline.close_time,
+        }),
+        (WebhookEvent::Signal, Update::Signal(signal)) => json!({
+            "event": "signal",
+            "symbol": signal.symbol.to_uppercase(),
+            "interval": signal.interval,
+            "strategy": signal.strategy,
+            "side": signal.side.as_str(),
+            "open_time": signal.open_time,
+            "price": signal.price,
+            "size": signal.size,
+            "stop": signal.stop,
+        }),
+        _ => return None,
+    };
+    match value {
+        Value::Object(map) => Some(map),
+        _ => None,
+    }
+}
+
+/// Request body: the template with placeholders filled in, or the fields as JSON.
+fn render(template: Option<&str>, fields: &Map<String, Value>) -> String {
+    let Some(template) = template else {
+        return Value::Object(fields.clone()).to_string();
+    };
+    fields
+        .iter()
+        .fold(template.to_string(), |body, (name, value)| {
+            let text = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            body.replace(&format!("{{{}}}", name), &text)
+        })
+}
+
+async fn deliver(client: &reqwest::Client, hook: &WebhookConfig, body: String) -> Result<()> {
+    let mut delay = Duration::from_millis(hook.backoff_ms);
+    let mut attempt = 0;
+    loop {
+        let result = client
+            .post(&hook.url)
+            .header("Content-Type", &hook.content_type)
+            .body(body.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match result {
+            Ok(_) => return Ok(()),
+            Err(e) if attempt >= hook.retries => {
+                bail!("{} (after {} attempts)", e, attempt + 1)
+            }
+            Err(_) => {
+                metrics::counter_inc("webhook_retries_total", &[], 1);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Deliver `fields` to `hook`, counting the outcome; a failure raises an alert.
+async fn send(client: &reqwest::Client, hook: &WebhookConfig, fields: &Map<String, Value>) {
+    let body = render(hook.template.as_deref(), fields);
+    match deliver(client, hook, body).await {
+        Ok(()) => metrics::counter_inc(
+            "webhook_deliveries_total",
+            &[("event", hook.on.as_str())],
+            1,
+        ),
+        Err(e) => {
+            metrics::counter_inc("webhook_failures_total", &[("event", hook.on.as_str())], 1);
+            alerts::raise(
+                Level::Warning,
+                "webhook",
+                &format!("delivery to {} failed: {:#}", hook.url, e),
+            );
+        }
+    }
+}
+
+fn client(hook: &WebhookConfig) -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .timeout(Duration::from_secs(hook.timeout_secs))
+        .build()?)
+}
+
+/// Deliver `fields` to every webhook of `hooks` selecting `event`, one after
+/// another. Used for events that are not bus updates (summary reports).
+pub async fn push(hooks: &[WebhookConfig], event: WebhookEvent, fields: Map<String, Value>) {
+    for hook in hooks.iter().filter(|h| h.on == event) {
+        match client(hook) {
+            Ok(client) => send(&client, hook, &fields).await,
+            Err(e) => eprintln!("Webhook {}: {}", hook.url, e),
+        }
+    }
+}
+
+/// Start one delivery task per configured webhook on bus updates.
+pub fn spawn(hooks: &[WebhookConfig]) -> Result<()> {
+    // Reports are pushed by the report task
+    for hook in hooks.iter().filter(|h| h.on != WebhookEvent::Report) {
+        let client = client(hook)?;
+        let hook = hook.clone();
+        let mut updates = bus::subscribe();
+        println!("Webhook on {} events: {}", hook.on.as_str(), hook.url);
+        tokio::spawn(async move {
+            loop {
+                let update = match updates.recv().await {
+                    Ok(update) => update,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        metrics::counter_inc("webhook_skipped_updates_total", &[], skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let Some(fields) = fields(hook.on, update) else {
+                    continue;
+                };
+                send(&client, &hook, &fields).await;
+            }
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,247 @@
+//! Columnar ring buffer holding the rolling candle window.
+//!
+//! Every candle field lives in its own preallocated `Vec` of twice the
+//! capacity, and each candle is written to both halves (`slot` and
+//! `slot + capacity`). The newest `n` candles are therefore always one
+//! contiguous slice per column, so the feature frame is built with a single
+//! copy per column instead of `VecDeque<Kline>` → `Vec<Kline>` → columns.
+
+use crate::kline::Kline;
+use anyhow::Result;
+use polars::prelude::*;
+use std::ops::Range;
+
+/// Rolling window of the newest `capacity` candles, stored column by column.
+#[derive(Debug, Clone)]
+pub struct KlineWindow {
+    capacity: usize,
+    /// Physical index of the oldest candle, in `0..capacity`.
+    start: usize,
+    len: usize,
+    open_time: Vec<i64>,
+    open: Vec<f64>,
+    high: Vec<f64>,
+    low: Vec<f64>,
+    close: Vec<f64>,
+    volume: Vec<f64>,
+    close_time: Vec<i64>,
+}
+
+impl KlineWindow {
+    /// Empty window keeping at most `capacity` candles (at least one).
+    pub fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            start: 0,
+            len: 0,
+            open_time: vec![0; 2 * capacity],
+            open: vec![0.0; 2 * capacity],
+            high: vec![0.0; 2 * capacity],
+            low: vec![0.0; 2 * capacity],
+            close: vec![0.0; 2 * capacity],
+            volume: vec![0.0; 2 * capacity],
+            close_time: vec![0; 2 * capacity],
+        }
+    }
+
+    /// Window holding the newest `capacity` of `klines` (oldest first).
+    pub fn from_klines<'a>(klines: impl IntoIterator<Item = &'a Kline>, capacity: usize) -> Self {
+        let mut window = Self::with_capacity(capacity);
+        for kline in klines {
+            window.push(kline);
+        }
+        window
+    }
+
+    /// Append a candle, evicting the oldest one when the window is full.
+    pub fn push(&mut self, kline: &Kline) {
+        if self.len == self.capacity {
+            self.start = (self.start + 1) % self.capacity;
+        } else {
+            self.len += 1;
+        }
+        let slot = (self.start + self.len - 1) % self.capacity;
+        self.write(slot, kline);
+    }
+
+    /// Overwrite the candle with the `open_time` of `kline`; returns whether
+    /// the window holds one.
+    pub fn replace(&mut self, kline: &Kline) -> bool {
+        let r = self.range(self.len);
+        let Ok(offset) = self.open_time[r.clone()].binary_search(&kline.open_time) else {
+            return false;
+        };
+        self.write((r.start + offset) % self.capacity, kline);
+        true
+    }
+
+    /// Write `kline` to both halves of a physical slot in `0..capacity`.
+    fn write(&mut self, slot: usize, kline: &Kline) {
+        for i in [slot, slot + self.capacity] {
+            self.open_time[i] = kline.open_time;
+            self.open[i] = kline.open;
+            self.high[i] = kline.high;
+            self.low[i] = kline.low;
+            self.close[i] = kline.close;
+            self.volume[i] = kline.volume;
+            self.close_time[i] = kline.close_time;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Candle at logical position `i` (0 = oldest).
+    pub fn get(&self, i: usize) -> Option<Kline> {
+        (i < self.len).then(|| {
+            let p = self.start + i;
+            Kline {
+                open_time: self.open_time[p],
+                open: self.open[p],
+                high: self.high[p],
+                low: self.low[p],
+                close: self.close[p],
+                volume: self.volume[p],
+                close_time: self.close_time[p],
+            }
+        })
+    }
+
+    /// Newest candle.
+    pub fn last(&self) -> Option<Kline> {
+        self.len.checked_sub(1).and_then(|i| self.get(i))
+    }
+
+    /// The newest `n` candles as rows (oldest first). Meant for the rare paths
+    /// that need owned klines (snapshots, EMA replays), not the per-candle one.
+    pub fn tail(&self, n: usize) -> Vec<Kline> {
+        let n = n.min(self.len);
+        (self.len - n..self.len)
+            .filter_map(|i| self.get(i))
+            .collect()
+    }
+
+    /// Every candle of the window as rows (oldest first).
+    pub fn to_klines(&self) -> Vec<Kline> {
+        self.tail(self.len)
+    }
+
+    /// Physical range of the newest `n` candles; contiguous thanks to the
+    /// mirrored halves.
+    fn range(&self, n: usize) -> Range<usize> {
+        let end = self.start + self.len;
+        end - n.min(self.len)..end
+    }
+
+    /// The newest `n` candles as a DataFrame with the same columns as
+    /// [`crate::data_storage::klines_to_dataframe`].
+    pub fn frame(&self, n: usize) -> Result<DataFrame> {
+        let r = self.range(n);
+        let row_numbers: Vec<u32> = (1..=r.len() as u32).collect();
+        let df = df!(
+            "index" => row_numbers,
+            "open_time" => &self.open_time[r.clone()],
+            "open" => &self.open[r.clone()],
+            "high" => &self.high[r.clone()],
+            "low" => &self.low[r.clone()],
+            "close" => &self.close[r.clone()],
+            "volume" => &self.volume[r.clone()],
+            "close_time" => &self.close_time[r],
+        )?;
+        Ok(df)
+    }
+
+    /// Open times of the newest `n` candles (oldest first).
+    pub fn open_times(&self, n: usize) -> &[i64] {
+        &self.open_time[self.range(n)]
+    }
+
+    /// Highs of the newest `n` candles (oldest first).
+    pub fn highs(&self, n: usize) -> &[f64] {
+        &self.high[self.range(n)]
+    }
+
+    /// Lows of the newest `n` candles (oldest first).
+    pub fn lows(&self, n: usize) -> &[f64] {
+        &self.low[self.range(n)]
+    }
+
+    /// Closes of the newest `n` candles (oldest first).
+    pub fn closes(&self, n: usize) -> &[f64] {
+        &self.close[self.range(n)]
+    }
+
+    /// Bytes held by the column buffers (both halves).
+    pub fn allocated_bytes(&self) -> usize {
+        2 * self.capacity * (2 * size_of::<i64>() + 5 * size_of::<f64>())
+    }
+}
+
+/// Rolling statistics over the newest candles of a [`KlineWindow`], read
+/// straight from its columns. Every method looks at the newest `n` candles
+/// (fewer while the window is filling up) and returns `None` on an empty
+/// window.
+#[derive(Debug, Clone, Copy)]
+pub struct WindowStats<'a> {
+    window: &'a KlineWindow,
+}
+
+impl<'a> WindowStats<'a> {
+    pub fn new(window: &'a KlineWindow) -> Self {
+        Self { window }
+    }
+
+    /// The window the statistics are computed on.
+    pub fn window(&self) -> &'a KlineWindow {
+        self.window
+    }
+
+    /// Highest high of the newest `n` candles.
+    pub fn high(&self, n: usize) -> Option<f64> {
+        self.window.highs(n).iter().copied().reduce(f64::max)
+    }
+
+    /// Lowest low of the newest `n` candles.
+    pub fn low(&self, n: usize) -> Option<f64> {
+        self.window.lows(n).iter().copied().reduce(f64::min)
+    }
+
+    /// Percentile (0–100) of the newest close among the closes of the newest
+    /// `n` candles: the share of them at or below it.
+    pub fn percentile(&self, n: usize) -> Option<f64> {
+        let closes = self.window.closes(n);
+        let current = *closes.last()?;
+        let below = closes.iter().filter(|&&c| c <= current).count();
+        Some(100.0 * below as f64 / closes.len() as f64)
+    }
+
+    /// Candles since the newest close-to-close move of at least `pct`
+    /// percent (0 when the newest candle made one); `None` if no candle in
+    /// the window did.
+    pub fn candles_since_move(&self, pct: f64) -> Option<usize> {
+        let closes = self.window.closes(self.window.len());
+        closes
+            .windows(2)
+            .rev()
+            .position(|w| ((w[1] - w[0]) / w[0]).abs() * 100.0 >= pct)
+    }
+
+    /// Days from the newest close-to-close move of at least `pct` percent to
+    /// the newest candle; `None` if no candle in the window made one.
+    pub fn days_since_move(&self, pct: f64) -> Option<f64> {
+        let candles = self.candles_since_move(pct)?;
+        let open_times = self.window.open_times(candles + 1);
+        let elapsed = open_times.last()? - open_times.first()?;
+        Some(elapsed as f64 / 86_400_000.0)
+    }
+}
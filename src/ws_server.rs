@@ -0,0 +1,79 @@
+//! Local WebSocket server re-broadcasting enriched candles.
+//!
+//! Every feature row published on the update bus is pushed as one JSON text
+//! message (`{"symbol", "interval", "features": {...}}`, the same shape as
+//! `GET /latest/{symbol}`) to all connected clients. Clients only receive;
+//! anything they send is ignored. A client that falls too far behind skips
+//! the rows it missed instead of slowing the pipeline down.
+
+use crate::bus::{self, Update};
+use crate::{latest, metrics};
+use anyhow::{Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+/// Bind `addr` and accept subscribers in a background task.
+pub async fn spawn(addr: &str) -> Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind WebSocket server on {}", addr))?;
+    println!("WebSocket feed listening on ws://{}", addr);
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    tokio::spawn(serve_client(stream, peer));
+                }
+                Err(e) => eprintln!("WebSocket accept error: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+async fn serve_client(stream: TcpStream, peer: SocketAddr) {
+    let ws = match tokio_tungstenite::accept_async(stream).await {
+        Ok(ws) => ws,
+        Err(e) => {
+            eprintln!("WebSocket handshake with {} failed: {}", peer, e);
+            return;
+        }
+    };
+    println!("WebSocket client {} connected", peer);
+    metrics::counter_inc("ws_feed_connections_total", &[], 1);
+
+    let mut updates = bus::subscribe();
+    let (mut write, mut read) = ws.split();
+    let reason = loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(Update::Features { symbol, interval, values, .. }) => {
+                    let text = latest::feature_message(&symbol, &interval, &values);
+                    if let Err(e) = write.send(Message::Text(text.to_string())).await {
+                        break e.to_string();
+                    }
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    eprintln!("WebSocket client {} lagging, skipped {} updates", peer, skipped);
+                    metrics::counter_inc("ws_feed_skipped_updates_total", &[], skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break "shutdown".to_string(),
+            },
+            msg = read.next() => match msg {
+                Some(Ok(Message::Ping(payload))) => {
+                    if let Err(e) = write.send(Message::Pong(payload)).await {
+                        break e.to_string();
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None => break "closed by client".to_string(),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => break e.to_string(),
+            },
+        }
+    };
+    println!("WebSocket client {} disconnected: {}", peer, reason);
+}
@@ -0,0 +1,84 @@
+//! ZeroMQ PUB socket broadcasting pipeline output.
+//!
+//! Each message has two frames: a topic `{SYMBOL}.{kind}` (`kind` is
+//! `candle`, `features` or `signal`) and a JSON payload. ZeroMQ filters on
+//! topic prefixes, so subscribing to `BTCUSDT.` receives everything for one
+//! symbol, `BTCUSDT.signal` only its signals and an empty filter all traffic.
+
+use crate::bus::{self, Update};
+use crate::{latest, metrics};
+use anyhow::{Context, Result};
+use serde_json::json;
+use tokio::sync::broadcast;
+use zeromq::{Socket, SocketSend, ZmqMessage};
+
+/// Topic and JSON payload for one bus update.
+fn message(update: Update) -> Result<(String, String)> {
+    Ok(match update {
+        Update::Candle {
+            symbol,
+            interval,
+            kline,
+        } => {
+            let symbol = symbol.to_uppercase();
+            let payload = json!({ "symbol": symbol, "interval": interval, "candle": kline });
+            (format!("{}.candle", symbol), payload.to_string())
+        }
+        Update::Features {
+            symbol,
+            interval,
+            values,
+            ..
+        } => {
+            let symbol = symbol.to_uppercase();
+            let payload = latest::feature_message(&symbol, &interval, &values);
+            (format!("{}.features", symbol), payload.to_string())
+        }
+        Update::Signal(signal) => (
+            format!("{}.signal", signal.symbol.to_uppercase()),
+            serde_json::to_string(&signal)?,
+        ),
+    })
+}
+
+/// Bind the PUB socket on `endpoint` (e.g. "tcp://127.0.0.1:5556") and
+/// forward bus updates in a background task.
+pub async fn spawn(endpoint: &str) -> Result<()> {
+    let mut socket = zeromq::PubSocket::new();
+    socket
+        .bind(endpoint)
+        .await
+        .with_context(|| format!("Failed to bind ZeroMQ PUB socket on {}", endpoint))?;
+    println!("ZeroMQ publisher bound on {}", endpoint);
+
+    let mut updates = bus::subscribe();
+    tokio::spawn(async move {
+        loop {
+            let update = match updates.recv().await {
+                Ok(update) => update,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    metrics::counter_inc("zmq_skipped_updates_total", &[], skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let (topic, payload) = match message(update) {
+                Ok(message) => message,
+                Err(e) => {
+                    eprintln!("ZeroMQ: failed to encode update: {:#}", e);
+                    continue;
+                }
+            };
+            let mut frames = ZmqMessage::from(topic);
+            frames.push_back(payload.into());
+            match socket.send(frames).await {
+                Ok(()) => metrics::counter_inc("zmq_published_total", &[], 1),
+                Err(e) => {
+                    metrics::counter_inc("zmq_publish_errors_total", &[], 1);
+                    eprintln!("ZeroMQ publish failed: {}", e);
+                }
+            }
+        }
+    });
+    Ok(())
+}